@@ -0,0 +1,274 @@
+//! # 读取路径基准测试
+//!
+//! 模拟 benchmark_server 的负载：客户端连续发送大量 64 字节的小消息，
+//! 比较 `Connection::read_request`（`BytesMut` + `read_buf`）与早期的
+//! `Vec` + `drain` 实现的吞吐量。
+//!
+//! 另外比较 4 KiB 消息在解析时直接引用接收缓冲区（`Bytes`）与复制到 `Vec` 的开销，
+//! 以及 256 KiB 的大消息在不同读取方式下的吞吐量和每条消息的读取次数。
+//! 读取次数通过包装流的读取端统计，每次返回数据的读取对应一次系统调用。
+//!
+//! 运行方式：
+//!
+//! ```bash
+//! cargo bench --bench read_path
+//! ```
+
+use bytes::{Buf, BytesMut};
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, Join, ReadBuf};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+use zerust::Request;
+use zerust::codec::PacketCodec;
+use zerust::connection::{Connection, DEFAULT_READ_BUFFER_SIZE};
+use zerust::datapack::DataPack;
+
+/// 每轮发送的消息数量
+const BATCH: usize = 1000;
+/// 每条消息的数据长度
+const PAYLOAD_SIZE: usize = 64;
+/// 比较解析开销时每条消息的数据长度
+const LARGE_PAYLOAD_SIZE: usize = 4096;
+/// 大消息每轮发送的消息数量
+const HUGE_BATCH: usize = 16;
+/// 大消息的数据长度
+const HUGE_PAYLOAD_SIZE: usize = 256 * 1024;
+
+/// 建立一对本地 TCP 连接，返回 (服务端流, 客户端流)
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (server, client)
+}
+
+/// 统计读取次数的读取端
+struct CountingReader<R> {
+    /// 被包装的读取端
+    inner: R,
+    /// 返回了数据的读取次数
+    reads: Arc<AtomicUsize>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if buf.filled().len() > filled {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+/// 早期的读取实现：每取出一段数据都要把剩余数据移动到 `Vec` 的头部
+struct VecDrainReader<R = TcpStream> {
+    /// 流的读取端
+    stream: R,
+    /// 已读取但尚未解析的数据
+    pending_data: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> VecDrainReader<R> {
+    /// 读取一个完整的请求
+    async fn read_request(&mut self) -> Request {
+        let header = self.read_exact(DataPack::HEADER_SIZE).await;
+        let (msg_id, data_len) = DataPack::unpack_header(&header).unwrap();
+        let data = self.read_exact(data_len as usize).await;
+        Request::new(msg_id, data)
+    }
+
+    /// 读取指定长度的数据
+    async fn read_exact(&mut self, size: usize) -> Vec<u8> {
+        while self.pending_data.len() < size {
+            let mut buffer = [0u8; 1024];
+            let n = self.stream.read(&mut buffer).await.unwrap();
+            assert_ne!(n, 0, "connection closed");
+            self.pending_data.extend_from_slice(&buffer[..n]);
+        }
+        self.pending_data.drain(..size).collect()
+    }
+}
+
+/// 在一轮中发送 `batch` 并通过 `read` 读取 `count` 条消息，返回多轮的总耗时
+fn run_batches<F>(
+    rt: &Runtime,
+    client: &mut TcpStream,
+    (batch, count): (&[u8], usize),
+    iters: u64,
+    mut read: F,
+) -> Duration
+where
+    F: AsyncFnMut(),
+{
+    rt.block_on(async {
+        let start = Instant::now();
+        for _ in 0..iters {
+            let (_, written) = tokio::join!(
+                async {
+                    for _ in 0..count {
+                        read().await;
+                    }
+                },
+                client.write_all(batch)
+            );
+            written.unwrap();
+        }
+        start.elapsed()
+    })
+}
+
+fn read_path(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let batch: Vec<u8> = (0..BATCH)
+        .flat_map(|_| DataPack::try_pack(1, &[0u8; PAYLOAD_SIZE]).unwrap())
+        .collect();
+
+    let mut group = c.benchmark_group("read_path_64b");
+    group.throughput(Throughput::Elements(BATCH as u64));
+
+    group.bench_function("bytes_mut", |b| {
+        let (server, mut client) = rt.block_on(tcp_pair());
+        let mut conn = Connection::new(server);
+        b.iter_custom(|iters| {
+            run_batches(&rt, &mut client, (&batch, BATCH), iters, async || {
+                black_box(conn.read_request().await.unwrap());
+            })
+        });
+    });
+
+    group.bench_function("vec_drain", |b| {
+        let (server, mut client) = rt.block_on(tcp_pair());
+        let mut reader = VecDrainReader {
+            stream: server,
+            pending_data: Vec::new(),
+        };
+        b.iter_custom(|iters| {
+            run_batches(&rt, &mut client, (&batch, BATCH), iters, async || {
+                black_box(reader.read_request().await);
+            })
+        });
+    });
+
+    group.finish();
+}
+
+/// 读取端统计读取次数的服务端流
+type CountedStream = Join<CountingReader<OwnedReadHalf>, OwnedWriteHalf>;
+
+/// 建立一对本地 TCP 连接，返回 (服务端流, 服务端的读取次数, 客户端流)
+fn counted_pair(rt: &Runtime) -> (CountedStream, Arc<AtomicUsize>, TcpStream) {
+    let (server, client) = rt.block_on(tcp_pair());
+    let (read_half, write_half) = server.into_split();
+    let reads = Arc::new(AtomicUsize::new(0));
+    let reader = CountingReader {
+        inner: read_half,
+        reads: reads.clone(),
+    };
+    (tokio::io::join(reader, write_half), reads, client)
+}
+
+/// 输出每条消息平均的读取次数
+fn report_reads(name: &str, reads: &AtomicUsize, messages: usize) {
+    if messages > 0 {
+        let reads = reads.load(Ordering::Relaxed) as f64;
+        eprintln!("{name}: {:.1} reads per message", reads / messages as f64);
+    }
+}
+
+fn read_path_huge(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let batch: Vec<u8> = (0..HUGE_BATCH)
+        .flat_map(|_| DataPack::try_pack(1, &[0u8; HUGE_PAYLOAD_SIZE]).unwrap())
+        .collect();
+
+    let mut group = c.benchmark_group("read_path_256k");
+    group.throughput(Throughput::Bytes(batch.len() as u64));
+
+    // 按消息剩余长度预留空间，读取次数与接收缓冲区的扩容大小无关
+    for read_buffer_size in [1024, DEFAULT_READ_BUFFER_SIZE] {
+        let name = format!("bytes_mut_{}k", read_buffer_size / 1024);
+        let (server, reads, mut client) = counted_pair(&rt);
+        let mut conn = Connection::new(server).with_read_buffer_size(read_buffer_size);
+        let mut messages = 0;
+        group.bench_function(&name, |b| {
+            b.iter_custom(|iters| {
+                run_batches(&rt, &mut client, (&batch, HUGE_BATCH), iters, async || {
+                    black_box(conn.read_request().await.unwrap());
+                    messages += 1;
+                })
+            });
+        });
+        report_reads(&name, &reads, messages);
+    }
+
+    let (server, reads, mut client) = counted_pair(&rt);
+    let mut reader = VecDrainReader {
+        stream: server,
+        pending_data: Vec::new(),
+    };
+    let mut messages = 0;
+    group.bench_function("vec_drain_1k", |b| {
+        b.iter_custom(|iters| {
+            run_batches(&rt, &mut client, (&batch, HUGE_BATCH), iters, async || {
+                black_box(reader.read_request().await);
+                messages += 1;
+            })
+        });
+    });
+    report_reads("vec_drain_1k", &reads, messages);
+
+    group.finish();
+}
+
+fn decode_payload(c: &mut Criterion) {
+    let codec = DataPack::default();
+    let batch: Vec<u8> = (0..BATCH)
+        .flat_map(|_| DataPack::try_pack(1, &[0u8; LARGE_PAYLOAD_SIZE]).unwrap())
+        .collect();
+
+    let mut group = c.benchmark_group("decode_4k");
+    group.throughput(Throughput::Elements(BATCH as u64));
+
+    group.bench_function("bytes", |b| {
+        b.iter(|| {
+            let mut buf = BytesMut::from(&batch[..]);
+            while let Some((msg_id, data)) = codec.decode(&mut buf, u32::MAX).unwrap() {
+                black_box(Request::from_bytes(msg_id, data));
+            }
+        })
+    });
+
+    group.bench_function("vec_copy", |b| {
+        b.iter(|| {
+            let mut buf = BytesMut::from(&batch[..]);
+            while buf.len() >= DataPack::HEADER_SIZE {
+                let (msg_id, data_len) =
+                    DataPack::unpack_header(&buf[..DataPack::HEADER_SIZE]).unwrap();
+                buf.advance(DataPack::HEADER_SIZE);
+                let data = buf.split_to(data_len as usize).to_vec();
+                black_box(Request::new(msg_id, data));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, read_path, read_path_huge, decode_payload);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+//! # Zerust 异步处理函数示例
+//!
+//! 本示例演示如何使用 `add_async_route` 注册异步处理函数：
+//! - 处理函数中模拟一次耗时 10ms 的数据库查询
+//! - 查询期间不会阻塞其他连接的请求处理
+//! - 多个客户端并发请求时，总耗时接近单次查询耗时，而不是逐个累加
+//!
+//! ✅ 运行方式：
+//! ```bash
+//! cargo run --example async_db_server
+//! ```
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use zerust::{Client, DefaultRouter, Response, Server, ZerustError};
+
+/// 并发客户端数量
+const CLIENTS: usize = 20;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // ========================================
+    // 1. 注册异步处理函数：模拟一次数据库查询
+    // ========================================
+    let router = Arc::new(DefaultRouter::new());
+    router.add_async_route(1, |req| async move {
+        let user = query_user(req.data()).await;
+        Response::new(req.msg_id(), user.into_bytes())
+    })?;
+
+    // ========================================
+    // 2. 启动服务器
+    // ========================================
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = Server::new("127.0.0.1:0", router).bind().await?;
+    let addr = server.local_addr()?;
+    let server_handle = tokio::spawn(async move {
+        if let Err(e) = server.run(shutdown_rx).await {
+            eprintln!("[Zerust] Server runtime error: {}", e);
+        }
+    });
+
+    // ========================================
+    // 3. 多个客户端并发发送请求
+    // ========================================
+    let start = Instant::now();
+    let mut clients = Vec::with_capacity(CLIENTS);
+    for i in 0..CLIENTS {
+        clients.push(tokio::spawn(request(addr, format!("user-{}", i))));
+    }
+    for client in clients {
+        let reply = client.await??;
+        println!("[Client] {}", reply);
+    }
+    let elapsed = start.elapsed();
+
+    // 每次查询耗时 10ms，如果处理函数阻塞了连接任务，总耗时会接近 CLIENTS * 10ms
+    println!(
+        "✅ {} concurrent requests finished in {:?} (sequential would take ~{:?})",
+        CLIENTS,
+        elapsed,
+        Duration::from_millis(10) * CLIENTS as u32
+    );
+
+    // ========================================
+    // 4. 关闭服务器
+    // ========================================
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.await;
+    Ok(())
+}
+
+/// 模拟一次耗时 10ms 的数据库查询
+async fn query_user(key: &[u8]) -> String {
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    format!("found {}", String::from_utf8_lossy(key))
+}
+
+/// 建立一个连接，发送一次查询请求并返回响应内容
+async fn request(addr: SocketAddr, key: String) -> Result<String, ZerustError> {
+    let mut client = Client::connect(addr).await?;
+    let resp = client.request(1, key.as_bytes()).await?;
+    Ok(String::from_utf8_lossy(resp.data()).into_owned())
+}
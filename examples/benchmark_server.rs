@@ -20,7 +20,16 @@
 //! cargo run --release --example benchmark_server -- client 100 1000
 //! ```
 //! 将创建100个并发连接，每个连接发送1000个请求
+//!
+//! 对比启用和禁用 `TCP_NODELAY` 时的延迟（在进程内启动两个服务器，不需要单独启动服务器）：
+//! ```bash
+//! cargo run --release --example benchmark_server -- nodelay [连接数] [每连接请求数]
+//! ```
+//!
+//! 服务器端的每个连接在发送响应时复用同一个发送缓冲区，客户端也只打包一次请求，
+//! 因此测试结果主要反映框架本身的处理开销。
 
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::env;
 use std::sync::{
     Arc,
@@ -28,19 +37,39 @@ use std::sync::{
 };
 use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::{TcpStream, ToSocketAddrs};
 use tokio::sync::{Barrier, Semaphore, oneshot};
 use tokio::time::sleep;
 use zerust::datapack::DataPack;
 use zerust::{DefaultRouter, Response, Server};
 
+/// 统计内存分配次数的分配器，服务器的统计信息中会输出平均每个请求的分配次数
+struct CountingAlloc;
+
+/// 进程启动以来的内存分配次数
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
 
     match args.get(1).map(|s| s.as_str()) {
         Some("server") => run_server().await?,
-        Some("client") => {
+        Some(mode @ ("client" | "nodelay")) => {
             let connections = args
                 .get(2)
                 .and_then(|s| s.parse::<usize>().ok())
@@ -49,14 +78,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .get(3)
                 .and_then(|s| s.parse::<usize>().ok())
                 .unwrap_or(1000);
-            run_client(connections, requests_per_conn).await?
+            if mode == "client" {
+                let result =
+                    run_client("127.0.0.1:8888", connections, requests_per_conn, true).await?;
+                result.print(connections, requests_per_conn);
+            } else {
+                compare_nodelay(connections, requests_per_conn).await?
+            }
         }
         _ => {
             println!(
-                "用法: cargo run --release --example benchmark_server -- [server|client] [连接数] [每连接请求数]"
+                "用法: cargo run --release --example benchmark_server -- [server|client|nodelay] [连接数] [每连接请求数]"
             );
             println!("  server          - 启动基准测试服务器");
             println!("  client [连接数] [每连接请求数] - 启动客户端测试");
+            println!("  nodelay [连接数] [每连接请求数] - 对比启用和禁用 TCP_NODELAY 时的延迟");
         }
     }
 
@@ -72,39 +108,53 @@ async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
     let router = Arc::new(DefaultRouter::new());
     let router_clone = router.clone();
 
-    // 计数器，用于统计处理的请求数
-    let request_counter = Arc::new(AtomicUsize::new(0));
-    let counter_clone = request_counter.clone();
-
     // 注册高性能回显处理函数 - 不打印日志，直接返回
-    router_clone.add_route(1, move |req| {
-        counter_clone.fetch_add(1, Ordering::Relaxed);
-        Response::new(req.msg_id(), req.data().to_vec())
-    });
+    router_clone.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))?;
 
     // 启动服务器
     let server_addr = "127.0.0.1:8888";
+    // 服务器默认禁用 Nagle 算法，小负载请求-响应场景下响应不会被延迟合并
     let server = Server::new(server_addr, router);
+    // 服务器内置的指标统计处理的请求数和处理耗时
+    let metrics = server.metrics();
     println!("[Server] 基准测试服务器启动在 {}", server_addr);
 
     // 启动统计任务
     let stats_handle = tokio::spawn(async move {
         let mut last_count = 0;
+        let mut last_allocations = ALLOCATIONS.load(Ordering::Relaxed);
         let mut last_time = Instant::now();
 
         loop {
             sleep(Duration::from_secs(1)).await;
-            let current_count = request_counter.load(Ordering::Relaxed);
+            let snapshot = metrics.snapshot();
+            let current_count = snapshot.requests();
+            let current_allocations = ALLOCATIONS.load(Ordering::Relaxed);
             let current_time = Instant::now();
             let elapsed = current_time.duration_since(last_time).as_secs_f64();
 
-            let rps = (current_count - last_count) as f64 / elapsed;
+            let requests = current_count - last_count;
+            let rps = requests as f64 / elapsed;
+            let allocations_per_request = if requests > 0 {
+                (current_allocations - last_allocations) as f64 / requests as f64
+            } else {
+                0.0
+            };
+            let p99 = snapshot
+                .route(1)
+                .and_then(|route| route.latency_quantile(0.99))
+                .unwrap_or_default();
             println!(
-                "[Stats] 当前RPS: {:.2} req/s, 总请求数: {}",
-                rps, current_count
+                "[Stats] 当前RPS: {:.2} req/s, 总请求数: {}, 在线连接数: {}, 处理耗时P99: {:?}, 每请求分配次数: {:.2}",
+                rps,
+                current_count,
+                snapshot.active_connections(),
+                p99,
+                allocations_per_request
             );
 
             last_count = current_count;
+            last_allocations = current_allocations;
             last_time = current_time;
         }
     });
@@ -131,11 +181,107 @@ async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// 一次客户端基准测试的结果
+struct BenchResult {
+    /// 完成的请求数
+    completed: usize,
+    /// 总耗时
+    elapsed: Duration,
+    /// 每个请求的延迟（微秒），已排序
+    latencies: Vec<u64>,
+}
+
+impl BenchResult {
+    /// 平均延迟（微秒）
+    fn avg_latency(&self) -> f64 {
+        if self.latencies.is_empty() {
+            return 0.0;
+        }
+        self.latencies.iter().sum::<u64>() as f64 / self.latencies.len() as f64
+    }
+
+    /// 延迟的分位数（微秒）
+    fn latency_quantile(&self, q: f64) -> u64 {
+        if self.latencies.is_empty() {
+            return 0;
+        }
+        let index = ((self.latencies.len() as f64 * q).ceil() as usize).max(1) - 1;
+        self.latencies[index.min(self.latencies.len() - 1)]
+    }
+
+    /// 吞吐量（请求/秒）
+    fn throughput(&self) -> f64 {
+        self.completed as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// 打印测试结果
+    fn print(&self, connections: usize, requests_per_conn: usize) {
+        println!("\n===== 基准测试结果 =====");
+        println!("总连接数: {}", connections);
+        println!("每连接请求数: {}", requests_per_conn);
+        println!("总请求数: {}", connections * requests_per_conn);
+        println!("完成请求数: {}", self.completed);
+        println!("总耗时: {:.2} 秒", self.elapsed.as_secs_f64());
+        println!("平均延迟: {:.2} 微秒", self.avg_latency());
+        println!("P99延迟: {} 微秒", self.latency_quantile(0.99));
+        println!("吞吐量: {:.2} 请求/秒", self.throughput());
+    }
+}
+
+/// 在进程内分别启动启用和禁用 `TCP_NODELAY` 的服务器，用相同的负载测试并对比延迟
+async fn compare_nodelay(
+    connections: usize,
+    requests_per_conn: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut results = Vec::new();
+    for nodelay in [true, false] {
+        let router = Arc::new(DefaultRouter::new());
+        router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))?;
+        let server = Server::new("127.0.0.1:0", router)
+            .with_nodelay(nodelay)
+            .bind()
+            .await?;
+        let addr = server.local_addr()?;
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server_handle = tokio::spawn(server.run(shutdown_rx));
+
+        println!("\n[Compare] TCP_NODELAY = {}", nodelay);
+        let result = run_client(addr, connections, requests_per_conn, nodelay).await?;
+        let _ = shutdown_tx.send(());
+        server_handle.await??;
+        results.push((nodelay, result));
+    }
+
+    println!("\n===== TCP_NODELAY 延迟对比 =====");
+    println!(
+        "{:<14}{:>14}{:>14}{:>16}",
+        "TCP_NODELAY", "平均(微秒)", "P99(微秒)", "吞吐量(请求/秒)"
+    );
+    for (nodelay, result) in &results {
+        println!(
+            "{:<14}{:>14.2}{:>14}{:>16.2}",
+            nodelay,
+            result.avg_latency(),
+            result.latency_quantile(0.99),
+            result.throughput()
+        );
+    }
+    Ok(())
+}
+
 /// 运行客户端基准测试
+///
+/// # 参数
+/// * `addr` - 服务器地址
+/// * `connections` - 并发连接数
+/// * `requests_per_conn` - 每个连接发送的请求数
+/// * `nodelay` - 客户端的连接是否启用 `TCP_NODELAY`
 async fn run_client(
+    addr: impl ToSocketAddrs + Copy + Send + 'static,
     connections: usize,
     requests_per_conn: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
+    nodelay: bool,
+) -> Result<BenchResult, Box<dyn std::error::Error>> {
     println!(
         "[Client] 开始基准测试: {} 并发连接, 每连接 {} 请求",
         connections, requests_per_conn
@@ -150,7 +296,6 @@ async fn run_client(
     // 统计数据
     let total_requests = connections * requests_per_conn;
     let completed_requests = Arc::new(AtomicUsize::new(0));
-    let total_latency = Arc::new(AtomicUsize::new(0)); // 以微秒为单位
 
     // 启动客户端连接
     let mut handles = Vec::with_capacity(connections);
@@ -161,30 +306,38 @@ async fn run_client(
         let semaphore_clone = semaphore.clone();
         let barrier_clone = barrier.clone();
         let completed_clone = completed_requests.clone();
-        let latency_clone = total_latency.clone();
 
         let handle = tokio::spawn(async move {
+            // 每个请求的延迟（微秒）
+            let mut latencies = Vec::with_capacity(requests_per_conn);
+
             // 获取信号量许可
             let _permit = semaphore_clone.acquire().await.unwrap();
 
             // 连接到服务器
-            let mut stream = match TcpStream::connect("127.0.0.1:8888").await {
+            let mut stream = match TcpStream::connect(addr).await {
                 Ok(stream) => stream,
                 Err(e) => {
                     eprintln!("[Client {}] 连接失败: {}", i, e);
-                    return;
+                    // 仍然需要到达屏障，避免其他连接一直等待
+                    barrier_clone.wait().await;
+                    return latencies;
                 }
             };
+            let _ = stream.set_nodelay(nodelay);
 
             // 等待所有连接就绪
             barrier_clone.wait().await;
 
+            // 请求内容固定，只打包一次；响应数据的缓冲区在多次请求之间复用，
+            // 避免客户端自身的内存分配影响测试结果
+            let payload = [b'A'; 64]; // 固定64字节负载
+            let mut request = Vec::with_capacity(DataPack::HEADER_SIZE + payload.len());
+            DataPack::pack_into(1, &payload, &mut request).unwrap();
+            let mut data = Vec::with_capacity(payload.len());
+
             // 发送请求并测量延迟
             for _ in 0..requests_per_conn {
-                // 准备请求数据 - 使用随机大小的负载
-                let payload = vec![b'A'; 64]; // 固定64字节负载
-                let request = DataPack::pack(1, &payload);
-
                 let request_start = Instant::now();
 
                 // 发送请求
@@ -200,7 +353,7 @@ async fn run_client(
                     break;
                 }
 
-                let (msg_id, data_len) = match DataPack::unpack_header(&header) {
+                let (_msg_id, data_len) = match DataPack::unpack_header(&header) {
                     Ok(result) => result,
                     Err(e) => {
                         eprintln!("[Client {}] 解析响应头失败: {}", i, e);
@@ -209,19 +362,19 @@ async fn run_client(
                 };
 
                 // 读取响应数据
-                let mut data = vec![0u8; data_len as usize];
+                data.resize(data_len as usize, 0);
                 if let Err(e) = stream.read_exact(&mut data).await {
                     eprintln!("[Client {}] 读取响应数据失败: {}", i, e);
                     break;
                 }
 
-                // 计算延迟（微秒）
-                let latency = request_start.elapsed().as_micros() as usize;
-                latency_clone.fetch_add(latency, Ordering::Relaxed);
+                // 记录延迟（微秒）
+                latencies.push(request_start.elapsed().as_micros() as u64);
 
                 // 增加完成请求计数
                 completed_clone.fetch_add(1, Ordering::Relaxed);
             }
+            latencies
         });
 
         handles.push(handle);
@@ -249,35 +402,21 @@ async fn run_client(
     println!("[Client] 所有连接已就绪，开始测试...");
     barrier.wait().await;
 
-    // 等待所有客户端完成
+    // 等待所有客户端完成，汇总每个请求的延迟
+    let mut latencies = Vec::with_capacity(total_requests);
     for handle in handles {
-        let _ = handle.await;
+        if let Ok(conn_latencies) = handle.await {
+            latencies.extend(conn_latencies);
+        }
     }
+    latencies.sort_unstable();
 
     // 停止进度报告
     progress_handle.abort();
 
-    // 计算结果
-    let elapsed = start_time.elapsed();
-    let completed = completed_requests.load(Ordering::Relaxed);
-    let avg_latency = if completed > 0 {
-        total_latency.load(Ordering::Relaxed) as f64 / completed as f64
-    } else {
-        0.0
-    };
-
-    // 打印结果
-    println!("\n===== 基准测试结果 =====");
-    println!("总连接数: {}", connections);
-    println!("每连接请求数: {}", requests_per_conn);
-    println!("总请求数: {}", total_requests);
-    println!("完成请求数: {}", completed);
-    println!("总耗时: {:.2} 秒", elapsed.as_secs_f64());
-    println!("平均延迟: {:.2} 微秒", avg_latency);
-    println!(
-        "吞吐量: {:.2} 请求/秒",
-        completed as f64 / elapsed.as_secs_f64()
-    );
-
-    Ok(())
+    Ok(BenchResult {
+        completed: completed_requests.load(Ordering::Relaxed),
+        elapsed: start_time.elapsed(),
+        latencies,
+    })
 }
@@ -0,0 +1,100 @@
+//! # Zerust 聊天室示例
+//!
+//! 本示例演示如何通过 `ConnManager` 向其他连接主动推送消息：
+//! - 每个连接建立时分配一个连接ID，并登记到服务器的连接管理器中
+//! - 客户端发送的聊天消息（msg_id = 1）会被转发给其他所有在线客户端（msg_id = 2）
+//! - 推送的消息进入目标连接的发送队列，由该连接自己的任务写入网络流
+//!
+//! ✅ 运行方式：
+//! ```bash
+//! cargo run --example chat_server
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use zerust::{Client, DefaultRouter, Response, Server, ZerustError};
+
+/// 客户端发送聊天消息使用的消息ID
+const MSG_CHAT: u32 = 1;
+/// 服务器转发聊天消息使用的消息ID
+const MSG_BROADCAST: u32 = 2;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // ========================================
+    // 1. 创建服务器，并取得它的连接管理器
+    // ========================================
+    let router = Arc::new(DefaultRouter::new());
+    let server = Server::new("127.0.0.1:0", router.clone())
+        .with_on_conn_start(|conn| async move {
+            println!(
+                "[Server] #{} joined from {}",
+                conn.conn_id(),
+                conn.remote_addr()
+            );
+        })
+        .with_on_conn_stop(|conn| async move {
+            println!("[Server] #{} left", conn.conn_id());
+        });
+    let manager = server.conn_manager();
+
+    // ========================================
+    // 2. 注册聊天处理函数：转发给除发送方以外的所有连接
+    // ========================================
+    router.add_route(MSG_CHAT, move |req| {
+        let mut text = format!("#{}: ", req.conn_id()).into_bytes();
+        text.extend_from_slice(req.data());
+        let message = Response::new(MSG_BROADCAST, text);
+        let delivered = manager.broadcast_except(req.conn_id(), message);
+        Response::new(
+            req.msg_id(),
+            format!("delivered to {}", delivered).into_bytes(),
+        )
+    })?;
+
+    // ========================================
+    // 3. 启动服务器
+    // ========================================
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = server.bind().await?;
+    let addr = server.local_addr()?;
+    let manager = server.conn_manager();
+    println!("[Server] Listening on {}", addr);
+    let server_handle = tokio::spawn(async move {
+        if let Err(e) = server.run(shutdown_rx).await {
+            eprintln!("[Zerust] Server runtime error: {}", e);
+        }
+    });
+
+    // ========================================
+    // 4. 三个客户端加入聊天室，其中一个发言
+    // ========================================
+    let mut alice = Client::connect(addr).await?;
+    let mut bob = Client::connect(addr).await?;
+    let mut carol = Client::connect(addr).await?;
+    // 等待三个连接都登记到连接管理器
+    while manager.len() < 3 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    alice.send(MSG_CHAT, b"hello everyone").await?;
+    println!("[Alice] {}", read_message(&mut alice).await?);
+    println!("[Bob] {}", read_message(&mut bob).await?);
+    println!("[Carol] {}", read_message(&mut carol).await?);
+
+    // ========================================
+    // 5. 关闭服务器
+    // ========================================
+    drop((alice, bob, carol));
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.await;
+    println!("✅ Chat server stopped");
+    Ok(())
+}
+
+/// 读取服务器发送的一条消息，返回其文本内容
+async fn read_message(client: &mut Client) -> Result<String, ZerustError> {
+    let msg = client.recv().await?;
+    Ok(String::from_utf8_lossy(msg.data()).into_owned())
+}
@@ -0,0 +1,96 @@
+//! # Zerust JSON 示例
+//!
+//! 本示例演示如何通过 `serde_json` 功能收发 JSON 消息：
+//! - 服务器通过 `DefaultRouter::add_json_route` 注册处理函数，直接处理反序列化后的请求
+//! - 客户端通过 `serde_json::to_vec` 编码请求，通过 `Response::parse_json` 解码响应
+//! - 无法解析的请求得到错误响应，连接不会被关闭
+//!
+//! ✅ 运行方式：
+//! ```bash
+//! cargo run --example json_server --features serde_json
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use zerust::{Client, DefaultRouter, Server};
+
+/// 下单的消息ID
+const MSG_ORDER: u32 = 1;
+
+/// 下单请求
+#[derive(Serialize, Deserialize)]
+struct Order {
+    item: String,
+    quantity: u32,
+}
+
+/// 下单结果
+#[derive(Serialize, Deserialize, Debug)]
+struct Receipt {
+    item: String,
+    total_cents: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // ========================================
+    // 1. 注册处理反序列化后请求的处理函数
+    // ========================================
+    let router = Arc::new(DefaultRouter::new());
+    router.add_json_route(MSG_ORDER, |order: Order| {
+        let unit_cents = match order.item.as_str() {
+            "apple" => 120,
+            _ => 250,
+        };
+        Receipt {
+            total_cents: unit_cents * u64::from(order.quantity),
+            item: order.item,
+        }
+    })?;
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = Server::new("127.0.0.1:0", router).bind().await?;
+    let addr = server.local_addr()?;
+    let server_handle = tokio::spawn(async move {
+        if let Err(e) = server.run(shutdown_rx).await {
+            eprintln!("[Zerust] Server runtime error: {}", e);
+        }
+    });
+
+    // ========================================
+    // 2. 客户端编码请求，解码响应
+    // ========================================
+    let mut client = Client::connect(addr).await?;
+    for (item, quantity) in [("apple", 3), ("melon", 2)] {
+        let order = serde_json::to_vec(&Order {
+            item: item.into(),
+            quantity,
+        })?;
+        let resp = client.request(MSG_ORDER, &order).await?;
+        let receipt: Receipt = resp.parse_json()?;
+        println!("[Client] {:?}", receipt);
+    }
+
+    // ========================================
+    // 3. 无法解析的请求得到错误响应，连接仍然可以继续使用
+    // ========================================
+    let resp = client.request(MSG_ORDER, br#"{"item":"apple"}"#).await?;
+    println!(
+        "[Client] msg {}: {}",
+        resp.msg_id(),
+        String::from_utf8_lossy(resp.data())
+    );
+    let resp = client
+        .request(MSG_ORDER, br#"{"item":"apple","quantity":1}"#)
+        .await?;
+    assert_eq!(resp.parse_json::<Receipt>()?.total_cents, 120);
+
+    // ========================================
+    // 4. 关闭服务器
+    // ========================================
+    drop(client);
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.await;
+    Ok(())
+}
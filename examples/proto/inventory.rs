@@ -0,0 +1,21 @@
+// This file is @generated by prost-build.
+/// 查询库存的请求
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct StockQuery {
+    /// 商品编号
+    #[prost(string, tag = "1")]
+    pub sku: ::prost::alloc::string::String,
+}
+/// 库存查询结果
+#[derive(Clone, PartialEq, Eq, Hash, ::prost::Message)]
+pub struct StockReply {
+    /// 商品编号
+    #[prost(string, tag = "1")]
+    pub sku: ::prost::alloc::string::String,
+    /// 库存数量
+    #[prost(uint32, tag = "2")]
+    pub quantity: u32,
+    /// 有货的仓库
+    #[prost(string, repeated, tag = "3")]
+    pub warehouses: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
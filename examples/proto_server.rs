@@ -0,0 +1,89 @@
+//! # Zerust protobuf 示例
+//!
+//! 本示例演示如何通过 `prost` 功能收发 protobuf 消息：
+//! - `proto/inventory.proto` 定义了消息，`proto/inventory.rs` 是 prost-build 生成的代码
+//! - 服务器通过 `DefaultRouter::add_proto_route` 注册处理函数，直接处理解码后的消息
+//! - 客户端通过 `prost::Message::encode_to_vec` 编码请求，通过 `Response::parse_proto` 解码响应
+//! - 无法解码的请求得到错误响应，连接不会被关闭
+//!
+//! ✅ 运行方式：
+//! ```bash
+//! cargo run --example proto_server --features prost
+//! ```
+
+#[path = "proto/inventory.rs"]
+mod inventory;
+
+use inventory::{StockQuery, StockReply};
+use prost::Message;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use zerust::{Client, DefaultRouter, Server};
+
+/// 查询库存的消息ID
+const MSG_STOCK: u32 = 1;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // ========================================
+    // 1. 注册处理解码后消息的处理函数
+    // ========================================
+    let router = Arc::new(DefaultRouter::new());
+    router.add_proto_route(MSG_STOCK, |query: StockQuery| {
+        let (quantity, warehouses) = match query.sku.as_str() {
+            "apple" => (42, vec!["north".to_string(), "east".to_string()]),
+            _ => (0, Vec::new()),
+        };
+        StockReply {
+            sku: query.sku,
+            quantity,
+            warehouses,
+        }
+    })?;
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = Server::new("127.0.0.1:0", router).bind().await?;
+    let addr = server.local_addr()?;
+    let server_handle = tokio::spawn(async move {
+        if let Err(e) = server.run(shutdown_rx).await {
+            eprintln!("[Zerust] Server runtime error: {}", e);
+        }
+    });
+
+    // ========================================
+    // 2. 客户端编码请求，解码响应
+    // ========================================
+    let mut client = Client::connect(addr).await?;
+    for sku in ["apple", "pear"] {
+        let query = StockQuery { sku: sku.into() }.encode_to_vec();
+        let resp = client.request(MSG_STOCK, &query).await?;
+        let reply: StockReply = resp.parse_proto()?;
+        println!(
+            "[Client] {}: {} in {:?}",
+            reply.sku, reply.quantity, reply.warehouses
+        );
+    }
+
+    // ========================================
+    // 3. 无法解码的请求得到错误响应，连接仍然可以继续使用
+    // ========================================
+    let resp = client.request(MSG_STOCK, &[0xff, 0xff]).await?;
+    println!(
+        "[Client] msg {}: {}",
+        resp.msg_id(),
+        String::from_utf8_lossy(resp.data())
+    );
+    let query = StockQuery {
+        sku: "apple".into(),
+    };
+    let resp = client.request(MSG_STOCK, &query.encode_to_vec()).await?;
+    assert_eq!(resp.parse_proto::<StockReply>()?.quantity, 42);
+
+    // ========================================
+    // 4. 关闭服务器
+    // ========================================
+    drop(client);
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.await;
+    Ok(())
+}
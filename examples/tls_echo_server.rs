@@ -0,0 +1,71 @@
+//! # Zerust TLS 回显服务器示例
+//!
+//! 本示例演示如何通过 `Server::with_tls` 使用 TLS 加密连接：
+//! - 服务器使用 `tests/certs` 中的自签名证书（由测试 CA 签发，仅用于演示）
+//! - 客户端信任该 CA，通过 `Client::connect_tls` 建立加密连接后发送消息
+//! - 处理函数仍然可以通过连接上下文获取客户端地址
+//!
+//! ✅ 运行方式：
+//! ```bash
+//! cargo run --example tls_echo_server --features tls
+//! ```
+
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use zerust::{Client, DefaultRouter, Response, Server, tls};
+
+/// 测试 CA 的证书，客户端用它验证服务器证书
+const CA_PEM: &[u8] = include_bytes!("../tests/certs/ca.pem");
+/// 服务器证书，签发给 `localhost` 和 `127.0.0.1`
+const CERT_PEM: &[u8] = include_bytes!("../tests/certs/cert.pem");
+/// 服务器证书的私钥
+const KEY_PEM: &[u8] = include_bytes!("../tests/certs/key.pem");
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // ========================================
+    // 1. 加载证书和私钥，创建 TLS 配置
+    // ========================================
+    let tls_config = tls::server_config_from_pem(CERT_PEM, KEY_PEM)?;
+
+    // ========================================
+    // 2. 注册回显处理函数并启动服务器
+    // ========================================
+    let router = Arc::new(DefaultRouter::new());
+    router.add_route(1, |req| {
+        if let Some(conn) = req.connection() {
+            println!("[Server] echo for {}", conn.remote_addr());
+        }
+        Response::new(req.msg_id(), req.data().to_vec())
+    })?;
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = Server::new("127.0.0.1:0", router)
+        .with_tls(tls_config)
+        .with_on_tls_error(|addr, err| {
+            eprintln!("[Server] TLS handshake with {addr} failed: {err}")
+        })
+        .bind()
+        .await?;
+    let addr = server.local_addr()?;
+    let server_handle = tokio::spawn(async move {
+        if let Err(e) = server.run(shutdown_rx).await {
+            eprintln!("[Zerust] Server runtime error: {}", e);
+        }
+    });
+
+    // ========================================
+    // 3. 客户端信任测试 CA，建立 TLS 连接并发送消息
+    // ========================================
+    let connector = tls::connector_from_pem(CA_PEM)?;
+    let mut client = Client::connect_tls(addr, "localhost", &connector).await?;
+    let resp = client.request(1, b"hello over tls").await?;
+    println!("[Client] {}", String::from_utf8_lossy(resp.data()));
+
+    // ========================================
+    // 4. 关闭服务器
+    // ========================================
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.await;
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! # Zerust TOML 配置示例
+//!
+//! 本示例演示如何通过 `toml` 功能从配置文件创建服务器：
+//! - 通过 `ServerConfig::from_toml_file` 加载 `examples/zerust.toml`，无效的配置项在启动前报错
+//! - 以 `ZERUST_` 开头的环境变量覆盖文件中的配置，例如 `ZERUST_LISTEN_ADDR`
+//! - 通过 `ServerBuilder::from_config` 设置路由器等无法写在文件中的部分
+//!
+//! ✅ 运行方式：
+//! ```bash
+//! cargo run --example toml_config_server --features toml
+//! ZERUST_LISTEN_ADDR=127.0.0.1:9000 cargo run --example toml_config_server --features toml
+//! # 使用其他配置文件
+//! cargo run --example toml_config_server --features toml -- path/to/zerust.toml
+//! ```
+
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use zerust::{Client, DefaultRouter, Response, ServerBuilder, ServerConfig};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // ========================================
+    // 1. 加载配置文件，环境变量优先于文件中的配置
+    // ========================================
+    let path = std::env::args().nth(1).unwrap_or_else(|| {
+        concat!(env!("CARGO_MANIFEST_DIR"), "/examples/zerust.toml").to_string()
+    });
+    let config = ServerConfig::from_toml_file(&path)?;
+    println!("[Zerust] loaded {}: {:?}", path, config);
+
+    // ========================================
+    // 2. 注册回显处理函数并启动服务器
+    // ========================================
+    let router = Arc::new(DefaultRouter::new());
+    router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))?;
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = ServerBuilder::from_config(config)
+        .router(router)
+        .try_build()?
+        .bind()
+        .await?;
+    let addr = server.local_addr()?;
+    let server_handle = tokio::spawn(async move {
+        if let Err(e) = server.run(shutdown_rx).await {
+            eprintln!("[Zerust] Server runtime error: {}", e);
+        }
+    });
+    println!("[Zerust] listening on {}", addr);
+
+    // ========================================
+    // 3. 客户端发送一条消息
+    // ========================================
+    let mut client = Client::connect(addr).await?;
+    let resp = client.request(1, b"hello from toml config").await?;
+    println!("[Client] {}", String::from_utf8_lossy(resp.data()));
+
+    // ========================================
+    // 4. 关闭服务器
+    // ========================================
+    drop(client);
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.await;
+    Ok(())
+}
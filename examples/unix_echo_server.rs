@@ -0,0 +1,62 @@
+//! # Zerust Unix 域套接字回显服务器示例
+//!
+//! 本示例演示如何通过 `Server::new_unix` 监听 Unix 域套接字：
+//! - 同一台机器上的服务之间通信，不经过 TCP 协议栈
+//! - 消息格式、路由和处理函数与 TCP 服务器完全相同
+//! - 客户端没有网络地址，连接上下文中的地址为 `0.0.0.0:0`
+//!
+//! ✅ 运行方式（仅支持 Unix 平台）：
+//! ```bash
+//! cargo run --example unix_echo_server
+//! ```
+
+#[cfg(unix)]
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use std::sync::Arc;
+    use tokio::net::UnixStream;
+    use tokio::sync::oneshot;
+    use zerust::connection::Connection;
+    use zerust::{Client, DefaultRouter, Response, Server};
+
+    let path = std::env::temp_dir().join("zerust_unix_echo.sock");
+    // 上一次运行留下的套接字文件会导致绑定失败
+    let _ = std::fs::remove_file(&path);
+
+    // ========================================
+    // 1. 注册回显处理函数并启动服务器
+    // ========================================
+    let router = Arc::new(DefaultRouter::new());
+    router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))?;
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server = Server::new_unix(&path, router).bind().await?;
+    let server_handle = tokio::spawn(async move {
+        if let Err(e) = server.run(shutdown_rx).await {
+            eprintln!("[Zerust] Server runtime error: {}", e);
+        }
+    });
+    println!("[Zerust] listening on {}", path.display());
+
+    // ========================================
+    // 2. 客户端通过 Unix 域套接字发送消息
+    // ========================================
+    let stream = UnixStream::connect(&path).await?;
+    let mut client = Client::new(Connection::new(stream));
+    let resp = client.request(1, b"hello over uds").await?;
+    println!("[Client] {}", String::from_utf8_lossy(resp.data()));
+
+    // ========================================
+    // 3. 关闭服务器并删除套接字文件
+    // ========================================
+    drop(client);
+    let _ = shutdown_tx.send(());
+    let _ = server_handle.await;
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("Unix domain sockets are only available on Unix platforms");
+}
@@ -0,0 +1,1005 @@
+//! # 客户端模块
+//!
+//! 该模块提供了连接 Zerust 服务器的客户端，使用与服务器相同的 `Connection`
+//! 和编解码工具，调用方不必手动拼装消息头、读取响应帧。
+//!
+//! * `Client` - 一个连接，按顺序发送请求并读取响应
+//! * `ClientPool` - 多个连接组成的连接池，轮流使用其中的连接，可以在多个任务中共享
+//! * `ReconnectingClient` - 一个连接，断开后按退避策略自动重新连接
+//! * `PipelineClient` - 一个使用 `SeqDataPack` 的连接，多个请求可以同时在途，
+//!   按序列号匹配乱序到达的响应
+
+use crate::codec::{PacketCodec, SeqDataPack};
+use crate::connection::{
+    Connection, ConnectionReader, ConnectionWriter, Transport, connect_tcp, with_timeout,
+};
+use crate::control::{self, PING_MSG_ID, PONG_MSG_ID};
+use crate::datapack::DataPack;
+use crate::error::ZerustError;
+use crate::response::Response;
+use crate::runtime::{
+    Instant, JoinHandle, Mutex, TcpStream, ToSocketAddrs, lookup_host, oneshot, sleep, spawn,
+};
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, RandomState};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// 连接 Zerust 服务器的客户端
+///
+/// 一个 `Client` 对应一个连接。`request` 发送一个请求后读取下一条消息作为响应，
+/// 适合一问一答的协议；服务器主动推送的消息（例如心跳）需要通过 `recv` 自行处理。
+///
+/// 需要设置编解码工具或读写超时时，先创建 `Connection` 再通过 `Client::new` 创建客户端；
+/// 限制整个请求的等待时间可以使用 `Client::with_request_timeout`。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use tokio::sync::oneshot;
+/// use zerust::{Client, DefaultRouter, Response, Server};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), zerust::ZerustError> {
+/// let router = Arc::new(DefaultRouter::new());
+/// router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec())).unwrap();
+/// let server = Server::new("127.0.0.1:0", router).bind().await?;
+/// let addr = server.local_addr()?;
+/// let (shutdown_tx, shutdown_rx) = oneshot::channel();
+/// let handle = tokio::spawn(server.run(shutdown_rx));
+///
+/// let mut client = Client::connect(addr).await?;
+/// let resp = client.request(1, b"ping").await?;
+/// assert_eq!(resp.data(), b"ping");
+///
+/// drop(client);
+/// let _ = shutdown_tx.send(());
+/// handle.await.unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Client<S = TcpStream> {
+    /// 与服务器之间的连接
+    conn: Connection<S>,
+    /// 发送请求并等待响应的超时时间，`None` 表示不限制
+    request_timeout: Option<Duration>,
+    /// 等待 pong 期间收到的其他消息，由 `recv` 按到达顺序返回
+    buffered: VecDeque<Response>,
+    /// 上一个 ping 携带的编号，用于识别超时后才到达的 pong
+    last_ping: u64,
+}
+
+impl Client<TcpStream> {
+    /// 连接到服务器，使用默认的编解码工具
+    ///
+    /// 与服务器的默认设置相同，连接启用 `TCP_NODELAY`；需要其他套接字选项时，
+    /// 可以通过 `Client::connection` 获取连接后设置。
+    ///
+    /// # 参数
+    /// * `addr` - 服务器地址，例如 `"127.0.0.1:8999"`
+    ///
+    /// # 返回值
+    /// * `Ok(Client)` - 已连接的客户端
+    /// * `Err(ZerustError)` - 连接失败时返回的错误
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, ZerustError> {
+        let stream = connect_tcp(addr).await?;
+        Ok(Self::new(Connection::new(stream)))
+    }
+
+    /// 连接到服务器，使用指定的编解码工具
+    ///
+    /// 编解码工具需要与服务器使用的一致，例如服务器开启了 `Server::with_checksum` 时
+    /// 传入 `CheckedDataPack`。
+    ///
+    /// # 参数
+    /// * `addr` - 服务器地址，例如 `"127.0.0.1:8999"`
+    /// * `codec` - 编解码工具
+    ///
+    /// # 返回值
+    /// * `Ok(Client)` - 已连接的客户端
+    /// * `Err(ZerustError)` - 连接失败时返回的错误
+    pub async fn connect_with_codec(
+        addr: impl ToSocketAddrs,
+        codec: Arc<dyn PacketCodec>,
+    ) -> Result<Self, ZerustError> {
+        let stream = connect_tcp(addr).await?;
+        Ok(Self::new(Connection::new(stream).with_codec(codec)))
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Client<tokio_rustls::client::TlsStream<TcpStream>> {
+    /// 连接到服务器并完成 TLS 握手，使用默认的编解码工具
+    ///
+    /// 需要开启 `tls` 功能，连接工具可以通过 `tls::connector` 创建。
+    ///
+    /// # 参数
+    /// * `addr` - 服务器地址，例如 `"127.0.0.1:8443"`
+    /// * `server_name` - 用于验证服务器证书的域名或 IP 地址
+    /// * `connector` - 客户端 TLS 连接工具
+    ///
+    /// # 返回值
+    /// * `Ok(Client)` - 已连接的客户端
+    /// * `Err(ZerustError)` - 连接或握手失败时返回的错误
+    pub async fn connect_tls(
+        addr: impl ToSocketAddrs,
+        server_name: &str,
+        connector: &tokio_rustls::TlsConnector,
+    ) -> Result<Self, ZerustError> {
+        Ok(Self::new(
+            crate::tls::connect(addr, server_name, connector).await?,
+        ))
+    }
+}
+
+impl<S: Transport> Client<S> {
+    /// 使用已建立的连接创建客户端
+    ///
+    /// 连接的编解码工具、最大消息体长度和超时时间均保持不变。
+    ///
+    /// # 参数
+    /// * `conn` - 与服务器之间的连接
+    pub fn new(conn: Connection<S>) -> Self {
+        Self {
+            conn,
+            request_timeout: None,
+            buffered: VecDeque::new(),
+            last_ping: 0,
+        }
+    }
+
+    /// 设置 `request` 发送请求并等待响应的超时时间
+    ///
+    /// 超时后响应可能仍会到达，被当作下一个请求的响应，因此请求超时后应当丢弃该客户端。
+    ///
+    /// # 参数
+    /// * `timeout` - 超时时间，`None` 表示不限制
+    ///
+    /// # 返回值
+    /// 返回设置了超时时间的 `Client` 实例
+    pub fn with_request_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 获取发送请求并等待响应的超时时间，`None` 表示不限制
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// 发送一个请求并等待响应
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 请求数据
+    ///
+    /// # 返回值
+    /// * `Ok(Response)` - 服务器发送的下一条消息
+    /// * `Err(ZerustError::ConnectionClosed)` - 服务器已经关闭连接
+    /// * `Err(ZerustError::Timeout)` - 超过 `request_timeout` 仍未收到响应
+    /// * `Err(ZerustError)` - 发送或读取失败，连接已经无法继续使用
+    pub async fn request(&mut self, msg_id: u32, data: &[u8]) -> Result<Response, ZerustError> {
+        let timeout = self.request_timeout;
+        with_timeout(timeout, async {
+            self.send(msg_id, data).await?;
+            self.recv().await
+        })
+        .await
+    }
+
+    /// 发送一条消息，不等待响应
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 消息数据
+    pub async fn send(&mut self, msg_id: u32, data: &[u8]) -> Result<(), ZerustError> {
+        self.conn.send_msg(msg_id, data).await
+    }
+
+    /// 读取服务器发送的下一条消息
+    ///
+    /// 先返回 `ping` 等待期间收到的消息。控制帧不会返回给调用方，例如 `ping` 超时后才到达的 pong，
+    /// 以及服务器关闭连接前发送的消息过大通知（之后返回 `ZerustError::ConnectionClosed`）。
+    ///
+    /// # 返回值
+    /// * `Ok(Response)` - 读取到的消息
+    /// * `Err(ZerustError::ConnectionClosed)` - 服务器已经关闭连接
+    /// * `Err(ZerustError)` - 读取失败时返回的其他错误
+    pub async fn recv(&mut self) -> Result<Response, ZerustError> {
+        if let Some(msg) = self.buffered.pop_front() {
+            return Ok(msg);
+        }
+        loop {
+            let msg = self.read_msg().await?;
+            if !control::is_reserved(msg.msg_id()) {
+                return Ok(msg);
+            }
+        }
+    }
+
+    /// 发送 ping 控制帧并等待对应的 pong，测量与服务器之间的往返时间
+    ///
+    /// 服务器会自动回复 ping，不需要注册处理函数。每个 ping 携带递增的编号，
+    /// 只有编号相同的 pong 才算作回应，之前超时的 ping 的 pong 会被忽略。
+    /// 等待期间收到的其他消息不会丢失，之后由 `recv`（以及 `request`）按到达顺序返回。
+    ///
+    /// # 参数
+    /// * `timeout` - 等待 pong 的超时时间
+    ///
+    /// # 返回值
+    /// * `Ok(Duration)` - 从发送 ping 到收到 pong 经过的时间
+    /// * `Err(ZerustError::Timeout)` - 超过 `timeout` 仍未收到 pong
+    /// * `Err(ZerustError)` - 发送或读取失败，连接已经无法继续使用
+    pub async fn ping(&mut self, timeout: Duration) -> Result<Duration, ZerustError> {
+        self.last_ping = self.last_ping.wrapping_add(1);
+        let nonce = self.last_ping.to_be_bytes();
+        let start = Instant::now();
+        with_timeout(Some(timeout), async {
+            self.send(PING_MSG_ID, &nonce).await?;
+            loop {
+                let msg = self.read_msg().await?;
+                if msg.msg_id() == PONG_MSG_ID && msg.data() == nonce {
+                    return Ok(start.elapsed());
+                } else if !control::is_reserved(msg.msg_id()) {
+                    self.buffered.push_back(msg);
+                }
+            }
+        })
+        .await
+    }
+
+    /// 从连接中读取下一条消息，不经过 `buffered`
+    async fn read_msg(&mut self) -> Result<Response, ZerustError> {
+        let msg = self.conn.read_request().await?;
+        Ok(Response::from_bytes(msg.msg_id(), msg.data_bytes()).inherit_seq(msg.seq()))
+    }
+
+    /// 获取底层的连接
+    pub fn connection(&self) -> &Connection<S> {
+        &self.conn
+    }
+
+    /// 取回底层的连接
+    ///
+    /// `ping` 等待期间收到、尚未被 `recv` 取走的消息会被丢弃。
+    pub fn into_connection(self) -> Connection<S> {
+        self.conn
+    }
+}
+
+/// 客户端连接池
+///
+/// 保持固定数量的连接，每个请求轮流使用下一个连接；同一时刻每个连接只处理一个请求，
+/// 因此最多有 `size` 个请求同时在途。连接池可以放在 `Arc` 中由多个任务共享。
+///
+/// 请求失败或被取消时，所用的连接会被丢弃，下次轮到它时重新连接服务器。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use tokio::sync::oneshot;
+/// use zerust::{ClientPool, DefaultRouter, Response, Server};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), zerust::ZerustError> {
+/// let router = Arc::new(DefaultRouter::new());
+/// router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec())).unwrap();
+/// let server = Server::new("127.0.0.1:0", router).bind().await?;
+/// let addr = server.local_addr()?;
+/// let (shutdown_tx, shutdown_rx) = oneshot::channel();
+/// let handle = tokio::spawn(server.run(shutdown_rx));
+///
+/// let pool = ClientPool::connect(addr, 4).await?;
+/// let resp = pool.request(1, b"pooled").await?;
+/// assert_eq!(resp.data(), b"pooled");
+///
+/// drop(pool);
+/// let _ = shutdown_tx.send(());
+/// handle.await.unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ClientPool {
+    /// 服务器地址，重新连接时使用
+    addr: SocketAddr,
+    /// 池中的连接，`None` 表示该连接已被丢弃，下次使用时重新连接
+    clients: Vec<Mutex<Option<Client>>>,
+    /// 所有连接使用的编解码工具
+    codec: Arc<dyn PacketCodec>,
+    /// 下一个请求使用的连接序号
+    next: AtomicUsize,
+}
+
+impl ClientPool {
+    /// 建立 `size` 个连接到服务器
+    ///
+    /// # 参数
+    /// * `addr` - 服务器地址，解析出多个地址时使用第一个
+    /// * `size` - 连接数量
+    ///
+    /// # 返回值
+    /// * `Ok(ClientPool)` - 所有连接都已建立
+    /// * `Err(ZerustError)` - 解析地址或建立任意一个连接失败
+    ///
+    /// # Panics
+    /// `size` 为 0 时会 panic
+    pub async fn connect(addr: impl ToSocketAddrs, size: usize) -> Result<Self, ZerustError> {
+        Self::connect_with_codec(addr, size, Arc::new(DataPack::default())).await
+    }
+
+    /// 建立 `size` 个使用指定编解码工具的连接，参见 `Client::connect_with_codec`
+    ///
+    /// 参数、返回值与 `connect` 相同，重新建立的连接也使用该编解码工具。
+    ///
+    /// # Panics
+    /// `size` 为 0 时会 panic
+    pub async fn connect_with_codec(
+        addr: impl ToSocketAddrs,
+        size: usize,
+        codec: Arc<dyn PacketCodec>,
+    ) -> Result<Self, ZerustError> {
+        assert!(size > 0, "client pool size must be greater than 0");
+        let addr = lookup_host(addr).await?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to")
+        })?;
+        let mut clients = Vec::with_capacity(size);
+        for _ in 0..size {
+            let client = Client::connect_with_codec(addr, codec.clone()).await?;
+            clients.push(Mutex::new(Some(client)));
+        }
+        Ok(Self {
+            addr,
+            clients,
+            codec,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// 使用下一个连接发送请求并等待响应
+    ///
+    /// 该连接正在处理其他请求时会等待它完成。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 请求数据
+    ///
+    /// # 返回值
+    /// * `Ok(Response)` - 服务器的响应
+    /// * `Err(ZerustError)` - 重新连接、发送或读取失败时返回的错误
+    pub async fn request(&self, msg_id: u32, data: &[u8]) -> Result<Response, ZerustError> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        let mut slot = self.clients[index].lock().await;
+        // 先取出连接，出错或被取消时它不会被放回池中
+        let mut client = match slot.take() {
+            Some(client) => client,
+            None => Client::connect_with_codec(self.addr, self.codec.clone()).await?,
+        };
+        let resp = client.request(msg_id, data).await?;
+        *slot = Some(client);
+        Ok(resp)
+    }
+
+    /// 获取服务器地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 获取连接数量
+    pub fn size(&self) -> usize {
+        self.clients.len()
+    }
+}
+
+/// 连接状态变化回调类型
+///
+/// 通过 `ReconnectingClient::with_on_state_change` 注册，在发现连接断开或重新连接成功时调用。
+pub type StateHook = Arc<dyn Fn(ConnectionState) + Send + Sync>;
+
+/// `ReconnectingClient` 的连接状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// 已建立连接
+    Connected,
+    /// 连接已断开，下一个请求会重新连接
+    Disconnected,
+}
+
+/// 连接断开时正在发送的请求的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PendingPolicy {
+    /// 请求返回导致断开的错误，之后的请求重新连接
+    #[default]
+    FailPending,
+    /// 重新连接后再发送一次该请求
+    ///
+    /// 服务器可能已经处理了该请求，只适合可以重复执行的请求。
+    RetryPending,
+}
+
+/// 重新连接的策略
+///
+/// 第 `n` 次重试前等待 `initial_delay * 2^(n-1)`，最长不超过 `max_delay`；
+/// 开启随机抖动时实际等待时间在该值的一半到该值之间随机选取，
+/// 避免大量客户端在服务器重启后同时重连。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::time::Duration;
+/// use zerust::client::{PendingPolicy, ReconnectPolicy};
+///
+/// // 最多尝试 5 次，等待时间从 200 毫秒开始翻倍，最长 5 秒
+/// let policy = ReconnectPolicy::new()
+///     .with_initial_delay(Duration::from_millis(200))
+///     .with_max_delay(Duration::from_secs(5))
+///     .with_max_attempts(Some(5))
+///     .with_pending_policy(PendingPolicy::RetryPending);
+/// assert_eq!(policy.max_attempts(), Some(5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// 第一次重试前等待的时间
+    initial_delay: Duration,
+    /// 两次重试之间最长的等待时间
+    max_delay: Duration,
+    /// 每次重新连接最多尝试的次数，`None` 表示不限制
+    max_attempts: Option<u32>,
+    /// 是否为等待时间加入随机抖动
+    jitter: bool,
+    /// 连接断开时正在发送的请求的处理方式
+    pending: PendingPolicy,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            max_attempts: None,
+            jitter: true,
+            pending: PendingPolicy::default(),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 创建默认的重连策略
+    ///
+    /// 等待时间从 100 毫秒开始，最长 10 秒，不限制尝试次数，开启随机抖动，
+    /// 断开时正在发送的请求返回错误。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置第一次重试前等待的时间
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// 设置两次重试之间最长的等待时间
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// 设置每次重新连接最多尝试的次数，`None` 表示不限制
+    pub fn with_max_attempts(mut self, max_attempts: Option<u32>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// 设置是否为等待时间加入随机抖动
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// 设置连接断开时正在发送的请求的处理方式
+    pub fn with_pending_policy(mut self, pending: PendingPolicy) -> Self {
+        self.pending = pending;
+        self
+    }
+
+    /// 获取第一次重试前等待的时间
+    pub fn initial_delay(&self) -> Duration {
+        self.initial_delay
+    }
+
+    /// 获取两次重试之间最长的等待时间
+    pub fn max_delay(&self) -> Duration {
+        self.max_delay
+    }
+
+    /// 获取每次重新连接最多尝试的次数，`None` 表示不限制
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    /// 获取是否为等待时间加入随机抖动
+    pub fn jitter(&self) -> bool {
+        self.jitter
+    }
+
+    /// 获取连接断开时正在发送的请求的处理方式
+    pub fn pending_policy(&self) -> PendingPolicy {
+        self.pending
+    }
+
+    /// 计算第 `attempt` 次重试前等待的时间，`attempt` 从 1 开始
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay);
+        if !self.jitter {
+            return delay;
+        }
+        // 每个 RandomState 使用不同的随机密钥，哈希值可以作为随机数
+        let random = RandomState::new().hash_one(attempt);
+        let fraction = (random >> 11) as f64 / (1u64 << 53) as f64;
+        delay.mul_f64(0.5 + fraction / 2.0)
+    }
+}
+
+/// 断开后自动重新连接的客户端
+///
+/// 与 `Client` 一样按顺序发送请求并读取响应，但发送或读取失败时会丢弃连接，
+/// 下一个请求按 `ReconnectPolicy` 重新连接服务器，适合需要在服务器重启后继续工作的长期运行的客户端。
+/// `request` 只需要 `&self`，多个任务的请求依次使用同一个连接。
+///
+/// 客户端创建时不会立即连接，可以调用 `ensure_connected` 提前建立连接。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use tokio::sync::oneshot;
+/// use zerust::client::{ConnectionState, ReconnectPolicy};
+/// use zerust::{DefaultRouter, ReconnectingClient, Response, Server};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), zerust::ZerustError> {
+/// let router = Arc::new(DefaultRouter::new());
+/// router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec())).unwrap();
+/// let server = Server::new("127.0.0.1:0", router).bind().await?;
+/// let addr = server.local_addr()?;
+/// let (shutdown_tx, shutdown_rx) = oneshot::channel();
+/// let handle = tokio::spawn(server.run(shutdown_rx));
+///
+/// let client = ReconnectingClient::new(addr)
+///     .with_policy(ReconnectPolicy::new().with_max_attempts(Some(3)))
+///     .with_on_state_change(|state| println!("connection {state:?}"));
+/// let resp = client.request(1, b"ping").await?;
+/// assert_eq!(resp.data(), b"ping");
+/// assert_eq!(client.state(), ConnectionState::Connected);
+///
+/// drop(client);
+/// let _ = shutdown_tx.send(());
+/// handle.await.unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ReconnectingClient {
+    /// 服务器地址
+    addr: SocketAddr,
+    /// 重新连接的策略
+    policy: ReconnectPolicy,
+    /// 每个请求的超时时间，`None` 表示不限制
+    request_timeout: Option<Duration>,
+    /// 每次建立连接时使用的编解码工具
+    codec: Arc<dyn PacketCodec>,
+    /// 当前的连接，`None` 表示尚未连接或已经断开
+    client: Mutex<Option<Client>>,
+    /// 连接状态变化时调用的回调
+    on_state_change: Option<StateHook>,
+}
+
+impl ReconnectingClient {
+    /// 创建客户端，使用默认的重连策略，第一个请求时才连接服务器
+    ///
+    /// # 参数
+    /// * `addr` - 服务器地址
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            policy: ReconnectPolicy::default(),
+            request_timeout: None,
+            codec: Arc::new(DataPack::default()),
+            client: Mutex::new(None),
+            on_state_change: None,
+        }
+    }
+
+    /// 设置重新连接的策略
+    pub fn with_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 设置每次建立连接时使用的编解码工具，参见 `Client::connect_with_codec`
+    pub fn with_codec(mut self, codec: Arc<dyn PacketCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 设置每个请求的超时时间，参见 `Client::with_request_timeout`
+    ///
+    /// 请求超时后连接会被丢弃，下一个请求重新连接。
+    pub fn with_request_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 注册连接状态变化时调用的回调
+    ///
+    /// 连接成功时以 `ConnectionState::Connected` 调用，发现连接断开时以
+    /// `ConnectionState::Disconnected` 调用。回调在发起请求的任务中同步执行。
+    pub fn with_on_state_change<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(ConnectionState) + Send + Sync + 'static,
+    {
+        self.on_state_change = Some(Arc::new(hook));
+        self
+    }
+
+    /// 获取服务器地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 获取重新连接的策略
+    pub fn policy(&self) -> &ReconnectPolicy {
+        &self.policy
+    }
+
+    /// 获取当前的连接状态
+    ///
+    /// 有请求正在使用连接时，返回 `ConnectionState::Connected`。
+    pub fn state(&self) -> ConnectionState {
+        match self.client.try_lock() {
+            Ok(client) if client.is_none() => ConnectionState::Disconnected,
+            _ => ConnectionState::Connected,
+        }
+    }
+
+    /// 尚未连接时按重连策略连接服务器
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 已经建立连接
+    /// * `Err(ZerustError)` - 达到最大尝试次数仍未连接成功，返回最后一次的错误
+    pub async fn ensure_connected(&self) -> Result<(), ZerustError> {
+        let mut client = self.client.lock().await;
+        if client.is_none() {
+            *client = Some(self.reconnect().await?);
+        }
+        Ok(())
+    }
+
+    /// 发送一个请求并等待响应，连接已经断开时先重新连接
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 请求数据
+    ///
+    /// # 返回值
+    /// * `Ok(Response)` - 服务器的响应
+    /// * `Err(ZerustError)` - 重新连接失败，或者请求失败且没有（或重试后仍然）成功
+    pub async fn request(&self, msg_id: u32, data: &[u8]) -> Result<Response, ZerustError> {
+        let mut slot = self.client.lock().await;
+        let mut retried = false;
+        loop {
+            // 先取出连接，出错或被取消时它不会被放回
+            let mut client = match slot.take() {
+                Some(client) => client,
+                None => self.reconnect().await?,
+            };
+            match client.request(msg_id, data).await {
+                Ok(resp) => {
+                    *slot = Some(client);
+                    return Ok(resp);
+                }
+                Err(e) => {
+                    self.notify(ConnectionState::Disconnected);
+                    let disconnected =
+                        matches!(e, ZerustError::ConnectionClosed | ZerustError::IoError(_));
+                    if !disconnected || retried || self.policy.pending == PendingPolicy::FailPending
+                    {
+                        return Err(e);
+                    }
+                    retried = true;
+                }
+            }
+        }
+    }
+
+    /// 按重连策略建立新的连接
+    async fn reconnect(&self) -> Result<Client, ZerustError> {
+        let mut attempt = 0;
+        loop {
+            match connect_tcp(self.addr).await {
+                Ok(stream) => {
+                    self.notify(ConnectionState::Connected);
+                    let client =
+                        Client::new(Connection::new(stream).with_codec(self.codec.clone()));
+                    return Ok(client.with_request_timeout(self.request_timeout));
+                }
+                Err(e) => {
+                    attempt += 1;
+                    if self.policy.max_attempts.is_some_and(|max| attempt >= max) {
+                        return Err(e.into());
+                    }
+                    sleep(self.policy.delay(attempt)).await;
+                }
+            }
+        }
+    }
+
+    /// 调用连接状态变化的回调
+    fn notify(&self, state: ConnectionState) {
+        if let Some(hook) = &self.on_state_change {
+            hook(state);
+        }
+    }
+}
+
+/// 推送消息回调类型
+///
+/// 接收没有对应请求的消息，例如服务器主动推送的消息。
+/// 通过 `PipelineClient::with_on_push` 注册，在读取响应的后台任务中调用。
+pub type PushHook = Arc<dyn Fn(Response) + Send + Sync>;
+
+/// 等待响应的请求，按序列号索引；连接关闭后为 `None`
+type Pending = Arc<std::sync::Mutex<Option<HashMap<u32, oneshot::Sender<Response>>>>>;
+
+/// 支持多个请求同时在途的客户端
+///
+/// 每个请求分配一个序列号，服务器把请求的序列号带到它的响应上，
+/// 后台任务读取响应并按序列号交给等待它的请求，因此响应可以按任意顺序到达，
+/// 处理较慢的请求不会阻塞同一连接上的其他请求。`request` 只需要 `&self`，
+/// 客户端可以放在 `Arc` 中由多个任务共享。
+///
+/// 客户端和服务器都需要使用携带序列号的编解码工具，例如 `SeqDataPack`。
+/// 没有序列号或序列号没有对应请求的消息（例如服务器主动推送的消息）交给
+/// `with_on_push` 注册的回调，没有注册回调时被丢弃。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use tokio::sync::oneshot;
+/// use zerust::codec::SeqDataPack;
+/// use zerust::{DefaultRouter, PipelineClient, Response, Server};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), zerust::ZerustError> {
+/// let router = Arc::new(DefaultRouter::new());
+/// router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec())).unwrap();
+/// let server = Server::new("127.0.0.1:0", router)
+///     .with_codec(Arc::new(SeqDataPack::new()))
+///     .bind()
+///     .await?;
+/// let addr = server.local_addr()?;
+/// let (shutdown_tx, shutdown_rx) = oneshot::channel();
+/// let handle = tokio::spawn(server.run(shutdown_rx));
+///
+/// let client = PipelineClient::connect(addr).await?;
+/// let (a, b) = tokio::join!(client.request(1, b"a"), client.request(1, b"b"));
+/// assert_eq!((a?.data(), b?.data()), (&b"a"[..], &b"b"[..]));
+///
+/// drop(client);
+/// let _ = shutdown_tx.send(());
+/// handle.await.unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PipelineClient {
+    /// 连接的写入端
+    writer: ConnectionWriter,
+    /// 等待响应的请求
+    pending: Pending,
+    /// 下一个请求使用的序列号
+    next_seq: AtomicU32,
+    /// 接收推送消息的回调
+    on_push: Arc<RwLock<Option<PushHook>>>,
+    /// 读取响应的后台任务，客户端被丢弃时终止
+    reader: JoinHandle<()>,
+}
+
+impl PipelineClient {
+    /// 连接到服务器，使用默认字节序的 `SeqDataPack`
+    ///
+    /// # 参数
+    /// * `addr` - 服务器地址，例如 `"127.0.0.1:8999"`
+    ///
+    /// # 返回值
+    /// * `Ok(PipelineClient)` - 已连接的客户端
+    /// * `Err(ZerustError)` - 连接失败时返回的错误
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, ZerustError> {
+        let stream = connect_tcp(addr).await?;
+        Ok(Self::new(
+            Connection::new(stream).with_codec(Arc::new(SeqDataPack::new())),
+        ))
+    }
+
+    /// 使用已建立的连接创建客户端，并启动读取响应的后台任务
+    ///
+    /// 连接需要使用携带序列号的编解码工具，否则所有响应都会因为没有序列号而被丢弃。
+    ///
+    /// # 参数
+    /// * `conn` - 与服务器之间的连接
+    pub fn new<S: Transport>(conn: Connection<S>) -> Self {
+        let (reader, writer) = conn.split();
+        let writer_for_pong = writer.clone();
+        let pending = Pending::new(std::sync::Mutex::new(Some(HashMap::new())));
+        let on_push = Arc::new(RwLock::new(None));
+        Self {
+            writer,
+            pending: pending.clone(),
+            next_seq: AtomicU32::new(1),
+            on_push: on_push.clone(),
+            reader: spawn(Self::read_loop(reader, writer_for_pong, pending, on_push)),
+        }
+    }
+
+    /// 注册接收推送消息的回调，替换已注册的回调
+    ///
+    /// 没有序列号、或者序列号没有对应等待中的请求的消息都会交给该回调，
+    /// 包括服务器主动推送的消息，以及请求被取消后才到达的响应。
+    /// 注册之前到达的这类消息会被丢弃。回调在读取响应的后台任务中执行，
+    /// 执行期间不会读取后续的响应，耗时的处理应当转交给其他任务。
+    ///
+    /// # 参数
+    /// * `hook` - 接收消息的回调
+    ///
+    /// # 返回值
+    /// 返回注册了回调的 `PipelineClient` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust,no_run
+    /// use zerust::PipelineClient;
+    ///
+    /// # async fn run() -> Result<(), zerust::ZerustError> {
+    /// let client = PipelineClient::connect("127.0.0.1:8999")
+    ///     .await?
+    ///     .with_on_push(|msg| println!("pushed: msg_id={}", msg.msg_id()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_on_push<F>(self, hook: F) -> Self
+    where
+        F: Fn(Response) + Send + Sync + 'static,
+    {
+        *self.on_push.write().unwrap() = Some(Arc::new(hook));
+        self
+    }
+
+    /// 发送一个请求并等待对应的响应
+    ///
+    /// 可以在多个任务中同时调用，每个调用只会得到自己的请求对应的响应。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 请求数据
+    ///
+    /// # 返回值
+    /// * `Ok(Response)` - 服务器的响应，序列号与请求相同
+    /// * `Err(ZerustError::ConnectionClosed)` - 连接在收到响应前关闭
+    /// * `Err(ZerustError)` - 发送失败时返回的其他错误
+    pub async fn request(&self, msg_id: u32, data: &[u8]) -> Result<Response, ZerustError> {
+        let seq = self.next_seq();
+        let (tx, rx) = oneshot::channel();
+        match self.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(seq, tx),
+            None => return Err(ZerustError::ConnectionClosed),
+        };
+        // 发送失败或调用被取消时移除等待中的请求
+        let _guard = PendingGuard {
+            pending: &self.pending,
+            seq,
+        };
+        let req = Response::new(msg_id, data.to_vec()).with_seq(seq);
+        self.writer.send_response(&req).await?;
+        rx.await.map_err(|_| ZerustError::ConnectionClosed)
+    }
+
+    /// 获取尚未收到响应的请求数量
+    pub fn pending(&self) -> usize {
+        self.pending
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, HashMap::len)
+    }
+
+    /// 分配下一个序列号，跳过没有对应请求的消息使用的 0
+    fn next_seq(&self) -> u32 {
+        loop {
+            let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+            if seq != 0 {
+                return seq;
+            }
+        }
+    }
+
+    /// 读取响应并交给等待它的请求，直到连接关闭
+    ///
+    /// 没有对应请求的消息交给推送回调，ping 控制帧自动回复 pong。
+    async fn read_loop(
+        mut reader: ConnectionReader,
+        writer: ConnectionWriter,
+        pending: Pending,
+        on_push: Arc<RwLock<Option<PushHook>>>,
+    ) {
+        while let Ok(msg) = reader.read_request().await {
+            if msg.msg_id() == PING_MSG_ID {
+                let pong =
+                    Response::from_bytes(PONG_MSG_ID, msg.data_bytes()).inherit_seq(msg.seq());
+                if writer.send_response(&pong).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+            let resp = Response::from_bytes(msg.msg_id(), msg.data_bytes());
+            let Some(seq) = msg.seq() else {
+                Self::push(&on_push, resp);
+                continue;
+            };
+            let resp = resp.with_seq(seq);
+            let tx = pending
+                .lock()
+                .unwrap()
+                .as_mut()
+                .and_then(|p| p.remove(&seq));
+            match tx {
+                // 请求已被取消时，接收端已经关闭
+                Some(tx) => {
+                    if let Err(resp) = tx.send(resp) {
+                        Self::push(&on_push, resp);
+                    }
+                }
+                None => Self::push(&on_push, resp),
+            }
+        }
+        // 连接已经关闭，等待中的请求得到 ConnectionClosed 错误
+        pending.lock().unwrap().take();
+    }
+
+    /// 把没有对应请求的消息交给推送回调，没有注册回调时丢弃
+    fn push(on_push: &RwLock<Option<PushHook>>, msg: Response) {
+        // 先释放读锁再调用回调，回调执行期间不阻塞注册新的回调
+        let hook = on_push.read().unwrap().clone();
+        if let Some(hook) = hook {
+            hook(msg);
+        }
+    }
+}
+
+impl Drop for PipelineClient {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// 请求结束时从等待列表中移除它的序列号
+struct PendingGuard<'a> {
+    /// 等待响应的请求
+    pending: &'a Pending,
+    /// 请求的序列号
+    seq: u32,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            pending.remove(&self.seq);
+        }
+    }
+}
@@ -0,0 +1,611 @@
+//! # 帧编解码接口模块
+//!
+//! 该模块定义了 `PacketCodec` trait，用于把消息编码为网络上传输的字节帧，
+//! 以及从接收缓冲区中增量地解析出完整的消息。
+//!
+//! 框架默认使用 `DataPack` 实现的 8 字节消息头协议；需要兼容其他线路格式
+//! （例如带魔数、校验和的私有协议）时，可以实现该 trait，并通过
+//! `Connection::with_codec` 或 `Server::with_codec` 替换默认实现。
+//!
+//! 对端只使用长度前缀、不携带消息ID时，可以使用 `LengthPrefixCodec`；
+//! 需要在同一个连接上同时发出多个请求、按序列号匹配乱序到达的响应时，
+//! 可以使用在消息头中增加了序列号的 `SeqDataPack`；链路不可靠、需要检测损坏的数据时，
+//! 可以使用在数据之后增加了 CRC32 校验和的 `CheckedDataPack`。
+
+use crate::datapack::{ByteOrderMode, DataPack};
+use crate::error::ZerustError;
+use bytes::{Buf, Bytes, BytesMut};
+
+/// 帧编解码接口
+///
+/// 实现需要满足 `Send + Sync`，同一个实例会被服务器的所有连接共享。
+///
+/// # 示例
+///
+/// 一个使用 2 字节魔数、u16 消息ID 和 u32 数据长度的协议：
+///
+/// ```rust
+/// use bytes::{Buf, Bytes, BytesMut};
+/// use zerust::ZerustError;
+/// use zerust::codec::PacketCodec;
+///
+/// struct LegacyCodec;
+///
+/// const MAGIC: [u8; 2] = [0xCA, 0xFE];
+///
+/// impl PacketCodec for LegacyCodec {
+///     fn encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+///         let msg_id = u16::try_from(msg_id)
+///             .map_err(|_| ZerustError::ProtocolError("msg_id exceeds u16".into()))?;
+///         let mut buf = MAGIC.to_vec();
+///         buf.extend_from_slice(&msg_id.to_be_bytes());
+///         buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+///         buf.extend_from_slice(data);
+///         Ok(buf)
+///     }
+///
+///     fn decode(
+///         &self,
+///         buf: &mut BytesMut,
+///         max_len: u32,
+///     ) -> Result<Option<(u32, Bytes)>, ZerustError> {
+///         if buf.len() < 8 {
+///             return Ok(None);
+///         }
+///         if buf[..2] != MAGIC {
+///             return Err(ZerustError::InvalidHeader);
+///         }
+///         let msg_id = u16::from_be_bytes([buf[2], buf[3]]) as u32;
+///         let data_len = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+///         if data_len > max_len {
+///             return Err(ZerustError::MessageTooLarge {
+///                 size: data_len as u64,
+///                 limit: max_len as u64,
+///             });
+///         }
+///         if buf.len() < 8 + data_len as usize {
+///             return Ok(None);
+///         }
+///         buf.advance(8);
+///         Ok(Some((msg_id, buf.split_to(data_len as usize).freeze())))
+///     }
+/// }
+///
+/// let codec = LegacyCodec;
+/// let mut buf = BytesMut::from(&codec.encode(7, b"hi").unwrap()[..]);
+/// assert_eq!(codec.decode(&mut buf, 1024).unwrap(), Some((7, Bytes::from_static(b"hi"))));
+/// assert!(buf.is_empty());
+/// ```
+pub trait PacketCodec: Send + Sync {
+    /// 将消息ID和数据编码为一个完整的帧
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 消息数据
+    ///
+    /// # 返回值
+    /// 成功时返回编码后的字节，消息无法用该格式表示时返回错误
+    fn encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError>;
+
+    /// 将消息ID和数据编码为一个完整的帧，追加到已有的缓冲区末尾
+    ///
+    /// 连接发送消息时调用该方法，并在多次发送之间复用同一个缓冲区。
+    /// 默认实现调用 `encode` 后复制结果；实现可以直接写入缓冲区以避免每条消息的分配。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 消息数据
+    /// * `buf` - 追加编码结果的缓冲区
+    ///
+    /// # 返回值
+    /// 成功时返回 `Ok(())`，消息无法用该格式表示时返回错误
+    fn encode_into(&self, msg_id: u32, data: &[u8], buf: &mut Vec<u8>) -> Result<(), ZerustError> {
+        buf.extend_from_slice(&self.encode(msg_id, data)?);
+        Ok(())
+    }
+
+    /// 从接收缓冲区中解析一个完整的帧
+    ///
+    /// 缓冲区中的数据还不足一个完整的帧时返回 `Ok(None)`，并且不能从缓冲区中移除数据，
+    /// 连接会读取更多数据后再次调用；解析出完整的帧时，需要把该帧从缓冲区中移除。
+    /// 通过 `BytesMut::split_to` 和 `freeze` 取出消息体可以避免复制数据。
+    ///
+    /// # 参数
+    /// * `buf` - 接收缓冲区，包含尚未解析的数据
+    /// * `max_len` - 允许的最大数据长度，实现应当在分配消息体之前检查该限制
+    ///
+    /// # 返回值
+    /// * `Ok(Some((msg_id, data)))` - 解析出的一个完整消息
+    /// * `Ok(None)` - 数据不足一个完整的帧
+    /// * `Err(ZerustError)` - 数据格式错误或数据长度超过限制，连接会被关闭
+    fn decode(&self, buf: &mut BytesMut, max_len: u32)
+    -> Result<Option<(u32, Bytes)>, ZerustError>;
+
+    /// 将携带序列号的消息编码为一个完整的帧，追加到已有的缓冲区末尾
+    ///
+    /// 连接发送消息时调用该方法。默认实现忽略序列号并调用 `encode_into`，
+    /// 帧格式包含序列号的实现（例如 `SeqDataPack`）需要覆盖该方法。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `seq` - 序列号，`None` 表示消息没有对应的请求（例如主动推送的消息）
+    /// * `data` - 消息数据
+    /// * `buf` - 追加编码结果的缓冲区
+    fn encode_seq_into(
+        &self,
+        msg_id: u32,
+        seq: Option<u32>,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), ZerustError> {
+        let _ = seq;
+        self.encode_into(msg_id, data, buf)
+    }
+
+    /// 将消息编码为数据被压缩的帧，追加到已有的缓冲区末尾
+    ///
+    /// 连接发送 `Response::new_compressed` 创建的响应时调用该方法。
+    /// 默认实现不支持压缩，调用 `encode_seq_into`；`CompressedCodec` 忽略压缩阈值，
+    /// 总是尝试压缩数据。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `seq` - 序列号，`None` 表示消息没有对应的请求
+    /// * `data` - 消息数据
+    /// * `buf` - 追加编码结果的缓冲区
+    fn encode_compressed_into(
+        &self,
+        msg_id: u32,
+        seq: Option<u32>,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), ZerustError> {
+        self.encode_seq_into(msg_id, seq, data, buf)
+    }
+
+    /// 从接收缓冲区中解析一个携带序列号的完整帧
+    ///
+    /// 连接读取消息时调用该方法。默认实现调用 `decode`，返回的序列号为 `None`。
+    ///
+    /// # 返回值
+    /// * `Ok(Some((msg_id, seq, data)))` - 解析出的一个完整消息
+    /// * `Ok(None)` - 数据不足一个完整的帧
+    /// * `Err(ZerustError)` - 数据格式错误或数据长度超过限制，连接会被关闭
+    fn decode_seq(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Option<u32>, Bytes)>, ZerustError> {
+        Ok(self
+            .decode(buf, max_len)?
+            .map(|(msg_id, data)| (msg_id, None, data)))
+    }
+}
+
+/// `DataPack` 实现的 8 字节消息头协议，也是框架的默认编解码方式
+impl PacketCodec for DataPack {
+    fn encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        self.try_encode(msg_id, data)
+    }
+
+    fn encode_into(&self, msg_id: u32, data: &[u8], buf: &mut Vec<u8>) -> Result<(), ZerustError> {
+        DataPack::encode_into(self, msg_id, data, buf)
+    }
+
+    fn decode(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Bytes)>, ZerustError> {
+        if buf.len() < Self::HEADER_SIZE {
+            return Ok(None);
+        }
+        // 先检查消息头，数据过长时在等待消息体之前返回错误
+        let (msg_id, data_len) =
+            self.decode_header_with_limit(&buf[..Self::HEADER_SIZE], max_len)?;
+        let frame_len = Self::checked_frame_len(Self::HEADER_SIZE, data_len)?;
+        if buf.len() < frame_len {
+            // 为剩余的消息体预留空间，避免多次扩容
+            buf.reserve(frame_len - buf.len());
+            return Ok(None);
+        }
+        buf.advance(Self::HEADER_SIZE);
+        // 消息体与接收缓冲区共享内存，不复制数据
+        let data = buf.split_to(data_len as usize).freeze();
+        Ok(Some((msg_id, data)))
+    }
+}
+
+/// 只有 4 字节长度前缀的帧格式：u32 数据长度 + 数据
+///
+/// 帧中不包含消息ID：解析出的所有消息都使用同一个消息ID（默认为 0），
+/// 因此只需要为该消息ID注册一个处理函数，由它根据数据内容自行分发。
+/// 编码时忽略响应的消息ID，只写入长度和数据。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use zerust::codec::LengthPrefixCodec;
+/// use zerust::datapack::ByteOrderMode;
+/// use zerust::{DefaultRouter, Response, Server};
+///
+/// let router = Arc::new(DefaultRouter::new());
+/// // 所有消息都交给消息ID为 0 的处理函数
+/// router.add_route(0, |req| Response::new(req.msg_id(), req.data().to_vec())).unwrap();
+///
+/// let server = Server::new("127.0.0.1:0", router)
+///     .with_codec(Arc::new(LengthPrefixCodec::new().with_order(ByteOrderMode::Big)));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LengthPrefixCodec {
+    /// 解析出的消息使用的消息ID
+    msg_id: u32,
+    /// 长度前缀使用的字节序
+    order: ByteOrderMode,
+}
+
+impl LengthPrefixCodec {
+    /// 长度前缀的长度（字节）
+    pub const HEADER_SIZE: usize = 4;
+
+    /// 创建一个使用小端序、消息ID为 0 的编解码工具
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置解析出的消息使用的消息ID
+    ///
+    /// # 参数
+    /// * `msg_id` - 所有消息都会被路由到该消息ID
+    pub fn with_msg_id(mut self, msg_id: u32) -> Self {
+        self.msg_id = msg_id;
+        self
+    }
+
+    /// 设置长度前缀使用的字节序
+    ///
+    /// # 参数
+    /// * `order` - 字节序
+    pub fn with_order(mut self, order: ByteOrderMode) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// 获取解析出的消息使用的消息ID
+    pub fn msg_id(&self) -> u32 {
+        self.msg_id
+    }
+
+    /// 获取长度前缀使用的字节序
+    pub fn order(&self) -> ByteOrderMode {
+        self.order
+    }
+}
+
+impl PacketCodec for LengthPrefixCodec {
+    fn encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        let mut buf = Vec::new();
+        self.encode_into(msg_id, data, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn encode_into(&self, _msg_id: u32, data: &[u8], buf: &mut Vec<u8>) -> Result<(), ZerustError> {
+        let data_len = u32::try_from(data.len()).map_err(|_| ZerustError::MessageTooLarge {
+            size: data.len() as u64,
+            limit: u32::MAX as u64,
+        })?;
+        let prefix = match self.order {
+            ByteOrderMode::Little => data_len.to_le_bytes(),
+            ByteOrderMode::Big => data_len.to_be_bytes(),
+        };
+        buf.reserve(Self::HEADER_SIZE + data.len());
+        buf.extend_from_slice(&prefix);
+        buf.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn decode(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Bytes)>, ZerustError> {
+        let Some(prefix) = buf.first_chunk::<{ Self::HEADER_SIZE }>() else {
+            return Ok(None);
+        };
+        let data_len = match self.order {
+            ByteOrderMode::Little => u32::from_le_bytes(*prefix),
+            ByteOrderMode::Big => u32::from_be_bytes(*prefix),
+        };
+        if data_len > max_len {
+            return Err(ZerustError::MessageTooLarge {
+                size: data_len as u64,
+                limit: max_len as u64,
+            });
+        }
+        let frame_len = DataPack::checked_frame_len(Self::HEADER_SIZE, data_len)?;
+        if buf.len() < frame_len {
+            buf.reserve(frame_len - buf.len());
+            return Ok(None);
+        }
+        buf.advance(Self::HEADER_SIZE);
+        Ok(Some((
+            self.msg_id,
+            buf.split_to(data_len as usize).freeze(),
+        )))
+    }
+}
+
+/// 在 `DataPack` 的消息头后增加 4 字节序列号的帧格式：
+/// u32 消息ID + u32 数据长度 + u32 序列号 + 数据
+///
+/// 服务器会把请求的序列号带到它的响应上，客户端可以在同一个连接上同时发出多个请求，
+/// 再按序列号匹配乱序到达的响应，参见 `PipelineClient`。
+/// 没有对应请求的消息（例如主动推送的消息和心跳）的序列号为 0。
+///
+/// # 示例
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use zerust::codec::{PacketCodec, SeqDataPack};
+///
+/// let codec = SeqDataPack::new();
+/// let mut buf = Vec::new();
+/// codec.encode_seq_into(1, Some(42), b"hi", &mut buf).unwrap();
+/// assert_eq!(buf.len(), SeqDataPack::HEADER_SIZE + 2);
+///
+/// let mut buf = BytesMut::from(&buf[..]);
+/// let (msg_id, seq, data) = codec.decode_seq(&mut buf, 1024).unwrap().unwrap();
+/// assert_eq!((msg_id, seq, &data[..]), (1, Some(42), &b"hi"[..]));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SeqDataPack {
+    /// 消息头使用的字节序
+    order: ByteOrderMode,
+}
+
+impl SeqDataPack {
+    /// 消息头的长度（字节）
+    pub const HEADER_SIZE: usize = 12;
+
+    /// 创建一个使用小端序的编解码工具
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置消息头使用的字节序
+    ///
+    /// # 参数
+    /// * `order` - 字节序
+    pub fn with_order(mut self, order: ByteOrderMode) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// 获取消息头使用的字节序
+    pub fn order(&self) -> ByteOrderMode {
+        self.order
+    }
+
+    /// 按配置的字节序编码一个 u32
+    fn put_u32(&self, value: u32) -> [u8; 4] {
+        match self.order {
+            ByteOrderMode::Little => value.to_le_bytes(),
+            ByteOrderMode::Big => value.to_be_bytes(),
+        }
+    }
+
+    /// 按配置的字节序解码一个 u32
+    fn get_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes.try_into().expect("u32 field is 4 bytes");
+        match self.order {
+            ByteOrderMode::Little => u32::from_le_bytes(bytes),
+            ByteOrderMode::Big => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+impl PacketCodec for SeqDataPack {
+    fn encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        let mut buf = Vec::new();
+        self.encode_seq_into(msg_id, None, data, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn encode_into(&self, msg_id: u32, data: &[u8], buf: &mut Vec<u8>) -> Result<(), ZerustError> {
+        self.encode_seq_into(msg_id, None, data, buf)
+    }
+
+    fn decode(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Bytes)>, ZerustError> {
+        Ok(self
+            .decode_seq(buf, max_len)?
+            .map(|(msg_id, _, data)| (msg_id, data)))
+    }
+
+    fn encode_seq_into(
+        &self,
+        msg_id: u32,
+        seq: Option<u32>,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), ZerustError> {
+        let data_len = u32::try_from(data.len()).map_err(|_| ZerustError::MessageTooLarge {
+            size: data.len() as u64,
+            limit: u32::MAX as u64,
+        })?;
+        buf.reserve(Self::HEADER_SIZE + data.len());
+        buf.extend_from_slice(&self.put_u32(msg_id));
+        buf.extend_from_slice(&self.put_u32(data_len));
+        buf.extend_from_slice(&self.put_u32(seq.unwrap_or(0)));
+        buf.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn decode_seq(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Option<u32>, Bytes)>, ZerustError> {
+        let Some(header) = buf.first_chunk::<{ Self::HEADER_SIZE }>() else {
+            return Ok(None);
+        };
+        let msg_id = self.get_u32(&header[..4]);
+        let data_len = self.get_u32(&header[4..8]);
+        let seq = self.get_u32(&header[8..]);
+        if data_len > max_len {
+            return Err(ZerustError::MessageTooLarge {
+                size: data_len as u64,
+                limit: max_len as u64,
+            });
+        }
+        let frame_len = DataPack::checked_frame_len(Self::HEADER_SIZE, data_len)?;
+        if buf.len() < frame_len {
+            buf.reserve(frame_len - buf.len());
+            return Ok(None);
+        }
+        buf.advance(Self::HEADER_SIZE);
+        Ok(Some((
+            msg_id,
+            Some(seq),
+            buf.split_to(data_len as usize).freeze(),
+        )))
+    }
+}
+
+/// 校验和不匹配时的处理策略，参见 `CheckedDataPack::with_mismatch_policy`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumPolicy {
+    /// 返回 `ZerustError::ChecksumMismatch`，服务器随后关闭该连接
+    #[default]
+    Close,
+    /// 丢弃损坏的帧，继续解析之后的帧
+    ///
+    /// 只有数据部分损坏时才能继续，消息头中的数据长度损坏会导致之后的帧都无法对齐。
+    /// 损坏的请求不会得到响应，客户端需要依靠请求超时发现它。
+    Skip,
+}
+
+/// 在 `DataPack` 的数据之后增加 4 字节 CRC32 校验和的帧格式：
+/// u32 消息ID + u32 数据长度 + 数据 + u32 校验和
+///
+/// 消息头中的数据长度不包含校验和。解析时校验和与数据不一致会按 `ChecksumPolicy`
+/// 处理，默认返回 `ZerustError::ChecksumMismatch`，服务器随后关闭该连接。
+/// 也可以通过 `Server::with_checksum` 开启；客户端需要使用同样的帧格式，
+/// 例如通过 `Client::connect_with_codec` 连接。不使用该帧格式时没有任何额外开销。
+///
+/// # 示例
+///
+/// ```rust
+/// use bytes::BytesMut;
+/// use zerust::codec::{CheckedDataPack, PacketCodec};
+/// use zerust::datapack::DataPack;
+///
+/// let codec = CheckedDataPack::new();
+/// let frame = codec.encode(1, b"hi").unwrap();
+/// assert_eq!(frame, DataPack::try_pack_checked(1, b"hi").unwrap());
+///
+/// let mut buf = BytesMut::from(&frame[..]);
+/// let (msg_id, data) = codec.decode(&mut buf, 1024).unwrap().unwrap();
+/// assert_eq!((msg_id, &data[..]), (1, &b"hi"[..]));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckedDataPack {
+    /// 消息头和校验和使用的字节序
+    order: ByteOrderMode,
+    /// 校验和不匹配时的处理策略
+    mismatch_policy: ChecksumPolicy,
+}
+
+impl CheckedDataPack {
+    /// 创建一个使用小端序的编解码工具
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置消息头和校验和使用的字节序
+    ///
+    /// # 参数
+    /// * `order` - 字节序
+    pub fn with_order(mut self, order: ByteOrderMode) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// 设置校验和不匹配时的处理策略
+    ///
+    /// # 参数
+    /// * `policy` - 校验和不匹配时的处理策略
+    pub fn with_mismatch_policy(mut self, policy: ChecksumPolicy) -> Self {
+        self.mismatch_policy = policy;
+        self
+    }
+
+    /// 获取消息头和校验和使用的字节序
+    pub fn order(&self) -> ByteOrderMode {
+        self.order
+    }
+
+    /// 获取校验和不匹配时的处理策略
+    pub fn mismatch_policy(&self) -> ChecksumPolicy {
+        self.mismatch_policy
+    }
+}
+
+impl PacketCodec for CheckedDataPack {
+    fn encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        let mut buf = Vec::new();
+        self.encode_into(msg_id, data, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn encode_into(&self, msg_id: u32, data: &[u8], buf: &mut Vec<u8>) -> Result<(), ZerustError> {
+        DataPack::with_order(self.order).encode_checked_into(msg_id, data, buf)
+    }
+
+    fn decode(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Bytes)>, ZerustError> {
+        let datapack = DataPack::with_order(self.order);
+        // 跳过损坏的帧后继续解析缓冲区中的下一个帧
+        loop {
+            if buf.len() < DataPack::HEADER_SIZE {
+                return Ok(None);
+            }
+            let (msg_id, data_len) =
+                datapack.decode_header_with_limit(&buf[..DataPack::HEADER_SIZE], max_len)?;
+            let frame_len = DataPack::checked_frame_len(
+                DataPack::HEADER_SIZE + DataPack::CHECKSUM_SIZE,
+                data_len,
+            )?;
+            let data_end = frame_len - DataPack::CHECKSUM_SIZE;
+            if buf.len() < frame_len {
+                buf.reserve(frame_len - buf.len());
+                return Ok(None);
+            }
+            let verified = datapack.verify_checksum(
+                &buf[DataPack::HEADER_SIZE..data_end],
+                &buf[data_end..frame_len],
+            );
+            match verified {
+                Ok(()) => {}
+                Err(_e) if self.mismatch_policy == ChecksumPolicy::Skip => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(msg_id, error = %_e, "skipping corrupted frame");
+                    buf.advance(frame_len);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            buf.advance(DataPack::HEADER_SIZE);
+            let data = buf.split_to(data_len as usize).freeze();
+            buf.advance(DataPack::CHECKSUM_SIZE);
+            return Ok(Some((msg_id, data)));
+        }
+    }
+}
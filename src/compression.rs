@@ -0,0 +1,271 @@
+//! # 消息压缩模块
+//!
+//! `CompressedCodec` 包装另一个编解码工具（默认为 `DataPack`），在每条消息的数据前增加
+//! 1 字节的压缩标记，数据长度达到阈值时使用 zstd 或 gzip 压缩数据。
+//! 是否压缩按消息分别决定：接收方根据标记解压，因此双方可以使用不同的阈值和压缩算法，
+//! 只需要都使用 `CompressedCodec`。
+//!
+//! 帧格式为内层编解码工具的帧，其数据部分为：u8 压缩标记 + 数据（可能被压缩）。
+//! 处理函数通过 `Request::data` 看到的始终是解压后的数据。
+//! 通过 `Response::new_compressed` 创建的响应不受阈值限制，总是尝试压缩。
+//!
+//! 需要开启 `compression` 功能。
+//!
+//! # 示例
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use zerust::compression::{CompressedCodec, CompressionAlgorithm, CompressionConfig};
+//! use zerust::{Client, DefaultRouter, Server};
+//!
+//! # async fn run() -> Result<(), zerust::ZerustError> {
+//! // 服务器压缩超过 1 KiB 的响应
+//! let server = Server::new("127.0.0.1:8999", Arc::new(DefaultRouter::new())).with_compression(1024);
+//!
+//! // 客户端使用 gzip 压缩超过 4 KiB 的请求，也能解压服务器发送的 zstd 数据
+//! let config = CompressionConfig::new(4096).with_algorithm(CompressionAlgorithm::Gzip);
+//! let codec = Arc::new(CompressedCodec::new(config));
+//! let mut client = Client::connect_with_codec("127.0.0.1:8999", codec).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::codec::PacketCodec;
+use crate::datapack::DataPack;
+use crate::error::ZerustError;
+use bytes::{Bytes, BytesMut};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+/// 数据未压缩的标记
+const FLAG_NONE: u8 = 0;
+/// 数据使用 gzip 压缩的标记
+const FLAG_GZIP: u8 = 1;
+/// 数据使用 zstd 压缩的标记
+const FLAG_ZSTD: u8 = 2;
+
+/// 压缩算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionAlgorithm {
+    /// gzip，兼容性好，压缩和解压较慢
+    Gzip,
+    /// zstd，压缩率和速度通常都优于 gzip
+    #[default]
+    Zstd,
+}
+
+impl CompressionAlgorithm {
+    /// 获取该算法在帧中的压缩标记
+    fn flag(self) -> u8 {
+        match self {
+            Self::Gzip => FLAG_GZIP,
+            Self::Zstd => FLAG_ZSTD,
+        }
+    }
+}
+
+/// 压缩配置
+///
+/// 只压缩数据长度不小于 `threshold` 字节的消息；压缩后没有变小的消息按原样发送。
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionConfig {
+    /// 压缩数据的最小长度（字节）
+    threshold: usize,
+    /// 压缩算法
+    algorithm: CompressionAlgorithm,
+}
+
+impl CompressionConfig {
+    /// 创建压缩配置，使用 zstd 压缩数据长度不小于 `threshold` 字节的消息
+    ///
+    /// # 参数
+    /// * `threshold` - 压缩数据的最小长度（字节）
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            algorithm: CompressionAlgorithm::default(),
+        }
+    }
+
+    /// 设置压缩算法
+    ///
+    /// # 参数
+    /// * `algorithm` - 压缩算法
+    pub fn with_algorithm(mut self, algorithm: CompressionAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// 获取压缩数据的最小长度（字节）
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// 获取压缩算法
+    pub fn algorithm(&self) -> CompressionAlgorithm {
+        self.algorithm
+    }
+}
+
+/// 按消息压缩数据的编解码工具，参见模块文档
+pub struct CompressedCodec {
+    /// 内层编解码工具，负责消息头和分帧
+    inner: Arc<dyn PacketCodec>,
+    /// 压缩配置
+    config: CompressionConfig,
+}
+
+impl CompressedCodec {
+    /// 创建以 `DataPack` 为内层编解码工具的压缩编解码工具
+    ///
+    /// # 参数
+    /// * `config` - 压缩配置
+    pub fn new(config: CompressionConfig) -> Self {
+        Self::wrap(Arc::new(DataPack::default()), config)
+    }
+
+    /// 包装给定的编解码工具
+    ///
+    /// # 参数
+    /// * `inner` - 内层编解码工具，例如 `SeqDataPack`
+    /// * `config` - 压缩配置
+    pub fn wrap(inner: Arc<dyn PacketCodec>, config: CompressionConfig) -> Self {
+        Self { inner, config }
+    }
+
+    /// 获取压缩配置
+    pub fn config(&self) -> &CompressionConfig {
+        &self.config
+    }
+
+    /// 在数据前加上压缩标记，数据达到阈值时压缩
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        self.compress_above(data, self.config.threshold)
+    }
+
+    /// 在数据前加上压缩标记，数据长度不小于 `threshold` 时压缩
+    fn compress_above(&self, data: &[u8], threshold: usize) -> Result<Vec<u8>, ZerustError> {
+        if data.len() >= threshold {
+            let mut out = vec![self.config.algorithm.flag()];
+            match self.config.algorithm {
+                CompressionAlgorithm::Gzip => {
+                    let mut encoder = GzEncoder::new(out, flate2::Compression::default());
+                    encoder.write_all(data)?;
+                    out = encoder.finish()?;
+                }
+                CompressionAlgorithm::Zstd => {
+                    zstd::stream::copy_encode(data, &mut out, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+                }
+            }
+            // 压缩后没有变小（例如已经压缩过的图片）时按原样发送
+            if out.len() <= data.len() {
+                return Ok(out);
+            }
+        }
+        let mut out = Vec::with_capacity(1 + data.len());
+        out.push(FLAG_NONE);
+        out.extend_from_slice(data);
+        Ok(out)
+    }
+
+    /// 根据压缩标记取出数据，解压后的长度超过 `max_len` 时返回错误
+    fn decompress(payload: Bytes, max_len: u32) -> Result<Bytes, ZerustError> {
+        let Some(&flag) = payload.first() else {
+            return Err(ZerustError::ProtocolError(
+                "missing compression flag".to_string(),
+            ));
+        };
+        let compressed = &payload[1..];
+        let decoder: Box<dyn Read + '_> = match flag {
+            // 未压缩的数据与接收缓冲区共享内存，不复制数据
+            FLAG_NONE => return Ok(payload.slice(1..)),
+            FLAG_GZIP => Box::new(GzDecoder::new(compressed)),
+            FLAG_ZSTD => Box::new(zstd::stream::read::Decoder::new(compressed)?),
+            flag => {
+                return Err(ZerustError::ProtocolError(format!(
+                    "unknown compression flag {flag}"
+                )));
+            }
+        };
+        // 最多读取 max_len + 1 字节，防止很小的压缩数据解压出超大的消息；
+        // 数据来自内存，读取失败只可能是压缩数据损坏
+        let mut data = Vec::new();
+        decoder
+            .take(u64::from(max_len) + 1)
+            .read_to_end(&mut data)
+            .map_err(|e| ZerustError::ProtocolError(format!("invalid compressed data: {e}")))?;
+        if data.len() > max_len as usize {
+            return Err(ZerustError::MessageTooLarge {
+                size: data.len() as u64,
+                limit: max_len as u64,
+            });
+        }
+        Ok(Bytes::from(data))
+    }
+}
+
+impl PacketCodec for CompressedCodec {
+    fn encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        self.inner.encode(msg_id, &self.compress(data)?)
+    }
+
+    fn encode_into(&self, msg_id: u32, data: &[u8], buf: &mut Vec<u8>) -> Result<(), ZerustError> {
+        self.inner.encode_into(msg_id, &self.compress(data)?, buf)
+    }
+
+    fn decode(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Bytes)>, ZerustError> {
+        Ok(self
+            .decode_seq(buf, max_len)?
+            .map(|(msg_id, _, data)| (msg_id, data)))
+    }
+
+    fn encode_seq_into(
+        &self,
+        msg_id: u32,
+        seq: Option<u32>,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), ZerustError> {
+        self.inner
+            .encode_seq_into(msg_id, seq, &self.compress(data)?, buf)
+    }
+
+    fn encode_compressed_into(
+        &self,
+        msg_id: u32,
+        seq: Option<u32>,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), ZerustError> {
+        self.inner
+            .encode_seq_into(msg_id, seq, &self.compress_above(data, 0)?, buf)
+    }
+
+    fn decode_seq(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Option<u32>, Bytes)>, ZerustError> {
+        // 压缩标记占用 1 字节，压缩后的数据不会超过原始数据的长度
+        let frame = self
+            .inner
+            .decode_seq(buf, max_len.saturating_add(1))
+            .map_err(|e| match e {
+                ZerustError::MessageTooLarge { size, .. } => ZerustError::MessageTooLarge {
+                    size: size.saturating_sub(1),
+                    limit: max_len as u64,
+                },
+                e => e,
+            })?;
+        let Some((msg_id, seq, payload)) = frame else {
+            return Ok(None);
+        };
+        Ok(Some((msg_id, seq, Self::decompress(payload, max_len)?)))
+    }
+}
@@ -0,0 +1,650 @@
+//! # 服务器配置模块
+//!
+//! 该模块集中定义了服务器的各项配置，以及用于创建服务器的构建器。
+//!
+//! * `ServerConfig` - 服务器配置，可以通过 `Server::config` 查看正在使用的配置
+//! * `ServerBuilder` - 以链式调用的方式设置各项配置，最后通过 `build` 创建服务器
+//! * `TomlOptions` - 开启 `toml` 功能后，通过 `ServerConfig::from_toml_file` 从 TOML 文件加载配置，
+//!   配置项、环境变量和校验规则参见 `ServerConfig::from_toml_file`
+//!
+//! 新增的配置项只需要在这里添加字段和对应的构建方法，不会破坏已有的调用代码。
+
+use crate::codec::{CheckedDataPack, PacketCodec};
+#[cfg(feature = "compression")]
+use crate::compression::{CompressedCodec, CompressionConfig};
+use crate::conn_manager::{DEFAULT_WRITE_QUEUE_CAPACITY, WriteQueuePolicy};
+use crate::connection::DEFAULT_READ_BUFFER_SIZE;
+#[cfg(feature = "prometheus")]
+use crate::control;
+use crate::datapack::{DEFAULT_MAX_PACKET_SIZE, DataPack};
+use crate::error::ZerustError;
+use crate::rate_limit::RateLimitConfig;
+use crate::response::Response;
+use crate::router::{DefaultRouter, Router};
+use crate::server::{AcceptErrorPolicy, ConnLimitPolicy, HeartbeatConfig, Server, ShutdownMode};
+use crate::worker_pool::WorkerPoolConfig;
+use std::fmt;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "tls")]
+use tokio_rustls::rustls;
+
+#[cfg(feature = "toml")]
+pub use crate::toml_config::{DEFAULT_ENV_PREFIX, TomlOptions};
+
+/// 默认的监听地址，与 Zinx 的默认配置一致
+pub const DEFAULT_ADDR: &str = "0.0.0.0:8999";
+
+/// 服务器配置
+///
+/// 包含监听地址以及应用到每个连接的各项限制和选项。
+/// 通过 `ServerBuilder` 设置，创建服务器后可以通过 `Server::config` 查看。
+#[derive(Clone)]
+pub struct ServerConfig {
+    /// 服务器监听的地址，格式为 "IP:端口"
+    pub(crate) addr: String,
+    /// Unix 域套接字的路径，设置后监听该路径而不是 `addr`
+    #[cfg(unix)]
+    pub(crate) unix_path: Option<PathBuf>,
+    /// 每个连接读取一个完整请求的超时时间，`None` 表示不限制
+    pub(crate) read_timeout: Option<Duration>,
+    /// 每个连接写入一条完整消息的超时时间，`None` 表示不限制
+    pub(crate) write_timeout: Option<Duration>,
+    /// 连接没有收到请求多久后被关闭，`None` 表示不限制
+    pub(crate) idle_timeout: Option<Duration>,
+    /// 是否为每个连接启用 `TCP_NODELAY`
+    pub(crate) nodelay: bool,
+    /// 每个连接的 TCP keepalive 空闲时间，`None` 表示沿用操作系统的设置
+    pub(crate) keepalive: Option<Duration>,
+    /// 每个连接的 `SO_LINGER` 时间，`None` 表示沿用操作系统的设置
+    pub(crate) linger: Option<Duration>,
+    /// 每个连接允许接收的最大消息体长度
+    pub(crate) max_packet_size: u32,
+    /// 收到过大的消息时，关闭连接前发送的响应，`None` 表示直接关闭
+    pub(crate) oversized_response: Option<Response>,
+    /// 每个连接的接收缓冲区每次扩容的字节数
+    pub(crate) read_buffer_size: usize,
+    /// 每个连接的发送队列最多容纳的消息数量
+    pub(crate) write_queue_capacity: usize,
+    /// 发送队列已满时的处理策略
+    pub(crate) write_queue_policy: WriteQueuePolicy,
+    /// 帧编解码工具，所有连接共享同一个实例
+    pub(crate) codec: Arc<dyn PacketCodec>,
+    /// 同时在线的最大连接数，`None` 表示不限制
+    pub(crate) max_connections: Option<usize>,
+    /// 在线连接数达到上限时的处理策略
+    pub(crate) conn_limit_policy: ConnLimitPolicy,
+    /// 收到关闭信号后处理在线连接的方式
+    pub(crate) shutdown_mode: ShutdownMode,
+    /// 接受连接失败时的处理策略
+    pub(crate) accept_error_policy: AcceptErrorPolicy,
+    /// 心跳配置，`None` 表示不开启心跳
+    pub(crate) heartbeat: Option<HeartbeatConfig>,
+    /// 工作池配置，`None` 表示在连接任务中直接处理请求
+    pub(crate) worker_pool: Option<WorkerPoolConfig>,
+    /// 每个连接的限流配置，`None` 表示不限流
+    pub(crate) rate_limit: Option<RateLimitConfig>,
+    /// 协议握手中服务器支持的最高协议版本，`None` 表示不进行握手
+    pub(crate) protocol_version: Option<u8>,
+    /// 返回 Prometheus 文本格式指标的消息ID，`None` 表示不开启
+    #[cfg(feature = "prometheus")]
+    pub(crate) metrics_route: Option<u32>,
+    /// TLS 配置，`None` 表示使用明文的 TCP 连接
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Option<Arc<rustls::ServerConfig>>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: DEFAULT_ADDR.to_string(),
+            #[cfg(unix)]
+            unix_path: None,
+            read_timeout: None,
+            write_timeout: None,
+            idle_timeout: None,
+            nodelay: true,
+            keepalive: None,
+            linger: None,
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            oversized_response: Some(Response::payload_too_large()),
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            write_queue_capacity: DEFAULT_WRITE_QUEUE_CAPACITY,
+            write_queue_policy: WriteQueuePolicy::default(),
+            codec: Arc::new(DataPack::default()),
+            max_connections: None,
+            conn_limit_policy: ConnLimitPolicy::default(),
+            shutdown_mode: ShutdownMode::default(),
+            accept_error_policy: AcceptErrorPolicy::default(),
+            heartbeat: None,
+            worker_pool: None,
+            rate_limit: None,
+            protocol_version: None,
+            #[cfg(feature = "prometheus")]
+            metrics_route: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 编解码工具是 trait 对象，不输出其内容
+        let mut debug = f.debug_struct("ServerConfig");
+        debug
+            .field("addr", &self.addr)
+            .field("read_timeout", &self.read_timeout)
+            .field("write_timeout", &self.write_timeout)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("nodelay", &self.nodelay)
+            .field("keepalive", &self.keepalive)
+            .field("linger", &self.linger)
+            .field("max_packet_size", &self.max_packet_size)
+            .field("oversized_response", &self.oversized_response)
+            .field("read_buffer_size", &self.read_buffer_size)
+            .field("write_queue_capacity", &self.write_queue_capacity)
+            .field("write_queue_policy", &self.write_queue_policy)
+            .field("max_connections", &self.max_connections)
+            .field("conn_limit_policy", &self.conn_limit_policy)
+            .field("shutdown_mode", &self.shutdown_mode)
+            .field("accept_error_policy", &self.accept_error_policy)
+            .field("heartbeat", &self.heartbeat)
+            .field("worker_pool", &self.worker_pool)
+            .field("rate_limit", &self.rate_limit)
+            .field("protocol_version", &self.protocol_version);
+        #[cfg(unix)]
+        debug.field("unix_path", &self.unix_path);
+        #[cfg(feature = "prometheus")]
+        debug.field("metrics_route", &self.metrics_route);
+        #[cfg(feature = "tls")]
+        debug.field("tls", &self.tls);
+        debug.finish_non_exhaustive()
+    }
+}
+
+impl ServerConfig {
+    /// 获取服务器监听的地址
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// 获取 Unix 域套接字的路径，`None` 表示监听 TCP 地址
+    #[cfg(unix)]
+    pub fn unix_path(&self) -> Option<&Path> {
+        self.unix_path.as_deref()
+    }
+
+    /// 获取每个连接读取一个完整请求的超时时间，`None` 表示不限制
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// 获取每个连接写入一条完整消息的超时时间，`None` 表示不限制
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// 获取连接没有收到请求多久后被关闭，`None` 表示不限制
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// 获取是否为每个连接启用 `TCP_NODELAY`
+    pub fn nodelay(&self) -> bool {
+        self.nodelay
+    }
+
+    /// 获取每个连接的 TCP keepalive 空闲时间，`None` 表示沿用操作系统的设置
+    pub fn keepalive(&self) -> Option<Duration> {
+        self.keepalive
+    }
+
+    /// 获取每个连接的 `SO_LINGER` 时间，`None` 表示沿用操作系统的设置
+    pub fn linger(&self) -> Option<Duration> {
+        self.linger
+    }
+
+    /// 获取每个连接允许接收的最大消息体长度
+    pub fn max_packet_size(&self) -> u32 {
+        self.max_packet_size
+    }
+
+    /// 获取收到过大的消息时，关闭连接前发送的响应，`None` 表示直接关闭
+    pub fn oversized_response(&self) -> Option<&Response> {
+        self.oversized_response.as_ref()
+    }
+
+    /// 获取每个连接的接收缓冲区每次扩容的字节数
+    pub fn read_buffer_size(&self) -> usize {
+        self.read_buffer_size
+    }
+
+    /// 获取连接使用的帧编解码工具
+    pub fn codec(&self) -> &Arc<dyn PacketCodec> {
+        &self.codec
+    }
+
+    /// 获取每个连接的发送队列最多容纳的消息数量
+    pub fn write_queue_capacity(&self) -> usize {
+        self.write_queue_capacity
+    }
+
+    /// 获取发送队列已满时的处理策略
+    pub fn write_queue_policy(&self) -> WriteQueuePolicy {
+        self.write_queue_policy
+    }
+
+    /// 获取同时在线的最大连接数，`None` 表示不限制
+    pub fn max_connections(&self) -> Option<usize> {
+        self.max_connections
+    }
+
+    /// 获取在线连接数达到上限时的处理策略
+    pub fn conn_limit_policy(&self) -> &ConnLimitPolicy {
+        &self.conn_limit_policy
+    }
+
+    /// 获取收到关闭信号后处理在线连接的方式
+    pub fn shutdown_mode(&self) -> ShutdownMode {
+        self.shutdown_mode
+    }
+
+    /// 获取接受连接失败时的处理策略
+    pub fn accept_error_policy(&self) -> AcceptErrorPolicy {
+        self.accept_error_policy
+    }
+
+    /// 获取心跳配置，`None` 表示不开启心跳
+    pub fn heartbeat(&self) -> Option<&HeartbeatConfig> {
+        self.heartbeat.as_ref()
+    }
+
+    /// 获取工作池配置，`None` 表示在连接任务中直接处理请求
+    pub fn worker_pool(&self) -> Option<&WorkerPoolConfig> {
+        self.worker_pool.as_ref()
+    }
+
+    /// 获取每个连接的限流配置，`None` 表示不限流
+    pub fn rate_limit(&self) -> Option<&RateLimitConfig> {
+        self.rate_limit.as_ref()
+    }
+
+    /// 获取协议握手中服务器支持的最高协议版本，`None` 表示不进行握手
+    pub fn protocol_version(&self) -> Option<u8> {
+        self.protocol_version
+    }
+
+    /// 获取返回 Prometheus 文本格式指标的消息ID，`None` 表示不开启
+    #[cfg(feature = "prometheus")]
+    pub fn metrics_route(&self) -> Option<u32> {
+        self.metrics_route
+    }
+
+    /// 获取 TLS 配置，`None` 表示使用明文的 TCP 连接
+    #[cfg(feature = "tls")]
+    pub fn tls(&self) -> Option<&Arc<rustls::ServerConfig>> {
+        self.tls.as_ref()
+    }
+
+    /// 检查配置中的取值是否有效
+    ///
+    /// `ServerBuilder::try_build`、`Server::run` 和 `Server::bind` 会调用该方法。
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 配置有效
+    /// * `Err(ZerustError::InvalidConfig)` - 配置无效，错误信息说明了无效的配置项
+    pub fn validate(&self) -> Result<(), ZerustError> {
+        let invalid = |msg: &str| Err(ZerustError::InvalidConfig(msg.to_string()));
+        if self.max_connections == Some(0) {
+            return invalid("max_connections must be greater than 0");
+        }
+        if self.max_packet_size == 0 {
+            return invalid("max_packet_size must be greater than 0");
+        }
+        if self.read_buffer_size == 0 {
+            return invalid("read_buffer_size must be greater than 0");
+        }
+        if self.write_queue_capacity == 0 {
+            return invalid("write_queue_capacity must be greater than 0");
+        }
+        if self.read_timeout == Some(Duration::ZERO) {
+            return invalid("read_timeout must be greater than 0");
+        }
+        if self.write_timeout == Some(Duration::ZERO) {
+            return invalid("write_timeout must be greater than 0");
+        }
+        if self.idle_timeout == Some(Duration::ZERO) {
+            return invalid("idle_timeout must be greater than 0");
+        }
+        if let Some(heartbeat) = &self.heartbeat {
+            if heartbeat.interval().is_zero() {
+                return invalid("heartbeat interval must be greater than 0");
+            }
+            // 为 0 时连接在第一次心跳之前就会被关闭
+            if heartbeat.max_missed() == 0 {
+                return invalid("heartbeat max_missed must be greater than 0");
+            }
+        }
+        if let Some(pool) = &self.worker_pool {
+            if pool.size() == 0 {
+                return invalid("worker_pool size must be greater than 0");
+            }
+            if pool.max_task_queue_len() == 0 {
+                return invalid("worker_pool max_task_queue_len must be greater than 0");
+            }
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            if rate_limit.max_per_sec() == 0 {
+                return invalid("rate_limit max_per_sec must be greater than 0");
+            }
+            if rate_limit.burst() == 0 {
+                return invalid("rate_limit burst must be greater than 0");
+            }
+        }
+        #[cfg(feature = "prometheus")]
+        if let (Some(heartbeat), Some(msg_id)) = (&self.heartbeat, self.metrics_route)
+            && heartbeat.msg_id() == msg_id
+        {
+            return invalid("metrics_route must differ from the heartbeat msg_id");
+        }
+        #[cfg(feature = "prometheus")]
+        if self.metrics_route.is_some_and(control::is_reserved) {
+            return invalid("metrics_route must not be a reserved control msg_id");
+        }
+        Ok(())
+    }
+}
+
+/// 服务器构建器
+///
+/// 以链式调用的方式设置服务器配置，最后调用 `build` 创建服务器。
+/// 未设置的配置项使用默认值：监听 `DEFAULT_ADDR`，使用空的 `DefaultRouter`，
+/// 不限制超时和连接数。
+///
+/// 错误响应生成函数和连接生命周期钩子可以在创建服务器之后通过
+/// `Server::with_error_handler` 等方法设置。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use zerust::{DefaultRouter, Server};
+///
+/// let server = Server::builder()
+///     .addr("127.0.0.1:0")
+///     .router(Arc::new(DefaultRouter::new()))
+///     .read_timeout(Duration::from_secs(30))
+///     .max_connections(1000)
+///     .nodelay(true)
+///     .build();
+/// assert_eq!(server.config().max_connections(), Some(1000));
+/// ```
+#[derive(Default)]
+pub struct ServerBuilder {
+    /// 正在构建的服务器配置
+    pub(crate) config: ServerConfig,
+    /// 路由器实例，未设置时使用空的 `DefaultRouter`
+    router: Option<Arc<dyn Router + Send + Sync>>,
+}
+
+impl ServerBuilder {
+    /// 创建一个使用默认配置的构建器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 以已有的配置为基础创建构建器
+    ///
+    /// 通常与 `ServerConfig::from_toml_file` 搭配使用，加载配置文件后再设置路由器等无法写在文件中的部分。
+    ///
+    /// # 参数
+    /// * `config` - 服务器配置
+    pub fn from_config(config: ServerConfig) -> Self {
+        Self {
+            config,
+            router: None,
+        }
+    }
+
+    /// 设置服务器监听的地址，格式为 "IP:端口"
+    pub fn addr(mut self, addr: &str) -> Self {
+        self.config.addr = addr.to_string();
+        self
+    }
+
+    /// 监听 Unix 域套接字而不是 TCP 地址，参见 `Server::new_unix`
+    #[cfg(unix)]
+    pub fn unix_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.config.unix_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// 设置用于分发请求的路由器
+    pub fn router(mut self, router: Arc<dyn Router + Send + Sync>) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    /// 设置所有连接读取请求的超时时间，参见 `Server::with_read_timeout`
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.read_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置所有连接写入消息的超时时间，参见 `Server::with_write_timeout`
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.config.write_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置连接没有收到请求多久后被关闭，参见 `Server::with_idle_timeout`
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置是否为所有连接启用 `TCP_NODELAY`，参见 `Server::with_nodelay`
+    #[doc(alias = "tcp_nodelay")]
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.config.nodelay = nodelay;
+        self
+    }
+
+    /// 为所有连接开启 TCP keepalive，参见 `Server::with_keepalive`
+    #[doc(alias = "tcp_keepalive")]
+    pub fn keepalive(mut self, time: Duration) -> Self {
+        self.config.keepalive = Some(time);
+        self
+    }
+
+    /// 设置所有连接的 `SO_LINGER` 时间，参见 `Server::with_linger`
+    pub fn linger(mut self, linger: Duration) -> Self {
+        self.config.linger = Some(linger);
+        self
+    }
+
+    /// 设置所有连接允许接收的最大消息体长度，参见 `Server::with_max_packet_size`
+    pub fn max_packet_size(mut self, max_packet_size: u32) -> Self {
+        self.config.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// 设置收到过大的消息时，关闭连接前发送的响应，参见 `Server::with_oversized_response`
+    pub fn oversized_response(mut self, response: Option<Response>) -> Self {
+        self.config.oversized_response = response;
+        self
+    }
+
+    /// 设置每个连接的接收缓冲区每次扩容的字节数，参见 `Server::with_read_buffer_size`
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.config.read_buffer_size = read_buffer_size;
+        self
+    }
+
+    /// 设置所有连接使用的消息编解码工具，参见 `Server::with_datapack`
+    pub fn datapack(mut self, datapack: DataPack) -> Self {
+        self.config.codec = Arc::new(datapack);
+        self
+    }
+
+    /// 设置是否在每个帧的数据之后附加 CRC32 校验和，参见 `Server::with_checksum`
+    pub fn checksum(mut self, enabled: bool) -> Self {
+        self.config.codec = if enabled {
+            Arc::new(CheckedDataPack::default())
+        } else {
+            Arc::new(DataPack::default())
+        };
+        self
+    }
+
+    /// 设置所有连接使用的帧编解码工具，参见 `Server::with_codec`
+    pub fn codec(mut self, codec: Arc<dyn PacketCodec>) -> Self {
+        self.config.codec = codec;
+        self
+    }
+
+    /// 设置每个连接的发送队列最多容纳的消息数量，参见 `Server::with_write_queue_capacity`
+    pub fn write_queue_capacity(mut self, capacity: usize) -> Self {
+        self.config.write_queue_capacity = capacity;
+        self
+    }
+
+    /// 设置发送队列已满时的处理策略，参见 `Server::with_write_queue_policy`
+    pub fn write_queue_policy(mut self, policy: WriteQueuePolicy) -> Self {
+        self.config.write_queue_policy = policy;
+        self
+    }
+
+    /// 设置同时在线的最大连接数，参见 `Server::with_max_connections`
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.config.max_connections = Some(max_connections);
+        self
+    }
+
+    /// 设置在线连接数达到上限时的处理策略，参见 `Server::with_conn_limit_policy`
+    pub fn conn_limit_policy(mut self, policy: ConnLimitPolicy) -> Self {
+        self.config.conn_limit_policy = policy;
+        self
+    }
+
+    /// 设置收到关闭信号后处理在线连接的方式，参见 `Server::with_shutdown_mode`
+    pub fn shutdown_mode(mut self, mode: ShutdownMode) -> Self {
+        self.config.shutdown_mode = mode;
+        self
+    }
+
+    /// 设置接受连接失败时的处理策略，参见 `Server::with_accept_error_policy`
+    pub fn accept_error_policy(mut self, policy: AcceptErrorPolicy) -> Self {
+        self.config.accept_error_policy = policy;
+        self
+    }
+
+    /// 开启心跳检测，参见 `Server::with_heartbeat`
+    pub fn heartbeat(mut self, interval: Duration, max_missed: u32) -> Self {
+        self.config.heartbeat = Some(HeartbeatConfig::new(interval, max_missed));
+        self
+    }
+
+    /// 使用给定的配置开启心跳检测，参见 `Server::with_heartbeat_config`
+    pub fn heartbeat_config(mut self, config: HeartbeatConfig) -> Self {
+        self.config.heartbeat = Some(config);
+        self
+    }
+
+    /// 开启工作池，参见 `Server::with_worker_pool`
+    pub fn worker_pool(mut self, size: usize, max_task_queue_len: usize) -> Self {
+        self.config.worker_pool = Some(WorkerPoolConfig::new(size, max_task_queue_len));
+        self
+    }
+
+    /// 使用给定的配置开启工作池，参见 `Server::with_worker_pool_config`
+    pub fn worker_pool_config(mut self, config: WorkerPoolConfig) -> Self {
+        self.config.worker_pool = Some(config);
+        self
+    }
+
+    /// 开启每个连接的限流，参见 `Server::with_rate_limit`
+    pub fn rate_limit(mut self, max_per_sec: u32) -> Self {
+        self.config.rate_limit = Some(RateLimitConfig::new(max_per_sec));
+        self
+    }
+
+    /// 使用给定的配置开启每个连接的限流，参见 `Server::with_rate_limit_config`
+    pub fn rate_limit_config(mut self, config: RateLimitConfig) -> Self {
+        self.config.rate_limit = Some(config);
+        self
+    }
+
+    /// 压缩数据长度不小于 `threshold` 字节的消息，参见 `Server::with_compression`
+    #[cfg(feature = "compression")]
+    pub fn compression(self, threshold: usize) -> Self {
+        self.compression_config(CompressionConfig::new(threshold))
+    }
+
+    /// 使用给定的配置压缩消息，参见 `Server::with_compression_config`
+    #[cfg(feature = "compression")]
+    pub fn compression_config(mut self, config: CompressionConfig) -> Self {
+        self.config.codec = Arc::new(CompressedCodec::wrap(self.config.codec, config));
+        self
+    }
+
+    /// 开启协议握手，参见 `Server::with_protocol_version`
+    pub fn protocol_version(mut self, version: u8) -> Self {
+        self.config.protocol_version = Some(version);
+        self
+    }
+
+    /// 使用该消息ID返回 Prometheus 文本格式的指标，参见 `Server::with_metrics_route`
+    #[cfg(feature = "prometheus")]
+    pub fn metrics_route(mut self, msg_id: u32) -> Self {
+        self.config.metrics_route = Some(msg_id);
+        self
+    }
+
+    /// 使用 TLS 加密连接，参见 `Server::with_tls`
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, config: Arc<rustls::ServerConfig>) -> Self {
+        self.config.tls = Some(config);
+        self
+    }
+
+    /// 使用当前配置创建服务器
+    ///
+    /// # 返回值
+    /// 返回一个新的 `Server` 实例
+    ///
+    /// # Panics
+    /// 配置无效时会 panic，参见 `ServerConfig::validate`；需要处理错误时使用 `try_build`
+    pub fn build(self) -> Server {
+        match self.try_build() {
+            Ok(server) => server,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// 检查配置后创建服务器
+    ///
+    /// # 返回值
+    /// * `Ok(Server)` - 新的 `Server` 实例
+    /// * `Err(ZerustError::InvalidConfig)` - 配置无效，参见 `ServerConfig::validate`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{Server, ZerustError};
+    ///
+    /// let result = Server::builder().max_connections(0).try_build();
+    /// assert!(matches!(result, Err(ZerustError::InvalidConfig(_))));
+    /// ```
+    pub fn try_build(self) -> Result<Server, ZerustError> {
+        self.config.validate()?;
+        let router = self
+            .router
+            .unwrap_or_else(|| Arc::new(DefaultRouter::new()));
+        Ok(Server::from_config(self.config, router))
+    }
+}
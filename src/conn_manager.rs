@@ -0,0 +1,456 @@
+//! # 连接管理模块
+//!
+//! 该模块提供了服务器范围内的连接注册表。服务器为每个接受的连接分配一个
+//! 单调递增的连接ID，并在连接存活期间把它的句柄登记在 `ConnManager` 中，
+//! 连接结束时自动移除。
+//!
+//! 通过连接句柄，程序的其他部分（例如某个连接的处理函数）可以向任意
+//! 在线连接主动推送消息，从而实现聊天室、通知推送等场景。
+//!
+//! 广播时所有连接共享同一份消息数据（`Response` 内部的 `Bytes`），不会为每个连接复制一份。
+//!
+//! 每个连接的发送队列都有容量上限（参见 `Server::with_write_queue_capacity`），
+//! 不读取数据的客户端不会让服务器为它无限地缓存消息。队列已满时，不等待的发送方法
+//! 按 `WriteQueuePolicy` 处理这条消息，`ConnectionHandle::send_wait` 则等待队列出现空位。
+//!
+//! 句柄还记录连接最后一次收到请求或处理完请求的时间，开启 `Server::with_idle_timeout` 时，
+//! 服务器据此关闭长时间没有发送请求的连接；有请求正在处理的连接不会被关闭。
+
+use crate::context::ConnContext;
+use crate::error::ZerustError;
+use crate::response::Response;
+use crate::runtime::mpsc::error::TrySendError;
+use crate::runtime::{Instant, Notify, mpsc};
+use dashmap::DashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 每个连接的发送队列默认最多容纳的消息数量
+pub const DEFAULT_WRITE_QUEUE_CAPACITY: usize = 1024;
+
+/// 连接的发送队列已满时的处理策略
+///
+/// 只作用于不等待的发送方法：`ConnectionHandle::send`、`ConnManager::send_to` 和各个广播方法，
+/// 处理函数在其他任务中推送消息时通常使用它们。需要等待队列出现空位的发送方使用
+/// `ConnectionHandle::send_wait` 或 `ConnManager::send_to_wait`。
+///
+/// 通过 `Server::with_write_queue_policy` 设置。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WriteQueuePolicy {
+    /// 丢弃这条消息，连接继续正常工作
+    #[default]
+    Drop,
+    /// 丢弃这条消息并断开该连接
+    ///
+    /// 适合不能容忍消息丢失的场景，客户端重新连接后重新同步状态。
+    /// 连接以 `ZerustError::WriteQueueFull` 结束，会触发连接错误钩子。
+    Disconnect,
+}
+
+/// 在线连接的句柄
+///
+/// 句柄可以被廉价地克隆，并在任意任务中使用。通过句柄发送的响应会进入
+/// 连接的发送队列，由连接任务按顺序写入网络流。
+#[derive(Debug, Clone)]
+pub struct ConnectionHandle {
+    /// 连接的上下文信息
+    context: ConnContext,
+    /// 连接发送队列的发送端
+    sender: mpsc::Sender<Response>,
+    /// 发送队列已满时的处理策略
+    policy: WriteQueuePolicy,
+    /// 按 `WriteQueuePolicy::Disconnect` 需要断开连接时通知连接任务
+    overflow: Arc<Notify>,
+    /// 最后一次收到请求或处理完请求的时间，连接建立时为建立的时间
+    last_active: Arc<Mutex<Instant>>,
+    /// 正在处理的请求数量，不为 0 时连接不算空闲
+    in_flight: Arc<AtomicUsize>,
+    /// 空闲时间超过限制、需要关闭连接时通知连接任务
+    idle: Arc<Notify>,
+}
+
+impl ConnectionHandle {
+    /// 创建一个新的连接句柄
+    pub(crate) fn new(
+        context: ConnContext,
+        sender: mpsc::Sender<Response>,
+        policy: WriteQueuePolicy,
+    ) -> Self {
+        Self {
+            context,
+            sender,
+            policy,
+            overflow: Arc::new(Notify::new()),
+            last_active: Arc::new(Mutex::new(Instant::now())),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            idle: Arc::new(Notify::new()),
+        }
+    }
+
+    /// 等待发送队列溢出且策略要求断开连接，由连接任务调用
+    pub(crate) async fn overflowed(&self) {
+        self.overflow.notified().await;
+    }
+
+    /// 等待连接因空闲过久被关闭，由连接任务调用
+    pub(crate) async fn idle_expired(&self) {
+        self.idle.notified().await;
+    }
+
+    /// 记录连接开始处理一个请求，由连接任务在每次成功读取请求后调用
+    ///
+    /// 返回的守卫随请求一起保存，响应进入发送队列后释放，释放时再次刷新最后活动时间。
+    /// 守卫释放之前连接不会因空闲被关闭，处理时间超过空闲超时的请求不会被中断。
+    pub(crate) fn begin_request(&self) -> RequestGuard {
+        *self.last_active.lock().unwrap() = Instant::now();
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        RequestGuard {
+            last_active: self.last_active.clone(),
+            in_flight: self.in_flight.clone(),
+        }
+    }
+
+    /// 获取连接最后一次收到请求或处理完请求的时间，尚未收到请求时为连接建立的时间
+    pub fn last_active(&self) -> Instant {
+        *self.last_active.lock().unwrap()
+    }
+
+    /// 获取连接已经空闲（没有收到或处理请求）的时间
+    ///
+    /// 有请求正在处理时，返回的是从收到该请求开始经过的时间，但连接不会因此被关闭。
+    pub fn idle_time(&self) -> Duration {
+        self.last_active().elapsed()
+    }
+
+    /// 判断连接是否有请求正在处理
+    fn is_busy(&self) -> bool {
+        self.in_flight.load(Ordering::Acquire) > 0
+    }
+
+    /// 获取连接ID
+    pub fn conn_id(&self) -> u64 {
+        self.context.conn_id()
+    }
+
+    /// 获取客户端地址
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.context.remote_addr()
+    }
+
+    /// 获取连接的上下文信息
+    pub fn context(&self) -> &ConnContext {
+        &self.context
+    }
+
+    /// 获取发送队列中等待发送的消息数量
+    pub fn queued(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    /// 获取发送队列最多容纳的消息数量
+    pub fn queue_capacity(&self) -> usize {
+        self.sender.max_capacity()
+    }
+
+    /// 向该连接推送一条消息
+    ///
+    /// 消息会进入连接的发送队列，该方法不会等待消息真正写入网络流。
+    /// 请求的响应也经过同一个队列，所有消息按进入队列的顺序发送。
+    /// 发送队列已满时按服务器配置的 `WriteQueuePolicy` 处理这条消息。
+    ///
+    /// # 参数
+    /// * `resp` - 要推送的消息
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 消息已进入发送队列
+    /// * `Err(ZerustError::ConnectionClosed)` - 连接已经关闭
+    /// * `Err(ZerustError::WriteQueueFull)` - 发送队列已满，消息被丢弃
+    pub fn send(&self, resp: Response) -> Result<(), ZerustError> {
+        match self.sender.try_send(resp) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Closed(_)) => Err(ZerustError::ConnectionClosed),
+            Err(TrySendError::Full(_)) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    conn_id = self.conn_id(),
+                    capacity = self.queue_capacity(),
+                    policy = ?self.policy,
+                    "write queue full, dropping message"
+                );
+                if self.policy == WriteQueuePolicy::Disconnect {
+                    self.overflow.notify_one();
+                }
+                Err(ZerustError::WriteQueueFull)
+            }
+        }
+    }
+
+    /// 向该连接推送一条消息，发送队列已满时等待出现空位
+    ///
+    /// 与 `send` 不同，消息不会因为队列已满而被丢弃，但客户端不读取数据时会一直等待，
+    /// 需要时可以用 `tokio::time::timeout` 限制等待的时间。
+    ///
+    /// # 参数
+    /// * `resp` - 要推送的消息
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 消息已进入发送队列
+    /// * `Err(ZerustError::ConnectionClosed)` - 连接已经关闭
+    pub async fn send_wait(&self, resp: Response) -> Result<(), ZerustError> {
+        self.sender
+            .send(resp)
+            .await
+            .map_err(|_| ZerustError::ConnectionClosed)
+    }
+}
+
+/// 正在处理的请求的守卫，参见 `ConnectionHandle::begin_request`
+///
+/// 释放时刷新连接的最后活动时间并减少正在处理的请求数量。
+pub(crate) struct RequestGuard {
+    /// 连接最后一次收到请求或处理完请求的时间
+    last_active: Arc<Mutex<Instant>>,
+    /// 连接正在处理的请求数量
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        *self.last_active.lock().unwrap() = Instant::now();
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// 一次广播的结果，参见 `ConnManager::broadcast_filter`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BroadcastReport {
+    /// 消息成功进入发送队列的连接数量
+    sent: usize,
+    /// 已经关闭、没能接收消息的连接数量
+    failed: usize,
+    /// 发送队列已满、消息被丢弃的连接数量
+    dropped: usize,
+}
+
+impl BroadcastReport {
+    /// 获取消息成功进入发送队列的连接数量
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    /// 获取已经关闭、没能接收消息的连接数量
+    ///
+    /// 这些连接正在退出，服务器会在连接任务结束时把它们从管理器中移除。
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// 获取发送队列已满、消息被丢弃的连接数量，参见 `WriteQueuePolicy`
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+/// 连接管理器
+///
+/// 使用 `DashMap` 保存连接ID到连接句柄的映射，支持在多个任务中并发访问。
+/// 由 `Server` 持有，通过 `Server::conn_manager` 获取。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use zerust::{DefaultRouter, Response, Server};
+///
+/// let router = Arc::new(DefaultRouter::new());
+/// let server = Server::new("127.0.0.1:0", router.clone());
+///
+/// // 处理函数把收到的消息转发给所有在线连接
+/// let manager = server.conn_manager();
+/// router.add_route(1, move |req| {
+///     manager.broadcast(Response::new(2, req.data().to_vec()));
+///     Response::new(req.msg_id(), b"ok".to_vec())
+/// }).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct ConnManager {
+    /// 连接ID到连接句柄的映射
+    connections: DashMap<u64, ConnectionHandle>,
+    /// 下一个分配的连接ID
+    next_id: AtomicU64,
+}
+
+impl ConnManager {
+    /// 创建一个空的连接管理器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 分配一个新的连接ID，从 1 开始单调递增
+    pub(crate) fn next_conn_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// 登记一个连接
+    pub(crate) fn insert(&self, handle: ConnectionHandle) {
+        self.connections.insert(handle.conn_id(), handle);
+    }
+
+    /// 根据连接ID查找在线连接
+    ///
+    /// # 参数
+    /// * `conn_id` - 连接ID
+    ///
+    /// # 返回值
+    /// 连接在线时返回其句柄的克隆，否则返回 `None`
+    pub fn get(&self, conn_id: u64) -> Option<ConnectionHandle> {
+        self.connections.get(&conn_id).map(|entry| entry.clone())
+    }
+
+    /// 从管理器中移除一个连接
+    ///
+    /// 只移除登记信息，不会关闭连接；连接结束时服务器会自动调用该方法。
+    ///
+    /// # 参数
+    /// * `conn_id` - 连接ID
+    ///
+    /// # 返回值
+    /// 连接存在时返回被移除的句柄，否则返回 `None`
+    pub fn remove(&self, conn_id: u64) -> Option<ConnectionHandle> {
+        self.connections.remove(&conn_id).map(|(_, handle)| handle)
+    }
+
+    /// 获取在线连接数量
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// 获取在线连接数量
+    ///
+    /// 与 `len` 相同，便于在管理接口或监控中表达语义。
+    /// 连接在被服务器接受时登记，在连接任务结束时移除。
+    pub fn connection_count(&self) -> usize {
+        self.len()
+    }
+
+    /// 判断当前是否没有在线连接
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// 通知空闲时间超过 `idle_timeout` 的连接关闭，返回通知的连接数量
+    ///
+    /// 由服务器按 `Server::with_idle_timeout` 定期调用，连接任务收到通知后结束。
+    /// 有请求正在处理的连接不算空闲。
+    pub(crate) fn evict_idle(&self, idle_timeout: Duration) -> usize {
+        let mut evicted = 0;
+        for entry in self.connections.iter() {
+            let handle = entry.value();
+            if !handle.is_busy() && handle.idle_time() > idle_timeout {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(conn_id = handle.conn_id(), idle = ?handle.idle_time(), "closing idle connection");
+                handle.idle.notify_one();
+                evicted += 1;
+            }
+        }
+        evicted
+    }
+
+    /// 向指定连接推送一条消息
+    ///
+    /// 消息会进入目标连接的发送队列，由该连接的任务负责写入网络流。
+    ///
+    /// # 参数
+    /// * `conn_id` - 目标连接的ID
+    /// * `resp` - 要推送的消息
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 消息已进入发送队列
+    /// * `Err(ZerustError::ConnectionClosed)` - 目标连接不存在或已经关闭
+    /// * `Err(ZerustError::WriteQueueFull)` - 目标连接的发送队列已满，参见 `ConnectionHandle::send`
+    pub fn send_to(&self, conn_id: u64, resp: Response) -> Result<(), ZerustError> {
+        match self.connections.get(&conn_id) {
+            Some(handle) => handle.send(resp),
+            None => Err(ZerustError::ConnectionClosed),
+        }
+    }
+
+    /// 向指定连接推送一条消息，发送队列已满时等待出现空位，参见 `ConnectionHandle::send_wait`
+    ///
+    /// # 参数
+    /// * `conn_id` - 目标连接的ID
+    /// * `resp` - 要推送的消息
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 消息已进入发送队列
+    /// * `Err(ZerustError::ConnectionClosed)` - 目标连接不存在或已经关闭
+    pub async fn send_to_wait(&self, conn_id: u64, resp: Response) -> Result<(), ZerustError> {
+        // 等待期间不持有 DashMap 的锁
+        let handle = self.get(conn_id).ok_or(ZerustError::ConnectionClosed)?;
+        handle.send_wait(resp).await
+    }
+
+    /// 向所有在线连接推送同一条消息
+    ///
+    /// # 参数
+    /// * `resp` - 要推送的消息
+    ///
+    /// # 返回值
+    /// 返回成功进入发送队列的连接数量，已经关闭和发送队列已满的连接会被跳过
+    pub fn broadcast(&self, resp: Response) -> usize {
+        self.connections
+            .iter()
+            .filter(|entry| entry.send(resp.clone()).is_ok())
+            .count()
+    }
+
+    /// 向除指定连接以外的所有在线连接推送同一条消息
+    ///
+    /// 常用于聊天室等场景：把某个客户端发送的消息转发给其他所有客户端。
+    ///
+    /// # 参数
+    /// * `conn_id` - 不接收该消息的连接ID，通常是消息的发送方
+    /// * `resp` - 要推送的消息
+    ///
+    /// # 返回值
+    /// 返回成功进入发送队列的连接数量
+    pub fn broadcast_except(&self, conn_id: u64, resp: Response) -> usize {
+        self.connections
+            .iter()
+            .filter(|entry| *entry.key() != conn_id)
+            .filter(|entry| entry.send(resp.clone()).is_ok())
+            .count()
+    }
+
+    /// 向满足条件的在线连接推送同一条消息
+    ///
+    /// 条件函数可以通过 `ConnectionHandle::context` 读取连接的属性，
+    /// 例如只推送给已经通过认证的连接。某个连接已经关闭时跳过它，继续推送给其他连接。
+    ///
+    /// # 参数
+    /// * `resp` - 要推送的消息
+    /// * `filter` - 返回 `true` 的连接会收到该消息
+    ///
+    /// # 返回值
+    /// 返回推送成功、连接已关闭和消息被丢弃的连接数量，不满足条件的连接不计入
+    pub fn broadcast_filter<F>(&self, resp: Response, filter: F) -> BroadcastReport
+    where
+        F: Fn(&ConnectionHandle) -> bool,
+    {
+        let mut report = BroadcastReport::default();
+        for entry in self
+            .connections
+            .iter()
+            .filter(|entry| filter(entry.value()))
+        {
+            match entry.send(resp.clone()) {
+                Ok(()) => report.sent += 1,
+                Err(ZerustError::WriteQueueFull) => report.dropped += 1,
+                Err(_) => report.failed += 1,
+            }
+        }
+        report
+    }
+}
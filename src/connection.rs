@@ -2,45 +2,259 @@
 //!
 //! 该模块负责管理TCP连接的生命周期和数据传输，包括读取请求、发送响应等操作。
 //! 它是服务器与客户端之间通信的桥梁，处理底层的网络IO操作。
+//!
+//! 通过 `Connection::split` 可以把连接拆分为读取端 `ConnectionReader` 和写入端
+//! `ConnectionWriter`，在一个任务中读取请求的同时，从其他任务发送响应或推送消息。
+//!
+//! 服务器开启协议握手后（参见 `Server::with_protocol_version`），双方在发送第一个请求之前
+//! 交换 3 字节的前导：2 字节的协议标识 `PROTOCOL_MAGIC` + 1 字节的协议版本。
+//! 客户端通过 `Connection::handshake` 发送前导，服务器回复双方都支持的版本（两者中较小的一个）。
+//!
+//! 连接默认使用 `TcpStream`，也可以使用实现了 `Transport` 的其他流，
+//! 例如 TLS 流或 `tokio::io::duplex` 创建的内存流，后者便于在不打开套接字的情况下测试协议。
 
-use crate::{datapack::DataPack, error::ZerustError, request::Request, response::Response};
-use std::net::SocketAddr;
-use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
+use crate::codec::PacketCodec;
+use crate::context::ConnContext;
+use crate::control::{PING_MSG_ID, PONG_MSG_ID};
+use crate::datapack::{DEFAULT_MAX_PACKET_SIZE, DataPack};
+#[cfg(unix)]
+use crate::runtime::UnixStream;
+use crate::runtime::{
+    self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, Join, Mutex, TcpStream,
+    ToSocketAddrs,
 };
+use crate::{error::ZerustError, request::Request, response::Response};
+use bytes::{Buf, BytesMut};
+use socket2::{SockRef, TcpKeepalive};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 接收缓冲区没有空闲空间时，每次至少扩容的字节数的默认值
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// 协议握手前导中的协议标识
+pub const PROTOCOL_MAGIC: [u8; 2] = *b"ZR";
+
+/// 协议握手前导的长度：协议标识 + 1 字节的协议版本
+pub const PREAMBLE_SIZE: usize = PROTOCOL_MAGIC.len() + 1;
+
+/// 发送缓冲区在两次发送之间最多保留的容量，发送过大的消息后会释放多余的空间
+const MAX_RETAINED_WRITE_BUFFER: usize = 64 * 1024;
+
+/// 拆分后的流的读取端
+pub type TransportReader = Box<dyn AsyncRead + Unpin + Send>;
+
+/// 拆分后的流的写入端
+pub type TransportWriter = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// 可以作为连接底层的流
+///
+/// 任何支持异步读写的流都可以实现该 trait，默认实现通过 `tokio::io::split` 拆分流；
+/// `TcpStream` 使用不需要加锁的 `TcpStream::into_split`。
+///
+/// 框架为 `TcpStream`、Unix 域套接字、`tokio::io::duplex` 创建的内存流、
+/// `tokio::io::join` 组合的读取端和写入端，以及开启 `tls` 功能时的 TLS 流实现了该 trait。
+/// 其他只实现了 `AsyncRead` 和 `AsyncWrite` 的流可以先用 `tokio::io::split` 拆分，
+/// 再用 `tokio::io::join` 组合后使用。
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+    /// 把流拆分为可以分别在不同任务中使用的读取端和写入端
+    fn into_halves(self) -> (TransportReader, TransportWriter)
+    where
+        Self: Sized,
+    {
+        let (read_half, write_half) = runtime::split(self);
+        (Box::new(read_half), Box::new(write_half))
+    }
+
+    /// 获取对端的套接字地址
+    ///
+    /// 默认返回 `ErrorKind::Unsupported` 错误，适用于没有网络地址的流。
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "transport has no socket address",
+        ))
+    }
+}
+
+impl Transport for TcpStream {
+    fn into_halves(self) -> (TransportReader, TransportWriter) {
+        let (read_half, write_half) = self.into_split();
+        (Box::new(read_half), Box::new(write_half))
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::peer_addr(self)
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixStream {
+    fn into_halves(self) -> (TransportReader, TransportWriter) {
+        let (read_half, write_half) = self.into_split();
+        (Box::new(read_half), Box::new(write_half))
+    }
+}
+
+impl Transport for DuplexStream {}
+
+impl<R, W> Transport for Join<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    fn into_halves(self) -> (TransportReader, TransportWriter) {
+        // 读取端和写入端本来就是独立的，拆分时不需要加锁
+        let (read_half, write_half) = self.into_inner();
+        (Box::new(read_half), Box::new(write_half))
+    }
+}
+
+#[cfg(feature = "tls")]
+impl<S: Transport> Transport for tokio_rustls::server::TlsStream<S> {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().0.peer_addr()
+    }
+}
+
+#[cfg(feature = "tls")]
+impl<S: Transport> Transport for tokio_rustls::client::TlsStream<S> {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.get_ref().0.peer_addr()
+    }
+}
 
-/// 表示一个TCP连接
+/// 表示一个客户端连接
 ///
-/// `Connection` 封装了一个TCP流和相关的缓冲区，提供了读取请求和发送响应的方法。
+/// `Connection` 封装了一个流和相关的缓冲区，提供了读取请求和发送响应的方法。
 /// 它负责处理底层的网络IO操作，并将原始字节数据转换为应用层的请求和响应对象。
-pub struct Connection {
-    /// TCP流，用于与客户端进行网络通信
-    stream: TcpStream,
+/// 底层的流默认是 `TcpStream`，也可以是实现了 `Transport` 的其他流，
+/// 例如在测试中使用 `tokio::io::duplex` 创建的内存流，不需要打开真实的套接字。
+pub struct Connection<S = TcpStream> {
+    /// 连接的上下文信息，随每个请求一起传递给处理函数
+    context: ConnContext,
+    /// 底层的流，用于与客户端进行网络通信
+    stream: S,
     /// 用于存放从流中读取但尚未被应用层处理的数据
-    pending_data: Vec<u8>,
+    pending_data: BytesMut,
+    /// 接收缓冲区没有空闲空间时，每次至少扩容的字节数
+    read_buffer_size: usize,
+    /// 允许接收的最大消息体长度，超过该长度的消息会被拒绝
+    max_packet_size: u32,
+    /// 帧编解码工具，决定消息在网络上的格式
+    codec: Arc<dyn PacketCodec>,
+    /// 读取一个完整请求的超时时间，`None` 表示不限制
+    read_timeout: Option<Duration>,
+    /// 发送一个响应的超时时间，`None` 表示不限制
+    write_timeout: Option<Duration>,
+    /// 发送缓冲区，在多次发送之间复用，避免为每条消息分配内存
+    write_buf: Vec<u8>,
+    /// 自动回复的 pong 中尚未写入流的部分，下一次读取或发送之前先写完
+    pending_write: BytesMut,
 }
 
-impl Connection {
-    /// 消息头部大小常量，单位为字节
+/// 使用 `TcpStream` 的连接
+pub type TcpConnection = Connection<TcpStream>;
+
+impl Connection<TcpStream> {
+    /// 设置底层TCP流的 `TCP_NODELAY` 选项
     ///
-    /// 消息头由两部分组成：
-    /// * 4字节的消息ID (msg_id)
-    /// * 4字节的数据长度 (data_len)
-    const HEADER_SIZE: usize = 8; // msg_id(4) + data_len(4)
+    /// 启用后禁用 Nagle 算法，小响应会立即发送而不是等待与后续数据合并，
+    /// 适合请求-响应式的小消息协议。
+    ///
+    /// # 参数
+    /// * `nodelay` - 是否启用 `TCP_NODELAY`
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 设置成功
+    /// * `Err(ZerustError)` - 设置套接字选项失败时返回的错误信息
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<(), ZerustError> {
+        self.stream
+            .set_nodelay(nodelay)
+            .map_err(ZerustError::IoError)
+    }
 
-    /// 创建一个新的连接实例
+    /// 获取底层TCP流当前的 `TCP_NODELAY` 选项
+    ///
+    /// # 返回值
+    /// * `Ok(bool)` - 是否已启用 `TCP_NODELAY`
+    /// * `Err(ZerustError)` - 读取套接字选项失败时返回的错误信息
+    pub fn nodelay(&self) -> Result<bool, ZerustError> {
+        self.stream.nodelay().map_err(ZerustError::IoError)
+    }
+
+    /// 设置底层TCP流的 keepalive 选项
     ///
     /// # 参数
-    /// * `stream` - TCP流，用于与客户端进行网络通信
+    /// * `time` - 连接空闲多久后开始发送探测包，`None` 表示关闭 keepalive
     ///
     /// # 返回值
-    /// 返回一个新的 `Connection` 实例
-    pub fn new(stream: TcpStream) -> Self {
-        Self {
-            stream,
-            pending_data: Vec::new(),
+    /// * `Ok(())` - 设置成功
+    /// * `Err(ZerustError)` - 设置套接字选项失败时返回的错误信息
+    pub fn set_keepalive(&self, time: Option<Duration>) -> Result<(), ZerustError> {
+        let socket = SockRef::from(&self.stream);
+        match time {
+            Some(time) => socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(time)),
+            None => socket.set_keepalive(false),
         }
+        .map_err(ZerustError::IoError)
+    }
+
+    /// 获取底层TCP流是否开启了 keepalive
+    ///
+    /// # 返回值
+    /// * `Ok(bool)` - 是否已开启 keepalive
+    /// * `Err(ZerustError)` - 读取套接字选项失败时返回的错误信息
+    pub fn keepalive(&self) -> Result<bool, ZerustError> {
+        SockRef::from(&self.stream)
+            .keepalive()
+            .map_err(ZerustError::IoError)
+    }
+
+    /// 设置底层TCP流的 `SO_LINGER` 选项
+    ///
+    /// # 参数
+    /// * `linger` - 关闭连接时等待数据发送完毕的最长时间，为 0 时关闭连接会发送 RST；
+    ///   `None` 表示使用操作系统默认的关闭方式
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 设置成功
+    /// * `Err(ZerustError)` - 设置套接字选项失败时返回的错误信息
+    pub fn set_linger(&self, linger: Option<Duration>) -> Result<(), ZerustError> {
+        SockRef::from(&self.stream)
+            .set_linger(linger)
+            .map_err(ZerustError::IoError)
+    }
+
+    /// 获取底层TCP流当前的 `SO_LINGER` 选项
+    ///
+    /// # 返回值
+    /// * `Ok(Option<Duration>)` - 当前的 linger 时间，`None` 表示未设置
+    /// * `Err(ZerustError)` - 读取套接字选项失败时返回的错误信息
+    pub fn linger(&self) -> Result<Option<Duration>, ZerustError> {
+        SockRef::from(&self.stream)
+            .linger()
+            .map_err(ZerustError::IoError)
+    }
+}
+
+impl<S: Transport> Connection<S> {
+    /// 创建一个新的连接实例
+    ///
+    /// # 参数
+    /// * `stream` - 用于与客户端进行网络通信的流，例如 `TcpStream`
+    ///
+    /// # 返回值
+    /// 返回一个新的 `Connection` 实例，最大消息体长度为 `DEFAULT_MAX_PACKET_SIZE`
+    pub fn new(stream: S) -> Self {
+        // 获取不到对端地址（例如内存流）时使用未指定地址，不影响连接的正常使用
+        let remote_addr = stream
+            .peer_addr()
+            .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0)));
+        Self::from_stream(stream, remote_addr)
     }
 
     /// 获取远程客户端的套接字地址
@@ -50,84 +264,716 @@ impl Connection {
     /// # 返回值
     ///
     /// * `Ok(SocketAddr)` - 成功获取到的远程套接字地址
-    /// * `Err(ZerustError)` - 获取地址失败时返回的错误信息
+    /// * `Err(ZerustError)` - 获取地址失败，或者底层的流没有网络地址时返回的错误信息
     ///
     /// # 错误处理
     ///
     /// 当底层IO操作出现错误时，会将IO错误转换为ZerustError::IoError返回
     pub fn remote_addr(&self) -> Result<SocketAddr, ZerustError> {
         // 获取对端地址，如果出现IO错误则转换为ZerustError
-        self.stream.peer_addr().map_err(ZerustError::IoError)
+        Transport::peer_addr(&self.stream).map_err(ZerustError::IoError)
+    }
+
+    /// 使用给定的客户端地址创建一个新的连接实例
+    ///
+    /// 适用于无法从流中获取客户端地址的情况，例如内存流，或者由代理转发、
+    /// 需要使用代理报告的原始地址的连接。
+    ///
+    /// # 参数
+    /// * `stream` - 用于与客户端进行网络通信的流
+    /// * `remote_addr` - 客户端地址
+    ///
+    /// # 返回值
+    /// 返回一个新的 `Connection` 实例，最大消息体长度为 `DEFAULT_MAX_PACKET_SIZE`
+    pub fn from_stream(stream: S, remote_addr: SocketAddr) -> Self {
+        Self {
+            context: ConnContext::new(0, remote_addr),
+            stream,
+            pending_data: BytesMut::new(),
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            codec: Arc::new(DataPack::default()),
+            read_timeout: None,
+            write_timeout: None,
+            write_buf: Vec::new(),
+            pending_write: BytesMut::new(),
+        }
+    }
+
+    /// 设置连接ID
+    ///
+    /// 服务器接受连接时会从 `ConnManager` 分配一个单调递增的连接ID。
+    /// 通过 `read_request` 读取的请求都会在上下文中携带该连接ID。
+    ///
+    /// # 参数
+    /// * `conn_id` - 连接ID
+    ///
+    /// # 返回值
+    /// 返回设置了连接ID的 `Connection` 实例
+    pub fn with_conn_id(mut self, conn_id: u64) -> Self {
+        self.context.set_conn_id(conn_id);
+        self
+    }
+
+    /// 使用服务器创建的上下文，使请求与连接句柄共享自定义属性
+    pub(crate) fn with_context(mut self, context: ConnContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// 获取连接ID
+    ///
+    /// # 返回值
+    /// 返回连接ID，未分配时为 0
+    pub fn conn_id(&self) -> u64 {
+        self.context.conn_id()
+    }
+
+    /// 获取连接的上下文信息
+    ///
+    /// # 返回值
+    /// 返回包含连接ID、客户端地址和建立时间的上下文
+    pub fn context(&self) -> &ConnContext {
+        &self.context
+    }
+
+    /// 设置读取请求的超时时间
+    ///
+    /// 设置后，`read_request` 必须在该时间内读取到一个完整的请求（消息头和消息体），
+    /// 否则返回 `ZerustError::Timeout`。空闲连接和只发送部分数据的慢速客户端
+    /// 都会因此超时。传入 `None` 表示一直等待，这也是默认行为。
+    ///
+    /// # 参数
+    /// * `timeout` - 读取超时时间
+    ///
+    /// # 返回值
+    /// 返回设置了读取超时的 `Connection` 实例
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// 设置发送响应的超时时间
+    ///
+    /// 设置后，`send_response` 必须在该时间内把响应完整写入流，
+    /// 否则返回 `ZerustError::Timeout`。传入 `None` 表示一直等待，这也是默认行为。
+    /// 超时时响应可能只写入了一部分，之后不应再通过该连接发送数据。
+    ///
+    /// # 参数
+    /// * `timeout` - 写入超时时间
+    ///
+    /// # 返回值
+    /// 返回设置了写入超时的 `Connection` 实例
+    pub fn with_write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// 设置连接使用的消息编解码工具
+    ///
+    /// 读取请求和发送响应都会使用该工具，例如与使用大端序消息头的客户端通信时，
+    /// 传入 `DataPack::with_order(ByteOrderMode::Big)`，与 Zinx 服务器通信时传入 `DataPack::zinx()`。
+    ///
+    /// # 参数
+    /// * `datapack` - 消息编解码工具
+    ///
+    /// # 返回值
+    /// 返回使用新编解码工具的 `Connection` 实例
+    pub fn with_datapack(mut self, datapack: DataPack) -> Self {
+        self.codec = Arc::new(datapack);
+        self
+    }
+
+    /// 设置连接使用的帧编解码工具
+    ///
+    /// 用于替换默认的 `DataPack` 协议，例如兼容带魔数和校验和的私有线路格式。
+    ///
+    /// # 参数
+    /// * `codec` - 帧编解码工具
+    ///
+    /// # 返回值
+    /// 返回使用新编解码工具的 `Connection` 实例
+    pub fn with_codec(mut self, codec: Arc<dyn PacketCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 设置允许接收的最大消息体长度
+    ///
+    /// 消息头声明的数据长度超过该值时，`read_request` 会在读取消息体之前返回错误。
+    ///
+    /// # 参数
+    /// * `max_packet_size` - 最大消息体长度，单位为字节
+    ///
+    /// # 返回值
+    /// 返回设置了新限制的 `Connection` 实例
+    pub fn with_max_packet_size(mut self, max_packet_size: u32) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// 设置接收缓冲区每次扩容的字节数
+    ///
+    /// 缓冲区中没有空闲空间时，至少扩容该字节数后再从流中读取。较大的值可以减少
+    /// 批量小请求的读取次数，较小的值可以降低大量空闲连接占用的内存。
+    /// 已经收到消息头时，编解码工具会按消息剩余的长度预留空间，
+    /// 大消息的消息体直接读入缓冲区，读取次数不受该值限制。
+    /// 默认值为 `DEFAULT_READ_BUFFER_SIZE`（8 KiB）。
+    ///
+    /// # 参数
+    /// * `read_buffer_size` - 每次扩容的字节数，应大于 0
+    pub fn with_read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = read_buffer_size;
+        self
     }
 
     /// 从连接中异步读取一个完整的请求消息
     ///
     /// 该函数首先读取固定大小的消息头，解析出消息ID和数据长度，
     /// 然后根据数据长度读取相应的消息体数据，最后构造成Request对象返回。
+    /// 数据长度超过最大消息体长度时，会在读取消息体之前返回错误。
+    /// 设置了读取超时时，整个请求需要在超时时间内读取完毕。
+    ///
+    /// 收到 ping 控制帧时自动回复数据和序列号相同的 pong，然后继续读取，
+    /// ping 不会返回给调用方（参见 `control` 模块）。`ConnectionReader` 无法发送消息，
+    /// 因此拆分后的读取端会原样返回 ping。
+    ///
+    /// 该方法是取消安全的：在 `tokio::select!` 中被取消时，已经收到的数据会保留，
+    /// 下一次调用会从中断处继续读取。回复 pong 时被取消的话，尚未写入的部分同样会保留，
+    /// 在下一次读取或发送之前写完，不会与其他消息交错。
     ///
     /// # Returns
     ///
     /// * `Result<Request, ZerustError>` - 成功时返回解析出的请求对象，失败时返回错误信息
     ///
+    /// 对端在两个帧之间正常关闭时返回 `ZerustError::ConnectionClosed`；
+    /// 在一个帧的中间关闭时返回 `io::ErrorKind::UnexpectedEof` 的 `ZerustError::IoError`。
     pub async fn read_request(&mut self) -> Result<Request, ZerustError> {
-        // 读取消息头
-        let header_bytes = self.read_exact(Self::HEADER_SIZE).await?;
-        // 解析消息头
-        let (msg_id, data_len) = DataPack::unpack_header(&header_bytes)?;
-        // 读取消息体
-        let data = if data_len > 0 {
-            self.read_exact(data_len as usize).await?
-        } else {
-            Vec::new()
-        };
-        Ok(Request::new(msg_id, data))
+        loop {
+            self.flush_pending_write().await?;
+            let timeout = self.read_timeout;
+            let req = with_timeout(
+                timeout,
+                read_frame(
+                    &mut self.stream,
+                    &mut self.pending_data,
+                    self.read_buffer_size,
+                    self.codec.as_ref(),
+                    self.max_packet_size,
+                    &self.context,
+                ),
+            )
+            .await?;
+            if req.msg_id() != PING_MSG_ID {
+                return Ok(req);
+            }
+            // 先编码到待写入的缓冲区，写入中途被取消时下一次调用会继续写完
+            self.write_buf.clear();
+            encode_frame(
+                self.codec.as_ref(),
+                PONG_MSG_ID,
+                req.seq(),
+                req.data(),
+                false,
+                &mut self.write_buf,
+            )?;
+            self.pending_write.extend_from_slice(&self.write_buf);
+        }
     }
 
-    /// 从流中精确读取指定数量的字节数据
+    /// 发送响应消息
     ///
-    /// 该函数会优先从 `pending_data` 中获取数据，如果不够则从流中读取。
+    /// 将响应消息打包并发送到网络流中
     ///
     /// # 参数
-    /// * `size` - 需要读取的字节数
+    /// * `resp` - 要发送的响应消息
     ///
     /// # 返回值
-    /// * `Ok(Vec<u8>)` - 成功读取的字节数据
-    /// * `Err(ZerustError)` - 读取过程中发生的错误，包括连接关闭等
-    async fn read_exact(&mut self, size: usize) -> Result<Vec<u8>, ZerustError> {
-        // 首先检查 pending_data 中是否有足够的数据
-        while self.pending_data.len() < size {
-            // pending_data 中的数据不够，需要从流中读取更多
-            let mut buffer = [0u8; 1024]; // 临时缓冲区
-            let n = self.stream.read(&mut buffer).await?;
-            if n == 0 {
-                return Err(ZerustError::ConnectionClosed);
+    /// * `Result<(),ZerustError>` - 发送结果，成功返回Ok(())，失败返回ZerustError错误
+    ///
+    /// # 异常
+    /// * 当网络写入失败时会返回ZerustError错误
+    /// * 响应数据长度超过 `u32::MAX` 时返回 `ZerustError::MessageTooLarge`
+    /// * 设置了写入超时且未能在超时时间内写完时返回 `ZerustError::Timeout`
+    ///
+    /// `Response::none` 创建的空响应不会写入任何数据，批量响应依次写入其中的每条消息。
+    pub async fn send_response(&mut self, resp: &Response) -> Result<(), ZerustError> {
+        for frame in resp.frames() {
+            let compress = frame.is_compressed();
+            self.send_frame(frame.msg_id(), frame.seq(), frame.data(), compress)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 发送一条消息
+    ///
+    /// 与 `send_response` 相同，但不需要先创建 `Response`，适合客户端发送请求。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 消息数据
+    ///
+    /// # 返回值
+    /// * `Result<(),ZerustError>` - 发送结果，成功返回Ok(())，失败返回ZerustError错误
+    pub async fn send_msg(&mut self, msg_id: u32, data: &[u8]) -> Result<(), ZerustError> {
+        self.send_frame(msg_id, None, data, false).await
+    }
+
+    /// 关闭连接的写入端
+    ///
+    /// 先把已经写入的数据发送完毕，再通知对端不会再发送数据
+    /// （TCP 连接发送 FIN，TLS 连接发送 close_notify），之后仍然可以读取对端发送的数据。
+    /// 设置了写入超时时同样受该超时限制。
+    pub async fn shutdown(&mut self) -> Result<(), ZerustError> {
+        self.flush_pending_write().await?;
+        with_timeout(self.write_timeout, async {
+            Ok(self.stream.shutdown().await?)
+        })
+        .await
+    }
+
+    /// 作为客户端进行协议握手
+    ///
+    /// 发送协议标识和客户端支持的最高版本，等待服务器回复协商出的版本，
+    /// 并把它记录到连接的上下文中。需要在发送第一个请求之前调用，
+    /// 读取和发送分别受读取超时和写入超时限制。
+    ///
+    /// # 参数
+    /// * `version` - 客户端支持的最高协议版本
+    ///
+    /// # 返回值
+    /// 返回协商出的协议版本
+    ///
+    /// # 异常
+    /// * 服务器回复的协议标识不匹配，或者版本高于 `version` 时返回 `ZerustError::ProtocolError`
+    pub async fn handshake(&mut self, version: u8) -> Result<u8, ZerustError> {
+        self.write_preamble(version).await?;
+        let negotiated = self.read_preamble().await?;
+        if negotiated > version {
+            return Err(ZerustError::ProtocolError(format!(
+                "server chose unsupported protocol version {negotiated}"
+            )));
+        }
+        self.context.set_protocol_version(negotiated);
+        Ok(negotiated)
+    }
+
+    /// 作为服务器进行协议握手
+    ///
+    /// 读取客户端的前导，回复双方都支持的版本，并把它记录到连接的上下文中。
+    /// 协议标识不匹配时不回复任何数据，也不会读取之后的请求。
+    /// 客户端在前导之后立即发送的请求会保留下来，由 `read_request` 读取。
+    ///
+    /// # 参数
+    /// * `version` - 服务器支持的最高协议版本
+    ///
+    /// # 返回值
+    /// 返回协商出的协议版本
+    ///
+    /// # 异常
+    /// * 协议标识不匹配时返回 `ZerustError::ProtocolError`
+    pub async fn accept_handshake(&mut self, version: u8) -> Result<u8, ZerustError> {
+        let negotiated = self.read_preamble().await?.min(version);
+        self.write_preamble(negotiated).await?;
+        self.context.set_protocol_version(negotiated);
+        Ok(negotiated)
+    }
+
+    /// 在读取超时时间内读取对端的前导，检查协议标识后返回其中的协议版本
+    async fn read_preamble(&mut self) -> Result<u8, ZerustError> {
+        let preamble = with_timeout(self.read_timeout, async {
+            while self.pending_data.len() < PREAMBLE_SIZE {
+                if self.stream.read_buf(&mut self.pending_data).await? == 0 {
+                    return Err(ZerustError::ConnectionClosed);
+                }
             }
-            // 将新读取的数据追加到 pending_data
-            self.pending_data.extend_from_slice(&buffer[..n]);
+            Ok(self.pending_data.split_to(PREAMBLE_SIZE))
+        })
+        .await?;
+        if preamble[..PROTOCOL_MAGIC.len()] != PROTOCOL_MAGIC {
+            return Err(ZerustError::ProtocolError(format!(
+                "invalid protocol magic {:02x?}",
+                &preamble[..PROTOCOL_MAGIC.len()]
+            )));
         }
+        Ok(preamble[PROTOCOL_MAGIC.len()])
+    }
+
+    /// 在写入超时时间内发送前导
+    async fn write_preamble(&mut self, version: u8) -> Result<(), ZerustError> {
+        let [m0, m1] = PROTOCOL_MAGIC;
+        with_timeout(self.write_timeout, async {
+            Ok(self.stream.write_all(&[m0, m1, version]).await?)
+        })
+        .await
+    }
+
+    /// 发送一条携带可选序列号的消息
+    async fn send_frame(
+        &mut self,
+        msg_id: u32,
+        seq: Option<u32>,
+        data: &[u8],
+        compress: bool,
+    ) -> Result<(), ZerustError> {
+        self.flush_pending_write().await?;
+        let codec = self.codec.as_ref();
+        write_frame(
+            &mut self.stream,
+            &mut self.write_buf,
+            |buf| encode_frame(codec, msg_id, seq, data, compress, buf),
+            self.write_timeout,
+        )
+        .await
+    }
+
+    /// 写完自动回复的 pong 中尚未写入流的部分
+    async fn flush_pending_write(&mut self) -> Result<(), ZerustError> {
+        flush_pending(
+            &mut self.stream,
+            &mut self.pending_write,
+            self.write_timeout,
+        )
+        .await
+    }
+
+    /// 将连接拆分为读取端和写入端
+    ///
+    /// 读取端保留已经收到但尚未解析的数据，以及连接的上下文、最大消息体长度和读取超时；
+    /// 写入端可以克隆，多个任务可以同时通过它发送消息，每条消息都会被完整写入，
+    /// 不会与其他消息交错。两端共享同一个编解码工具。
+    ///
+    /// # 返回值
+    /// 返回 `(ConnectionReader, ConnectionWriter)`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::connection::Connection;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), zerust::ZerustError> {
+    /// # let (stream, _client) = tokio::io::duplex(1024);
+    /// let (mut reader, writer) = Connection::new(stream).split();
+    ///
+    /// // 在另一个任务中主动推送消息，不影响当前任务读取请求
+    /// let pusher = writer.clone();
+    /// tokio::spawn(async move {
+    ///     let _ = pusher.send_msg(100, b"server push").await;
+    /// });
+    /// # drop(_client);
+    /// while let Ok(req) = reader.read_request().await {
+    ///     writer.send_msg(req.msg_id(), req.data()).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn split(self) -> (ConnectionReader, ConnectionWriter) {
+        let (read_half, write_half) = self.stream.into_halves();
+        let reader = ConnectionReader {
+            context: self.context,
+            stream: read_half,
+            pending_data: self.pending_data,
+            read_buffer_size: self.read_buffer_size,
+            max_packet_size: self.max_packet_size,
+            codec: self.codec.clone(),
+            read_timeout: self.read_timeout,
+        };
+        let writer = ConnectionWriter {
+            state: Arc::new(Mutex::new(WriteState {
+                stream: write_half,
+                write_buf: self.write_buf,
+                pending_write: self.pending_write,
+            })),
+            codec: self.codec,
+            write_timeout: self.write_timeout,
+        };
+        (reader, writer)
+    }
+}
+
+/// 连接的读取端
+///
+/// 由 `Connection::split` 创建，负责从连接中读取请求。
+pub struct ConnectionReader {
+    /// 连接的上下文信息，随每个请求一起传递给处理函数
+    context: ConnContext,
+    /// 流的读取端
+    stream: TransportReader,
+    /// 用于存放从流中读取但尚未被应用层处理的数据
+    pending_data: BytesMut,
+    /// 接收缓冲区没有空闲空间时，每次至少扩容的字节数
+    read_buffer_size: usize,
+    /// 允许接收的最大消息体长度，超过该长度的消息会被拒绝
+    max_packet_size: u32,
+    /// 帧编解码工具，决定消息在网络上的格式
+    codec: Arc<dyn PacketCodec>,
+    /// 读取一个完整请求的超时时间，`None` 表示不限制
+    read_timeout: Option<Duration>,
+}
+
+impl ConnectionReader {
+    /// 获取连接ID
+    ///
+    /// # 返回值
+    /// 返回连接ID，未分配时为 0
+    pub fn conn_id(&self) -> u64 {
+        self.context.conn_id()
+    }
+
+    /// 获取连接的上下文信息
+    pub fn context(&self) -> &ConnContext {
+        &self.context
+    }
 
-        // 现在 pending_data 中至少有 size 个字节
-        let result = self.pending_data.drain(..size).collect(); // 取出前 size 个字节
-        Ok(result)
+    /// 从连接中异步读取一个完整的请求消息
+    ///
+    /// 行为与 `Connection::read_request` 相同，同样是取消安全的。
+    ///
+    /// # 返回值
+    /// * `Result<Request, ZerustError>` - 成功时返回解析出的请求对象，失败时返回错误信息
+    pub async fn read_request(&mut self) -> Result<Request, ZerustError> {
+        let timeout = self.read_timeout;
+        with_timeout(
+            timeout,
+            read_frame(
+                &mut self.stream,
+                &mut self.pending_data,
+                self.read_buffer_size,
+                self.codec.as_ref(),
+                self.max_packet_size,
+                &self.context,
+            ),
+        )
+        .await
     }
+}
 
+/// 连接的写入端
+///
+/// 由 `Connection::split` 创建，负责向连接发送消息。克隆的开销很小，
+/// 所有克隆共享同一个底层写入端，并发发送的消息按获得写入权的顺序逐条写入。
+#[derive(Clone)]
+pub struct ConnectionWriter {
+    /// 流的写入端和发送缓冲区，通过互斥锁保证每条消息被完整写入
+    state: Arc<Mutex<WriteState>>,
+    /// 帧编解码工具，决定消息在网络上的格式
+    codec: Arc<dyn PacketCodec>,
+    /// 发送一条消息的超时时间，`None` 表示不限制
+    write_timeout: Option<Duration>,
+}
+
+impl ConnectionWriter {
     /// 发送响应消息
     ///
-    /// 将响应消息打包并发送到网络流中
+    /// 行为与 `Connection::send_response` 相同，写入超时包含等待其他发送完成的时间。
+    /// 空响应同样不会写入任何数据。
     ///
     /// # 参数
     /// * `resp` - 要发送的响应消息
     ///
     /// # 返回值
     /// * `Result<(),ZerustError>` - 发送结果，成功返回Ok(())，失败返回ZerustError错误
+    pub async fn send_response(&self, resp: &Response) -> Result<(), ZerustError> {
+        for frame in resp.frames() {
+            let compress = frame.is_compressed();
+            self.send_frame(frame.msg_id(), frame.seq(), frame.data(), compress)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// 发送一条消息
     ///
-    /// # 异常
-    /// * 当网络写入失败时会返回ZerustError错误
-    pub async fn send_response(&mut self, resp: &Response) -> Result<(), ZerustError> {
-        // 将响应消息打包成字节数据
-        let bytes = DataPack::pack(resp.msg_id(), resp.data());
-        // 异步写入网络流
-        self.stream.write_all(&bytes).await?;
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 消息数据
+    ///
+    /// # 返回值
+    /// * `Result<(),ZerustError>` - 发送结果，成功返回Ok(())，失败返回ZerustError错误
+    pub async fn send_msg(&self, msg_id: u32, data: &[u8]) -> Result<(), ZerustError> {
+        self.send_frame(msg_id, None, data, false).await
+    }
+
+    /// 关闭连接的写入端
+    ///
+    /// 会等待正在发送的消息写完，参见 `Connection::shutdown`。
+    /// 关闭后所有克隆都不应再发送消息。
+    pub async fn shutdown(&self) -> Result<(), ZerustError> {
+        with_timeout(self.write_timeout, async {
+            let mut state = self.state.lock().await;
+            Ok(state.stream.shutdown().await?)
+        })
+        .await
+    }
+
+    /// 发送一条携带可选序列号的消息
+    async fn send_frame(
+        &self,
+        msg_id: u32,
+        seq: Option<u32>,
+        data: &[u8],
+        compress: bool,
+    ) -> Result<(), ZerustError> {
+        with_timeout(self.write_timeout, async {
+            let mut state = self.state.lock().await;
+            let WriteState {
+                stream,
+                write_buf,
+                pending_write,
+            } = &mut *state;
+            flush_pending(stream, pending_write, None).await?;
+            let codec = self.codec.as_ref();
+            let encode = |buf: &mut Vec<u8>| encode_frame(codec, msg_id, seq, data, compress, buf);
+            write_frame(stream, write_buf, encode, None).await
+        })
+        .await
+    }
+}
+
+/// 写入端的所有克隆共享的发送状态
+struct WriteState {
+    /// 流的写入端
+    stream: TransportWriter,
+    /// 发送缓冲区，在多次发送之间复用
+    write_buf: Vec<u8>,
+    /// 拆分前自动回复的 pong 中尚未写入流的部分，下一次发送之前先写完
+    pending_write: BytesMut,
+}
+
+/// 从流中读取一个完整的帧，构造携带连接上下文的请求
+///
+/// 数据直接读入 `pending_data` 的空闲空间，解析出的帧通过 `split_to` 从缓冲区头部取出，
+/// 剩余数据不需要移动。缓冲区只在空闲空间用尽时才扩容，而 `BytesMut::reserve`
+/// 会优先回收头部已经取走的空间。
+///
+/// 编解码工具只在缓冲区中有完整的帧时才会把它移除，
+/// 因此该函数可以在 `tokio::select!` 中被安全地取消，不会破坏消息边界。
+async fn read_frame<R: AsyncRead + Unpin>(
+    stream: &mut R,
+    pending_data: &mut BytesMut,
+    read_buffer_size: usize,
+    codec: &dyn PacketCodec,
+    max_packet_size: u32,
+    context: &ConnContext,
+) -> Result<Request, ZerustError> {
+    loop {
+        // 尝试从已读取的数据中解析出一个完整的消息
+        if let Some((msg_id, seq, data)) = codec.decode_seq(pending_data, max_packet_size)? {
+            let req = Request::from_bytes(msg_id, data).with_context(context.clone());
+            return Ok(match seq {
+                Some(seq) => req.with_seq(seq),
+                None => req,
+            });
+        }
+        // 数据不足，从流中读取更多
+        if pending_data.capacity() == pending_data.len() {
+            pending_data.reserve(read_buffer_size);
+        }
+        let n = stream.read_buf(pending_data).await?;
+        if n == 0 {
+            // 在两个帧之间关闭是正常的断开，在帧的中间关闭说明数据不完整
+            if pending_data.is_empty() {
+                return Err(ZerustError::ConnectionClosed);
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed in the middle of a frame",
+            )
+            .into());
+        }
+    }
+}
+
+/// 把一条消息编码到发送缓冲区末尾
+///
+/// 帧格式不包含序列号时 `seq` 被忽略；`compress` 为 `true` 时通过
+/// `PacketCodec::encode_compressed_into` 编码，参见 `Response::new_compressed`。
+fn encode_frame(
+    codec: &dyn PacketCodec,
+    msg_id: u32,
+    seq: Option<u32>,
+    data: &[u8],
+    compress: bool,
+    buf: &mut Vec<u8>,
+) -> Result<(), ZerustError> {
+    if compress {
+        codec.encode_compressed_into(msg_id, seq, data, buf)
+    } else {
+        codec.encode_seq_into(msg_id, seq, data, buf)
+    }
+}
+
+/// 通过 `encode` 把一条消息编码到发送缓冲区，并在可选的超时时间内把它完整写入流
+async fn write_frame<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    write_buf: &mut Vec<u8>,
+    encode: impl FnOnce(&mut Vec<u8>) -> Result<(), ZerustError>,
+    write_timeout: Option<Duration>,
+) -> Result<(), ZerustError> {
+    // 将消息打包到复用的发送缓冲区
+    write_buf.clear();
+    encode(write_buf)?;
+    // 异步写入网络流
+    let result = with_timeout(write_timeout, async {
+        stream.write_all(write_buf).await?;
         Ok(())
+    })
+    .await;
+    if write_buf.capacity() > MAX_RETAINED_WRITE_BUFFER {
+        *write_buf = Vec::new();
+    }
+    result
+}
+
+/// 在可选的超时时间内把 `pending` 中的数据完整写入流
+///
+/// 每次 `write` 要么写入一部分数据，要么被取消而没有写入任何数据，写入的部分立即从缓冲区移除，
+/// 因此在任意位置被取消后，缓冲区中剩下的正好是尚未写入的数据。
+async fn flush_pending<W: AsyncWrite + Unpin>(
+    stream: &mut W,
+    pending: &mut BytesMut,
+    write_timeout: Option<Duration>,
+) -> Result<(), ZerustError> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+    with_timeout(write_timeout, async {
+        while !pending.is_empty() {
+            let written = stream.write(pending).await?;
+            if written == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+            }
+            pending.advance(written);
+        }
+        stream.flush().await?;
+        Ok(())
+    })
+    .await
+}
+
+/// 连接到服务器并启用 `TCP_NODELAY`，与服务器的默认设置相同
+///
+/// 客户端发送的请求通常很小，禁用 Nagle 算法后请求会立即发送。
+/// 设置失败不影响连接的正常使用。
+pub(crate) async fn connect_tcp(addr: impl ToSocketAddrs) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect(addr).await?;
+    let _ = stream.set_nodelay(true);
+    Ok(stream)
+}
+
+/// 在可选的超时时间内等待一个IO操作完成
+///
+/// `timeout` 为 `None` 时直接等待操作完成；超时则返回 `ZerustError::Timeout`。
+pub(crate) async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    op: impl Future<Output = Result<T, ZerustError>>,
+) -> Result<T, ZerustError> {
+    match timeout {
+        Some(limit) => runtime::timeout(limit, op)
+            .await
+            .map_err(|_| ZerustError::Timeout)?,
+        None => op.await,
     }
 }
@@ -0,0 +1,162 @@
+//! # 连接上下文模块
+//!
+//! 该模块定义了描述一个连接的上下文信息，包括连接ID、客户端地址和建立时间。
+//! 服务器读取的每个请求都携带其所属连接的上下文，处理函数可以通过
+//! `Request::context` 获取，例如记录客户端IP，或者根据连接ID查找连接。
+//!
+//! 上下文还保存连接的自定义属性（与 Zinx 的 `SetProperty`/`GetProperty` 相同），
+//! 例如登录后的用户ID，同一个连接后续的请求可以读取这些属性。
+
+use dashmap::DashMap;
+use std::any::Any;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+/// 连接的自定义属性，上下文的所有克隆共享同一份
+type Properties = Arc<DashMap<String, Arc<dyn Any + Send + Sync>>>;
+
+/// 连接的上下文信息
+///
+/// 上下文在连接建立时创建，除自定义属性和握手协商出的协议版本外此后不会改变；克隆的开销很小，
+/// 可以随请求一起传递给处理函数，也可以保存下来在之后使用。
+/// 所有克隆共享同一份自定义属性，服务器在连接结束、停止钩子执行完毕后清空这些属性。
+///
+/// # 示例
+///
+/// ```rust
+/// use zerust::ConnContext;
+///
+/// let context = ConnContext::new(1, "127.0.0.1:9000".parse().unwrap());
+/// // 登录成功后记录用户ID，之后的请求可以读取
+/// context.set_property("user_id", 42u64);
+///
+/// let shared = context.clone();
+/// assert_eq!(shared.get_property::<u64>("user_id").as_deref(), Some(&42));
+/// // 类型不匹配时返回 None
+/// assert!(shared.get_property::<String>("user_id").is_none());
+///
+/// assert!(context.remove_property("user_id"));
+/// assert!(shared.get_property::<u64>("user_id").is_none());
+/// ```
+#[derive(Clone)]
+pub struct ConnContext {
+    /// 连接ID，由服务器分配，未分配时为 0
+    conn_id: u64,
+    /// 客户端地址
+    remote_addr: SocketAddr,
+    /// 连接建立的时间
+    connected_at: Instant,
+    /// 连接的自定义属性
+    properties: Properties,
+    /// 协议握手协商出的协议版本，所有克隆共享同一份
+    protocol_version: Arc<OnceLock<u8>>,
+}
+
+impl fmt::Debug for ConnContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 属性的值是 trait 对象，只输出属性的数量
+        f.debug_struct("ConnContext")
+            .field("conn_id", &self.conn_id)
+            .field("remote_addr", &self.remote_addr)
+            .field("connected_at", &self.connected_at)
+            .field("properties", &self.properties.len())
+            .field("protocol_version", &self.protocol_version.get())
+            .finish()
+    }
+}
+
+impl ConnContext {
+    /// 创建一个新的连接上下文，建立时间为当前时间
+    ///
+    /// # 参数
+    /// * `conn_id` - 连接ID
+    /// * `remote_addr` - 客户端地址
+    ///
+    /// # 返回值
+    /// 返回一个新的 `ConnContext` 实例
+    pub fn new(conn_id: u64, remote_addr: SocketAddr) -> Self {
+        Self {
+            conn_id,
+            remote_addr,
+            connected_at: Instant::now(),
+            properties: Arc::default(),
+            protocol_version: Arc::default(),
+        }
+    }
+
+    /// 设置连接ID
+    pub(crate) fn set_conn_id(&mut self, conn_id: u64) {
+        self.conn_id = conn_id;
+    }
+
+    /// 获取连接ID
+    ///
+    /// # 返回值
+    /// 返回连接ID，由服务器分配，从 1 开始；未分配时为 0
+    pub fn conn_id(&self) -> u64 {
+        self.conn_id
+    }
+
+    /// 获取客户端地址
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// 获取连接建立的时间
+    pub fn connected_at(&self) -> Instant {
+        self.connected_at
+    }
+
+    /// 获取协议握手协商出的协议版本
+    ///
+    /// # 返回值
+    /// 服务器开启了协议握手（参见 `Server::with_protocol_version`）时返回双方协商出的版本，
+    /// 未进行握手时返回 `None`
+    pub fn protocol_version(&self) -> Option<u8> {
+        self.protocol_version.get().copied()
+    }
+
+    /// 记录协商出的协议版本，由握手成功的连接调用
+    pub(crate) fn set_protocol_version(&self, version: u8) {
+        let _ = self.protocol_version.set(version);
+    }
+
+    /// 设置连接的自定义属性，已有同名属性时替换
+    ///
+    /// # 参数
+    /// * `key` - 属性名
+    /// * `value` - 属性值，可以是任意可以在线程间共享的类型
+    pub fn set_property<T: Any + Send + Sync>(&self, key: impl Into<String>, value: T) {
+        self.properties.insert(key.into(), Arc::new(value));
+    }
+
+    /// 获取连接的自定义属性
+    ///
+    /// # 参数
+    /// * `key` - 属性名
+    ///
+    /// # 返回值
+    /// 属性存在且类型为 `T` 时返回属性值，否则返回 `None`
+    pub fn get_property<T: Any + Send + Sync>(&self, key: &str) -> Option<Arc<T>> {
+        let value = self.properties.get(key)?.value().clone();
+        value.downcast().ok()
+    }
+
+    /// 移除连接的自定义属性
+    ///
+    /// # 参数
+    /// * `key` - 属性名
+    ///
+    /// # 返回值
+    /// 属性存在时返回 `true`，否则返回 `false`
+    pub fn remove_property(&self, key: &str) -> bool {
+        self.properties.remove(key).is_some()
+    }
+
+    /// 清空连接的自定义属性，由服务器在连接结束时调用
+    pub(crate) fn clear_properties(&self) {
+        self.properties.clear();
+    }
+}
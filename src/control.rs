@@ -0,0 +1,47 @@
+//! # 控制帧模块
+//!
+//! 消息ID从 `RESERVED_MSG_ID_START` 到 `u32::MAX` 的范围保留给框架自身的控制帧，
+//! 应用程序不能为它们注册处理函数（`DefaultRouter` 的注册方法返回 `ZerustError::ReservedMsgId`）。
+//! 服务器不会把保留的消息ID交给路由器，包括 `set_fallback` 设置的兜底函数：
+//! ping 和 pong 按下文处理，其他暂未定义的控制帧直接丢弃。
+//!
+//! 目前定义了三种控制帧：
+//! * `PING_MSG_ID` - ping，数据由发送方决定
+//! * `PONG_MSG_ID` - pong，数据与对应的 ping 相同，携带序列号的帧格式中序列号也相同
+//! * `PAYLOAD_TOO_LARGE_MSG_ID` - 服务器收到过大的消息、关闭连接前发送的通知，
+//!   数据为 `Response::error` 格式的错误码 413 和错误描述，参见 `Response::payload_too_large`
+//!
+//! 收到 ping 时，服务器、`Connection::read_request` 和 `PipelineClient` 会自动回复 pong，
+//! ping 不会交给路由器或者返回给调用方。`Client` 同样不会把 pong 和消息过大的通知返回给调用方，
+//! 服务器随后关闭连接，调用方得到 `ZerustError::ConnectionClosed`。客户端可以通过 `Client::ping` 测量往返时间，
+//! 服务器的心跳默认也使用 ping（参见 `server::HeartbeatConfig`）。
+//!
+//! ```rust
+//! use zerust::control::{self, PING_MSG_ID};
+//!
+//! assert!(control::is_reserved(PING_MSG_ID));
+//! assert!(!control::is_reserved(1));
+//! ```
+
+/// 保留给控制帧的最小消息ID
+pub const RESERVED_MSG_ID_START: u32 = 0xFFFF_FF00;
+
+/// ping 控制帧的消息ID
+pub const PING_MSG_ID: u32 = 0xFFFF_FFFE;
+
+/// pong 控制帧的消息ID
+pub const PONG_MSG_ID: u32 = 0xFFFF_FFFD;
+
+/// 消息过大通知的消息ID
+pub const PAYLOAD_TOO_LARGE_MSG_ID: u32 = 0xFFFF_FFFC;
+
+/// 判断消息ID是否保留给控制帧
+///
+/// # 参数
+/// * `msg_id` - 消息ID
+///
+/// # 返回值
+/// 消息ID不小于 `RESERVED_MSG_ID_START` 时返回 `true`
+pub fn is_reserved(msg_id: u32) -> bool {
+    msg_id >= RESERVED_MSG_ID_START
+}
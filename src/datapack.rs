@@ -7,28 +7,174 @@
 //! 消息由一个 8 字节的头部和一个可变长度的数据部分组成：
 //!
 //! ### 头部 (8 bytes)
-//! * 前 4 字节：`msg_id` (u32, Little-Endian) - 消息ID，用于标识消息类型
-//! * 后 4 字节：`data_len` (u32, Little-Endian) - 表示后续数据部分的字节长度
+//! * 前 4 字节：`msg_id` (u32) - 消息ID，用于标识消息类型
+//! * 后 4 字节：`data_len` (u32) - 表示后续数据部分的字节长度
+//!
+//! 头部字段默认使用小端序（Little-Endian），也可以通过 `ByteOrderMode`
+//! 切换为大端序（Big-Endian），以便与使用网络字节序的客户端通信。
+//!
+//! Go 语言的 Zinx 默认的 DataPack 把两个字段的顺序反过来：先写 `data_len` 再写 `msg_id`，
+//! 两者都是小端序。`DataPack::zinx` 创建的实例按该顺序收发，参见 `HeaderLayout`。
 //!
 //! ### 数据部分
 //! * 紧接着头部，长度为 `data_len` 字节的原始数据
 //!
+//! ### 校验和（可选）
+//! * 使用 `pack_checked` 或 `codec::CheckedDataPack` 时，数据部分之后还有 4 字节的
+//!   CRC32 校验和，字节序与头部相同；`data_len` 不包含这 4 字节
+//!
 //! 该协议设计简单高效，适用于各种网络通信场景。
+//!
+//! ## 在自己的事件循环中分帧
+//!
+//! 不使用 `Connection` 时，可以把收到的数据追加到缓冲区，再通过 `try_unpack`
+//! 逐个取出完整的帧；缓冲区中的数据还不足一个帧时返回 `Ok(None)`，等待更多数据即可。
+//!
+//! ```rust
+//! use zerust::datapack::DataPack;
+//!
+//! let mut received = DataPack::try_pack(1, b"hello").unwrap();
+//! received.extend_from_slice(&DataPack::try_pack(2, b"world").unwrap()[..5]);
+//!
+//! let (msg_id, data, consumed) = DataPack::try_unpack(&received).unwrap().unwrap();
+//! assert_eq!((msg_id, data), (1, &b"hello"[..]));
+//! received.drain(..consumed);
+//! // 第二个帧还不完整
+//! assert!(DataPack::try_unpack(&received).unwrap().is_none());
+//! ```
 
+use crate::codec::PacketCodec;
 use crate::error::ZerustError;
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use crate::request::Request;
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use bytes::{Bytes, BytesMut};
 use std::io::Cursor;
 
+/// 默认允许的最大数据长度（8 MiB）
+///
+/// 消息头中的 `data_len` 超过该值时，连接会在分配缓冲区之前拒绝该消息，
+/// 防止恶意客户端通过伪造的消息头耗尽服务器内存。
+pub const DEFAULT_MAX_PACKET_SIZE: u32 = 8 * 1024 * 1024;
+
+/// `DataPack::try_unpack` 解析出的帧：消息ID、数据，以及该帧占用的字节数
+pub type UnpackedFrame<'a> = (u32, &'a [u8], usize);
+
+/// 消息头字段的字节序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ByteOrderMode {
+    /// 小端序，框架的默认字节序
+    #[default]
+    Little,
+    /// 大端序，即网络字节序
+    Big,
+}
+
+/// 消息头中两个字段的顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderLayout {
+    /// 先 `msg_id` 后 `data_len`，框架的默认顺序
+    #[default]
+    IdThenLen,
+    /// 先 `data_len` 后 `msg_id`，与 Go 语言的 Zinx 默认的 DataPack 相同
+    LenThenId,
+}
+
 /// 数据包处理工具
 ///
-/// 提供了消息打包和解包的静态方法，用于实现自定义二进制协议。
-/// 该结构体不需要实例化，所有方法都是静态方法。
-pub struct DataPack;
+/// 提供了消息打包和解包的方法，用于实现自定义二进制协议。
+///
+/// * 静态方法 `try_pack`、`unpack_header` 和 `unpack_header_with_limit` 固定使用小端序和默认的字段顺序
+/// * 通过 `DataPack::with_order`、`DataPack::zinx` 等创建的实例，其 `try_encode`、`decode_header`
+///   和 `decode_header_with_limit` 方法使用实例配置的字节序和字段顺序
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DataPack {
+    /// 消息头字段使用的字节序
+    order: ByteOrderMode,
+    /// 消息头中两个字段的顺序
+    layout: HeaderLayout,
+}
 
 impl DataPack {
+    /// 消息头部大小，单位为字节：msg_id(4) + data_len(4)
+    pub const HEADER_SIZE: usize = 8;
+
+    /// 数据部分之后的 CRC32 校验和大小，单位为字节
+    pub const CHECKSUM_SIZE: usize = 4;
+
+    /// 创建一个使用指定字节序的数据包处理工具
+    ///
+    /// # 参数
+    /// * `order` - 消息头字段使用的字节序
+    ///
+    /// # 返回值
+    /// 返回一个新的 `DataPack` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::datapack::{ByteOrderMode, DataPack};
+    ///
+    /// let pack = DataPack::with_order(ByteOrderMode::Big);
+    /// let bytes = pack.try_encode(1, b"hi").unwrap();
+    /// assert_eq!(&bytes[..4], &[0, 0, 0, 1]);
+    /// assert_eq!(pack.decode_header(&bytes[..8]).unwrap(), (1, 2));
+    /// ```
+    pub fn with_order(order: ByteOrderMode) -> Self {
+        Self {
+            order,
+            layout: HeaderLayout::default(),
+        }
+    }
+
+    /// 创建一个与 Go 语言的 Zinx 默认的 DataPack 兼容的数据包处理工具
+    ///
+    /// 消息头为小端序的 `data_len` 加上小端序的 `msg_id`，
+    /// 服务器和连接通过 `with_datapack` 使用它即可与现有的 Zinx 客户端通信。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::datapack::DataPack;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// let pack = DataPack::zinx();
+    /// assert_eq!(pack.try_encode(1, b"hi").unwrap(), [2, 0, 0, 0, 1, 0, 0, 0, b'h', b'i']);
+    ///
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new())).with_datapack(pack);
+    /// ```
+    pub fn zinx() -> Self {
+        Self::with_order(ByteOrderMode::Little).with_header_layout(HeaderLayout::LenThenId)
+    }
+
+    /// 设置消息头中两个字段的顺序
+    ///
+    /// # 参数
+    /// * `layout` - 消息头中两个字段的顺序
+    ///
+    /// # 返回值
+    /// 返回使用该顺序的 `DataPack` 实例，字节序保持不变
+    pub fn with_header_layout(mut self, layout: HeaderLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// 获取消息头字段使用的字节序
+    ///
+    /// # 返回值
+    /// 返回当前实例配置的字节序
+    pub fn order(&self) -> ByteOrderMode {
+        self.order
+    }
+
+    /// 获取消息头中两个字段的顺序
+    pub fn header_layout(&self) -> HeaderLayout {
+        self.layout
+    }
+
     /// 解包消息头信息
     ///
-    /// 从给定的字节切片中读取消息ID和数据长度信息
+    /// 从给定的字节切片中以小端序读取消息ID和数据长度信息
     ///
     /// # 参数
     /// * `header` - 包含消息头信息的字节切片
@@ -39,17 +185,98 @@ impl DataPack {
     /// * `data_len` - 数据长度
     ///
     /// # 错误处理
-    /// 当字节切片长度不足或格式不正确时，会返回相应的ZerustError错误
+    /// 字节切片短于 `HEADER_SIZE` 时返回 `ZerustError::InvalidHeader`
     pub fn unpack_header(header: &[u8]) -> Result<(u32, u32), ZerustError> {
-        // 创建游标用于读取字节数据
-        let mut cursor = Cursor::new(header);
-        // 以小端序读取消息ID和数据长度
-        let msg_id = cursor.read_u32::<LittleEndian>()?;
-        let data_len = cursor.read_u32::<LittleEndian>()?;
-        Ok((msg_id, data_len))
+        Self::default().decode_header(header)
     }
 
-    /// 将消息ID和数据打包成字节向量
+    /// 解包消息头信息，并检查数据长度是否超过限制
+    ///
+    /// 与 `unpack_header` 相同，但在 `data_len` 大于 `max_len` 时返回错误，
+    /// 调用方可以据此在分配消息体缓冲区之前拒绝过大的消息。
+    ///
+    /// # 参数
+    /// * `header` - 包含消息头信息的字节切片
+    /// * `max_len` - 允许的最大数据长度
+    ///
+    /// # 返回值
+    /// 成功时返回 `(msg_id, data_len)` 元组
+    ///
+    /// # 错误处理
+    /// * 消息头格式不正确时返回与 `unpack_header` 相同的错误
+    /// * `data_len` 超过 `max_len` 时返回 `ZerustError::MessageTooLarge`
+    pub fn unpack_header_with_limit(
+        header: &[u8],
+        max_len: u32,
+    ) -> Result<(u32, u32), ZerustError> {
+        Self::default().decode_header_with_limit(header, max_len)
+    }
+
+    /// 从字节切片的开头以小端序解析一个完整的帧，不复制数据
+    ///
+    /// 数据长度限制为 `DEFAULT_MAX_PACKET_SIZE`，需要其他限制时使用 `try_unpack_with_limit`。
+    ///
+    /// # 参数
+    /// * `buf` - 已经收到的数据，可以包含多个帧或不完整的帧
+    ///
+    /// # 返回值
+    /// * `Ok(Some((msg_id, data, consumed)))` - 第一个帧的消息ID、数据，以及该帧占用的字节数
+    /// * `Ok(None)` - 数据不足一个完整的帧（包括消息头不完整）
+    /// * `Err(ZerustError::MessageTooLarge)` - 消息头声明的数据长度超过限制，之后的数据无法再分帧
+    pub fn try_unpack(buf: &[u8]) -> Result<Option<UnpackedFrame<'_>>, ZerustError> {
+        Self::try_unpack_with_limit(buf, DEFAULT_MAX_PACKET_SIZE)
+    }
+
+    /// 从字节切片的开头以小端序解析一个完整的帧，并检查数据长度是否超过限制
+    ///
+    /// # 参数
+    /// * `buf` - 已经收到的数据
+    /// * `max_len` - 允许的最大数据长度
+    ///
+    /// # 返回值
+    /// 同 `try_unpack`
+    pub fn try_unpack_with_limit(
+        buf: &[u8],
+        max_len: u32,
+    ) -> Result<Option<UnpackedFrame<'_>>, ZerustError> {
+        Self::default().try_decode(buf, max_len)
+    }
+
+    /// 从接收缓冲区中以小端序取出一个完整的帧
+    ///
+    /// 与 `try_unpack` 相同，但解析出的帧会从缓冲区中移除，数据与缓冲区共享内存；
+    /// 数据不足一个完整的帧时缓冲区保持不变。
+    ///
+    /// # 参数
+    /// * `buf` - 接收缓冲区
+    ///
+    /// # 返回值
+    /// * `Ok(Some((msg_id, data)))` - 取出的一个完整消息
+    /// * `Ok(None)` - 数据不足一个完整的帧
+    /// * `Err(ZerustError::MessageTooLarge)` - 数据长度超过 `DEFAULT_MAX_PACKET_SIZE`
+    pub fn try_unpack_buf(buf: &mut BytesMut) -> Result<Option<(u32, Bytes)>, ZerustError> {
+        PacketCodec::decode(&Self::default(), buf, DEFAULT_MAX_PACKET_SIZE)
+    }
+
+    /// 从字节切片的开头以小端序解析一个完整的帧，返回对应的请求
+    ///
+    /// 与 `try_unpack` 相同，但数据被复制到新创建的 `Request` 中，返回后可以立即
+    /// 从缓冲区中移除已经解析的字节。数据长度限制为 `DEFAULT_MAX_PACKET_SIZE`。
+    /// 需要实现 `tokio_util::codec::Decoder` 时，可以直接使用 `framed::ZerustCodec`
+    /// （需要开启 `codec` 功能）。
+    ///
+    /// # 参数
+    /// * `buf` - 已经收到的数据，可以包含多个帧或不完整的帧
+    ///
+    /// # 返回值
+    /// * `Ok(Some((request, consumed)))` - 第一个帧对应的请求，以及该帧占用的字节数
+    /// * `Ok(None)` - 数据不足一个完整的帧
+    /// * `Err(ZerustError::MessageTooLarge)` - 消息头声明的数据长度超过限制
+    pub fn try_unpack_request(buf: &[u8]) -> Result<Option<(Request, usize)>, ZerustError> {
+        Self::default().try_decode_request(buf, DEFAULT_MAX_PACKET_SIZE)
+    }
+
+    /// 将消息ID和数据以小端序打包成字节向量
     ///
     /// 该函数按照特定协议格式将消息ID和数据封装成一个字节向量，
     /// 格式为：消息ID(4字节)+数据长度(4字节)+数据内容
@@ -60,15 +287,396 @@ impl DataPack {
     ///
     /// # 返回值
     /// 返回包含打包后数据的字节向量
+    ///
+    /// # Panics
+    /// 当数据长度超过 `u32::MAX`、无法写入消息头时会 panic。
+    /// 已弃用，请使用返回错误的 `try_pack`
+    #[deprecated(note = "use `try_pack`, which returns an error instead of panicking")]
     pub fn pack(msg_id: u32, data: &[u8]) -> Vec<u8> {
+        Self::try_pack(msg_id, data).expect("data length exceeds u32::MAX")
+    }
+
+    /// 将消息ID和数据以小端序打包成字节向量，数据过长时返回错误
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，32位无符号整数
+    /// * `data` - 要打包的数据切片
+    ///
+    /// # 返回值
+    /// * `Ok(Vec<u8>)` - 打包后的字节向量
+    /// * `Err(ZerustError::MessageTooLarge)` - 数据长度超过 `u32::MAX`
+    pub fn try_pack(msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        Self::default().try_encode(msg_id, data)
+    }
+
+    /// 将消息ID和数据以小端序追加到已有的缓冲区末尾
+    ///
+    /// 与 `try_pack` 产生的字节完全相同，但不分配新的缓冲区，
+    /// 适合在发送大量小消息时复用同一个缓冲区。缓冲区中原有的数据会被保留。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，32位无符号整数
+    /// * `data` - 要打包的数据切片
+    /// * `buf` - 追加打包结果的缓冲区
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 打包结果已追加到缓冲区
+    /// * `Err(ZerustError::MessageTooLarge)` - 数据长度超过 `u32::MAX`，缓冲区保持不变
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::datapack::DataPack;
+    ///
+    /// let mut buf = Vec::with_capacity(64);
+    /// for i in 0..3 {
+    ///     buf.clear();
+    ///     DataPack::pack_into(i, b"tick", &mut buf).unwrap();
+    ///     assert_eq!(buf, DataPack::try_pack(i, b"tick").unwrap());
+    /// }
+    /// ```
+    pub fn pack_into(msg_id: u32, data: &[u8], buf: &mut Vec<u8>) -> Result<(), ZerustError> {
+        Self::default().encode_into(msg_id, data, buf)
+    }
+
+    /// 按实例配置的字节序和字段顺序解包消息头信息
+    ///
+    /// # 参数
+    /// * `header` - 包含消息头信息的字节切片
+    ///
+    /// # 返回值
+    /// 成功时返回 `(msg_id, data_len)` 元组
+    ///
+    /// # 错误处理
+    /// 字节切片短于 `HEADER_SIZE` 时返回 `ZerustError::InvalidHeader`，
+    /// 超出 `HEADER_SIZE` 的部分会被忽略
+    pub fn decode_header(&self, header: &[u8]) -> Result<(u32, u32), ZerustError> {
+        // 长度不足时直接返回，不交给 byteorder 产生 IoError
+        if header.len() < Self::HEADER_SIZE {
+            return Err(ZerustError::InvalidHeader);
+        }
+        // 创建游标用于读取字节数据
+        let mut cursor = Cursor::new(header);
+        // 按配置的字节序和字段顺序读取消息ID和数据长度
+        let first = self.read_u32(&mut cursor)?;
+        let second = self.read_u32(&mut cursor)?;
+        match self.layout {
+            HeaderLayout::IdThenLen => Ok((first, second)),
+            HeaderLayout::LenThenId => Ok((second, first)),
+        }
+    }
+
+    /// 按实例配置的字节序解包消息头信息，并检查数据长度是否超过限制
+    ///
+    /// # 参数
+    /// * `header` - 包含消息头信息的字节切片
+    /// * `max_len` - 允许的最大数据长度
+    ///
+    /// # 返回值
+    /// 成功时返回 `(msg_id, data_len)` 元组
+    ///
+    /// # 错误处理
+    /// * 消息头格式不正确时返回与 `decode_header` 相同的错误
+    /// * `data_len` 超过 `max_len` 时返回 `ZerustError::MessageTooLarge`
+    pub fn decode_header_with_limit(
+        &self,
+        header: &[u8],
+        max_len: u32,
+    ) -> Result<(u32, u32), ZerustError> {
+        let (msg_id, data_len) = self.decode_header(header)?;
+        if data_len > max_len {
+            return Err(ZerustError::MessageTooLarge {
+                size: data_len as u64,
+                limit: max_len as u64,
+            });
+        }
+        Ok((msg_id, data_len))
+    }
+
+    /// 按实例配置的字节序和字段顺序，从字节切片的开头解析一个完整的帧
+    ///
+    /// # 参数
+    /// * `buf` - 已经收到的数据
+    /// * `max_len` - 允许的最大数据长度
+    ///
+    /// # 返回值
+    /// 同 `try_unpack`
+    pub fn try_decode<'a>(
+        &self,
+        buf: &'a [u8],
+        max_len: u32,
+    ) -> Result<Option<UnpackedFrame<'a>>, ZerustError> {
+        if buf.len() < Self::HEADER_SIZE {
+            return Ok(None);
+        }
+        let (msg_id, data_len) =
+            self.decode_header_with_limit(&buf[..Self::HEADER_SIZE], max_len)?;
+        let frame_len = Self::checked_frame_len(Self::HEADER_SIZE, data_len)?;
+        if buf.len() < frame_len {
+            return Ok(None);
+        }
+        Ok(Some((
+            msg_id,
+            &buf[Self::HEADER_SIZE..frame_len],
+            frame_len,
+        )))
+    }
+
+    /// 按实例配置的字节序和字段顺序，从字节切片的开头解析一个完整的帧并返回对应的请求
+    ///
+    /// # 参数
+    /// * `buf` - 已经收到的数据
+    /// * `max_len` - 允许的最大数据长度
+    ///
+    /// # 返回值
+    /// 同 `try_unpack_request`
+    pub fn try_decode_request(
+        &self,
+        buf: &[u8],
+        max_len: u32,
+    ) -> Result<Option<(Request, usize)>, ZerustError> {
+        Ok(self
+            .try_decode(buf, max_len)?
+            .map(|(msg_id, data, consumed)| (Request::new(msg_id, data.to_vec()), consumed)))
+    }
+
+    /// 按实例配置的字节序将消息ID和数据打包成字节向量
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，32位无符号整数
+    /// * `data` - 要打包的数据切片
+    ///
+    /// # 返回值
+    /// 返回包含打包后数据的字节向量
+    ///
+    /// # Panics
+    /// 当数据长度超过 `u32::MAX`、无法写入消息头时会 panic。
+    /// 已弃用，请使用返回错误的 `try_encode`
+    #[deprecated(note = "use `try_encode`, which returns an error instead of panicking")]
+    pub fn encode(&self, msg_id: u32, data: &[u8]) -> Vec<u8> {
+        self.try_encode(msg_id, data)
+            .expect("data length exceeds u32::MAX")
+    }
+
+    /// 按实例配置的字节序将消息ID和数据打包成字节向量，数据过长时返回错误
+    ///
+    /// 消息头中的数据长度字段只有 4 字节，超过 `u32::MAX` 的数据无法正确表示，
+    /// 此时返回错误而不是截断长度字段。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，32位无符号整数
+    /// * `data` - 要打包的数据切片
+    ///
+    /// # 返回值
+    /// * `Ok(Vec<u8>)` - 打包后的字节向量
+    /// * `Err(ZerustError::MessageTooLarge)` - 数据长度超过 `u32::MAX`
+    pub fn try_encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
         // 创建缓冲区，容量为头部8字节加上数据长度
-        let mut buf = Vec::with_capacity(8 + data.len());
-        // 写入消息ID，使用小端序
-        buf.write_u32::<LittleEndian>(msg_id).unwrap();
-        // 写入数据长度，使用小端序
-        buf.write_u32::<LittleEndian>(data.len() as u32).unwrap();
+        let mut buf = Vec::with_capacity(Self::HEADER_SIZE + data.len());
+        self.encode_into(msg_id, data, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// 按实例配置的字节序将消息ID和数据追加到已有的缓冲区末尾
+    ///
+    /// 与 `try_encode` 产生的字节完全相同，但不分配新的缓冲区。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，32位无符号整数
+    /// * `data` - 要打包的数据切片
+    /// * `buf` - 追加打包结果的缓冲区
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 打包结果已追加到缓冲区
+    /// * `Err(ZerustError::MessageTooLarge)` - 数据长度超过 `u32::MAX`，缓冲区保持不变
+    pub fn encode_into(
+        &self,
+        msg_id: u32,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), ZerustError> {
+        let data_len = u32::try_from(data.len()).map_err(|_| ZerustError::MessageTooLarge {
+            size: data.len() as u64,
+            limit: u32::MAX as u64,
+        })?;
+        buf.reserve(Self::HEADER_SIZE + data.len());
+        buf.extend_from_slice(&self.encode_header(msg_id, data_len));
         // 追加数据内容
         buf.extend_from_slice(data);
-        buf
+        Ok(())
+    }
+
+    /// 按实例配置的字节序和字段顺序编码消息头
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data_len` - 数据长度
+    ///
+    /// # 返回值
+    /// 返回 `HEADER_SIZE` 字节的消息头，与 `encode` 产生的帧的开头相同
+    pub fn encode_header(&self, msg_id: u32, data_len: u32) -> [u8; Self::HEADER_SIZE] {
+        // 按配置的字段顺序写入消息ID和数据长度
+        let (first, second) = match self.layout {
+            HeaderLayout::IdThenLen => (msg_id, data_len),
+            HeaderLayout::LenThenId => (data_len, msg_id),
+        };
+        let to_bytes = |value: u32| match self.order {
+            ByteOrderMode::Little => value.to_le_bytes(),
+            ByteOrderMode::Big => value.to_be_bytes(),
+        };
+        let mut header = [0; Self::HEADER_SIZE];
+        header[..4].copy_from_slice(&to_bytes(first));
+        header[4..].copy_from_slice(&to_bytes(second));
+        header
+    }
+
+    /// 以小端序打包消息，并在数据之后追加 CRC32 校验和
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 要打包的数据切片
+    ///
+    /// # 返回值
+    /// 返回包含消息头、数据和校验和的字节向量
+    ///
+    /// # Panics
+    /// 当数据长度超过 `u32::MAX` 时会 panic。
+    /// 已弃用，请使用返回错误的 `try_pack_checked`
+    #[deprecated(note = "use `try_pack_checked`, which returns an error instead of panicking")]
+    pub fn pack_checked(msg_id: u32, data: &[u8]) -> Vec<u8> {
+        Self::try_pack_checked(msg_id, data).expect("data length exceeds u32::MAX")
+    }
+
+    /// 以小端序打包消息，并在数据之后追加 CRC32 校验和，数据过长时返回错误
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 要打包的数据切片
+    ///
+    /// # 返回值
+    /// * `Ok(Vec<u8>)` - 包含消息头、数据和校验和的字节向量
+    /// * `Err(ZerustError::MessageTooLarge)` - 数据长度超过 `u32::MAX`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::datapack::DataPack;
+    ///
+    /// let frame = DataPack::try_pack_checked(1, b"hello").unwrap();
+    /// assert_eq!(frame.len(), DataPack::HEADER_SIZE + 5 + DataPack::CHECKSUM_SIZE);
+    /// assert_eq!(DataPack::unpack_checked(&frame).unwrap(), (1, &b"hello"[..]));
+    /// ```
+    pub fn try_pack_checked(msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        let mut buf = Vec::new();
+        Self::default().encode_checked_into(msg_id, data, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// 以小端序解包一个带校验和的完整帧，并验证校验和
+    ///
+    /// # 参数
+    /// * `frame` - 一个完整的帧，包含消息头、数据和校验和
+    ///
+    /// # 返回值
+    /// 成功时返回 `(msg_id, data)` 元组，`data` 借用自 `frame`
+    ///
+    /// # 错误处理
+    /// * 帧短于消息头与校验和的长度之和时返回 `ZerustError::InvalidHeader`
+    /// * 帧的长度与消息头中的数据长度不一致时返回 `ZerustError::ProtocolError`
+    /// * 校验和不匹配时返回 `ZerustError::ChecksumMismatch`
+    pub fn unpack_checked(frame: &[u8]) -> Result<(u32, &[u8]), ZerustError> {
+        Self::default().decode_checked(frame)
+    }
+
+    /// 按实例配置的字节序打包消息并追加 CRC32 校验和，追加到已有的缓冲区末尾
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 要打包的数据切片
+    /// * `buf` - 追加打包结果的缓冲区
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 打包结果已追加到缓冲区
+    /// * `Err(ZerustError::MessageTooLarge)` - 数据长度超过 `u32::MAX`，缓冲区保持不变
+    pub fn encode_checked_into(
+        &self,
+        msg_id: u32,
+        data: &[u8],
+        buf: &mut Vec<u8>,
+    ) -> Result<(), ZerustError> {
+        self.encode_into(msg_id, data, buf)?;
+        self.write_u32(buf, crc32fast::hash(data));
+        Ok(())
+    }
+
+    /// 按实例配置的字节序解包一个带校验和的完整帧，并验证校验和
+    ///
+    /// 错误处理与 `unpack_checked` 相同。
+    pub fn decode_checked<'a>(&self, frame: &'a [u8]) -> Result<(u32, &'a [u8]), ZerustError> {
+        if frame.len() < Self::HEADER_SIZE + Self::CHECKSUM_SIZE {
+            return Err(ZerustError::InvalidHeader);
+        }
+        let (msg_id, data_len) = self.decode_header(frame)?;
+        let frame_len = Self::checked_frame_len(Self::HEADER_SIZE + Self::CHECKSUM_SIZE, data_len)?;
+        let data_end = frame_len - Self::CHECKSUM_SIZE;
+        if frame.len() != frame_len {
+            return Err(ZerustError::ProtocolError(format!(
+                "frame length {} does not match data_len {data_len}",
+                frame.len()
+            )));
+        }
+        let data = &frame[Self::HEADER_SIZE..data_end];
+        self.verify_checksum(data, &frame[data_end..])?;
+        Ok((msg_id, data))
+    }
+
+    /// 验证数据的 CRC32 与按实例配置的字节序编码的校验和是否一致
+    ///
+    /// # 参数
+    /// * `data` - 收到的数据
+    /// * `checksum` - 帧中携带的 4 字节校验和
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 校验和一致
+    /// * `Err(ZerustError::ChecksumMismatch)` - 数据在传输中被损坏
+    pub fn verify_checksum(&self, data: &[u8], checksum: &[u8]) -> Result<(), ZerustError> {
+        let expected = self.read_u32(&mut Cursor::new(checksum))?;
+        let actual = crc32fast::hash(data);
+        if expected != actual {
+            return Err(ZerustError::ChecksumMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// 计算帧的总长度：固定部分（消息头和校验和）的长度加上数据长度
+    ///
+    /// 在 `usize` 为 32 位或更小的平台上，消息头声明的数据长度加上固定部分可能溢出，
+    /// 此时返回 `ZerustError::MessageTooLarge`。
+    pub(crate) fn checked_frame_len(overhead: usize, data_len: u32) -> Result<usize, ZerustError> {
+        usize::try_from(data_len)
+            .ok()
+            .and_then(|len| len.checked_add(overhead))
+            .ok_or(ZerustError::MessageTooLarge {
+                size: data_len as u64 + overhead as u64,
+                limit: usize::MAX as u64,
+            })
+    }
+
+    /// 按配置的字节序从游标中读取一个 u32
+    fn read_u32(&self, cursor: &mut Cursor<&[u8]>) -> Result<u32, ZerustError> {
+        let value = match self.order {
+            ByteOrderMode::Little => cursor.read_u32::<LittleEndian>()?,
+            ByteOrderMode::Big => cursor.read_u32::<BigEndian>()?,
+        };
+        Ok(value)
+    }
+
+    /// 按配置的字节序向缓冲区写入一个 u32
+    fn write_u32(&self, buf: &mut Vec<u8>, value: u32) {
+        // 写入 Vec 不会失败
+        match self.order {
+            ByteOrderMode::Little => buf.write_u32::<LittleEndian>(value).unwrap(),
+            ByteOrderMode::Big => buf.write_u32::<BigEndian>(value).unwrap(),
+        }
     }
 }
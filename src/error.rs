@@ -37,4 +37,67 @@ pub enum ZerustError {
     /// 当消息不符合协议规范时会返回此错误，附带具体的错误描述。
     #[error("Protocol error: {0}")]
     ProtocolError(String),
+
+    /// 消息过大错误
+    ///
+    /// 当收到的消息头声明的数据长度超过连接允许的最大值，
+    /// 或待发送的数据长度超过协议能够表示的范围时会返回此错误。
+    ///
+    /// 带长度限制的消息头解析（`DataPack::unpack_header_with_limit` 等）用它表示负载过大，
+    /// 服务器收到该错误时发送 `Server::with_oversized_response` 设置的响应
+    /// （默认为 `Response::payload_too_large`）后关闭连接。
+    /// 发送方向的长度可能超过 `u32::MAX`，因此两个字段都使用 `u64`。
+    #[error("Message too large: {size} bytes exceeds limit of {limit} bytes")]
+    MessageTooLarge {
+        /// 消息的数据长度
+        size: u64,
+        /// 允许的最大数据长度
+        limit: u64,
+    },
+
+    /// 校验和不匹配错误
+    ///
+    /// 当使用带校验和的帧格式（参见 `codec::CheckedDataPack`）时，
+    /// 收到的数据的 CRC32 与帧中携带的校验和不一致，说明数据在传输中被损坏。
+    #[error("Checksum mismatch: expected {expected:#010x}, got {actual:#010x}")]
+    ChecksumMismatch {
+        /// 帧中携带的校验和
+        expected: u32,
+        /// 按收到的数据计算出的校验和
+        actual: u32,
+    },
+
+    /// 操作超时错误
+    ///
+    /// 当读取请求或发送响应在配置的超时时间内未能完成时会返回此错误。
+    #[error("Operation timed out")]
+    Timeout,
+
+    /// 发送队列已满错误
+    ///
+    /// 向连接推送消息时，该连接的发送队列已经达到容量上限，消息被丢弃。
+    /// 通常说明客户端读取数据的速度跟不上服务器发送的速度，参见 `conn_manager::WriteQueuePolicy`。
+    #[error("Write queue full")]
+    WriteQueueFull,
+
+    /// 服务器配置错误，包含错误描述信息
+    ///
+    /// 当服务器配置中存在无效的取值（例如最大连接数为 0）时，
+    /// 构建或启动服务器会返回此错误。
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+
+    /// 处理函数发生了 panic，包含 panic 的信息
+    ///
+    /// 服务器捕获处理函数的 panic 后，把它作为处理函数返回的错误交给错误响应生成函数
+    /// （参见 `Server::with_error_handler`），连接继续处理后续请求。
+    #[error("Handler panicked: {0}")]
+    HandlerPanic(String),
+
+    /// 消息ID保留给控制帧错误
+    ///
+    /// 为保留给控制帧的消息ID（参见 `control::is_reserved`）注册处理函数时会返回此错误，
+    /// 这些消息由框架自身处理，不会交给路由器。
+    #[error("Message ID {0:#010x} is reserved for control frames")]
+    ReservedMsgId(u32),
 }
@@ -0,0 +1,160 @@
+//! # Framed 编解码模块
+//!
+//! `ZerustCodec` 实现了 `tokio_util::codec` 的 `Decoder` 和 `Encoder`，
+//! 不使用 `Server` 和 `Connection` 的程序可以通过 `Framed` 收发 Zerust 的帧，
+//! 例如在已有的事件循环中与 Zerust 服务器通信。
+//!
+//! 帧格式与 `DataPack` 完全相同：消息头的解析和编码都交给 `DataPack`，
+//! 编码结果与 `DataPack::pack`（或对应实例的 `encode`）逐字节一致。
+//!
+//! 需要开启 `codec` 功能。
+//!
+//! # 示例
+//!
+//! ```rust
+//! use bytes::Bytes;
+//! use futures::{SinkExt, StreamExt};
+//! use tokio_util::codec::Framed;
+//! use zerust::framed::ZerustCodec;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), zerust::ZerustError> {
+//! let (client, server) = tokio::io::duplex(1024);
+//! let mut client = Framed::new(client, ZerustCodec::new());
+//! let mut server = Framed::new(server, ZerustCodec::new());
+//!
+//! client.send((1, Bytes::from_static(b"ping"))).await?;
+//! let (msg_id, data) = server.next().await.unwrap()?;
+//! assert_eq!((msg_id, &data[..]), (1, &b"ping"[..]));
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::datapack::{DEFAULT_MAX_PACKET_SIZE, DataPack};
+use crate::error::ZerustError;
+use crate::response::Response;
+use bytes::{Buf, Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Zerust 帧格式的编解码器，参见模块文档
+///
+/// 解码出的消息为 `(msg_id, data)`，数据与接收缓冲区共享内存，不复制数据。
+/// 可以编码 `(msg_id, data)` 或者 `Response`；批量响应依次编码其中的每条消息，
+/// `Response::none` 不产生任何数据。
+#[derive(Debug, Clone, Copy)]
+pub struct ZerustCodec {
+    /// 消息头的字节序和字段顺序
+    datapack: DataPack,
+    /// 允许接收的最大数据长度
+    max_frame_length: u32,
+}
+
+impl ZerustCodec {
+    /// 创建使用默认 `DataPack`、最大数据长度为 `DEFAULT_MAX_PACKET_SIZE` 的编解码器
+    pub fn new() -> Self {
+        Self {
+            datapack: DataPack::default(),
+            max_frame_length: DEFAULT_MAX_PACKET_SIZE,
+        }
+    }
+
+    /// 设置消息头的字节序和字段顺序，例如 `DataPack::zinx()`
+    ///
+    /// # 参数
+    /// * `datapack` - 解析和编码消息头的 `DataPack`
+    pub fn with_datapack(mut self, datapack: DataPack) -> Self {
+        self.datapack = datapack;
+        self
+    }
+
+    /// 设置允许接收的最大数据长度
+    ///
+    /// 消息头声明的数据长度超过该值时，解码返回 `ZerustError::MessageTooLarge`，
+    /// 不会为消息体预留空间。编码不受该限制。
+    ///
+    /// # 参数
+    /// * `max_frame_length` - 允许的最大数据长度（字节）
+    pub fn with_max_frame_length(mut self, max_frame_length: u32) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// 获取解析和编码消息头的 `DataPack`
+    pub fn datapack(&self) -> DataPack {
+        self.datapack
+    }
+
+    /// 获取允许接收的最大数据长度
+    pub fn max_frame_length(&self) -> u32 {
+        self.max_frame_length
+    }
+
+    /// 把一条消息编码到缓冲区末尾
+    fn encode_frame(
+        &self,
+        msg_id: u32,
+        data: &[u8],
+        dst: &mut BytesMut,
+    ) -> Result<(), ZerustError> {
+        let data_len = u32::try_from(data.len()).map_err(|_| ZerustError::MessageTooLarge {
+            size: data.len() as u64,
+            limit: u32::MAX as u64,
+        })?;
+        dst.reserve(DataPack::HEADER_SIZE + data.len());
+        dst.extend_from_slice(&self.datapack.encode_header(msg_id, data_len));
+        dst.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+impl Default for ZerustCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for ZerustCodec {
+    type Item = (u32, BytesMut);
+    type Error = ZerustError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < DataPack::HEADER_SIZE {
+            return Ok(None);
+        }
+        // 先检查消息头，数据过长时在等待消息体之前返回错误
+        let (msg_id, data_len) = self
+            .datapack
+            .decode_header_with_limit(&src[..DataPack::HEADER_SIZE], self.max_frame_length)?;
+        let frame_len = DataPack::checked_frame_len(DataPack::HEADER_SIZE, data_len)?;
+        if src.len() < frame_len {
+            // 为剩余的消息体预留空间，避免多次扩容
+            src.reserve(frame_len - src.len());
+            return Ok(None);
+        }
+        src.advance(DataPack::HEADER_SIZE);
+        Ok(Some((msg_id, src.split_to(data_len as usize))))
+    }
+}
+
+impl Encoder<(u32, Bytes)> for ZerustCodec {
+    type Error = ZerustError;
+
+    fn encode(
+        &mut self,
+        (msg_id, data): (u32, Bytes),
+        dst: &mut BytesMut,
+    ) -> Result<(), ZerustError> {
+        self.encode_frame(msg_id, &data, dst)
+    }
+}
+
+impl Encoder<Response> for ZerustCodec {
+    type Error = ZerustError;
+
+    fn encode(&mut self, resp: Response, dst: &mut BytesMut) -> Result<(), ZerustError> {
+        for frame in resp.frames() {
+            self.encode_frame(frame.msg_id(), frame.data(), dst)?;
+        }
+        Ok(())
+    }
+}
@@ -17,23 +17,95 @@
 //! * `response` - 响应封装模块，处理服务器返回的响应数据
 //! * `router` - 路由系统模块，负责根据消息ID分发请求到对应的处理函数
 //! * `datapack` - 协议编解码模块，处理数据的打包和解包
+//! * `codec` - 帧编解码接口，可以替换默认的 `DataPack` 协议
+//! * `control` - 保留给框架的控制帧（ping/pong）的消息ID
 //! * `connection` - 连接管理模块，处理TCP连接的生命周期和数据传输
+//! * `conn_manager` - 连接注册表，按连接ID查找在线连接并向其推送消息
+//! * `context` - 连接上下文，向处理函数提供连接ID、客户端地址等信息
 //! * `server` - 服务器核心模块，提供TCP服务器的基本功能
+//! * `config` - 服务器配置与构建器
+//! * `worker_pool` - 工作池，在固定数量的工作任务中处理请求
+//! * `rate_limit` - 每个连接的令牌桶限流
+//! * `metrics` - 请求数量、错误数量、收发字节数、连接数量和处理耗时直方图的统计
+//! * `client` - 客户端与客户端连接池，用于连接 Zerust 服务器
+//! * `framed` - 供 `tokio_util::codec::Framed` 使用的编解码器（需要开启 `codec` 功能）
+//!
+//! ## 可选功能
+//!
+//! * `tokio-runtime`（默认开启）- 使用 Tokio 进行网络IO、定时和任务调度，是完整支持的运行时后端
+//! * `smol-runtime` - 改用 smol 进行网络IO、定时和任务调度，服务器和客户端可以在
+//!   `smol::block_on` 中运行，不需要 Tokio 运行时。需要通过 `default-features = false` 关闭
+//!   `tokio-runtime`，两者同时开启时使用 Tokio。同步原语和关闭通道等接口仍然使用 `tokio::sync`
+//!   中的类型，它们不依赖 Tokio 运行时；处理函数中使用 Tokio 的任务或定时器时仍然需要 Tokio 运行时
+//! * `tls` - 通过 `tokio-rustls` 支持 TLS 加密连接，参见 `Server::with_tls`；
+//!   `tls` 模块提供创建配置和建立客户端连接的便捷函数
+//! * `tracing`（默认开启）- 通过 `tracing` 输出结构化日志，每个连接的日志都在带有 `conn_id`
+//!   和 `remote_addr` 字段的 `conn` span 中，处理请求的日志还在带有 `msg_id` 和 `len`
+//!   字段的 `request` span 中。客户端正常断开记录为 debug，连接出错记录为 warn。
+//!   使用 `default-features = false` 关闭后不输出任何日志，也不依赖 `tracing`；
+//!   此时需要另外开启 `tokio-runtime` 或 `smol-runtime` 选择运行时后端
+//! * `compression` - 数据长度超过阈值的消息使用 zstd 或 gzip 压缩，参见 `Server::with_compression`；
+//!   `compression` 模块提供客户端使用的 `CompressedCodec`
+//! * `prometheus` - 把服务器的指标编码为 Prometheus 的文本格式，参见 `Server::render_prometheus`；
+//!   还可以通过 `Server::with_metrics_route` 让客户端用普通的请求拉取这些文本
+//! * `metrics` - 把请求数量、处理耗时、收发字节数和连接数量同时记录到 `metrics` 库的全局记录器，
+//!   可以搭配任意 `metrics` 导出器使用；不开启时仍可以通过 `Server::metrics_snapshot` 读取这些数据
+//! * `test-util` - `test_util` 模块提供在后台任务中运行服务器的 `TestServer`，
+//!   集成测试不需要轮询端口或等待固定的时间
+//! * `serde_json` - 收发 JSON 格式的消息，参见 `Request::parse_json`、`Response::from_json`
+//!   和 `DefaultRouter::add_json_route`
+//! * `prost` - 收发 protobuf 格式的消息，参见 `Request::parse_proto`、`Response::from_proto`
+//!   和 `DefaultRouter::add_proto_route`
+//! * `codec` - `framed` 模块提供实现了 `tokio_util::codec::{Decoder, Encoder}` 的 `ZerustCodec`，
+//!   不使用 `Server` 和 `Connection` 的程序也可以通过 `Framed` 收发 Zerust 的帧
+//! * `toml` - 通过 `ServerConfig::from_toml_file` 从 TOML 文件加载服务器配置，
+//!   支持用 `ZERUST_LISTEN_ADDR` 等环境变量覆盖文件中的配置项
 //!
 //! 示例请参考 `examples` 目录中的代码。
 
 // 导出各个模块
+pub mod client;
+pub mod codec;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod config;
+pub mod conn_manager;
 pub mod connection;
+pub mod context;
+pub mod control;
 pub mod datapack;
 pub mod error;
+#[cfg(feature = "codec")]
+pub mod framed;
+pub mod metrics;
+pub mod rate_limit;
 pub mod request;
 pub mod response;
 pub mod router;
 pub mod server;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "toml")]
+mod toml_config;
+pub mod worker_pool;
+
+// 运行时适配层，仅供框架内部使用
+mod runtime;
 
 // 重新导出常用的类型，方便用户直接使用
+pub use client::{Client, ClientPool, PipelineClient, ReconnectingClient};
+pub use config::{ServerBuilder, ServerConfig};
+pub use conn_manager::{BroadcastReport, ConnManager, ConnectionHandle, WriteQueuePolicy};
+pub use context::ConnContext;
 pub use error::ZerustError;
+pub use metrics::{Metrics, MetricsSnapshot};
 pub use request::Request;
-pub use response::Response;
-pub use router::{DefaultRouter, Router};
-pub use server::Server;
+pub use response::{Response, ResponseBuilder};
+pub use router::{BoxFuture, DefaultRouter, Router};
+pub use server::{BoundServer, Server};
+
+// 开启 `tls` 功能时重新导出 TLS 相关的库，用户无需单独声明依赖
+#[cfg(feature = "tls")]
+pub use tokio_rustls::{self, rustls};
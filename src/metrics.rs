@@ -0,0 +1,548 @@
+//! # 指标模块
+//!
+//! 该模块统计服务器处理的请求数量、错误数量、收发的字节数，以及每个消息ID的请求数量和处理耗时，
+//! 不需要在每个处理函数中手动计时。服务器在处理请求和发送消息时更新计数器，
+//! 通过 `Server::metrics` 获取的 `Metrics` 可以随时生成一份快照。
+//!
+//! 开启 `prometheus` 功能后，快照可以通过 `MetricsSnapshot::encode_prometheus` 编码为
+//! Prometheus 的文本格式，直接作为 `/metrics` 接口的响应。也可以读取快照中的各项数值，自行序列化或上报。
+//!
+//! 处理耗时按 `LATENCY_BUCKETS` 统计为直方图，不依赖外部的指标库。开启 `metrics` 功能后，
+//! 服务器还会把同样的数据记录到 `metrics` 库的全局记录器中，由调用方选择的导出器上报。
+
+use dashmap::DashMap;
+use std::collections::BTreeMap;
+#[cfg(feature = "prometheus")]
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// 处理耗时直方图的桶边界
+///
+/// 每个桶统计耗时不超过该边界、且超过前一个边界的请求数量，
+/// 超过最后一个边界的请求计入额外的一个桶。
+pub const LATENCY_BUCKETS: [Duration; 15] = [
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_micros(2500),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_micros(2_500_000),
+    Duration::from_secs(5),
+];
+
+/// 直方图的桶数量，包括超过最后一个边界的桶
+const BUCKET_COUNT: usize = LATENCY_BUCKETS.len() + 1;
+
+/// 最多单独统计的消息ID数量
+///
+/// 达到该数量后，新出现的消息ID的请求合并统计到 `MetricsSnapshot::unrouted` 中。
+pub const MAX_ROUTE_SERIES: usize = 1024;
+
+/// 合并统计的请求在 `msg_id` 标签中使用的值
+#[cfg(any(feature = "prometheus", feature = "metrics"))]
+const UNROUTED_LABEL: &str = "unrouted";
+
+/// 服务器的指标计数器
+///
+/// 所有计数器都是原子变量，可以在多个任务中同时更新和读取；
+/// 服务器运行期间通过 `snapshot` 读取当前的值。
+///
+/// 只统计交给路由器处理的请求：心跳消息和工作池繁忙时被拒绝的请求不计入。
+/// 收发的字节数只包含消息数据，不包含消息头。连接数量只统计登记到连接管理器的连接，
+/// 达到连接数上限而被拒绝的连接不计入。
+///
+/// 只有路由器注册了处理函数的消息ID（参见 `Router::has_route`）单独统计，且最多
+/// `MAX_ROUTE_SERIES` 个；其余请求合并统计，带有 `msg_id="unrouted"` 标签。
+/// 客户端发送大量不同的未注册消息ID时，指标占用的内存不会随之增长。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use tokio::sync::oneshot;
+/// use zerust::{Client, DefaultRouter, Response, Server};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), zerust::ZerustError> {
+/// let router = Arc::new(DefaultRouter::new());
+/// router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec())).unwrap();
+/// let server = Server::new("127.0.0.1:0", router);
+/// let metrics = server.metrics();
+/// let server = server.bind().await?;
+/// let addr = server.local_addr()?;
+/// let (shutdown_tx, shutdown_rx) = oneshot::channel();
+/// let handle = tokio::spawn(server.run(shutdown_rx));
+///
+/// let mut client = Client::connect(addr).await?;
+/// client.request(1, b"ping").await?;
+///
+/// let snapshot = metrics.snapshot();
+/// assert_eq!(snapshot.requests(), 1);
+/// assert_eq!(snapshot.route(1).unwrap().requests(), 1);
+///
+/// drop(client);
+/// let _ = shutdown_tx.send(());
+/// handle.await.unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// 处理的请求数量
+    requests: AtomicU64,
+    /// 处理函数返回错误的请求数量
+    errors: AtomicU64,
+    /// 收到的请求数据的字节数
+    bytes_in: AtomicU64,
+    /// 发送的消息数据的字节数
+    bytes_out: AtomicU64,
+    /// 接受的连接数量
+    connections_accepted: AtomicU64,
+    /// 已经关闭的连接数量
+    connections_closed: AtomicU64,
+    /// 每个消息ID的计数器
+    routes: DashMap<u32, RouteCounters>,
+    /// 没有单独统计的消息ID合并使用的计数器
+    unrouted: RouteCounters,
+}
+
+/// 一个消息ID的计数器
+#[derive(Debug, Default)]
+struct RouteCounters {
+    /// 处理的请求数量
+    requests: AtomicU64,
+    /// 处理函数返回错误的请求数量
+    errors: AtomicU64,
+    /// 处理耗时的总和（纳秒）
+    latency_nanos: AtomicU64,
+    /// 最长的处理耗时（纳秒）
+    max_latency_nanos: AtomicU64,
+    /// 处理耗时直方图每个桶的请求数量
+    latency_buckets: [AtomicU64; BUCKET_COUNT],
+}
+
+impl RouteCounters {
+    /// 记录一个请求的处理耗时和结果
+    fn record(&self, latency: Duration, failed: bool) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.latency_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.max_latency_nanos.fetch_max(nanos, Ordering::Relaxed);
+        let bucket = LATENCY_BUCKETS.partition_point(|bound| *bound < latency);
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 读取计数器当前的值
+    fn load(&self) -> RouteMetrics {
+        RouteMetrics {
+            requests: self.requests.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            total_latency: Duration::from_nanos(self.latency_nanos.load(Ordering::Relaxed)),
+            max_latency: Duration::from_nanos(self.max_latency_nanos.load(Ordering::Relaxed)),
+            latency_buckets: self
+                .latency_buckets
+                .each_ref()
+                .map(|bucket| bucket.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Metrics {
+    /// 记录一个已经处理完毕的请求
+    ///
+    /// # 参数
+    /// * `msg_id` - 请求的消息ID
+    /// * `routed` - 路由器是否注册了该消息ID的处理函数，未注册的请求合并统计
+    /// * `bytes_in` - 请求数据的字节数
+    /// * `latency` - 处理函数的耗时
+    /// * `failed` - 处理函数是否返回了错误
+    pub(crate) fn record_request(
+        &self,
+        msg_id: u32,
+        routed: bool,
+        bytes_in: usize,
+        latency: Duration,
+        failed: bool,
+    ) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_in.fetch_add(bytes_in as u64, Ordering::Relaxed);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        // 已经单独统计的消息ID不受数量上限影响；并发记录新的消息ID时可能略微超过上限
+        let separate =
+            routed && (self.routes.contains_key(&msg_id) || self.routes.len() < MAX_ROUTE_SERIES);
+        if separate {
+            self.routes
+                .entry(msg_id)
+                .or_default()
+                .record(latency, failed);
+        } else {
+            self.unrouted.record(latency, failed);
+        }
+        #[cfg(feature = "metrics")]
+        {
+            let msg_id = match separate {
+                true => msg_id.to_string(),
+                false => UNROUTED_LABEL.to_string(),
+            };
+            ::metrics::counter!("zerust_requests_total", "msg_id" => msg_id.clone()).increment(1);
+            ::metrics::counter!("zerust_received_bytes_total").increment(bytes_in as u64);
+            ::metrics::histogram!("zerust_route_latency_seconds", "msg_id" => msg_id.clone())
+                .record(latency.as_secs_f64());
+            if failed {
+                ::metrics::counter!("zerust_errors_total", "msg_id" => msg_id).increment(1);
+            }
+        }
+    }
+
+    /// 记录一条已经发送的消息
+    ///
+    /// # 参数
+    /// * `bytes_out` - 消息数据的字节数
+    pub(crate) fn record_sent(&self, bytes_out: usize) {
+        self.bytes_out
+            .fetch_add(bytes_out as u64, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!("zerust_sent_bytes_total").increment(bytes_out as u64);
+    }
+
+    /// 记录一个登记到连接管理器的连接
+    pub(crate) fn record_connection_opened(&self) {
+        self.connections_accepted.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        {
+            ::metrics::counter!("zerust_connections_accepted_total").increment(1);
+            ::metrics::gauge!("zerust_connections_active").increment(1.0);
+        }
+    }
+
+    /// 记录一个已经关闭的连接
+    pub(crate) fn record_connection_closed(&self) {
+        self.connections_closed.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        {
+            ::metrics::counter!("zerust_connections_closed_total").increment(1);
+            ::metrics::gauge!("zerust_connections_active").decrement(1.0);
+        }
+    }
+
+    /// 生成当前计数器的快照
+    ///
+    /// 各个计数器分别读取，服务器仍在处理请求时它们之间可能相差正在处理的几个请求。
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let routes = self
+            .routes
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().load()))
+            .collect();
+        MetricsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            connections_accepted: self.connections_accepted.load(Ordering::Relaxed),
+            connections_closed: self.connections_closed.load(Ordering::Relaxed),
+            routes,
+            unrouted: self.unrouted.load(),
+        }
+    }
+}
+
+/// 某一时刻的指标快照，参见 `Metrics::snapshot`
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    /// 处理的请求数量
+    requests: u64,
+    /// 处理函数返回错误的请求数量
+    errors: u64,
+    /// 收到的请求数据的字节数
+    bytes_in: u64,
+    /// 发送的消息数据的字节数
+    bytes_out: u64,
+    /// 接受的连接数量
+    connections_accepted: u64,
+    /// 已经关闭的连接数量
+    connections_closed: u64,
+    /// 每个消息ID的指标，按消息ID排序
+    routes: BTreeMap<u32, RouteMetrics>,
+    /// 没有单独统计的请求合并后的指标
+    unrouted: RouteMetrics,
+}
+
+impl MetricsSnapshot {
+    /// 获取处理的请求数量
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// 获取处理函数返回错误的请求数量
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// 获取收到的请求数据的字节数
+    pub fn bytes_in(&self) -> u64 {
+        self.bytes_in
+    }
+
+    /// 获取发送的消息数据的字节数，包括响应和通过 `ConnectionHandle` 推送的消息
+    pub fn bytes_out(&self) -> u64 {
+        self.bytes_out
+    }
+
+    /// 获取接受的连接数量
+    pub fn connections_accepted(&self) -> u64 {
+        self.connections_accepted
+    }
+
+    /// 获取已经关闭的连接数量
+    pub fn connections_closed(&self) -> u64 {
+        self.connections_closed
+    }
+
+    /// 获取在线的连接数量
+    pub fn active_connections(&self) -> u64 {
+        self.connections_accepted
+            .saturating_sub(self.connections_closed)
+    }
+
+    /// 获取每个消息ID的指标，按消息ID排序
+    pub fn routes(&self) -> &BTreeMap<u32, RouteMetrics> {
+        &self.routes
+    }
+
+    /// 获取一个消息ID的指标
+    ///
+    /// # 返回值
+    /// 单独统计过该消息ID的请求时返回它的指标，否则返回 `None`
+    pub fn route(&self, msg_id: u32) -> Option<&RouteMetrics> {
+        self.routes.get(&msg_id)
+    }
+
+    /// 获取没有单独统计的请求合并后的指标
+    ///
+    /// 包括未注册处理函数的消息ID的请求，以及超过 `MAX_ROUTE_SERIES` 后新出现的消息ID的请求。
+    pub fn unrouted(&self) -> &RouteMetrics {
+        &self.unrouted
+    }
+
+    /// 按输出顺序列出每个消息ID的指标及其 `msg_id` 标签，合并统计的请求排在最后
+    #[cfg(feature = "prometheus")]
+    fn route_series(&self) -> Vec<(String, &RouteMetrics)> {
+        let mut series: Vec<_> = self
+            .routes
+            .iter()
+            .map(|(msg_id, route)| (msg_id.to_string(), route))
+            .collect();
+        if self.unrouted.requests > 0 {
+            series.push((UNROUTED_LABEL.to_string(), &self.unrouted));
+        }
+        series
+    }
+
+    /// 编码为 Prometheus 的文本格式
+    ///
+    /// 指标名称以 `zerust_` 开头，每个消息ID的指标带有 `msg_id` 标签，
+    /// 合并统计的请求的标签为 `msg_id="unrouted"`，
+    /// 处理耗时以 histogram 类型按 `LATENCY_BUCKETS` 输出累计的桶、总和与数量。
+    ///
+    /// 需要开启 `prometheus` 功能。
+    #[cfg(feature = "prometheus")]
+    pub fn encode_prometheus(&self) -> String {
+        let mut out = String::new();
+        let totals = [
+            (
+                "requests_total",
+                "Requests handled by the router.",
+                self.requests,
+            ),
+            (
+                "errors_total",
+                "Requests whose handler returned an error.",
+                self.errors,
+            ),
+            (
+                "received_bytes_total",
+                "Request payload bytes received.",
+                self.bytes_in,
+            ),
+            (
+                "sent_bytes_total",
+                "Message payload bytes sent.",
+                self.bytes_out,
+            ),
+            (
+                "connections_accepted_total",
+                "Connections accepted.",
+                self.connections_accepted,
+            ),
+            (
+                "connections_closed_total",
+                "Connections closed.",
+                self.connections_closed,
+            ),
+        ];
+        for (name, help, value) in totals {
+            let _ = writeln!(out, "# HELP zerust_{name} {help}");
+            let _ = writeln!(out, "# TYPE zerust_{name} counter");
+            let _ = writeln!(out, "zerust_{name} {value}");
+        }
+        let _ = writeln!(
+            out,
+            "# HELP zerust_connections_active Connections currently open."
+        );
+        let _ = writeln!(out, "# TYPE zerust_connections_active gauge");
+        let _ = writeln!(
+            out,
+            "zerust_connections_active {}",
+            self.active_connections()
+        );
+        let series = self.route_series();
+        let per_route = [
+            (
+                "route_requests_total",
+                "Requests handled per msg_id.",
+                RouteMetrics::requests as fn(&RouteMetrics) -> u64,
+            ),
+            (
+                "route_errors_total",
+                "Handler errors per msg_id.",
+                RouteMetrics::errors,
+            ),
+        ];
+        for (name, help, value) in per_route {
+            let _ = writeln!(out, "# HELP zerust_{name} {help}");
+            let _ = writeln!(out, "# TYPE zerust_{name} counter");
+            for (msg_id, route) in &series {
+                let _ = writeln!(out, "zerust_{name}{{msg_id=\"{msg_id}\"}} {}", value(route));
+            }
+        }
+        let _ = writeln!(
+            out,
+            "# HELP zerust_route_latency_seconds Handler latency per msg_id."
+        );
+        let _ = writeln!(out, "# TYPE zerust_route_latency_seconds histogram");
+        for (msg_id, route) in &series {
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(route.latency_buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "zerust_route_latency_seconds_bucket{{msg_id=\"{msg_id}\",le=\"{}\"}} {cumulative}",
+                    bound.as_secs_f64()
+                );
+            }
+            let _ = writeln!(
+                out,
+                "zerust_route_latency_seconds_bucket{{msg_id=\"{msg_id}\",le=\"+Inf\"}} {}",
+                route.requests
+            );
+            let _ = writeln!(
+                out,
+                "zerust_route_latency_seconds_sum{{msg_id=\"{msg_id}\"}} {}",
+                route.total_latency.as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "zerust_route_latency_seconds_count{{msg_id=\"{msg_id}\"}} {}",
+                route.requests
+            );
+        }
+        out
+    }
+}
+
+/// 一个消息ID的指标
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RouteMetrics {
+    /// 处理的请求数量
+    requests: u64,
+    /// 处理函数返回错误的请求数量
+    errors: u64,
+    /// 处理耗时的总和
+    total_latency: Duration,
+    /// 最长的处理耗时
+    max_latency: Duration,
+    /// 处理耗时直方图每个桶的请求数量
+    latency_buckets: [u64; BUCKET_COUNT],
+}
+
+impl RouteMetrics {
+    /// 获取处理的请求数量
+    pub fn requests(&self) -> u64 {
+        self.requests
+    }
+
+    /// 获取处理函数返回错误的请求数量
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// 获取处理耗时的总和
+    pub fn total_latency(&self) -> Duration {
+        self.total_latency
+    }
+
+    /// 获取最长的处理耗时
+    pub fn max_latency(&self) -> Duration {
+        self.max_latency
+    }
+
+    /// 获取处理耗时直方图每个桶的请求数量
+    ///
+    /// 第 `i` 个元素是耗时不超过 `LATENCY_BUCKETS[i]`、且超过前一个边界的请求数量，
+    /// 最后一个元素是耗时超过最后一个边界的请求数量。
+    pub fn latency_buckets(&self) -> &[u64] {
+        &self.latency_buckets
+    }
+
+    /// 根据直方图估算处理耗时的分位数
+    ///
+    /// 返回分位数所在桶的上边界，落在最后一个桶时返回最长的处理耗时。
+    ///
+    /// # 参数
+    /// * `quantile` - 分位数，取值范围为 0 到 1，例如 0.99 表示 P99
+    ///
+    /// # 返回值
+    /// 没有请求时返回 `None`
+    pub fn latency_quantile(&self, quantile: f64) -> Option<Duration> {
+        if self.requests == 0 {
+            return None;
+        }
+        let rank = (quantile.clamp(0.0, 1.0) * self.requests as f64)
+            .ceil()
+            .max(1.0) as u64;
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(self.latency_buckets) {
+            cumulative += count;
+            if cumulative >= rank {
+                return Some(*bound);
+            }
+        }
+        Some(self.max_latency)
+    }
+
+    /// 获取平均处理耗时，没有请求时为 0
+    pub fn average_latency(&self) -> Duration {
+        match u32::try_from(self.requests) {
+            Ok(0) => Duration::ZERO,
+            Ok(requests) => self.total_latency / requests,
+            Err(_) => {
+                Duration::from_secs_f64(self.total_latency.as_secs_f64() / self.requests as f64)
+            }
+        }
+    }
+}
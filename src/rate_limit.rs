@@ -0,0 +1,167 @@
+//! # 限流模块
+//!
+//! 开启限流后（参见 `Server::with_rate_limit`），每个连接拥有一个独立的令牌桶：
+//! 令牌以每秒 `max_per_sec` 个的速度补充，最多积累 `burst` 个，每个请求在交给路由器之前
+//! 消耗一个令牌，由服务器直接回复的 ping 控制帧和指标请求（参见 `Server::with_metrics_route`）
+//! 也不例外。令牌不足时按 `RateLimitPolicy` 延迟处理该请求，或者直接回复限流消息。
+//!
+//! 客户端对服务器心跳的回应不消耗令牌。
+//! 单个客户端发送再多的请求（包括 ping），也只会占用自己连接的处理能力。
+
+use crate::response::Response;
+use crate::runtime::Instant;
+use std::time::Duration;
+
+/// 令牌不足时的处理策略
+///
+/// 通过 `RateLimitConfig::with_policy` 设置。
+#[derive(Debug, Clone, Default)]
+pub enum RateLimitPolicy {
+    /// 等待令牌补充后再处理该请求，期间暂停读取该连接的后续请求
+    ///
+    /// 未读取的数据会留在套接字的接收缓冲区中，形成背压。
+    /// 等待期间服务器开始关闭时，该请求不再等待令牌，立即处理后连接结束。
+    #[default]
+    Delay,
+    /// 不处理该请求，直接回复限流消息
+    Throttle {
+        /// 回复给客户端的限流消息，会带上请求的序列号
+        throttle_response: Response,
+    },
+}
+
+/// 每个连接的限流配置
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use zerust::rate_limit::{RateLimitConfig, RateLimitPolicy};
+/// use zerust::{DefaultRouter, Response, Server};
+///
+/// // 每个连接每秒最多 100 个请求，允许瞬间发送 20 个，超出时回复限流消息
+/// let config = RateLimitConfig::new(100)
+///     .with_burst(20)
+///     .with_policy(RateLimitPolicy::Throttle {
+///         throttle_response: Response::new(429, b"too many requests".to_vec()),
+///     });
+/// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+///     .with_rate_limit_config(config);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// 每秒补充的令牌数量
+    max_per_sec: u32,
+    /// 最多积累的令牌数量
+    burst: u32,
+    /// 令牌不足时的处理策略
+    policy: RateLimitPolicy,
+}
+
+impl RateLimitConfig {
+    /// 创建限流配置，最多积累 `max_per_sec` 个令牌，令牌不足时延迟处理
+    ///
+    /// # 参数
+    /// * `max_per_sec` - 每个连接每秒最多处理的请求数量
+    ///
+    /// `max_per_sec` 必须大于 0，否则创建或启动服务器时返回 `ZerustError::InvalidConfig`
+    /// （参见 `ServerConfig::validate`）
+    pub fn new(max_per_sec: u32) -> Self {
+        Self {
+            max_per_sec,
+            burst: max_per_sec,
+            policy: RateLimitPolicy::default(),
+        }
+    }
+
+    /// 设置最多积累的令牌数量，即连接空闲后可以立即处理的请求数量
+    ///
+    /// # 参数
+    /// * `burst` - 最多积累的令牌数量
+    ///
+    /// `burst` 必须大于 0，否则创建或启动服务器时返回 `ZerustError::InvalidConfig`
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    /// 设置令牌不足时的处理策略
+    ///
+    /// # 参数
+    /// * `policy` - 处理策略
+    pub fn with_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 获取每秒补充的令牌数量
+    pub fn max_per_sec(&self) -> u32 {
+        self.max_per_sec
+    }
+
+    /// 获取最多积累的令牌数量
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// 获取令牌不足时的处理策略
+    pub fn policy(&self) -> &RateLimitPolicy {
+        &self.policy
+    }
+}
+
+/// 一个连接的令牌桶
+pub(crate) struct TokenBucket {
+    /// 限流配置
+    config: RateLimitConfig,
+    /// 当前的令牌数量，预约了之后的令牌时为负数
+    tokens: f64,
+    /// 上一次补充令牌的时间
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// 创建装满令牌的令牌桶
+    pub(crate) fn new(config: RateLimitConfig) -> Self {
+        Self {
+            tokens: f64::from(config.burst),
+            updated: Instant::now(),
+            config,
+        }
+    }
+
+    /// 按经过的时间补充令牌
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * f64::from(self.config.max_per_sec))
+            .min(f64::from(self.config.burst));
+        self.updated = now;
+    }
+
+    /// 有可用令牌时消耗一个
+    ///
+    /// # 返回值
+    /// 消耗了令牌时返回 `true`，令牌不足时返回 `false`
+    pub(crate) fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// 预约一个令牌，令牌不足时预支之后补充的令牌
+    ///
+    /// # 返回值
+    /// 返回需要等待多久该令牌才可用，有可用令牌时为 0
+    pub(crate) fn reserve(&mut self) -> Duration {
+        self.refill();
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-self.tokens / f64::from(self.config.max_per_sec))
+    }
+}
@@ -3,19 +3,39 @@
 //! 该模块定义了客户端请求的数据结构和相关方法，用于在服务器端表示和处理客户端发送的请求。
 //! 请求包含消息ID和消息数据两部分，消息ID用于路由到对应的处理函数。
 
+use crate::conn_manager::ConnectionHandle;
+use crate::context::ConnContext;
+#[cfg(any(feature = "serde_json", feature = "prost"))]
+use crate::error::ZerustError;
+use bytes::Bytes;
+use std::net::SocketAddr;
+
 /// 表示客户端发送的请求
 ///
 /// 请求包含两个主要部分：
 /// * `msg_id` - 消息ID，用于标识请求类型并路由到对应的处理函数
-/// * `data` - 请求携带的数据，以字节数组形式存储
+/// * `data` - 请求携带的数据，以 `Bytes` 形式存储
+///
+/// 由服务器读取的请求，其数据直接引用连接的接收缓冲区，读取过程中不会复制消息体；
+/// 克隆请求或通过 `data_bytes` 获取数据也只增加引用计数。
+///
+/// 由服务器读取的请求还会携带其所属连接的上下文（连接ID、客户端地址等），
+/// 处理函数可以据此识别客户端，或者通过 `ConnManager` 找到发送该请求的连接。
 ///
-/// 实现了 `Debug` trait，方便调试和日志记录。
-#[derive(Debug)]
+/// 实现了 `Debug` trait，方便调试和日志记录；实现了 `Clone` trait，
+/// 拦截器需要在异步处理函数取得请求所有权后继续访问请求。
+#[derive(Debug, Clone)]
 pub struct Request {
     /// 消息ID，用于标识请求类型
     msg_id: u32,
     /// 请求携带的数据
-    data: Vec<u8>,
+    data: Bytes,
+    /// 请求所属连接的上下文，不属于任何连接时为 `None`
+    context: Option<ConnContext>,
+    /// 请求所属连接的句柄，只有由服务器读取的请求才有
+    connection: Option<ConnectionHandle>,
+    /// 请求的序列号，帧格式不包含序列号时为 `None`
+    seq: Option<u32>,
 }
 
 impl Request {
@@ -26,9 +46,124 @@ impl Request {
     /// * `data` - 请求携带的数据
     ///
     /// # 返回值
-    /// 返回一个新的 `Request` 实例
+    /// 返回一个新的 `Request` 实例，不属于任何连接
     pub fn new(msg_id: u32, data: Vec<u8>) -> Self {
-        Self { msg_id, data }
+        Self::from_bytes(msg_id, Bytes::from(data))
+    }
+
+    /// 使用 `Bytes` 创建一个新的请求实例，不复制数据
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，用于标识请求类型
+    /// * `data` - 请求携带的数据
+    ///
+    /// # 返回值
+    /// 返回一个新的 `Request` 实例，不属于任何连接
+    pub fn from_bytes(msg_id: u32, data: Bytes) -> Self {
+        Self {
+            msg_id,
+            data,
+            context: None,
+            connection: None,
+            seq: None,
+        }
+    }
+
+    /// 设置请求所属连接的上下文
+    ///
+    /// `Connection::read_request` 会自动设置；直接构造请求（例如在测试中调用处理函数）时
+    /// 可以用它模拟来自某个连接的请求。
+    ///
+    /// # 参数
+    /// * `context` - 连接上下文
+    ///
+    /// # 返回值
+    /// 返回设置了连接上下文的 `Request` 实例
+    pub fn with_context(mut self, context: ConnContext) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// 设置请求的序列号
+    ///
+    /// 使用携带序列号的编解码工具（例如 `SeqDataPack`）时，`Connection::read_request`
+    /// 会自动设置。服务器会把请求的序列号带到它的响应上，客户端据此把乱序到达的响应
+    /// 与请求对应起来。
+    ///
+    /// # 参数
+    /// * `seq` - 序列号
+    ///
+    /// # 返回值
+    /// 返回设置了序列号的 `Request` 实例
+    pub fn with_seq(mut self, seq: u32) -> Self {
+        self.seq = Some(seq);
+        self
+    }
+
+    /// 获取请求的序列号
+    ///
+    /// # 返回值
+    /// 帧格式携带序列号时返回请求的序列号，否则返回 `None`
+    pub fn seq(&self) -> Option<u32> {
+        self.seq
+    }
+
+    /// 设置请求所属连接的句柄，由服务器在分发请求前调用
+    pub(crate) fn with_connection(mut self, connection: ConnectionHandle) -> Self {
+        self.connection = Some(connection);
+        self
+    }
+
+    /// 获取请求所属连接的句柄
+    ///
+    /// 句柄可以被克隆并保存到其他任务中，用于稍后回复或主动推送消息，
+    /// 通过同一个句柄发送的消息按发送顺序到达客户端，参见 `Response::none`。
+    ///
+    /// # 返回值
+    /// 由服务器读取的请求返回其连接的句柄，直接构造或由 `Connection` 读取的请求返回 `None`
+    pub fn connection(&self) -> Option<&ConnectionHandle> {
+        self.connection.as_ref()
+    }
+
+    /// 获取请求所属连接的上下文
+    ///
+    /// # 返回值
+    /// 由服务器读取的请求返回其连接的上下文，直接构造的请求返回 `None`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// router.add_route(1, |req| {
+    ///     let peer = req
+    ///         .context()
+    ///         .map(|ctx| ctx.remote_addr().to_string())
+    ///         .unwrap_or_default();
+    ///     Response::new(req.msg_id(), peer.into_bytes())
+    /// }).unwrap();
+    /// ```
+    pub fn context(&self) -> Option<&ConnContext> {
+        self.context.as_ref()
+    }
+
+    /// 获取请求所属连接的ID
+    ///
+    /// # 返回值
+    /// 返回连接ID，由服务器分配，从 1 开始；不属于任何连接的请求返回 0
+    pub fn conn_id(&self) -> u64 {
+        self.context.as_ref().map_or(0, ConnContext::conn_id)
+    }
+
+    /// 获取发送请求的客户端地址
+    ///
+    /// 与 `context().map(ConnContext::remote_addr)` 相同。
+    ///
+    /// # 返回值
+    /// 由服务器读取的请求返回客户端地址，不属于任何连接的请求返回 `None`
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.context.as_ref().map(ConnContext::remote_addr)
     }
 
     /// 获取请求的消息ID
@@ -46,4 +181,66 @@ impl Request {
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+
+    /// 获取请求携带的数据，不复制数据
+    ///
+    /// 返回的 `Bytes` 与请求共享同一块内存，适合在异步任务之间传递或保存数据切片。
+    ///
+    /// # 返回值
+    /// 返回请求携带的数据
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use bytes::Bytes;
+    /// use zerust::Request;
+    ///
+    /// let data = Bytes::from_static(b"hello");
+    /// let req = Request::from_bytes(1, data.clone());
+    /// assert_eq!(req.data_bytes().as_ptr(), data.as_ptr());
+    /// ```
+    pub fn data_bytes(&self) -> Bytes {
+        self.data.clone()
+    }
+
+    /// 把请求数据解析为 JSON
+    ///
+    /// 需要开启 `serde_json` 功能。
+    ///
+    /// # 返回值
+    /// * `Ok(T)` - 解析出的值
+    /// * `Err(ZerustError::ProtocolError)` - 数据不是有效的 JSON，或者与 `T` 的结构不符
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::Request;
+    ///
+    /// let req = Request::new(1, br#"{"x": 1, "y": 2}"#.to_vec());
+    /// let point: std::collections::HashMap<String, i32> = req.parse_json().unwrap();
+    /// assert_eq!(point["y"], 2);
+    /// assert!(Request::new(1, b"{".to_vec()).parse_json::<i32>().is_err());
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn parse_json<T: serde::de::DeserializeOwned>(&self) -> Result<T, ZerustError> {
+        serde_json::from_slice(&self.data)
+            .map_err(|e| ZerustError::ProtocolError(format!("invalid JSON request: {e}")))
+    }
+
+    /// 把请求数据解析为 protobuf 消息
+    ///
+    /// `bytes` 类型的字段与请求数据共享内存，不复制数据。需要开启 `prost` 功能。
+    ///
+    /// # 返回值
+    /// * `Ok(M)` - 解析出的消息
+    /// * `Err(ZerustError::ProtocolError)` - 数据不是有效的 `M` 消息，错误描述中包含消息ID
+    #[cfg(feature = "prost")]
+    pub fn parse_proto<M: prost::Message + Default>(&self) -> Result<M, ZerustError> {
+        M::decode(self.data.clone()).map_err(|e| {
+            ZerustError::ProtocolError(format!(
+                "invalid protobuf request for msg_id {}: {e}",
+                self.msg_id
+            ))
+        })
+    }
 }
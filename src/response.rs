@@ -2,20 +2,79 @@
 //!
 //! 该模块定义了服务器响应的数据结构和相关方法，用于表示服务器对客户端请求的响应。
 //! 响应包含消息ID和响应数据两部分，消息ID通常与请求的消息ID对应。
+//!
+//! 除 `Response::new` 外，还提供了表示常见约定的构造函数：
+//! * `Response::ok` - 成功的响应，与 `Response::new` 相同，与 `Response::error` 对应
+//! * `Response::empty` - 没有数据的确认响应
+//! * `Response::error` - 携带错误码和错误描述的错误响应，客户端通过 `Response::error_body` 解析
+//! * `Response::builder` - 分多次拼接数据的 `ResponseBuilder`
+//! * `Response::batch` - 对同一个请求依次发送的多条消息
+//! * `Response::from_json` - 数据为 JSON 的响应，需要开启 `serde_json` 功能
+//! * `Response::from_proto` - 数据为 protobuf 消息的响应，需要开启 `prost` 功能
+//!
+//! ## 错误响应的格式
+//!
+//! `Response::error` 创建的响应数据由两部分组成，其他语言的客户端可以按此解析：
+//!
+//! | 偏移 | 长度 | 内容                          |
+//! |------|------|-------------------------------|
+//! | 0    | 4    | 错误码，u32，小端序           |
+//! | 4    | 其余 | 错误描述，UTF-8 编码，不以 0 结尾 |
+//!
+//! 消息ID与普通响应一样通常沿用请求的消息ID，因此客户端需要按消息约定判断响应是否为错误响应，
+//! 再通过 `Response::error_body` 取出错误码和错误描述。框架生成的错误响应
+//! （`Response::not_found`、`Response::internal_error` 和 `Response::payload_too_large`）
+//! 都使用同样的格式。
+
+use crate::control;
+use crate::error::ZerustError;
+use bytes::Bytes;
+#[cfg(feature = "prost")]
+use bytes::BytesMut;
+#[cfg(feature = "prost")]
+use std::cell::RefCell;
+
+/// `Response::error` 的数据中错误码的长度
+const ERROR_CODE_SIZE: usize = 4;
+
+/// `Response::from_proto` 每个线程的编码缓冲区每次分配的字节数
+#[cfg(feature = "prost")]
+const PROTO_SCRATCH_CAPACITY: usize = 8 * 1024;
+
+#[cfg(feature = "prost")]
+thread_local! {
+    /// `Response::from_proto` 的编码缓冲区
+    ///
+    /// 多个响应依次编码到同一块内存中再切分出去，不需要为每个响应单独分配内存；
+    /// 切分出去的响应都被释放后，这块内存会被重新使用。
+    static PROTO_SCRATCH: RefCell<BytesMut> =
+        RefCell::new(BytesMut::with_capacity(PROTO_SCRATCH_CAPACITY));
+}
 
 /// 表示服务器返回的响应
 ///
 /// 响应包含两个主要部分：
 /// * `msg_id` - 消息ID，通常与请求的消息ID对应
-/// * `data` - 响应携带的数据，以字节数组形式存储
+/// * `data` - 响应携带的数据，以 `Bytes` 形式存储
 ///
-/// 实现了 `Debug` trait，方便调试和日志记录。
-#[derive(Debug)]
+/// 实现了 `Debug` trait，方便调试和日志记录；
+/// 实现了 `Clone` trait，便于把同一条消息推送给多个连接。
+#[derive(Debug, Clone)]
 pub struct Response {
     /// 消息ID，通常与请求的消息ID对应
     msg_id: u32,
     /// 响应携带的数据
-    data: Vec<u8>,
+    data: Bytes,
+    /// 是否为不需要发送的空响应，参见 `Response::none`
+    none: bool,
+    /// 响应对应的请求的序列号，参见 `Response::with_seq`
+    seq: Option<u32>,
+    /// 发送后是否关闭连接，参见 `Response::with_close`
+    close: bool,
+    /// 批量响应中依次发送的消息，参见 `Response::batch`
+    batch: Option<Vec<Response>>,
+    /// 发送时是否忽略压缩阈值压缩数据，参见 `Response::new_compressed`
+    compress: bool,
 }
 
 impl Response {
@@ -28,18 +87,383 @@ impl Response {
     /// # 返回值
     /// 返回一个新的 `Response` 实例
     pub fn new(msg_id: u32, data: Vec<u8>) -> Self {
-        Self { msg_id, data }
+        Self::from_bytes(msg_id, Bytes::from(data))
+    }
+
+    /// 使用 `Bytes` 创建一个新的响应实例，不复制数据
+    ///
+    /// 适合直接返回请求数据的切片或预先编码好的静态数据。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，通常与请求的消息ID对应
+    /// * `data` - 响应携带的数据
+    ///
+    /// # 返回值
+    /// 返回一个新的 `Response` 实例
+    pub fn from_bytes(msg_id: u32, data: Bytes) -> Self {
+        Self {
+            msg_id,
+            data,
+            none: false,
+            seq: None,
+            close: false,
+            batch: None,
+            compress: false,
+        }
+    }
+
+    /// 创建一个发送时压缩数据的响应
+    ///
+    /// 连接使用 `CompressedCodec` 时，无论数据长度是否达到压缩阈值都会尝试压缩，
+    /// 适合已知很大且容易压缩的数据（例如大段 JSON）；压缩后没有变小时仍按原样发送。
+    /// 连接使用其他编解码工具时与 `Response::new` 相同。接收方看到的始终是解压后的数据。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，通常与请求的消息ID对应
+    /// * `data` - 响应携带的数据
+    ///
+    /// # 返回值
+    /// 返回标记了压缩的 `Response` 实例
+    pub fn new_compressed(msg_id: u32, data: Vec<u8>) -> Self {
+        Self {
+            compress: true,
+            ..Self::new(msg_id, data)
+        }
+    }
+
+    /// 判断发送时是否压缩数据，参见 `Response::new_compressed`
+    pub fn is_compressed(&self) -> bool {
+        self.compress
+    }
+
+    /// 创建一个成功的响应
+    ///
+    /// 与 `Response::new` 相同，在同时返回 `Response::error` 的处理函数中使意图更清楚。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，通常与请求的消息ID对应
+    /// * `data` - 响应携带的数据
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// router.add_route(1, |req| match req.data() {
+    ///     [] => Response::error(req.msg_id(), 400, "empty request"),
+    ///     data => Response::ok(req.msg_id(), data.to_ascii_uppercase()),
+    /// }).unwrap();
+    /// ```
+    pub fn ok(msg_id: u32, data: Vec<u8>) -> Self {
+        Self::new(msg_id, data)
+    }
+
+    /// 创建一个没有数据的响应，适合只需要告知客户端请求已处理的确认消息
+    ///
+    /// 与 `Response::none` 不同，该响应会被发送给客户端。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，通常与请求的消息ID对应
+    ///
+    /// # 返回值
+    /// 返回一个数据为空的 `Response` 实例
+    pub fn empty(msg_id: u32) -> Self {
+        Self::from_bytes(msg_id, Bytes::new())
+    }
+
+    /// 创建一个错误响应
+    ///
+    /// 响应数据为 4 字节小端序的错误码，后面跟着 UTF-8 编码的错误描述，参见模块文档中的格式说明。
+    /// 消息ID通常使用请求的消息ID，客户端可以据此知道是哪个请求失败了，
+    /// 再通过 `Response::error_body` 取出错误码和错误描述。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，通常与请求的消息ID对应
+    /// * `code` - 错误码，例如沿用 HTTP 的 404、500
+    /// * `msg` - 错误描述
+    ///
+    /// # 返回值
+    /// 返回一个错误响应
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::Response;
+    ///
+    /// let resp = Response::error(7, 403, "permission denied");
+    /// assert_eq!(resp.msg_id(), 7);
+    /// assert_eq!(resp.error_body(), Some((403, "permission denied")));
+    /// ```
+    pub fn error(msg_id: u32, code: u32, msg: &str) -> Self {
+        let mut data = Vec::with_capacity(ERROR_CODE_SIZE + msg.len());
+        data.extend_from_slice(&code.to_le_bytes());
+        data.extend_from_slice(msg.as_bytes());
+        Self::new(msg_id, data)
+    }
+
+    /// 创建一个数据为 JSON 的响应
+    ///
+    /// 需要开启 `serde_json` 功能。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，通常与请求的消息ID对应
+    /// * `value` - 要编码为 JSON 的值
+    ///
+    /// # 返回值
+    /// * `Ok(Response)` - 数据为 `value` 的 JSON 编码的响应
+    /// * `Err(ZerustError::ProtocolError)` - `value` 无法编码为 JSON，例如键不是字符串的映射
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::Response;
+    ///
+    /// let resp = Response::from_json(1, &["a", "b"]).unwrap();
+    /// assert_eq!(resp.data(), br#"["a","b"]"#);
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn from_json<T: serde::Serialize + ?Sized>(
+        msg_id: u32,
+        value: &T,
+    ) -> Result<Self, ZerustError> {
+        let data = serde_json::to_vec(value)
+            .map_err(|e| ZerustError::ProtocolError(format!("invalid JSON response: {e}")))?;
+        Ok(Self::new(msg_id, data))
+    }
+
+    /// 创建一个数据为 protobuf 消息的响应
+    ///
+    /// 消息被编码到当前线程的缓冲区中，通常不需要为每个响应单独分配内存。
+    /// 需要开启 `prost` 功能。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID，通常与请求的消息ID对应
+    /// * `message` - 要编码的消息
+    ///
+    /// # 返回值
+    /// 返回数据为 `message` 的编码的响应
+    #[cfg(feature = "prost")]
+    pub fn from_proto(msg_id: u32, message: &impl prost::Message) -> Self {
+        PROTO_SCRATCH.with_borrow_mut(|buf| {
+            buf.reserve(message.encoded_len());
+            // 已经预留了足够的空间，编码不会失败
+            message
+                .encode(buf)
+                .expect("scratch buffer has enough capacity");
+            Self::from_bytes(msg_id, buf.split().freeze())
+        })
+    }
+
+    /// 创建一个响应构建器，参见 `ResponseBuilder`
+    pub fn builder() -> ResponseBuilder {
+        ResponseBuilder::new()
+    }
+
+    /// 创建一个依次发送多条消息的批量响应
+    ///
+    /// 服务器按顺序发送其中的每条消息，消息之间不会插入同一连接的其他消息，
+    /// 适合一个请求需要分多条消息回复的场景，例如分页返回的查询结果。
+    /// 每条消息各自保留消息ID和数据；嵌套的批量响应会被展开，`Response::none` 会被忽略，
+    /// 没有任何消息时等同于 `Response::none`。
+    /// 其中任意一条消息标记了 `with_close` 时，发送完所有消息后关闭连接。
+    ///
+    /// 批量响应本身的消息ID为 0、数据为空，通过 `Response::frames` 取得其中的消息。
+    ///
+    /// # 参数
+    /// * `responses` - 按发送顺序排列的响应
+    ///
+    /// # 返回值
+    /// 返回一个批量响应
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::Response;
+    ///
+    /// let resp = Response::batch((1..=3).map(|page| Response::new(5, vec![page])));
+    /// let pages: Vec<&[u8]> = resp.frames().iter().map(|frame| frame.data()).collect();
+    /// assert_eq!(pages, [[1], [2], [3]]);
+    /// ```
+    pub fn batch(responses: impl IntoIterator<Item = Response>) -> Self {
+        let mut frames = Vec::new();
+        let mut close = false;
+        for resp in responses {
+            close |= resp.close;
+            match resp.batch {
+                Some(inner) => frames.extend(inner),
+                None if resp.none => {}
+                None => frames.push(resp),
+            }
+        }
+        let resp = if frames.is_empty() {
+            Self::none()
+        } else {
+            Self {
+                batch: Some(frames),
+                ..Self::empty(0)
+            }
+        };
+        if close { resp.with_close() } else { resp }
+    }
+
+    /// 获取实际发送的消息
+    ///
+    /// # 返回值
+    /// 批量响应返回其中的所有消息，`Response::none` 返回空切片，其他响应返回只包含自身的切片
+    pub fn frames(&self) -> &[Response] {
+        match &self.batch {
+            Some(frames) => frames,
+            None if self.none => &[],
+            None => std::slice::from_ref(self),
+        }
+    }
+
+    /// 创建一个不需要发送的空响应
+    ///
+    /// 处理函数暂时无法给出响应时返回它，连接不会向客户端写入任何数据。
+    /// 之后可以通过 `Request::connection` 取得连接的句柄，在其他任务中发送真正的响应。
+    ///
+    /// # 返回值
+    /// 返回一个空响应，其消息ID为 0、数据为空
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// router.add_route(1, |req| {
+    ///     if let Some(conn) = req.connection().cloned() {
+    ///         let data = req.data().to_vec();
+    ///         tokio::spawn(async move {
+    ///             // 异步任务完成后再回复，连接已关闭时返回错误
+    ///             let _ = conn.send(Response::new(1, data));
+    ///         });
+    ///     }
+    ///     Response::none()
+    /// }).unwrap();
+    /// ```
+    pub fn none() -> Self {
+        Self {
+            msg_id: 0,
+            data: Bytes::new(),
+            none: true,
+            seq: None,
+            close: false,
+            batch: None,
+            compress: false,
+        }
+    }
+
+    /// 设置响应对应的请求的序列号
+    ///
+    /// 服务器会自动把请求的序列号带到处理函数返回的响应上，通常不需要手动设置；
+    /// 通过 `Request::connection` 在其他任务中回复时，需要设置为 `Request::seq` 的值。
+    /// 帧格式不包含序列号时，序列号不会被发送。对批量响应设置时作用于其中的每条消息。
+    ///
+    /// # 参数
+    /// * `seq` - 序列号
+    ///
+    /// # 返回值
+    /// 返回设置了序列号的 `Response` 实例
+    pub fn with_seq(mut self, seq: u32) -> Self {
+        self.seq = Some(seq);
+        for frame in self.batch.iter_mut().flatten() {
+            frame.seq = Some(seq);
+        }
+        self
+    }
+
+    /// 获取响应对应的请求的序列号，未设置时返回 `None`
+    pub fn seq(&self) -> Option<u32> {
+        self.seq
+    }
+
+    /// 未设置序列号时使用请求的序列号，由服务器在发送处理函数的响应前调用
+    pub(crate) fn inherit_seq(mut self, seq: Option<u32>) -> Self {
+        if self.seq.is_none() {
+            self.seq = seq;
+        }
+        for frame in self.batch.iter_mut().flatten() {
+            if frame.seq.is_none() {
+                frame.seq = seq;
+            }
+        }
+        self
+    }
+
+    /// 判断是否为 `Response::none` 创建的空响应
+    pub fn is_none(&self) -> bool {
+        self.none
+    }
+
+    /// 标记发送该响应后关闭连接
+    ///
+    /// 服务器发送完该响应（以及之前已经排队的消息）后关闭连接的写入端，
+    /// 不再读取和处理后续的请求，适合登出、协议错误等需要主动断开的场景。
+    /// 与 `Response::none` 一起使用时，不发送任何数据直接关闭连接。
+    ///
+    /// # 返回值
+    /// 返回标记了关闭连接的 `Response` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// router.add_route(9, |req| Response::new(req.msg_id(), b"bye".to_vec()).with_close()).unwrap();
+    /// ```
+    pub fn with_close(mut self) -> Self {
+        self.close = true;
+        self
+    }
+
+    /// 判断发送该响应后是否关闭连接
+    pub fn closes_connection(&self) -> bool {
+        self.close
     }
 
     /// 创建一个表示路由未找到的响应
     ///
-    /// 当请求的消息ID没有对应的处理函数时，返回此响应。
-    /// 使用404作为消息ID，响应数据为"Route not found"。
+    /// 当请求的消息ID没有对应的处理函数时，`DefaultRouter` 返回此响应。
+    /// 消息ID沿用请求的消息ID，响应数据为错误码 404 和错误描述"Route not found"，
+    /// 格式与 `Response::error` 相同。
+    ///
+    /// # 参数
+    /// * `msg_id` - 请求的消息ID
     ///
     /// # 返回值
     /// 返回一个表示路由未找到的 `Response` 实例
-    pub fn not_found() -> Self {
-        Self::new(404, b"Route not found".to_vec())
+    pub fn not_found(msg_id: u32) -> Self {
+        Self::error(msg_id, 404, "Route not found")
+    }
+
+    /// 创建一个表示消息过大的响应
+    ///
+    /// 客户端发送的消息超过服务器允许的最大消息体长度时，服务器默认在关闭连接前发送此响应。
+    /// 消息ID为保留给控制帧的 `control::PAYLOAD_TOO_LARGE_MSG_ID`，不会与应用程序的响应混淆；
+    /// 响应数据为错误码 413 和错误描述"Payload too large"，格式与 `Response::error` 相同。
+    ///
+    /// # 返回值
+    /// 返回一个表示消息过大的 `Response` 实例
+    pub fn payload_too_large() -> Self {
+        Self::error(control::PAYLOAD_TOO_LARGE_MSG_ID, 413, "Payload too large")
+    }
+
+    /// 创建一个表示处理失败的响应
+    ///
+    /// 当处理函数返回错误或发生 panic 时，服务器默认使用此响应。
+    /// 使用500作为消息ID，响应数据为错误码 500 和错误的描述信息，格式与 `Response::error` 相同。
+    ///
+    /// # 参数
+    /// * `err` - 处理函数返回的错误
+    ///
+    /// # 返回值
+    /// 返回一个表示处理失败的 `Response` 实例
+    pub fn internal_error(err: &ZerustError) -> Self {
+        Self::error(500, 500, &err.to_string())
     }
 
     /// 获取响应的消息ID
@@ -57,4 +481,109 @@ impl Response {
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+
+    /// 获取响应携带的数据，不复制数据
+    ///
+    /// # 返回值
+    /// 返回与响应共享同一块内存的数据
+    pub fn data_bytes(&self) -> Bytes {
+        self.data.clone()
+    }
+
+    /// 把响应数据解析为 JSON，适合客户端读取 `Response::from_json` 创建的响应
+    ///
+    /// 需要开启 `serde_json` 功能。
+    ///
+    /// # 返回值
+    /// * `Ok(T)` - 解析出的值
+    /// * `Err(ZerustError::ProtocolError)` - 数据不是有效的 JSON，或者与 `T` 的结构不符
+    #[cfg(feature = "serde_json")]
+    pub fn parse_json<T: serde::de::DeserializeOwned>(&self) -> Result<T, ZerustError> {
+        serde_json::from_slice(&self.data)
+            .map_err(|e| ZerustError::ProtocolError(format!("invalid JSON response: {e}")))
+    }
+
+    /// 把响应数据解析为 protobuf 消息，适合客户端读取 `Response::from_proto` 创建的响应
+    ///
+    /// 需要开启 `prost` 功能。
+    ///
+    /// # 返回值
+    /// * `Ok(M)` - 解析出的消息
+    /// * `Err(ZerustError::ProtocolError)` - 数据不是有效的 `M` 消息，错误描述中包含消息ID
+    #[cfg(feature = "prost")]
+    pub fn parse_proto<M: prost::Message + Default>(&self) -> Result<M, ZerustError> {
+        M::decode(self.data.clone()).map_err(|e| {
+            ZerustError::ProtocolError(format!(
+                "invalid protobuf response for msg_id {}: {e}",
+                self.msg_id
+            ))
+        })
+    }
+
+    /// 按 `Response::error` 的格式解析响应数据
+    ///
+    /// # 返回值
+    /// 数据至少有 4 字节、且之后的部分是有效的 UTF-8 时返回错误码和错误描述，否则返回 `None`。
+    /// 只有约定使用错误响应的消息才应该这样解析，普通数据也可能恰好满足该格式。
+    pub fn error_body(&self) -> Option<(u32, &str)> {
+        let (code, msg) = self.data.split_first_chunk::<ERROR_CODE_SIZE>()?;
+        let msg = std::str::from_utf8(msg).ok()?;
+        Some((u32::from_le_bytes(*code), msg))
+    }
+}
+
+/// 响应构建器
+///
+/// 适合分多次拼接响应数据，例如依次写入多个字段。
+/// 通过 `Response::builder` 创建，消息ID默认为 0，数据默认为空。
+///
+/// # 示例
+///
+/// ```rust
+/// use zerust::Response;
+///
+/// let resp = Response::builder()
+///     .msg_id(3)
+///     .data(b"hello")
+///     .push_bytes(b", ")
+///     .push_bytes(b"world")
+///     .build();
+/// assert_eq!((resp.msg_id(), resp.data()), (3, &b"hello, world"[..]));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ResponseBuilder {
+    /// 消息ID
+    msg_id: u32,
+    /// 已经写入的数据
+    data: Vec<u8>,
+}
+
+impl ResponseBuilder {
+    /// 创建一个消息ID为 0、数据为空的构建器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置消息ID
+    pub fn msg_id(mut self, msg_id: u32) -> Self {
+        self.msg_id = msg_id;
+        self
+    }
+
+    /// 替换已经写入的数据
+    pub fn data(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.data = data.into();
+        self
+    }
+
+    /// 在已经写入的数据之后追加数据
+    pub fn push_bytes(mut self, bytes: &[u8]) -> Self {
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    /// 创建响应
+    pub fn build(self) -> Response {
+        Response::new(self.msg_id, self.data)
+    }
 }
@@ -2,14 +2,38 @@
 //!
 //! 该模块定义了请求路由的接口和默认实现，负责将请求根据消息ID分发到对应的处理函数。
 //! 路由系统是框架的核心组件之一，它允许用户注册自定义的请求处理逻辑。
+//!
+//! 处理函数既可以是同步闭包，也可以是返回 `Future` 的异步闭包，
+//! 后者适合在处理过程中访问数据库或调用其他服务，而不会阻塞连接任务。
+//! 处理函数还可以返回 `Result`，由服务器将错误统一转换为错误响应。
+//!
+//! 日志、鉴权、统计等横切逻辑可以实现为 `Interceptor`，通过
+//! `DefaultRouter::add_interceptor` 注册，在处理函数前后统一执行。
+//! 只作用于部分消息的拦截器可以注册到 `RouteGroup` 上，与该组的处理函数一起合并到路由器中。
+//! 需要包裹整个处理过程（例如统计包括异步处理在内的耗时）时，可以通过
+//! `DefaultRouter::use_middleware` 注册中间件。
 
+use crate::control;
+use crate::error::ZerustError;
 use crate::request::Request;
 use crate::response::Response;
 use dashmap::DashMap;
+use std::any::Any;
+use std::future::{self, Future};
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::task::{Context, Poll};
+
+/// 装箱的异步结果类型
+///
+/// `Router::handle` 返回该类型，使得路由器既可以作为 trait 对象使用，
+/// 又可以在处理请求时执行异步操作。
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
 /// 路由器接口
 ///
-/// 定义了路由器的基本行为，即根据请求生成响应。
+/// 定义了路由器的基本行为，即根据请求异步地生成响应。
 /// 实现了 `Send` 和 `Sync` trait，使其可以在多线程环境中安全使用。
 ///
 /// * `Send` 超trait约束：表示该类型的所有权可以在不同的线程间安全转移
@@ -18,11 +42,198 @@ pub trait Router: Send + Sync {
     /// 处理请求并生成响应
     ///
     /// # 参数
+    /// * `req` - 请求对象，所有权移交给路由器，以便异步处理函数在 `.await` 之间持有它
+    ///
+    /// # 返回值
+    /// 返回一个 `Future`，完成时产生对应的响应对象；
+    /// 处理失败时产生 `ZerustError`，由服务器转换为错误响应
+    fn handle(&self, req: Request) -> BoxFuture<'_, Result<Response, ZerustError>>;
+
+    /// 判断指定消息ID是否注册了专门的处理函数
+    ///
+    /// 服务器只为返回 `true` 的消息ID单独统计指标，其余请求合并统计，参见 `Metrics`。
+    /// 默认返回 `true`，此时单独统计的消息ID数量受 `metrics::MAX_ROUTE_SERIES` 限制。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    fn has_route(&self, _msg_id: u32) -> bool {
+        true
+    }
+}
+
+/// 调用路由器处理请求，把处理过程中的 panic 转换为 `ZerustError::HandlerPanic`
+///
+/// 同步处理函数在 `Router::handle` 内执行，异步处理函数在 `Future` 被轮询时执行，
+/// 两者的 panic 都会被捕获，连接任务和工作任务不会因此结束。
+pub(crate) async fn handle_catching_panic(
+    router: &(dyn Router + Send + Sync),
+    req: Request,
+) -> Result<Response, ZerustError> {
+    #[cfg(feature = "tracing")]
+    let msg_id = req.msg_id();
+    // 发生 panic 的请求不再使用，其他状态（例如连接的属性）可能只更新了一半，由处理函数自行保证一致
+    let result = match panic::catch_unwind(AssertUnwindSafe(|| router.handle(req))) {
+        Ok(handling) => CatchUnwind(handling).await,
+        Err(payload) => Err(payload),
+    };
+    result.unwrap_or_else(|payload| {
+        let message = panic_message(payload.as_ref());
+        #[cfg(feature = "tracing")]
+        tracing::error!(msg_id, panic = %message, "handler panicked");
+        Err(ZerustError::HandlerPanic(message))
+    })
+}
+
+/// 捕获内部 `Future` 在轮询时发生的 panic
+struct CatchUnwind<F>(F);
+
+impl<F: Future + Unpin> Future for CatchUnwind<F> {
+    type Output = std::thread::Result<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.0;
+        match panic::catch_unwind(AssertUnwindSafe(|| Pin::new(inner).poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// 取出 panic 的信息，`panic!` 的参数不是字符串时返回固定的描述
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+/// 消息处理器接口
+///
+/// 与注册闭包相比，实现该 trait 的结构体可以直接持有自身的状态
+/// （例如数据库连接池或计数器），并通过返回 `Err` 报告处理失败。
+/// 处理器通过 `DefaultRouter::add_handler` 注册。
+///
+/// 与 Zinx 的 `IRouter` 一样分为三个阶段：路由器依次调用 `pre_handle`、`handle` 和
+/// `post_handle`。`pre_handle` 和 `post_handle` 有默认的空实现，只需要实现关心的阶段；
+/// `handle` 返回错误时不会调用 `post_handle`。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use zerust::router::MessageHandler;
+/// use zerust::{DefaultRouter, Request, Response, ZerustError};
+///
+/// /// 统计收到的请求数量，并把当前计数返回给客户端
+/// struct Counter {
+///     count: AtomicU64,
+/// }
+///
+/// impl MessageHandler for Counter {
+///     fn handle(&self, req: &Request) -> Result<Response, ZerustError> {
+///         let n = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+///         Ok(Response::new(req.msg_id(), n.to_le_bytes().to_vec()))
+///     }
+/// }
+///
+/// let router = DefaultRouter::new();
+/// router.add_handler(1, Arc::new(Counter { count: AtomicU64::new(0) })).unwrap();
+/// ```
+pub trait MessageHandler: Send + Sync {
+    /// 在 `handle` 之前调用，对应 Zinx 的 `PreHandle`
+    ///
+    /// # 参数
+    /// * `req` - 请求对象的引用
+    fn pre_handle(&self, _req: &Request) {}
+
+    /// 处理请求并生成响应
+    ///
+    /// # 参数
+    /// * `req` - 请求对象的引用
+    ///
+    /// # 返回值
+    /// 成功时返回响应对象，失败时返回错误，由服务器转换为错误响应
+    fn handle(&self, req: &Request) -> Result<Response, ZerustError>;
+
+    /// 在 `handle` 成功之后调用，可以修改即将发送的响应，对应 Zinx 的 `PostHandle`
+    ///
+    /// # 参数
+    /// * `req` - 请求对象的引用
+    /// * `resp` - `handle` 生成的响应
+    fn post_handle(&self, _req: &Request, _resp: &mut Response) {}
+}
+
+/// 依次调用处理器的三个阶段
+fn run_handler(handler: &dyn MessageHandler, req: &Request) -> Result<Response, ZerustError> {
+    handler.pre_handle(req);
+    let mut resp = handler.handle(req)?;
+    handler.post_handle(req, &mut resp);
+    Ok(resp)
+}
+
+/// 请求拦截器接口
+///
+/// 拦截器在处理函数前后执行，适合实现日志、鉴权、统计等与具体消息无关的逻辑。
+/// 两个方法都有默认实现，只需要实现关心的那一个。
+///
+/// 多个拦截器按注册顺序调用 `before`，按相反的顺序调用 `after`。
+/// 某个拦截器的 `before` 返回响应时，处理函数和后续拦截器都不再执行，
+/// 该响应只经过已经执行过 `before` 的拦截器的 `after`。
+/// 处理函数返回错误时不会调用 `after`，错误由服务器转换为错误响应。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use zerust::router::Interceptor;
+/// use zerust::{DefaultRouter, Request, Response};
+///
+/// /// 拒绝未携带令牌的请求
+/// struct Auth;
+///
+/// impl Interceptor for Auth {
+///     fn before(&self, req: &Request) -> Option<Response> {
+///         if req.data().starts_with(b"token:") {
+///             None
+///         } else {
+///             Some(Response::new(401, b"unauthorized".to_vec()))
+///         }
+///     }
+/// }
+///
+/// let router = DefaultRouter::new();
+/// router.add_interceptor(Arc::new(Auth));
+/// ```
+pub trait Interceptor: Send + Sync {
+    /// 在处理函数之前调用
+    ///
+    /// # 参数
+    /// * `req` - 请求对象的引用
+    ///
+    /// # 返回值
+    /// 返回 `None` 时继续处理请求；返回 `Some(resp)` 时跳过处理函数，直接使用该响应
+    fn before(&self, req: &Request) -> Option<Response> {
+        let _ = req;
+        None
+    }
+
+    /// 在处理函数之后调用
+    ///
+    /// # 参数
     /// * `req` - 请求对象的引用
+    /// * `resp` - 处理函数或内层拦截器生成的响应
     ///
     /// # 返回值
-    /// 返回对应的响应对象
-    fn handle(&self, req: &Request) -> Response;
+    /// 返回最终的响应，可以原样返回，也可以修改或替换
+    fn after(&self, req: &Request, resp: Response) -> Response {
+        let _ = req;
+        resp
+    }
 }
 
 /// 请求处理函数类型
@@ -31,13 +242,57 @@ pub trait Router: Send + Sync {
 /// 它代表了处理特定请求的逻辑。
 pub type Handler = Box<dyn Fn(&Request) -> Response + Send + Sync>;
 
+/// 可失败的请求处理函数类型
+///
+/// 与 `Handler` 类似，但返回 `Result<Response, ZerustError>`。
+/// 返回的错误会由服务器转换为错误响应，连接保持打开。
+pub type FallibleHandler = Box<dyn Fn(&Request) -> Result<Response, ZerustError> + Send + Sync>;
+
+/// 异步请求处理函数类型
+///
+/// `AsyncHandler` 接收请求的所有权，返回一个产生响应的 `Future`。
+/// 返回的 `Future` 必须是 `'static` 的，因此它不能借用处理函数本身的数据。
+pub type AsyncHandler =
+    Box<dyn Fn(Request) -> BoxFuture<'static, Result<Response, ZerustError>> + Send + Sync>;
+
+/// 中间件函数类型
+///
+/// 接收请求和调用链中的下一环 `Next`，返回产生响应的 `Future`。
+/// 通过 `DefaultRouter::use_middleware` 注册。
+pub type Middleware =
+    Arc<dyn Fn(Request, Next) -> BoxFuture<'static, Result<Response, ZerustError>> + Send + Sync>;
+
+/// 路由表中的一条处理规则
+enum Route {
+    /// 同步处理函数
+    Sync(Handler),
+    /// 可失败的同步处理函数
+    Fallible(FallibleHandler),
+    /// 实现了 `MessageHandler` 的处理器对象
+    Object(Arc<dyn MessageHandler>),
+    /// 异步处理函数
+    Async(AsyncHandler),
+    /// 路由组中的处理函数，经过该组的拦截器后由组内的路由表处理
+    Group(Arc<RouteGroup>),
+}
+
 /// 默认路由器实现
 ///
 /// 使用 `DashMap` 存储消息ID到处理函数的映射，支持并发访问。
 /// `DashMap` 是一个线程安全的哈希表，适合在多线程环境中使用。
 pub struct DefaultRouter {
     /// 存储消息ID到处理函数的映射
-    routes: DashMap<u32, Handler>,
+    ///
+    /// 处理请求时克隆对应规则的 `Arc`，之后替换或移除规则不影响正在处理的请求
+    routes: DashMap<u32, Arc<Route>>,
+    /// 按注册顺序排列的拦截器
+    ///
+    /// 注册时整体替换，处理请求时只需克隆一次 `Arc` 即可得到一致的快照
+    interceptors: RwLock<Arc<[Arc<dyn Interceptor>]>>,
+    /// 按注册顺序排列的中间件，与拦截器一样整体替换
+    middlewares: RwLock<Arc<[Middleware]>>,
+    /// 处理未注册消息ID的规则，`None` 表示返回 `Response::not_found`
+    fallback: RwLock<Option<Arc<Route>>>,
 }
 
 impl DefaultRouter {
@@ -59,17 +314,20 @@ impl DefaultRouter {
     /// router.add_route(1, |req| {
     ///     println!("处理消息ID为1的请求");
     ///     Response::new(req.msg_id(), b"Hello, World!".to_vec())
-    /// });
+    /// }).unwrap();
     ///
     /// // 添加另一个路由处理
     /// router.add_route(2, |req| {
     ///     println!("处理消息ID为2的请求");
     ///     Response::new(req.msg_id(), b"Echo: ".iter().chain(req.data().iter()).cloned().collect())
-    /// });
+    /// }).unwrap();
     /// ```
     pub fn new() -> Self {
         Self {
             routes: DashMap::new(),
+            interceptors: RwLock::new(Arc::new([])),
+            middlewares: RwLock::new(Arc::new([])),
+            fallback: RwLock::new(None),
         }
     }
 
@@ -86,12 +344,672 @@ impl DefaultRouter {
     /// * `F` - 处理函数的类型，必须实现 `Fn(&Request) -> Response + Send + Sync + 'static`
     ///   * `'static` 约束确保了闭包捕获的任何数据都拥有所有权或具有 'static 生命周期，
     ///     使得 Handler 可以安全地在程序的整个生命周期内存在
-    pub fn add_route<F>(&self, msg_id: u32, handler: F)
+    ///
+    /// # 返回值
+    /// * `Ok(true)` - 该消息ID首次注册
+    /// * `Ok(false)` - 该消息ID已经注册过，原有的处理函数被替换，可以据此在启动时发现重复注册的消息ID
+    /// * `Err(ZerustError::ReservedMsgId)` - 消息ID保留给控制帧（参见 `control::is_reserved`），
+    ///   没有注册处理函数；其他注册方法同样如此
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// assert!(router.add_route(1, |req| Response::new(req.msg_id(), Vec::new())).unwrap());
+    /// // 重复注册同一个消息ID
+    /// assert!(!router.add_route(1, |req| Response::new(req.msg_id(), Vec::new())).unwrap());
+    /// ```
+    pub fn add_route<F>(&self, msg_id: u32, handler: F) -> Result<bool, ZerustError>
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.insert(msg_id, Route::Sync(Box::new(handler)))
+    }
+
+    /// 添加返回多条响应的路由规则
+    ///
+    /// 与 `add_route` 类似，但处理函数返回按顺序发送的多条响应，
+    /// 服务器通过 `Response::batch` 依次发送，客户端按相同的顺序收到它们。
+    /// 返回空的 `Vec` 时不发送任何数据。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `handler` - 处理函数，接收请求对象的引用，返回按发送顺序排列的响应
+    ///
+    /// # 返回值
+    /// 同 `add_route`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// // 把请求数据按 4 字节分段返回
+    /// router.add_route_stream(1, |req| {
+    ///     req.data()
+    ///         .chunks(4)
+    ///         .map(|chunk| Response::new(req.msg_id(), chunk.to_vec()))
+    ///         .collect()
+    /// }).unwrap();
+    /// ```
+    pub fn add_route_stream<F>(&self, msg_id: u32, handler: F) -> Result<bool, ZerustError>
+    where
+        F: Fn(&Request) -> Vec<Response> + Send + Sync + 'static,
+    {
+        self.add_route(msg_id, move |req| Response::batch(handler(req)))
+    }
+
+    /// 添加收发 JSON 的路由规则
+    ///
+    /// 请求数据通过 `Request::parse_json` 解析为 `Req` 后交给处理函数，
+    /// 处理函数返回的 `Resp` 通过 `Response::from_json` 编码，响应使用请求的消息ID。
+    /// 请求数据不是有效的 JSON 或者与 `Req` 的结构不符时不会调用处理函数，
+    /// 而是与 `add_route_result` 的处理函数返回错误一样，由服务器把
+    /// `ZerustError::ProtocolError` 转换为错误响应，连接不会被关闭。
+    ///
+    /// 需要开启 `serde_json` 功能。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `handler` - 处理函数，接收解析后的请求，返回要编码的响应
+    ///
+    /// # 返回值
+    /// 同 `add_route`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use zerust::DefaultRouter;
+    ///
+    /// let router = DefaultRouter::new();
+    /// // 请求为 {"a": 1, "b": 2}，响应为各个值的和
+    /// router.add_json_route::<HashMap<String, i64>, i64>(1, |values| values.values().sum()).unwrap();
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn add_json_route<Req, Resp>(
+        &self,
+        msg_id: u32,
+        handler: impl Fn(Req) -> Resp + Send + Sync + 'static,
+    ) -> Result<bool, ZerustError>
+    where
+        Req: serde::de::DeserializeOwned,
+        Resp: serde::Serialize,
+    {
+        self.add_route_result(msg_id, move |req| {
+            Response::from_json(req.msg_id(), &handler(req.parse_json()?))
+        })
+    }
+
+    /// 添加收发 protobuf 消息的路由规则
+    ///
+    /// 与 `add_json_route` 相同，只是请求通过 `Request::parse_proto` 解析，
+    /// 响应通过 `Response::from_proto` 编码。请求数据不是有效的 `Req` 消息时不会调用处理函数，
+    /// 服务器把 `ZerustError::ProtocolError` 转换为错误响应，连接不会被关闭；
+    /// 错误响应的内容可以通过 `Server::with_error_handler` 配置。
+    ///
+    /// 需要开启 `prost` 功能，完整的示例参见 `examples/proto_server.rs`。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `handler` - 处理函数，接收解析后的请求消息，返回要编码的响应消息
+    ///
+    /// # 返回值
+    /// 同 `add_route`
+    #[cfg(feature = "prost")]
+    pub fn add_proto_route<Req, Resp>(
+        &self,
+        msg_id: u32,
+        handler: impl Fn(Req) -> Resp + Send + Sync + 'static,
+    ) -> Result<bool, ZerustError>
+    where
+        Req: prost::Message + Default,
+        Resp: prost::Message,
+    {
+        self.add_route_result(msg_id, move |req| {
+            Ok(Response::from_proto(
+                req.msg_id(),
+                &handler(req.parse_proto()?),
+            ))
+        })
+    }
+
+    /// 添加可失败的路由规则
+    ///
+    /// 与 `add_route` 类似，但处理函数返回 `Result<Response, ZerustError>`。
+    /// 处理函数返回 `Err` 时，服务器会把错误转换为错误响应发送给客户端
+    /// （默认使用 `Response::internal_error`），连接不会被关闭。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `handler` - 处理函数，接收请求对象的引用，返回响应对象或错误
+    ///
+    /// # 类型参数
+    /// * `F` - 处理函数的类型，必须实现 `Fn(&Request) -> Result<Response, ZerustError> + Send + Sync + 'static`
+    ///
+    /// # 返回值
+    /// 同 `add_route`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response, ZerustError};
+    ///
+    /// let router = DefaultRouter::new();
+    /// router.add_route_result(1, |req| {
+    ///     let text = std::str::from_utf8(req.data())
+    ///         .map_err(|e| ZerustError::ProtocolError(e.to_string()))?;
+    ///     Ok(Response::new(req.msg_id(), text.to_uppercase().into_bytes()))
+    /// }).unwrap();
+    /// ```
+    pub fn add_route_result<F>(&self, msg_id: u32, handler: F) -> Result<bool, ZerustError>
+    where
+        F: Fn(&Request) -> Result<Response, ZerustError> + Send + Sync + 'static,
+    {
+        self.insert(msg_id, Route::Fallible(Box::new(handler)))
+    }
+
+    /// 添加消息处理器
+    ///
+    /// 将指定的消息ID与实现了 `MessageHandler` 的处理器对象关联起来。
+    /// 可以与 `add_route` 等基于闭包的注册方式混合使用；
+    /// 同一个处理器对象也可以注册到多个消息ID上。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `handler` - 处理器对象
+    ///
+    /// # 返回值
+    /// 同 `add_route`
+    pub fn add_handler(
+        &self,
+        msg_id: u32,
+        handler: Arc<dyn MessageHandler>,
+    ) -> Result<bool, ZerustError> {
+        self.insert(msg_id, Route::Object(handler))
+    }
+
+    /// 添加异步路由规则
+    ///
+    /// 与 `add_route` 类似，但处理函数返回一个 `Future`，
+    /// 服务器会等待该 `Future` 完成后再发送响应。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `handler` - 异步处理函数，接收请求对象的所有权，返回产生响应的 `Future`
+    ///
+    /// # 类型参数
+    /// * `F` - 处理函数的类型，必须实现 `Fn(Request) -> Fut + Send + Sync + 'static`
+    /// * `Fut` - 处理函数返回的 `Future` 类型，必须实现 `Send + 'static`
+    ///
+    /// # 返回值
+    /// 同 `add_route`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    /// use std::time::Duration;
+    ///
+    /// let router = DefaultRouter::new();
+    /// router.add_async_route(1, |req| async move {
+    ///     // 模拟一次异步IO操作
+    ///     tokio::time::sleep(Duration::from_millis(10)).await;
+    ///     Response::new(req.msg_id(), req.data().to_vec())
+    /// }).unwrap();
+    /// ```
+    pub fn add_async_route<F, Fut>(&self, msg_id: u32, handler: F) -> Result<bool, ZerustError>
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        let handler: AsyncHandler = Box::new(move |req| {
+            let fut = handler(req);
+            Box::pin(async move { Ok(fut.await) })
+        });
+        self.insert(msg_id, Route::Async(handler))
+    }
+
+    /// 注册路由规则，返回该消息ID是否首次注册，保留给控制帧的消息ID返回错误
+    fn insert(&self, msg_id: u32, route: Route) -> Result<bool, ZerustError> {
+        if control::is_reserved(msg_id) {
+            return Err(ZerustError::ReservedMsgId(msg_id));
+        }
+        Ok(self.routes.insert(msg_id, Arc::new(route)).is_none())
+    }
+
+    /// 移除路由规则
+    ///
+    /// 可以在服务器运行期间调用。移除后，该消息ID的请求会交给 `set_fallback` 设置的函数处理，
+    /// 未设置时得到 `Response::not_found`；已经开始处理的请求不受影响。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    ///
+    /// # 返回值
+    /// 该消息ID注册过处理函数时返回 `true`，否则返回 `false`
+    pub fn remove_route(&self, msg_id: u32) -> bool {
+        self.routes.remove(&msg_id).is_some()
+    }
+
+    /// 替换已注册的路由规则
+    ///
+    /// 可以在服务器运行期间调用，用于热更新处理逻辑：替换是原子的，
+    /// 每个请求要么由旧的处理函数处理，要么由新的处理函数处理。
+    /// 与 `add_route` 不同，该消息ID尚未注册时不会添加新的路由规则。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `handler` - 新的处理函数
+    ///
+    /// # 返回值
+    /// 替换成功时返回 `true`；该消息ID尚未注册时返回 `false`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// router.add_route(1, |req| Response::new(req.msg_id(), b"v1".to_vec())).unwrap();
+    ///
+    /// assert!(router.replace_route(1, |req| Response::new(req.msg_id(), b"v2".to_vec())));
+    /// assert!(!router.replace_route(2, |req| Response::new(req.msg_id(), Vec::new())));
+    /// ```
+    pub fn replace_route<F>(&self, msg_id: u32, handler: F) -> bool
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        match self.routes.get_mut(&msg_id) {
+            Some(mut route) => {
+                *route = Arc::new(Route::Sync(Box::new(handler)));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 设置处理未注册消息ID的函数
+    ///
+    /// 未设置时，未注册的消息ID返回 `Response::not_found`。
+    /// 与普通的处理函数一样，请求会先经过中间件和拦截器。
+    /// 重复调用时替换之前设置的函数。
+    ///
+    /// # 参数
+    /// * `handler` - 处理函数，接收未匹配的请求对象的引用，返回响应对象
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// // 未知消息原样返回消息ID，由客户端自行处理
+    /// router.set_fallback(|req| Response::new(req.msg_id(), b"unknown message".to_vec()));
+    /// ```
+    #[doc(alias = "set_default_handler")]
+    pub fn set_fallback<F>(&self, handler: F)
     where
         F: Fn(&Request) -> Response + Send + Sync + 'static,
     {
-        self.routes.insert(msg_id, Box::new(handler));
+        let route = Arc::new(Route::Sync(Box::new(handler)));
+        *self
+            .fallback
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(route);
+    }
+
+    /// 添加拦截器
+    ///
+    /// 拦截器作用于所有消息ID，包括没有注册处理函数的消息。
+    /// 多个拦截器按注册顺序调用 `before`，按相反的顺序调用 `after`，参见 `Interceptor`。
+    ///
+    /// # 参数
+    /// * `interceptor` - 拦截器对象
+    pub fn add_interceptor(&self, interceptor: Arc<dyn Interceptor>) {
+        push_shared(&self.interceptors, interceptor);
+    }
+
+    /// 添加中间件
+    ///
+    /// 中间件接收请求和调用链中的下一环 `Next`：调用 `next.run(req)` 把请求交给后续的
+    /// 中间件，最终经过拦截器到达处理函数，并得到其结果，可以在返回前检查或修改响应；
+    /// 不调用 `next.run` 而直接返回响应则短路，处理函数不会执行。
+    ///
+    /// 多个中间件按注册顺序嵌套，先注册的在外层。中间件作用于所有消息ID，
+    /// 没有注册处理函数的消息也会经过中间件，此时 `next.run` 返回兜底函数的结果，
+    /// 参见 `set_fallback`。
+    ///
+    /// # 参数
+    /// * `middleware` - 异步中间件函数，接收请求和 `Next`，返回响应或错误
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::time::Instant;
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    ///
+    /// // 记录每个请求的处理耗时，包括未注册的消息
+    /// router.use_middleware(|req, next| async move {
+    ///     let msg_id = req.msg_id();
+    ///     let start = Instant::now();
+    ///     let result = next.run(req).await;
+    ///     if let Ok(resp) = &result {
+    ///         println!("msg {} -> {} in {:?}", msg_id, resp.msg_id(), start.elapsed());
+    ///     }
+    ///     result
+    /// });
+    ///
+    /// // 拒绝空请求
+    /// router.use_middleware(|req, next| async move {
+    ///     if req.data().is_empty() {
+    ///         return Ok(Response::new(400, b"empty request".to_vec()));
+    ///     }
+    ///     next.run(req).await
+    /// });
+    /// ```
+    pub fn use_middleware<F, Fut>(&self, middleware: F)
+    where
+        F: Fn(Request, Next) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Response, ZerustError>> + Send + 'static,
+    {
+        let middleware: Middleware = Arc::new(move |req, next| Box::pin(middleware(req, next)));
+        push_shared(&self.middlewares, middleware);
+    }
+
+    /// 创建一个路由组
+    ///
+    /// 路由组拥有独立的路由表和拦截器列表，注册完成后通过 `DefaultRouter::merge`
+    /// 合并到路由器中。
+    ///
+    /// # 参数
+    /// * `name` - 路由组的名称，用于日志和诊断，例如 `"player"`、`"admin"`
+    ///
+    /// # 返回值
+    /// 返回一个空的 `RouteGroup`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::router::Interceptor;
+    /// use zerust::{DefaultRouter, Request, Response};
+    ///
+    /// struct Auth;
+    ///
+    /// impl Interceptor for Auth {
+    ///     fn before(&self, req: &Request) -> Option<Response> {
+    ///         (req.conn_id() == 0).then(|| Response::new(401, Vec::new()))
+    ///     }
+    /// }
+    ///
+    /// let router = DefaultRouter::new();
+    /// // 登录不需要鉴权
+    /// router.add_route(1, |req| Response::new(req.msg_id(), Vec::new())).unwrap();
+    ///
+    /// // 玩家相关的消息都需要经过鉴权
+    /// let player = router.group("player");
+    /// player.add_interceptor(Arc::new(Auth));
+    /// player.add_route(10, |req| Response::new(req.msg_id(), b"profile".to_vec())).unwrap();
+    /// player.add_route(11, |req| Response::new(req.msg_id(), b"inventory".to_vec())).unwrap();
+    /// assert!(router.merge(player).is_empty());
+    ///
+    /// assert_eq!(router.group_name(10).as_deref(), Some("player"));
+    /// assert_eq!(router.group_name(1), None);
+    /// ```
+    pub fn group(&self, name: &str) -> RouteGroup {
+        RouteGroup {
+            name: name.to_string(),
+            router: DefaultRouter::new(),
+        }
     }
+
+    /// 把路由组中的所有处理函数合并到路由器中
+    ///
+    /// 合并后，这些消息ID的请求先经过路由器的拦截器，再经过路由组的拦截器，
+    /// 最后交给组内的处理函数。已经注册的相同消息ID会被替换，与 `add_route` 一样，
+    /// 可以根据返回值在启动时发现重复注册的消息ID。
+    ///
+    /// # 参数
+    /// * `group` - 注册完成的路由组
+    ///
+    /// # 返回值
+    /// 原有的处理函数被替换的消息ID，按从小到大排序；没有重复注册时为空
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use zerust::{DefaultRouter, Response};
+    ///
+    /// let router = DefaultRouter::new();
+    /// router.add_route(10, |req| Response::new(req.msg_id(), Vec::new())).unwrap();
+    ///
+    /// let group = router.group("player");
+    /// group.add_route(10, |req| Response::new(req.msg_id(), Vec::new())).unwrap();
+    /// group.add_route(11, |req| Response::new(req.msg_id(), Vec::new())).unwrap();
+    /// assert_eq!(router.merge(group), vec![10]);
+    /// ```
+    pub fn merge(&self, group: RouteGroup) -> Vec<u32> {
+        let group = Arc::new(group);
+        let msg_ids: Vec<u32> = group.router.routes.iter().map(|e| *e.key()).collect();
+        let mut replaced: Vec<u32> = msg_ids
+            .into_iter()
+            .filter(|&msg_id| {
+                self.routes
+                    .insert(msg_id, Arc::new(Route::Group(group.clone())))
+                    .is_some()
+            })
+            .collect();
+        replaced.sort_unstable();
+        replaced
+    }
+
+    /// 判断指定消息ID是否注册了处理函数
+    ///
+    /// 包括通过路由组合并的处理函数，不包括 `set_fallback` 设置的兜底函数。
+    /// 适合在启动时检查必要的消息ID都已注册。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    ///
+    /// # 返回值
+    /// 已注册时返回 `true`，否则返回 `false`
+    pub fn has_route(&self, msg_id: u32) -> bool {
+        self.routes.contains_key(&msg_id)
+    }
+
+    /// 获取所有已注册的消息ID，按从小到大排序
+    ///
+    /// 包括通过路由组合并的处理函数，不包括 `set_fallback` 设置的兜底函数，
+    /// 适合在启动时输出路由表用于诊断。
+    pub fn routes(&self) -> Vec<u32> {
+        let mut msg_ids: Vec<u32> = self.routes.iter().map(|e| *e.key()).collect();
+        msg_ids.sort_unstable();
+        msg_ids
+    }
+
+    /// 获取处理指定消息ID的路由组名称
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    ///
+    /// # 返回值
+    /// 该消息ID由路由组处理时返回组名，直接注册在路由器上或未注册时返回 `None`
+    pub fn group_name(&self, msg_id: u32) -> Option<String> {
+        match self.routes.get(&msg_id).as_deref().map(Arc::as_ref) {
+            Some(Route::Group(group)) => Some(group.name.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// 路由组
+///
+/// 一组共享拦截器的处理函数，通过 `DefaultRouter::group` 创建，
+/// 注册完成后通过 `DefaultRouter::merge` 合并到路由器中。
+/// 组内的拦截器只作用于组内的处理函数，执行顺序与 `DefaultRouter::add_interceptor` 相同。
+pub struct RouteGroup {
+    /// 路由组的名称，用于日志和诊断
+    name: String,
+    /// 组内的路由表和拦截器
+    router: DefaultRouter,
+}
+
+impl RouteGroup {
+    /// 获取路由组的名称
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 添加作用于组内所有处理函数的拦截器，参见 `DefaultRouter::add_interceptor`
+    pub fn add_interceptor(&self, interceptor: Arc<dyn Interceptor>) {
+        self.router.add_interceptor(interceptor);
+    }
+
+    /// 添加路由规则，参见 `DefaultRouter::add_route`
+    pub fn add_route<F>(&self, msg_id: u32, handler: F) -> Result<bool, ZerustError>
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.router.add_route(msg_id, handler)
+    }
+
+    /// 添加可失败的路由规则，参见 `DefaultRouter::add_route_result`
+    pub fn add_route_result<F>(&self, msg_id: u32, handler: F) -> Result<bool, ZerustError>
+    where
+        F: Fn(&Request) -> Result<Response, ZerustError> + Send + Sync + 'static,
+    {
+        self.router.add_route_result(msg_id, handler)
+    }
+
+    /// 添加消息处理器，参见 `DefaultRouter::add_handler`
+    pub fn add_handler(
+        &self,
+        msg_id: u32,
+        handler: Arc<dyn MessageHandler>,
+    ) -> Result<bool, ZerustError> {
+        self.router.add_handler(msg_id, handler)
+    }
+
+    /// 添加异步路由规则，参见 `DefaultRouter::add_async_route`
+    pub fn add_async_route<F, Fut>(&self, msg_id: u32, handler: F) -> Result<bool, ZerustError>
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.router.add_async_route(msg_id, handler)
+    }
+}
+
+/// 中间件调用链中的下一环
+///
+/// 由路由器传给中间件，参见 `DefaultRouter::use_middleware`。
+/// 调用 `run` 把请求交给后续的中间件，最后经过拦截器到达处理函数。
+pub struct Next {
+    /// 本次请求使用的中间件列表
+    middlewares: Arc<[Middleware]>,
+    /// 下一个要执行的中间件在列表中的位置
+    index: usize,
+    /// 本次请求使用的拦截器列表
+    interceptors: Arc<[Arc<dyn Interceptor>]>,
+    /// 处理该请求的规则，开始处理请求时确定，`None` 表示没有注册处理函数和兜底函数
+    route: Option<Arc<Route>>,
+}
+
+impl Next {
+    /// 把请求交给调用链中的下一环处理
+    ///
+    /// # 参数
+    /// * `req` - 请求对象，中间件可以原样传递，也可以替换为新的请求
+    ///
+    /// # 返回值
+    /// 返回一个 `Future`，完成时产生后续处理得到的响应或错误
+    pub fn run(self, req: Request) -> BoxFuture<'static, Result<Response, ZerustError>> {
+        match self.middlewares.get(self.index).cloned() {
+            Some(middleware) => {
+                let next = Next {
+                    index: self.index + 1,
+                    ..self
+                };
+                middleware(req, next)
+            }
+            None => run_route(self.interceptors, self.route, req),
+        }
+    }
+}
+
+/// 依次经过拦截器和处理函数处理请求
+fn run_route(
+    interceptors: Arc<[Arc<dyn Interceptor>]>,
+    route: Option<Arc<Route>>,
+    req: Request,
+) -> BoxFuture<'static, Result<Response, ZerustError>> {
+    if interceptors.is_empty() {
+        return call_route(route, req);
+    }
+    for (i, interceptor) in interceptors.iter().enumerate() {
+        if let Some(resp) = interceptor.before(&req) {
+            // 短路：只有已经执行过 before 的拦截器会处理该响应
+            let resp = apply_after(&interceptors[..i], &req, resp);
+            return Box::pin(future::ready(Ok(resp)));
+        }
+    }
+    // 异步处理函数会取得请求的所有权，after 使用请求的副本
+    let handled = call_route(route, req.clone());
+    Box::pin(async move {
+        let resp = handled.await?;
+        Ok(apply_after(&interceptors, &req, resp))
+    })
+}
+
+/// 调用处理函数，没有可用的处理函数时返回 `Response::not_found`，消息ID沿用请求的消息ID
+///
+/// 同步处理函数在本函数内直接执行，异步处理函数只在此创建 `Future`。
+fn call_route(
+    route: Option<Arc<Route>>,
+    req: Request,
+) -> BoxFuture<'static, Result<Response, ZerustError>> {
+    let Some(route) = route else {
+        return Box::pin(future::ready(Ok(Response::not_found(req.msg_id()))));
+    };
+    match route.as_ref() {
+        Route::Sync(handler) => Box::pin(future::ready(Ok(handler(&req)))),
+        Route::Fallible(handler) => Box::pin(future::ready(handler(&req))),
+        Route::Object(handler) => Box::pin(future::ready(run_handler(handler.as_ref(), &req))),
+        Route::Async(handler) => handler(req),
+        Route::Group(group) => {
+            // 组内的处理可能是异步的，由返回的 Future 持有组的引用计数
+            let group = group.clone();
+            Box::pin(async move { group.router.handle(req).await })
+        }
+    }
+}
+
+/// 获取注册列表的快照
+fn snapshot<T>(list: &RwLock<Arc<[T]>>) -> Arc<[T]> {
+    // 锁内只有替换和克隆操作，即使锁中毒，列表本身也是完整的
+    list.read().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+/// 在注册列表的末尾追加一项，整体替换列表
+fn push_shared<T: Clone>(list: &RwLock<Arc<[T]>>, item: T) {
+    let mut list = list.write().unwrap_or_else(PoisonError::into_inner);
+    let mut items = list.to_vec();
+    items.push(item);
+    *list = items.into();
+}
+
+/// 按相反的顺序把响应依次交给拦截器的 `after`
+fn apply_after(interceptors: &[Arc<dyn Interceptor>], req: &Request, resp: Response) -> Response {
+    interceptors
+        .iter()
+        .rev()
+        .fold(resp, |resp, interceptor| interceptor.after(req, resp))
 }
 
 /// 为 `DefaultRouter` 实现 `Default` trait
@@ -108,15 +1026,36 @@ impl Router for DefaultRouter {
     /// 根据请求的消息ID查找对应的处理函数，如果找到则调用该函数处理请求，
     /// 否则返回一个表示路由未找到的响应。
     ///
+    /// 同步处理函数会在本方法内直接执行；异步处理函数只在此创建 `Future`，
+    /// 路由表的读锁在返回前释放，不会跨越 `.await` 持有。
+    /// 请求依次经过中间件、拦截器的 `before`、处理函数和拦截器的 `after`。
+    ///
     /// # 参数
-    /// * `req` - 请求对象的引用
+    /// * `req` - 请求对象
     ///
     /// # 返回值
-    /// 返回对应的响应对象
-    fn handle(&self, req: &Request) -> Response {
-        match self.routes.get(&req.msg_id()) {
-            Some(handler) => handler(req),
-            None => Response::not_found(),
-        }
+    /// 返回一个 `Future`，完成时产生对应的响应对象或处理函数返回的错误
+    fn handle(&self, req: Request) -> BoxFuture<'_, Result<Response, ZerustError>> {
+        // 处理规则在开始处理时确定，路由表的读锁不会跨越 .await 持有
+        let route = match self.routes.get(&req.msg_id()) {
+            Some(route) => Some(route.clone()),
+            None => self
+                .fallback
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        };
+        let next = Next {
+            middlewares: snapshot(&self.middlewares),
+            index: 0,
+            interceptors: snapshot(&self.interceptors),
+            route,
+        };
+        next.run(req)
+    }
+
+    /// 判断指定消息ID是否注册了处理函数，兜底函数处理的消息ID不算注册
+    fn has_route(&self, msg_id: u32) -> bool {
+        DefaultRouter::has_route(self, msg_id)
     }
 }
@@ -0,0 +1,49 @@
+//! # 运行时适配模块
+//!
+//! 该模块集中管理框架对异步运行时的依赖，框架的其他部分只通过本模块访问这些原语：
+//!
+//! * 与运行时无关的部分：异步读写 trait、`select!`/`pin!` 以及同步原语（`Mutex`、`mpsc`、
+//!   `oneshot`、`watch`、`Semaphore`、`Notify`）。它们来自 Tokio 的 `io-util`、`macros` 和
+//!   `sync` 功能，只依赖标准库的 `Waker`，不需要 Tokio 运行时，在任何执行器上都可以使用。
+//!   因此 `Server::run` 的关闭通道等公开接口中的 `tokio::sync` 类型在两种后端下都可以使用。
+//! * 与运行时相关的部分：TCP/Unix 域套接字、定时器和任务管理，由运行时后端提供。
+//!
+//! 与网络IO无关的部分（`DataPack`、`Router`、`Request`/`Response`）不依赖这里的任何原语。
+//!
+//! ## 运行时后端
+//!
+//! * `tokio-runtime`（默认）- 直接使用 Tokio 的网络、定时器和任务，是完整支持的后端
+//! * `smol-runtime` - 使用 smol 的网络、定时器和全局执行器，参见 `smol` 子模块。
+//!   需要通过 `default-features = false` 关闭 `tokio-runtime`，两者同时开启时使用 Tokio
+//!
+//! 两种后端提供同名、用法相同的类型和函数，框架的其他部分不区分当前使用的后端。
+
+pub(crate) use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, Join, split,
+};
+pub(crate) use tokio::sync::{
+    Mutex, Notify, OwnedSemaphorePermit, Semaphore, mpsc, oneshot, watch,
+};
+pub(crate) use tokio::{pin, select};
+
+#[cfg(feature = "tokio-runtime")]
+pub(crate) use tokio::net::{TcpListener, TcpStream, ToSocketAddrs, lookup_host};
+#[cfg(all(unix, feature = "tokio-runtime"))]
+pub(crate) use tokio::net::{UnixListener, UnixStream};
+#[cfg(feature = "tokio-runtime")]
+pub(crate) use tokio::task::{JoinError, JoinHandle, JoinSet, spawn};
+#[cfg(feature = "tokio-runtime")]
+pub(crate) use tokio::time::{Instant, sleep, sleep_until, timeout};
+
+#[cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+mod smol;
+#[cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+pub(crate) use self::smol::{
+    Instant, JoinError, JoinHandle, JoinSet, TcpListener, TcpStream, ToSocketAddrs, lookup_host,
+    sleep, sleep_until, spawn, timeout,
+};
+#[cfg(all(unix, feature = "smol-runtime", not(feature = "tokio-runtime")))]
+pub(crate) use self::smol::{UnixListener, UnixStream};
+
+#[cfg(not(any(feature = "tokio-runtime", feature = "smol-runtime")))]
+compile_error!("zerust requires a runtime backend: enable `tokio-runtime` or `smol-runtime`");
@@ -0,0 +1,388 @@
+//! # smol 运行时后端
+//!
+//! 开启 `smol-runtime` 且关闭 `tokio-runtime` 时使用，提供与 Tokio 同名、用法相同的
+//! 网络、定时器和任务原语：
+//!
+//! * 网络 - 包装 `smol::net` 的套接字，并实现 Tokio 的 `AsyncRead`/`AsyncWrite`，
+//!   这样 `Connection` 和 TLS 流不需要区分后端。拆分后的写入端被丢弃时关闭写方向，与 Tokio 相同
+//! * 定时器 - 基于 `smol::Timer`，`Instant` 为 `std::time::Instant`
+//! * 任务 - 在 smol 的全局执行器上运行，工作线程的数量由 `SMOL_THREADS` 环境变量决定。
+//!   与 Tokio 相同，任务中的 panic 被捕获并通过 `JoinError` 返回，丢弃 `JoinHandle` 不会取消任务，
+//!   丢弃 `JoinSet` 会取消其中所有的任务
+//!
+//! 这里只实现了框架用到的接口。`JoinSet::join_next` 每次被唤醒时依次检查所有任务，
+//! 在线连接非常多时开销高于 Tokio 的实现。
+
+use ::smol::future::{self, FutureExt};
+use ::smol::{Task, Timer};
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{Shutdown, SocketAddr};
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+pub(crate) use ::smol::net::AsyncToSocketAddrs as ToSocketAddrs;
+pub(crate) use std::time::Instant;
+
+/// 把地址解析为套接字地址
+pub(crate) async fn lookup_host(
+    addr: impl ToSocketAddrs,
+) -> io::Result<impl Iterator<Item = SocketAddr>> {
+    Ok(::smol::net::resolve(addr).await?.into_iter())
+}
+
+/// 可以拆分为读取端和写入端的套接字
+pub trait Socket:
+    ::smol::io::AsyncRead + ::smol::io::AsyncWrite + Clone + Unpin + Send + 'static
+{
+    /// 关闭套接字的写方向
+    fn shutdown_write(&self) -> io::Result<()>;
+}
+
+impl Socket for ::smol::net::TcpStream {
+    fn shutdown_write(&self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+#[cfg(unix)]
+impl Socket for ::smol::net::unix::UnixStream {
+    fn shutdown_write(&self) -> io::Result<()> {
+        self.shutdown(Shutdown::Write)
+    }
+}
+
+/// 实现了 Tokio 读写 trait 的 smol 套接字
+#[derive(Debug)]
+pub struct Stream<S>(S);
+
+/// TCP 流
+pub type TcpStream = Stream<::smol::net::TcpStream>;
+
+/// Unix 域套接字的流
+#[cfg(unix)]
+pub type UnixStream = Stream<::smol::net::unix::UnixStream>;
+
+impl<S: Socket> Stream<S> {
+    /// 拆分为读取端和写入端，两者共享同一个套接字
+    pub(crate) fn into_split(self) -> (Stream<S>, WriteHalf<S>) {
+        (Stream(self.0.clone()), WriteHalf(self))
+    }
+}
+
+impl TcpStream {
+    /// 连接到指定地址
+    pub(crate) async fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        ::smol::net::TcpStream::connect(addr).await.map(Stream)
+    }
+
+    /// 获取对端的套接字地址
+    pub(crate) fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.0.peer_addr()
+    }
+
+    /// 获取是否启用了 `TCP_NODELAY`
+    pub(crate) fn nodelay(&self) -> io::Result<bool> {
+        self.0.nodelay()
+    }
+
+    /// 设置 `TCP_NODELAY`
+    pub(crate) fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.0.set_nodelay(nodelay)
+    }
+}
+
+#[cfg(unix)]
+impl<S: std::os::fd::AsFd> std::os::fd::AsFd for Stream<S> {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+#[cfg(windows)]
+impl<S: std::os::windows::io::AsSocket> std::os::windows::io::AsSocket for Stream<S> {
+    fn as_socket(&self) -> std::os::windows::io::BorrowedSocket<'_> {
+        self.0.as_socket()
+    }
+}
+
+impl<S: Socket> AsyncRead for Stream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = ready!(Pin::new(&mut self.0).poll_read(cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: Socket> AsyncWrite for Stream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(cx)
+    }
+}
+
+/// 拆分后的写入端，被丢弃时关闭套接字的写方向
+#[derive(Debug)]
+pub struct WriteHalf<S: Socket>(Stream<S>);
+
+impl<S: Socket> Drop for WriteHalf<S> {
+    fn drop(&mut self) {
+        let _ = self.0.0.shutdown_write();
+    }
+}
+
+impl<S: Socket> AsyncWrite for WriteHalf<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+/// TCP 监听器
+#[derive(Debug)]
+pub struct TcpListener(::smol::net::TcpListener);
+
+impl TcpListener {
+    /// 绑定监听地址
+    pub(crate) async fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        ::smol::net::TcpListener::bind(addr).await.map(Self)
+    }
+
+    /// 接受一个新连接，返回流和客户端地址
+    pub(crate) async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        let (stream, addr) = self.0.accept().await?;
+        Ok((Stream(stream), addr))
+    }
+
+    /// 获取实际绑定的本地地址
+    pub(crate) fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.local_addr()
+    }
+}
+
+/// Unix 域套接字的监听器
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixListener(::smol::net::unix::UnixListener);
+
+#[cfg(unix)]
+impl UnixListener {
+    /// 绑定套接字文件的路径
+    pub(crate) fn bind(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        ::smol::net::unix::UnixListener::bind(path).map(Self)
+    }
+
+    /// 接受一个新连接，返回流和客户端的地址
+    pub(crate) async fn accept(&self) -> io::Result<(UnixStream, ::smol::net::unix::SocketAddr)> {
+        let (stream, addr) = self.0.accept().await?;
+        Ok((Stream(stream), addr))
+    }
+}
+
+/// 等待指定的时间
+pub(crate) async fn sleep(duration: Duration) {
+    Timer::after(duration).await;
+}
+
+/// 等待到指定的时刻
+pub(crate) async fn sleep_until(deadline: Instant) {
+    Timer::at(deadline).await;
+}
+
+/// 等待超时的错误
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+/// 在指定的时间内等待 `future` 完成，超时则返回 `Err(Elapsed)`
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    // 先检查 `future`，已经完成的操作不会因为时间为 0 而超时
+    future::or(async { Ok(future.await) }, async {
+        Timer::after(duration).await;
+        Err(Elapsed)
+    })
+    .await
+}
+
+/// 任务的结果，`Err` 中是任务 panic 时的参数
+type TaskOutput<T> = Result<T, Box<dyn Any + Send>>;
+
+/// 在全局执行器上运行任务，捕获任务中的 panic
+fn spawn_task<F>(future: F) -> Task<TaskOutput<F::Output>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    ::smol::spawn(AssertUnwindSafe(future).catch_unwind())
+}
+
+/// 任务 panic 或者被取消
+pub struct JoinError {
+    /// panic 的参数，`None` 表示任务被取消
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl JoinError {
+    /// 任务是否因为 panic 而结束
+    pub fn is_panic(&self) -> bool {
+        self.panic.is_some()
+    }
+
+    /// 获取 panic 的参数
+    ///
+    /// # Panics
+    /// 任务不是因为 panic 而结束时会 panic
+    #[cfg_attr(not(feature = "test-util"), allow(dead_code))]
+    pub fn into_panic(self) -> Box<dyn Any + Send> {
+        self.panic.expect("task was cancelled, not panicked")
+    }
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_panic() {
+            f.write_str("task panicked")
+        } else {
+            f.write_str("task was cancelled")
+        }
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+/// 在全局执行器上运行任务
+pub(crate) fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    JoinHandle {
+        task: Mutex::new(Some(spawn_task(future))),
+    }
+}
+
+/// 任务的句柄，等待它得到任务的结果
+///
+/// 与 Tokio 相同，丢弃句柄不会取消任务，需要取消时调用 `abort`。
+pub struct JoinHandle<T> {
+    /// 正在运行的任务，被取消或者已经得到结果后为 `None`
+    task: Mutex<Option<Task<TaskOutput<T>>>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// 取消任务
+    pub(crate) fn abort(&self) {
+        // 丢弃 smol 的 `Task` 会取消任务
+        drop(self.task.lock().unwrap().take());
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut task = self.task.lock().unwrap();
+        let Some(running) = task.as_mut() else {
+            return Poll::Ready(Err(JoinError { panic: None }));
+        };
+        let output = ready!(Pin::new(running).poll(cx));
+        *task = None;
+        Poll::Ready(output.map_err(|panic| JoinError { panic: Some(panic) }))
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.get_mut().unwrap().take() {
+            task.detach();
+        }
+    }
+}
+
+/// 一组任务，可以依次等待其中的任务结束
+///
+/// 与 Tokio 相同，丢弃 `JoinSet` 会取消其中所有的任务。
+pub struct JoinSet<T> {
+    /// 尚未结束的任务
+    tasks: Vec<Task<TaskOutput<T>>>,
+}
+
+impl<T: Send + 'static> JoinSet<T> {
+    /// 创建一个空的任务组
+    pub(crate) fn new() -> Self {
+        Self { tasks: Vec::new() }
+    }
+
+    /// 在全局执行器上运行任务并加入任务组
+    pub(crate) fn spawn<F>(&mut self, future: F)
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        self.tasks.push(spawn_task(future));
+    }
+
+    /// 任务组中是否没有尚未结束的任务
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// 等待任意一个任务结束，任务组为空时返回 `None`
+    pub(crate) async fn join_next(&mut self) -> Option<Result<T, JoinError>> {
+        if self.tasks.is_empty() {
+            return None;
+        }
+        future::poll_fn(|cx| {
+            for i in 0..self.tasks.len() {
+                if let Poll::Ready(output) = Pin::new(&mut self.tasks[i]).poll(cx) {
+                    // 任务已经结束，丢弃它不会取消任何操作
+                    drop(self.tasks.swap_remove(i));
+                    return Poll::Ready(Some(
+                        output.map_err(|panic| JoinError { panic: Some(panic) }),
+                    ));
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
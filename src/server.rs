@@ -5,50 +5,1371 @@
 //!
 //! ## 主要功能
 //!
-//! * 绑定并监听TCP端口
+//! * 绑定并监听TCP端口，或者 Unix 域套接字
 //! * 接收客户端连接
 //! * 为每个连接创建独立的异步任务
 //! * 协调路由器和连接管理器的工作
+//! * 为每个连接分配连接ID，并登记到 `ConnManager` 中
+//! * 在连接建立和断开时调用用户注册的生命周期钩子
+//! * 限制同时在线的连接数量，超出时等待或拒绝新连接
+//! * 接受连接时的暂时性错误（例如文件描述符耗尽）不会结束服务器，参见 `AcceptErrorPolicy`
+//! * 收到关闭信号后优雅关闭，等待进行中的请求处理完毕，可以设置等待的超时时间
+//! * 通过心跳检测失去响应的客户端并关闭其连接
+//! * 可选地把请求交给固定数量的工作任务处理，限制全局的处理并发数
+//! * 可选地限制每个连接每秒处理的请求数量
+//! * 开启 `tls` 功能后可以使用 TLS 加密连接
+//! * 统计请求数量、错误数量、收发字节数、连接数量和每个消息ID的处理耗时，参见 `Server::metrics`
 
-use crate::{connection::Connection, error::ZerustError, router::Router};
+use crate::codec::{CheckedDataPack, PacketCodec};
+#[cfg(feature = "compression")]
+use crate::compression::{CompressedCodec, CompressionConfig};
+use crate::config::{ServerBuilder, ServerConfig};
+use crate::conn_manager::{
+    BroadcastReport, ConnManager, ConnectionHandle, RequestGuard, WriteQueuePolicy,
+};
+#[cfg(feature = "tls")]
+use crate::connection::with_timeout;
+use crate::connection::{Connection, ConnectionReader, ConnectionWriter, Transport};
+use crate::context::ConnContext;
+use crate::control::{self, PING_MSG_ID, PONG_MSG_ID};
+use crate::datapack::DataPack;
+use crate::metrics::{Metrics, MetricsSnapshot};
+use crate::rate_limit::{RateLimitConfig, RateLimitPolicy, TokenBucket};
+use crate::request::Request;
+use crate::router::{BoxFuture, Router, handle_catching_panic};
+use crate::runtime::{
+    AsyncWriteExt, Instant, JoinError, JoinSet, OwnedSemaphorePermit, Semaphore, TcpListener,
+    TcpStream, mpsc, oneshot, pin, select, sleep_until, timeout, watch,
+};
+#[cfg(unix)]
+use crate::runtime::{UnixListener, UnixStream};
+use crate::worker_pool::{WorkerPool, WorkerPoolConfig};
+use crate::{error::ZerustError, response::Response};
+use bytes::Bytes;
+use socket2::{SockRef, TcpKeepalive};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::Path;
 use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::oneshot;
+use std::time::Duration;
+#[cfg(feature = "tls")]
+use tokio_rustls::{TlsAcceptor, rustls};
+
+/// 错误响应生成函数类型
+///
+/// 当处理函数返回错误时，服务器调用该函数生成发送给客户端的错误响应。
+/// 参数依次为请求的消息ID和处理函数返回的错误。
+pub type ErrorHandler = Arc<dyn Fn(u32, &ZerustError) -> Response + Send + Sync>;
+
+/// 连接生命周期钩子类型
+///
+/// 接收连接的句柄，返回一个在连接任务中执行的 `Future`。
+/// 通过 `Server::with_on_conn_start` 和 `Server::with_on_conn_stop` 注册。
+pub type ConnHook = Arc<dyn Fn(ConnectionHandle) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// 心跳回应钩子类型
+///
+/// 接收客户端发送的心跳回应，返回一个在连接任务中执行的 `Future`。
+/// 通过 `Server::with_on_heartbeat` 注册。
+pub type HeartbeatHook = Arc<dyn Fn(Request) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// 连接错误钩子类型
+///
+/// 参数依次为连接的句柄和导致连接结束的错误。
+/// 通过 `Server::with_on_conn_error` 注册。
+pub type ConnErrorHook = Arc<dyn Fn(&ConnectionHandle, &ZerustError) + Send + Sync>;
+
+/// TLS 握手失败钩子类型
+///
+/// 参数依次为客户端地址和握手失败的原因，超时时为 `ZerustError::Timeout`。
+/// 通过 `Server::with_on_tls_error` 注册。
+#[cfg(feature = "tls")]
+pub type TlsErrorHook = Arc<dyn Fn(SocketAddr, &ZerustError) + Send + Sync>;
+
+/// 默认的心跳消息ID，即 ping 控制帧的消息ID
+///
+/// 客户端的 `Connection` 会自动回复 pong，不需要为心跳编写任何代码。
+/// 与 Zinx 客户端通信时，可以通过 `HeartbeatConfig::with_msg_id(99999)` 使用 Zinx 的默认配置。
+pub const DEFAULT_HEARTBEAT_MSG_ID: u32 = PING_MSG_ID;
+
+/// 拒绝连接时写入繁忙消息的超时时间
+const REJECT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 接受连接因资源不足失败后，默认等待多久再继续接受连接
+pub const DEFAULT_ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// 在线连接数达到上限时的处理策略
+///
+/// 通过 `Server::with_conn_limit_policy` 设置，仅在设置了
+/// `Server::with_max_connections` 时生效。
+#[derive(Debug, Clone, Default)]
+pub enum ConnLimitPolicy {
+    /// 暂停接受新连接，直到有连接结束
+    ///
+    /// 新连接会留在操作系统的监听队列中等待，形成背压。
+    #[default]
+    Wait,
+    /// 接受新连接后立即关闭
+    Reject {
+        /// 关闭前发送给客户端的消息（例如"服务器繁忙"），`None` 表示直接关闭
+        busy_response: Option<Response>,
+    },
+}
+
+/// 接受连接失败时的处理策略
+///
+/// 通过 `Server::with_accept_error_policy` 设置。接受连接失败通常是暂时的：
+/// 客户端在连接被接受之前断开（`ConnectionAborted` 等），或者进程的文件描述符、
+/// 内存等资源暂时耗尽（`EMFILE`、`ENFILE` 等）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcceptErrorPolicy {
+    /// 任何错误都结束服务器，`Server::run` 返回该错误
+    Fatal,
+    /// 忽略暂时性错误，继续接受连接
+    ///
+    /// 客户端断开导致的错误立即重试；其他错误（例如资源耗尽）先等待 `backoff`，
+    /// 避免在资源释放之前反复失败。`InvalidInput` 错误说明监听器已经不可用，仍然结束服务器。
+    Retry {
+        /// 资源耗尽等错误后等待的时间
+        backoff: Duration,
+    },
+}
+
+impl Default for AcceptErrorPolicy {
+    /// 默认等待 `DEFAULT_ACCEPT_BACKOFF` 后重试
+    fn default() -> Self {
+        AcceptErrorPolicy::Retry {
+            backoff: DEFAULT_ACCEPT_BACKOFF,
+        }
+    }
+}
+
+impl AcceptErrorPolicy {
+    /// 判断接受连接的错误应当如何处理
+    ///
+    /// # 返回值
+    /// * `None` - 错误是致命的，服务器应当结束
+    /// * `Some(delay)` - 等待 `delay` 后继续接受连接
+    fn retry_delay(&self, err: &io::Error) -> Option<Duration> {
+        let AcceptErrorPolicy::Retry { backoff } = *self else {
+            return None;
+        };
+        match err.kind() {
+            // 客户端在连接被接受之前已经断开，与其他连接无关
+            io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock => Some(Duration::ZERO),
+            // 监听器已经关闭或不再处于监听状态
+            io::ErrorKind::InvalidInput => None,
+            _ => Some(backoff),
+        }
+    }
+}
+
+/// 服务器收到关闭信号后处理在线连接的方式
+///
+/// 无论采用哪种方式，服务器都会立即停止接受新连接，
+/// 每个连接的停止钩子也都会被执行。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShutdownMode {
+    /// 等待所有连接处理完当前请求，不限制等待时间
+    #[default]
+    Wait,
+    /// 等待所有连接处理完当前请求，超过 `timeout` 后强制关闭剩余的连接
+    Graceful {
+        /// 等待连接结束的最长时间
+        timeout: Duration,
+    },
+    /// 立即强制关闭所有连接，不等待正在处理的请求
+    Immediate,
+}
+
+/// 心跳配置
+///
+/// 连接在 `interval` 内没有收到任何数据时，服务器向客户端发送一条心跳消息
+/// （消息ID为 `msg_id`、数据为 `payload`，默认为空）；连续 `max_missed` 次心跳都没有
+/// 得到回应时，服务器认为客户端已失去响应并关闭连接，关闭时同样会调用连接的停止钩子。
+/// 客户端发送的任何消息都视为回应，因此连接最多空闲 `max_idle` 后被关闭。
+///
+/// 心跳默认是 ping 控制帧，Zerust 客户端会自动回复 pong（参见 `control` 模块）。
+/// 客户端发送的 pong 不会交给路由器，而是交给 `Server::with_on_heartbeat` 注册的钩子处理；
+/// 使用其他消息ID时，该ID成为保留的消息ID，客户端发送的该ID的消息同样交给钩子处理。
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use zerust::server::HeartbeatConfig;
+/// use zerust::{DefaultRouter, Server};
+///
+/// // 每 30 秒空闲时发送消息ID为 1、数据为 "ping" 的心跳，连续 3 次无回应时关闭连接
+/// let config = HeartbeatConfig::new(Duration::from_secs(30), 3)
+///     .with_msg_id(1)
+///     .with_payload(b"ping".to_vec());
+/// assert_eq!(config.max_idle(), Duration::from_secs(120));
+/// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+///     .with_heartbeat_config(config);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    /// 连接空闲多久后发送心跳
+    interval: Duration,
+    /// 允许连续未得到回应的心跳次数
+    max_missed: u32,
+    /// 心跳消息ID
+    msg_id: u32,
+    /// 心跳消息携带的数据
+    payload: Bytes,
+}
+
+impl HeartbeatConfig {
+    /// 创建心跳配置，心跳消息ID为 `DEFAULT_HEARTBEAT_MSG_ID`
+    ///
+    /// # 参数
+    /// * `interval` - 连接空闲多久后发送心跳
+    /// * `max_missed` - 允许连续未得到回应的心跳次数
+    ///
+    /// # 返回值
+    /// 返回一个新的 `HeartbeatConfig` 实例
+    pub fn new(interval: Duration, max_missed: u32) -> Self {
+        Self {
+            interval,
+            max_missed,
+            msg_id: DEFAULT_HEARTBEAT_MSG_ID,
+            payload: Bytes::new(),
+        }
+    }
+
+    /// 设置心跳消息ID
+    ///
+    /// # 参数
+    /// * `msg_id` - 心跳消息ID，开启心跳后该ID保留给心跳使用
+    ///
+    /// # 返回值
+    /// 返回使用新消息ID的 `HeartbeatConfig` 实例
+    pub fn with_msg_id(mut self, msg_id: u32) -> Self {
+        self.msg_id = msg_id;
+        self
+    }
+
+    /// 设置心跳消息携带的数据
+    ///
+    /// # 参数
+    /// * `payload` - 心跳消息的数据，例如客户端约定的 ping 内容
+    ///
+    /// # 返回值
+    /// 返回使用新数据的 `HeartbeatConfig` 实例
+    pub fn with_payload(mut self, payload: impl Into<Bytes>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+
+    /// 获取连接空闲多久后发送心跳
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// 获取允许连续未得到回应的心跳次数
+    pub fn max_missed(&self) -> u32 {
+        self.max_missed
+    }
+
+    /// 获取心跳消息ID
+    pub fn msg_id(&self) -> u32 {
+        self.msg_id
+    }
+
+    /// 获取心跳消息携带的数据
+    pub fn payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    /// 获取连接在没有收到任何数据的情况下最多保持的时间
+    ///
+    /// 即发送 `max_missed` 次心跳并等待最后一次回应的总时间。
+    pub fn max_idle(&self) -> Duration {
+        self.interval
+            .saturating_mul(self.max_missed.saturating_add(1))
+    }
+}
+
+/// 连接的心跳状态
+struct HeartbeatState {
+    /// 心跳配置
+    config: HeartbeatConfig,
+    /// 下一次发送心跳（或判定超时）的时间
+    deadline: Instant,
+    /// 连续未得到回应的心跳次数
+    missed: u32,
+}
+
+impl HeartbeatState {
+    /// 创建心跳状态，从当前时间开始计时
+    fn new(config: HeartbeatConfig) -> Self {
+        Self {
+            deadline: Instant::now() + config.interval,
+            missed: 0,
+            config,
+        }
+    }
+
+    /// 收到客户端数据，重新开始计时
+    fn on_activity(&mut self) {
+        self.missed = 0;
+        self.deadline = Instant::now() + self.config.interval;
+    }
+}
+
+/// 服务器关闭的结果统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ShutdownReport {
+    /// 处理完当前请求后正常关闭的连接数量
+    closed_cleanly: usize,
+    /// 被强制关闭的连接数量
+    force_closed: usize,
+}
+
+impl ShutdownReport {
+    /// 记录一个在关闭期间结束的连接任务
+    fn record(&mut self, result: Result<ConnExit, JoinError>) {
+        match result {
+            Ok(ConnExit::ForceClosed) => self.force_closed += 1,
+            Ok(ConnExit::Closed) => self.closed_cleanly += 1,
+            // 被拒绝的连接没有处理过请求；panic 的连接任务既不算正常关闭，也不是被强制关闭
+            Ok(ConnExit::Rejected) | Err(_) => {}
+        }
+    }
+
+    /// 获取正常关闭的连接数量
+    ///
+    /// # 返回值
+    /// 返回收到关闭信号时仍然在线、在等待时间内处理完当前请求后关闭的连接数量
+    pub fn closed_cleanly(&self) -> usize {
+        self.closed_cleanly
+    }
+
+    /// 获取被强制关闭的连接数量
+    ///
+    /// # 返回值
+    /// 返回关闭时仍未处理完请求、被强制关闭的连接数量；
+    /// 使用 `ShutdownMode::Wait` 时始终为 0
+    pub fn force_closed(&self) -> usize {
+        self.force_closed
+    }
+}
+
+/// 连接任务的结束方式，用于统计关闭结果
+enum ConnExit {
+    /// 连接处理完当前请求后关闭
+    Closed,
+    /// 连接在关闭时仍未处理完请求，被强制关闭
+    ForceClosed,
+    /// 连接数已达上限，连接被拒绝
+    Rejected,
+}
+
+/// 通过 `Server::shutdown_graceful` 关闭服务器的信号
+#[derive(Default)]
+struct ShutdownSignal {
+    /// 关闭请求和已经结束的运行，在一个通道中一起更新，避免请求与运行结束交错
+    state: watch::Sender<ShutdownState>,
+}
+
+/// `ShutdownSignal` 的状态
+#[derive(Default)]
+struct ShutdownState {
+    /// 尚未处理的关闭请求，携带等待连接结束的最长时间；处理该请求的运行结束时清除
+    request: Option<Duration>,
+    /// 已经结束的运行次数
+    runs: u64,
+    /// 最近一次运行的关闭结果
+    report: ShutdownReport,
+}
+
+/// 连接任务持有的登记信息
+///
+/// 释放时先从连接管理器中移除连接并记录到指标中，再归还连接数许可，保证在线连接数不会超过上限。
+/// 由于依赖 `Drop`，即使连接任务 panic，登记和许可也会被正确清理。
+struct ConnGuard {
+    /// 连接所在的连接管理器
+    conn_manager: Arc<ConnManager>,
+    /// 服务器的指标计数器
+    metrics: Arc<Metrics>,
+    /// 连接ID
+    conn_id: u64,
+    /// 连接数许可，未限制连接数时为 `None`
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        // 字段在 drop 返回后才释放，因此许可在移除登记之后归还
+        self.conn_manager.remove(self.conn_id);
+        self.metrics.record_connection_closed();
+    }
+}
 
 /// 表示一个TCP服务器
 ///
 /// `Server` 是框架的主要入口点，负责监听TCP连接并处理客户端请求。
 /// 它使用 `Router` 来分发请求，使用 `Connection` 来管理客户端连接。
+///
+/// `with_*` 方法只修改配置，不检查取值，例如 `with_max_connections(0)` 不会立即报错；
+/// `run`、`bind` 等启动方法会先调用 `ServerConfig::validate`，配置无效时返回
+/// `ZerustError::InvalidConfig`。需要在创建时发现无效配置，请使用 `ServerBuilder::try_build`，
+/// 或者通过 `server.config().validate()` 提前检查。
 pub struct Server {
-    /// 服务器监听的地址，格式为 "IP:端口"
-    addr: String,
+    /// 服务器配置，包括监听地址和应用到每个连接的选项
+    config: ServerConfig,
     /// 路由器实例，用于分发请求到对应的处理函数
     ///
-    /// 使用 `Arc` 包装，可以在多个线程间安全地共享数据
-    router: Arc<dyn Router + Send + Sync>,
-}
+    /// 使用 `Arc` 包装，可以在多个线程间安全地共享数据
+    router: Arc<dyn Router + Send + Sync>,
+    /// 将处理函数返回的错误转换为错误响应
+    error_handler: ErrorHandler,
+    /// 在线连接的注册表
+    conn_manager: Arc<ConnManager>,
+    /// 请求与流量的指标计数器
+    metrics: Arc<Metrics>,
+    /// 连接建立后调用的钩子
+    on_conn_start: Option<ConnHook>,
+    /// 连接结束前调用的钩子
+    on_conn_stop: Option<ConnHook>,
+    /// 收到心跳回应时调用的钩子
+    on_heartbeat: Option<HeartbeatHook>,
+    /// 连接因错误结束时调用的钩子
+    on_conn_error: Option<ConnErrorHook>,
+    /// TLS 握手失败时调用的钩子
+    #[cfg(feature = "tls")]
+    on_tls_error: Option<TlsErrorHook>,
+    /// `shutdown_graceful` 使用的关闭信号
+    shutdown: ShutdownSignal,
+}
+
+impl Server {
+    /// 创建一个新的服务器实例
+    ///
+    /// 其余配置均使用默认值；需要设置多项配置时可以使用 `Server::builder`。
+    ///
+    /// # 参数
+    /// * `addr` - 服务器监听的地址，格式为 "IP:端口"
+    /// * `router` - 路由器实例，用于分发请求到对应的处理函数
+    ///
+    /// # 返回值
+    /// 返回一个新的 `Server` 实例，处理函数返回的错误默认转换为 `Response::internal_error`
+    pub fn new(addr: &str, router: Arc<dyn Router + Send + Sync>) -> Self {
+        Self::builder().addr(addr).router(router).build()
+    }
+
+    /// 创建一个服务器构建器
+    ///
+    /// # 返回值
+    /// 返回一个使用默认配置的 `ServerBuilder`
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::new()
+    }
+
+    /// 创建一个监听 Unix 域套接字的服务器
+    ///
+    /// 适合同一台机器上的服务之间通信，不经过 TCP 协议栈。连接的处理流程与 TCP 相同；
+    /// Unix 域套接字的客户端没有网络地址，`ConnContext::remote_addr` 返回未指定地址
+    /// `0.0.0.0:0`，`BoundServer::local_addr` 返回错误。
+    ///
+    /// 绑定时该路径不能已经存在，服务器关闭后也不会删除该文件。
+    ///
+    /// # 参数
+    /// * `path` - 套接字文件的路径
+    /// * `router` - 路由器实例，用于分发请求到对应的处理函数
+    ///
+    /// # 返回值
+    /// 返回一个新的 `Server` 实例
+    #[cfg(unix)]
+    pub fn new_unix(path: impl AsRef<Path>, router: Arc<dyn Router + Send + Sync>) -> Self {
+        Self::builder().unix_path(path).router(router).build()
+    }
+
+    /// 使用给定的配置创建服务器
+    ///
+    /// # 参数
+    /// * `config` - 服务器配置
+    /// * `router` - 路由器实例，用于分发请求到对应的处理函数
+    ///
+    /// # 返回值
+    /// 返回一个新的 `Server` 实例
+    pub fn from_config(config: ServerConfig, router: Arc<dyn Router + Send + Sync>) -> Self {
+        Self {
+            config,
+            router,
+            error_handler: Arc::new(|_, err| Response::internal_error(err)),
+            conn_manager: Arc::new(ConnManager::new()),
+            metrics: Arc::new(Metrics::default()),
+            on_conn_start: None,
+            on_conn_stop: None,
+            on_heartbeat: None,
+            on_conn_error: None,
+            #[cfg(feature = "tls")]
+            on_tls_error: None,
+            shutdown: ShutdownSignal::default(),
+        }
+    }
+
+    /// 获取服务器正在使用的配置
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// 获取服务器的连接管理器
+    ///
+    /// 返回的管理器与服务器共享，可以在服务器运行之前获取，
+    /// 并在处理函数或其他任务中使用它查找在线连接、推送消息。
+    ///
+    /// # 返回值
+    /// 返回连接管理器的共享引用
+    pub fn conn_manager(&self) -> Arc<ConnManager> {
+        self.conn_manager.clone()
+    }
+
+    /// 向所有在线连接推送一条消息
+    ///
+    /// 消息数据只保存一份，由所有连接共享；适合把同一条行情、通知推送给大量客户端。
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 消息数据
+    ///
+    /// # 返回值
+    /// 返回推送成功和失败的连接数量，参见 `ConnManager::broadcast_filter`
+    pub fn broadcast(&self, msg_id: u32, data: impl Into<Bytes>) -> BroadcastReport {
+        self.broadcast_filter(msg_id, data, |_| true)
+    }
+
+    /// 向满足条件的在线连接推送一条消息
+    ///
+    /// # 参数
+    /// * `msg_id` - 消息ID
+    /// * `data` - 消息数据
+    /// * `filter` - 返回 `true` 的连接会收到该消息
+    ///
+    /// # 返回值
+    /// 返回推送成功和失败的连接数量，参见 `ConnManager::broadcast_filter`
+    pub fn broadcast_filter<F>(
+        &self,
+        msg_id: u32,
+        data: impl Into<Bytes>,
+        filter: F,
+    ) -> BroadcastReport
+    where
+        F: Fn(&ConnectionHandle) -> bool,
+    {
+        self.conn_manager
+            .broadcast_filter(Response::from_bytes(msg_id, data.into()), filter)
+    }
+
+    /// 获取服务器的指标计数器
+    ///
+    /// 返回的计数器与服务器共享，可以在服务器运行之前获取，
+    /// 之后随时通过 `Metrics::snapshot` 读取请求数量、错误数量和收发的字节数。
+    ///
+    /// # 返回值
+    /// 返回指标计数器的共享引用
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// 生成服务器当前指标的快照
+    ///
+    /// 与 `server.metrics().snapshot()` 相同，快照是普通的数据结构，可以自行序列化或上报。
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        self.metrics.snapshot()
+    }
+
+    /// 把服务器当前的指标编码为 Prometheus 的文本格式
+    ///
+    /// 包括连接数量、每个消息ID的请求数量和处理耗时直方图，格式参见
+    /// `MetricsSnapshot::encode_prometheus`。需要开启 `prometheus` 功能。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()));
+    /// let text = server.render_prometheus();
+    /// assert!(text.contains("zerust_connections_active 0\n"));
+    /// ```
+    #[cfg(feature = "prometheus")]
+    pub fn render_prometheus(&self) -> String {
+        self.metrics.snapshot().encode_prometheus()
+    }
+
+    /// 设置所有连接允许接收的最大消息体长度
+    ///
+    /// 客户端发送的消息头声明的数据长度超过该值时，服务器会在分配缓冲区之前
+    /// 拒绝该消息，发送 `Server::with_oversized_response` 设置的响应后关闭连接，
+    /// 连接以 `ZerustError::MessageTooLarge` 错误结束。默认值为 `DEFAULT_MAX_PACKET_SIZE`（8 MiB）。
+    ///
+    /// # 参数
+    /// * `max_packet_size` - 最大消息体长度，单位为字节
+    ///
+    /// # 返回值
+    /// 返回设置了新限制的 `Server` 实例
+    pub fn with_max_packet_size(mut self, max_packet_size: u32) -> Self {
+        self.config.max_packet_size = max_packet_size;
+        self
+    }
+
+    /// 设置收到过大的消息时，关闭连接前发送给客户端的响应
+    ///
+    /// 默认为 `Response::payload_too_large`（消息ID为保留的 `control::PAYLOAD_TOO_LARGE_MSG_ID`），
+    /// 客户端可以据此区分消息过大和其他原因导致的断开。消息体没有被读取，响应不带序列号。
+    ///
+    /// # 参数
+    /// * `response` - 发送的响应，`None` 表示直接关闭连接
+    ///
+    /// # 返回值
+    /// 返回设置了该响应的 `Server` 实例
+    pub fn with_oversized_response(mut self, response: Option<Response>) -> Self {
+        self.config.oversized_response = response;
+        self
+    }
+
+    /// 设置所有连接的接收缓冲区每次扩容的字节数
+    ///
+    /// 参见 `Connection::with_read_buffer_size`。默认值为 `DEFAULT_READ_BUFFER_SIZE`（8 KiB）。
+    /// 取值为 0 时，`run` 和 `bind` 返回 `ZerustError::InvalidConfig`。
+    ///
+    /// # 参数
+    /// * `read_buffer_size` - 每次扩容的字节数，必须大于 0
+    ///
+    /// # 返回值
+    /// 返回设置了该选项的 `Server` 实例
+    pub fn with_read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.config.read_buffer_size = read_buffer_size;
+        self
+    }
+
+    /// 设置所有连接使用的消息编解码工具
+    ///
+    /// 默认使用小端序的消息头。需要与使用网络字节序的客户端通信时，
+    /// 传入 `DataPack::with_order(ByteOrderMode::Big)`；需要与 Go 语言的 Zinx 客户端通信时，
+    /// 传入 `DataPack::zinx()`。
+    ///
+    /// # 参数
+    /// * `datapack` - 消息编解码工具
+    ///
+    /// # 返回值
+    /// 返回使用新编解码工具的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::datapack::{ByteOrderMode, DataPack};
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_datapack(DataPack::with_order(ByteOrderMode::Big));
+    /// ```
+    pub fn with_datapack(mut self, datapack: DataPack) -> Self {
+        self.config.codec = Arc::new(datapack);
+        self
+    }
+
+    /// 设置是否在每个帧的数据之后附加 CRC32 校验和
+    ///
+    /// 开启后所有连接使用 `CheckedDataPack`，收到校验和不匹配的帧时关闭该连接，
+    /// 并以 `ZerustError::ChecksumMismatch` 调用连接错误钩子；客户端也需要使用 `CheckedDataPack`。
+    /// 关闭时恢复默认的 `DataPack`。该方法会替换之前设置的编解码工具。
+    ///
+    /// 需要丢弃损坏的帧、保持连接时，通过 `Server::with_codec` 传入设置了
+    /// `ChecksumPolicy::Skip` 的 `CheckedDataPack`。
+    ///
+    /// # 参数
+    /// * `enabled` - 是否附加校验和
+    ///
+    /// # 返回值
+    /// 返回使用新编解码工具的 `Server` 实例
+    pub fn with_checksum(mut self, enabled: bool) -> Self {
+        self.config.codec = if enabled {
+            Arc::new(CheckedDataPack::default())
+        } else {
+            Arc::new(DataPack::default())
+        };
+        self
+    }
+
+    /// 设置所有连接使用的帧编解码工具
+    ///
+    /// 用于替换默认的 `DataPack` 协议。同一个编解码工具会被所有连接共享，
+    /// 拒绝连接时发送的繁忙消息也使用它编码。
+    ///
+    /// # 参数
+    /// * `codec` - 帧编解码工具
+    ///
+    /// # 返回值
+    /// 返回使用新编解码工具的 `Server` 实例
+    pub fn with_codec(mut self, codec: Arc<dyn PacketCodec>) -> Self {
+        self.config.codec = codec;
+        self
+    }
+
+    /// 设置同时在线的最大连接数
+    ///
+    /// 在线连接数达到上限后，新连接按照 `ConnLimitPolicy` 处理，
+    /// 默认暂停接受新连接，直到有连接结束。默认不限制。
+    /// 取值为 0 时，`run` 和 `bind` 返回 `ZerustError::InvalidConfig`。
+    /// 当前在线连接数可以通过 `ConnManager::connection_count` 获取。
+    ///
+    /// # 参数
+    /// * `max_connections` - 最大连接数
+    ///
+    /// # 返回值
+    /// 返回设置了连接数上限的 `Server` 实例
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.config.max_connections = Some(max_connections);
+        self
+    }
+
+    /// 设置在线连接数达到上限时的处理策略
+    ///
+    /// # 参数
+    /// * `policy` - 处理策略
+    ///
+    /// # 返回值
+    /// 返回设置了该策略的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::server::ConnLimitPolicy;
+    /// use zerust::{DefaultRouter, Response, Server};
+    ///
+    /// // 最多 1000 个连接，超出时告知客户端服务器繁忙并关闭连接
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_max_connections(1000)
+    ///     .with_conn_limit_policy(ConnLimitPolicy::Reject {
+    ///         busy_response: Some(Response::new(503, b"server busy".to_vec())),
+    ///     });
+    /// ```
+    pub fn with_conn_limit_policy(mut self, policy: ConnLimitPolicy) -> Self {
+        self.config.conn_limit_policy = policy;
+        self
+    }
+
+    /// 设置收到关闭信号后处理在线连接的方式
+    ///
+    /// 默认为 `ShutdownMode::Wait`，即一直等待所有连接处理完当前请求。
+    ///
+    /// # 参数
+    /// * `mode` - 关闭方式
+    ///
+    /// # 返回值
+    /// 返回设置了关闭方式的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use zerust::server::ShutdownMode;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// // 关闭时最多等待 5 秒，之后强制关闭仍在处理请求的连接
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_shutdown_mode(ShutdownMode::Graceful {
+    ///         timeout: Duration::from_secs(5),
+    ///     });
+    /// ```
+    pub fn with_shutdown_mode(mut self, mode: ShutdownMode) -> Self {
+        self.config.shutdown_mode = mode;
+        self
+    }
+
+    /// 设置接受连接失败时的处理策略
+    ///
+    /// 默认忽略暂时性错误并继续接受连接，参见 `AcceptErrorPolicy`。
+    ///
+    /// # 参数
+    /// * `policy` - 处理策略
+    ///
+    /// # 返回值
+    /// 返回设置了该策略的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use zerust::server::AcceptErrorPolicy;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// // 文件描述符耗尽时等待 1 秒再继续接受连接
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_accept_error_policy(AcceptErrorPolicy::Retry {
+    ///         backoff: Duration::from_secs(1),
+    ///     });
+    /// ```
+    pub fn with_accept_error_policy(mut self, policy: AcceptErrorPolicy) -> Self {
+        self.config.accept_error_policy = policy;
+        self
+    }
+
+    /// 设置是否为所有连接启用 `TCP_NODELAY`
+    ///
+    /// 启用后禁用 Nagle 算法，每个响应都会立即发送。对于请求-响应式的小消息协议，
+    /// 这通常能显著降低延迟并提高吞吐量，因此默认启用；传入 `false` 时沿用操作系统的设置。
+    ///
+    /// # 参数
+    /// * `nodelay` - 是否启用 `TCP_NODELAY`
+    ///
+    /// # 返回值
+    /// 返回设置了该选项的 `Server` 实例
+    #[doc(alias = "with_tcp_nodelay")]
+    pub fn with_nodelay(mut self, nodelay: bool) -> Self {
+        self.config.nodelay = nodelay;
+        self
+    }
+
+    /// 为所有连接开启 TCP keepalive
+    ///
+    /// 连接空闲 `time` 后，操作系统开始发送 keepalive 探测包，能够发现对端主机已经断电或
+    /// 网络中断、但没有发送 FIN 的连接。与应用层的心跳（参见 `Server::with_heartbeat`）不同，
+    /// 探测包由操作系统处理，客户端不需要做任何事情。默认不开启，沿用操作系统的设置。
+    /// 只对 TCP 连接有效，设置失败不影响连接的正常使用。
+    ///
+    /// # 参数
+    /// * `time` - 连接空闲多久后开始发送探测包
+    ///
+    /// # 返回值
+    /// 返回设置了该选项的 `Server` 实例
+    #[doc(alias = "with_tcp_keepalive")]
+    pub fn with_keepalive(mut self, time: Duration) -> Self {
+        self.config.keepalive = Some(time);
+        self
+    }
+
+    /// 设置所有连接的 `SO_LINGER` 时间
+    ///
+    /// 关闭连接时最多等待 `linger` 把未发送的数据发送出去；为 0 时关闭连接会丢弃未发送的
+    /// 数据并发送 RST，不会留下 `TIME_WAIT` 状态的连接。默认不设置，沿用操作系统的设置。
+    /// 只对 TCP 连接有效，设置失败不影响连接的正常使用。
+    ///
+    /// # 参数
+    /// * `linger` - 关闭连接时等待数据发送完毕的最长时间
+    ///
+    /// # 返回值
+    /// 返回设置了该选项的 `Server` 实例
+    pub fn with_linger(mut self, linger: Duration) -> Self {
+        self.config.linger = Some(linger);
+        self
+    }
+
+    /// 设置每个连接的发送队列最多容纳的消息数量
+    ///
+    /// 请求的响应和通过 `ConnectionHandle` 推送的消息都经过该队列。客户端不读取数据时，
+    /// 队列被填满后服务器暂停读取该连接的请求，推送的消息按 `Server::with_write_queue_policy`
+    /// 处理，服务器不会为慢速客户端无限地缓存消息。默认为 `conn_manager::DEFAULT_WRITE_QUEUE_CAPACITY`。
+    ///
+    /// # 参数
+    /// * `capacity` - 每个连接的发送队列最多容纳的消息数量，必须大于 0
+    ///
+    /// # 返回值
+    /// 返回设置了队列容量的 `Server` 实例
+    pub fn with_write_queue_capacity(mut self, capacity: usize) -> Self {
+        self.config.write_queue_capacity = capacity;
+        self
+    }
+
+    /// 设置发送队列已满时推送消息的处理策略，默认丢弃消息
+    ///
+    /// 开启工作池时，工作任务发送响应同样使用该策略，不会因为某个客户端不读取数据而停下来。
+    ///
+    /// # 参数
+    /// * `policy` - 发送队列已满时的处理策略
+    ///
+    /// # 返回值
+    /// 返回设置了该策略的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::conn_manager::WriteQueuePolicy;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// // 排队超过 256 条消息的客户端被断开
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_write_queue_capacity(256)
+    ///     .with_write_queue_policy(WriteQueuePolicy::Disconnect);
+    /// ```
+    pub fn with_write_queue_policy(mut self, policy: WriteQueuePolicy) -> Self {
+        self.config.write_queue_policy = policy;
+        self
+    }
+
+    /// 设置所有连接读取请求的超时时间
+    ///
+    /// 客户端在该时间内没有发送一个完整的请求时，服务器会关闭该连接。
+    /// 这可以回收长时间空闲的连接，并防止慢速客户端（slowloris）长期占用资源。
+    /// 默认不限制。
+    ///
+    /// # 参数
+    /// * `timeout` - 读取超时时间
+    ///
+    /// # 返回值
+    /// 返回设置了读取超时的 `Server` 实例
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.read_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置所有连接写入消息的超时时间
+    ///
+    /// 客户端不读取数据时，内核发送缓冲区写满后写入会一直等待。设置后，
+    /// 每条消息必须在该时间内完整写入，否则服务器关闭该连接，并以
+    /// `ZerustError::Timeout` 调用连接错误钩子。超时时消息可能只写入了一部分，
+    /// 之后的数据无法再与帧边界对齐，因此连接不会重试而是直接关闭。默认不限制。
+    ///
+    /// # 参数
+    /// * `timeout` - 写入超时时间
+    ///
+    /// # 返回值
+    /// 返回设置了写入超时的 `Server` 实例
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.config.write_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置连接没有收到请求多久后被关闭
+    ///
+    /// 服务器在后台定期检查每个连接最后一次收到请求或处理完请求的时间（参见
+    /// `ConnectionHandle::last_active`），关闭空闲超过该时间的连接。
+    /// 有请求正在处理的连接不算空闲，处理时间较长的请求不会被中断。
+    /// 因空闲被关闭属于正常关闭，不会调用连接错误钩子，停止钩子照常调用。
+    /// 检查间隔为该时间的四分之一，因此连接最多在空闲 1.25 倍的时间后被关闭。
+    ///
+    /// 与 `with_read_timeout` 不同，它不依赖连接任务正在等待读取；与心跳不同，
+    /// 客户端的心跳回应不算作请求，只有交给处理函数的请求才会刷新空闲时间。
+    /// 默认不限制。
+    ///
+    /// # 参数
+    /// * `timeout` - 允许的最长空闲时间
+    ///
+    /// # 返回值
+    /// 返回设置了空闲超时的 `Server` 实例
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// 开启心跳检测
+    ///
+    /// 连接在 `interval` 内没有收到任何数据时，服务器发送一条消息ID为
+    /// `DEFAULT_HEARTBEAT_MSG_ID` 的心跳；连续 `max_missed` 次心跳都没有得到回应时关闭连接。
+    /// 需要使用其他消息ID时，请使用 `Server::with_heartbeat_config`。默认不开启。
+    ///
+    /// # 参数
+    /// * `interval` - 连接空闲多久后发送心跳
+    /// * `max_missed` - 允许连续未得到回应的心跳次数
+    ///
+    /// # 返回值
+    /// 返回开启了心跳检测的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// // 空闲 10 秒后发送心跳，连续 3 次无回应（约 40 秒无数据）时关闭连接
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_heartbeat(Duration::from_secs(10), 3);
+    /// ```
+    pub fn with_heartbeat(self, interval: Duration, max_missed: u32) -> Self {
+        self.with_heartbeat_config(HeartbeatConfig::new(interval, max_missed))
+    }
+
+    /// 使用给定的配置开启心跳检测
+    ///
+    /// # 参数
+    /// * `config` - 心跳配置
+    ///
+    /// # 返回值
+    /// 返回开启了心跳检测的 `Server` 实例
+    pub fn with_heartbeat_config(mut self, config: HeartbeatConfig) -> Self {
+        self.config.heartbeat = Some(config);
+        self
+    }
+
+    /// 开启工作池，把请求交给固定数量的工作任务处理
+    ///
+    /// 默认情况下每个连接在自己的任务中处理请求。开启工作池后，请求按连接ID分配给
+    /// `size` 个工作任务中的一个，同一个连接的请求由同一个工作任务按顺序处理，
+    /// 全局同时执行的处理函数不超过 `size` 个。每个工作任务最多排队
+    /// `max_task_queue_len` 个请求，队列已满时暂停读取该连接，参见 `QueueFullPolicy`。
+    ///
+    /// # 参数
+    /// * `size` - 工作任务的数量
+    /// * `max_task_queue_len` - 每个工作任务最多排队的请求数量
+    ///
+    /// # 返回值
+    /// 返回开启了工作池的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_worker_pool(8, 1024);
+    /// ```
+    pub fn with_worker_pool(self, size: usize, max_task_queue_len: usize) -> Self {
+        self.with_worker_pool_config(WorkerPoolConfig::new(size, max_task_queue_len))
+    }
+
+    /// 使用给定的配置开启工作池
+    ///
+    /// # 参数
+    /// * `config` - 工作池配置
+    ///
+    /// # 返回值
+    /// 返回开启了工作池的 `Server` 实例
+    pub fn with_worker_pool_config(mut self, config: WorkerPoolConfig) -> Self {
+        self.config.worker_pool = Some(config);
+        self
+    }
+
+    /// 开启每个连接的限流
+    ///
+    /// 每个连接每秒最多处理 `max_per_sec` 个请求，空闲的连接最多可以立即处理
+    /// `max_per_sec` 个请求；超出的请求等待令牌补充后再处理，期间暂停读取该连接。
+    /// 需要回复限流消息或调整突发数量时，请使用 `Server::with_rate_limit_config`。默认不开启。
+    ///
+    /// # 参数
+    /// * `max_per_sec` - 每个连接每秒最多处理的请求数量
+    ///
+    /// # 返回值
+    /// 返回开启了限流的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_rate_limit(100);
+    /// ```
+    pub fn with_rate_limit(self, max_per_sec: u32) -> Self {
+        self.with_rate_limit_config(RateLimitConfig::new(max_per_sec))
+    }
+
+    /// 使用给定的配置开启每个连接的限流
+    ///
+    /// # 参数
+    /// * `config` - 限流配置
+    ///
+    /// # 返回值
+    /// 返回开启了限流的 `Server` 实例
+    pub fn with_rate_limit_config(mut self, config: RateLimitConfig) -> Self {
+        self.config.rate_limit = Some(config);
+        self
+    }
+
+    /// 压缩数据长度不小于 `threshold` 字节的消息
+    ///
+    /// 使用 zstd 压缩，每条消息的数据前增加 1 字节的压缩标记，格式参见 `compression` 模块。
+    /// 处理函数看到的始终是解压后的数据，客户端需要使用 `CompressedCodec`。
+    ///
+    /// 该方法包装当前的编解码工具，需要在 `with_codec` 和 `with_datapack` 之后调用。
+    /// 需要开启 `compression` 功能。
+    ///
+    /// # 参数
+    /// * `threshold` - 压缩数据的最小长度（字节）
+    ///
+    /// # 返回值
+    /// 返回开启了压缩的 `Server` 实例
+    #[cfg(feature = "compression")]
+    pub fn with_compression(self, threshold: usize) -> Self {
+        self.with_compression_config(CompressionConfig::new(threshold))
+    }
+
+    /// 使用给定的配置压缩消息，可以选择压缩算法
+    ///
+    /// 与 `with_compression` 相同，需要在 `with_codec` 和 `with_datapack` 之后调用。
+    ///
+    /// # 参数
+    /// * `config` - 压缩配置
+    ///
+    /// # 返回值
+    /// 返回开启了压缩的 `Server` 实例
+    #[cfg(feature = "compression")]
+    pub fn with_compression_config(mut self, config: CompressionConfig) -> Self {
+        self.config.codec = Arc::new(CompressedCodec::wrap(self.config.codec, config));
+        self
+    }
+
+    /// 开启协议握手
+    ///
+    /// 接受连接后（TLS 连接在 TLS 握手之后），服务器先读取客户端发送的前导：
+    /// 协议标识 `connection::PROTOCOL_MAGIC` + 客户端支持的最高协议版本，
+    /// 再回复双方都支持的版本（两者中较小的一个），之后才开始读取请求。
+    /// 处理函数可以通过 `ConnContext::protocol_version` 获取协商出的版本。
+    ///
+    /// 协议标识不匹配或者未能在读取超时时间内收到前导的连接直接关闭，
+    /// 不会读取任何请求，也不调用开始和停止钩子；错误会传给 `with_on_conn_error` 注册的钩子。
+    /// 客户端需要先调用 `Connection::handshake` 再发送请求。
+    ///
+    /// # 参数
+    /// * `version` - 服务器支持的最高协议版本
+    ///
+    /// # 返回值
+    /// 返回开启了协议握手的 `Server` 实例
+    pub fn with_protocol_version(mut self, version: u8) -> Self {
+        self.config.protocol_version = Some(version);
+        self
+    }
+
+    /// 使用该消息ID返回 Prometheus 文本格式的指标
+    ///
+    /// 收到该消息ID的请求时，服务器不经过路由器，直接回复 `render_prometheus` 的结果，
+    /// 通过 Zerust 协议连接的监控程序可以用普通的请求拉取指标。该请求不计入指标，
+    /// 但与普通请求一样消耗限流的令牌。消息ID不能与心跳的消息ID相同。需要开启 `prometheus` 功能。
+    ///
+    /// # 参数
+    /// * `msg_id` - 返回指标的消息ID
+    ///
+    /// # 返回值
+    /// 返回开启了指标消息的 `Server` 实例
+    #[cfg(feature = "prometheus")]
+    pub fn with_metrics_route(mut self, msg_id: u32) -> Self {
+        self.config.metrics_route = Some(msg_id);
+        self
+    }
+
+    /// 使用 TLS 加密所有连接
+    ///
+    /// 接受连接后先完成 TLS 握手，再按正常流程处理请求；握手失败的连接直接关闭，
+    /// 不会调用连接的生命周期钩子。设置了读取超时时，握手同样受该超时限制。
+    /// 连接数达到上限被拒绝的连接不会收到繁忙消息。
+    ///
+    /// 需要开启 `tls` 功能。
+    ///
+    /// # 参数
+    /// * `config` - rustls 的服务端配置，包含证书链和私钥，可以通过 `tls::server_config` 创建
+    ///
+    /// # 返回值
+    /// 返回使用 TLS 的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust,no_run
+    /// use std::sync::Arc;
+    /// use zerust::rustls::{self, pki_types::{CertificateDer, PrivateKeyDer}};
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// # fn load() -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) { unimplemented!() }
+    /// let (certs, key) = load();
+    /// let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+    ///     rustls::crypto::ring::default_provider(),
+    /// ))
+    /// .with_safe_default_protocol_versions()
+    /// .unwrap()
+    /// .with_no_client_auth()
+    /// .with_single_cert(certs, key)
+    /// .unwrap();
+    /// let server = Server::new("127.0.0.1:8443", Arc::new(DefaultRouter::new()))
+    ///     .with_tls(Arc::new(config));
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, config: Arc<rustls::ServerConfig>) -> Self {
+        self.config.tls = Some(config);
+        self
+    }
+
+    /// 设置 TLS 握手失败时调用的钩子
+    ///
+    /// 握手失败或超时的连接会被直接关闭，服务器继续接受其他连接；
+    /// 可以通过该钩子记录失败的原因，例如客户端不信任服务器证书或使用了明文连接。
+    ///
+    /// 需要开启 `tls` 功能。
+    ///
+    /// # 参数
+    /// * `hook` - 钩子函数，接收客户端地址和握手失败的原因
+    ///
+    /// # 返回值
+    /// 返回设置了该钩子的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_on_tls_error(|addr, err| eprintln!("TLS handshake with {addr} failed: {err}"));
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn with_on_tls_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(SocketAddr, &ZerustError) + Send + Sync + 'static,
+    {
+        self.on_tls_error = Some(Arc::new(hook));
+        self
+    }
 
-impl Server {
-    /// 创建一个新的服务器实例
+    /// 设置收到心跳回应时调用的钩子
+    ///
+    /// 客户端发送的 pong 控制帧和心跳消息ID的消息不会交给路由器，而是交给该钩子处理，
+    /// 例如根据客户端携带的时间戳统计往返延迟。钩子不能发送响应；未设置钩子时，
+    /// 心跳回应只用于确认客户端仍然在线。
     ///
     /// # 参数
-    /// * `addr` - 服务器监听的地址，格式为 "IP:端口"
-    /// * `router` - 路由器实例，用于分发请求到对应的处理函数
+    /// * `hook` - 异步钩子函数，接收客户端发送的心跳回应
     ///
     /// # 返回值
-    /// 返回一个新的 `Server` 实例
-    pub fn new(addr: &str, router: Arc<dyn Router + Send + Sync>) -> Self {
-        Self {
-            addr: addr.to_string(),
-            router,
-        }
+    /// 返回设置了该钩子的 `Server` 实例
+    pub fn with_on_heartbeat<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_heartbeat = Some(Arc::new(move |req| Box::pin(hook(req))));
+        self
+    }
+
+    /// 设置连接建立后调用的钩子
+    ///
+    /// 钩子在连接任务中、开始读取请求之前执行，可以用来记录客户端地址、
+    /// 分配会话，或者通过 `ConnectionHandle::send` 向客户端推送欢迎消息。
+    /// 钩子执行完毕后连接才开始处理请求。
+    ///
+    /// # 参数
+    /// * `hook` - 异步钩子函数，接收连接的句柄
+    ///
+    /// # 返回值
+    /// 返回设置了该钩子的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_on_conn_start(|conn| async move {
+    ///         println!("connection {} from {}", conn.conn_id(), conn.remote_addr());
+    ///     })
+    ///     .with_on_conn_stop(|conn| async move {
+    ///         println!("connection {} closed", conn.conn_id());
+    ///     });
+    /// ```
+    #[doc(alias = "on_connect")]
+    pub fn with_on_conn_start<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ConnectionHandle) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_conn_start = Some(Arc::new(move |conn| Box::pin(hook(conn))));
+        self
+    }
+
+    /// 设置连接结束前调用的钩子
+    ///
+    /// 无论连接因何结束（客户端关闭、读写错误、超时或服务器关闭），
+    /// 钩子都会在连接任务退出前执行且只执行一次。钩子执行时连接已经从
+    /// `ConnManager` 中移除，不能再向客户端发送消息。
+    /// 服务器关闭时会等待所有连接的钩子执行完毕。
+    ///
+    /// # 参数
+    /// * `hook` - 异步钩子函数，接收连接的句柄
+    ///
+    /// # 返回值
+    /// 返回设置了该钩子的 `Server` 实例
+    #[doc(alias = "on_disconnect")]
+    pub fn with_on_conn_stop<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ConnectionHandle) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_conn_stop = Some(Arc::new(move |conn| Box::pin(hook(conn))));
+        self
+    }
+
+    /// 设置连接因错误结束时调用的钩子
+    ///
+    /// 只有真正的错误才会调用该钩子，例如读写失败、消息格式错误、消息过大、
+    /// 读取超时或心跳超时。客户端在两个请求之间正常断开、处理函数要求关闭连接、
+    /// 连接因 `with_idle_timeout` 空闲过久被关闭以及服务器关闭都不属于错误。钩子在连接任务中、停止钩子之前执行。
+    ///
+    /// # 参数
+    /// * `hook` - 钩子函数，接收连接的句柄和导致连接结束的错误
+    ///
+    /// # 返回值
+    /// 返回设置了该钩子的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_on_conn_error(|conn, err| eprintln!("connection {} failed: {err}", conn.conn_id()));
+    /// ```
+    pub fn with_on_conn_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&ConnectionHandle, &ZerustError) + Send + Sync + 'static,
+    {
+        self.on_conn_error = Some(Arc::new(hook));
+        self
+    }
+
+    /// 设置处理函数返回错误时发送给客户端的错误响应
+    ///
+    /// # 参数
+    /// * `handler` - 错误响应生成函数，接收请求的消息ID和错误，返回错误响应
+    ///
+    /// # 返回值
+    /// 返回使用新错误响应生成函数的 `Server` 实例
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::{DefaultRouter, Response, Server};
+    ///
+    /// // 错误响应沿用请求的消息ID，数据为错误描述
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+    ///     .with_error_handler(|msg_id, err| Response::new(msg_id, err.to_string().into_bytes()));
+    /// ```
+    pub fn with_error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(u32, &ZerustError) -> Response + Send + Sync + 'static,
+    {
+        self.error_handler = Arc::new(handler);
+        self
     }
 
     /// 启动服务器并监听指定地址的TCP连接
     ///
     /// 该函数会绑定到配置的地址并开始监听TCP连接，对于每个传入的连接，
     /// 都会创建一个异步任务来处理请求。如果在监听过程中发生IO错误，
-    /// 函数会立即返回错误。
+    /// 函数会停止接受新连接并返回错误。
+    ///
+    /// 收到关闭信号后，服务器会停止接受新连接，并通知所有活跃连接：
+    /// 正在处理的请求会完成处理并发送响应，之后连接关闭。
+    /// 等待时间由 `ShutdownMode` 决定，超时后剩余的连接会被强制关闭。
+    /// 所有连接任务结束后该函数才会返回。也可以通过 `shutdown_graceful` 关闭服务器。
     ///
     /// # 参数
     ///
@@ -56,67 +1377,1025 @@ impl Server {
     ///
     /// # 返回值
     ///
-    /// * `Ok(())` - 服务器正常启动并运行
-    /// * `Err(ZerustError)` - 服务器启动或运行过程中发生错误
-    pub async fn run(&self, mut shutdown: oneshot::Receiver<()>) -> Result<(), ZerustError> {
-        // 绑定TCP监听器到指定地址
-        let listener = TcpListener::bind(&self.addr).await?;
+    /// * `Ok(ShutdownReport)` - 服务器收到关闭信号并已关闭，包含被强制关闭的连接数量
+    /// * `Err(ZerustError)` - 配置无效（参见 `ServerConfig::validate`），或者服务器启动、运行过程中发生错误
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use tokio::sync::oneshot;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), zerust::ZerustError> {
+    /// let router = Arc::new(DefaultRouter::new());
+    /// let server = Server::new("127.0.0.1:0", router);
+    ///
+    /// // 在后台任务中运行服务器
+    /// let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    /// let handle = tokio::spawn(async move { server.run(shutdown_rx).await });
+    ///
+    /// // 发送关闭信号，没有进行中的请求，不会有连接被强制关闭
+    /// let _ = shutdown_tx.send(());
+    /// let report = handle.await.unwrap()?;
+    /// assert_eq!(report.force_closed(), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run(
+        &self,
+        shutdown: oneshot::Receiver<()>,
+    ) -> Result<ShutdownReport, ZerustError> {
+        self.config.validate()?;
+        // 绑定监听器到指定地址
+        let listener = Listener::bind(&self.config).await?;
+        self.serve(listener, shutdown).await
+    }
+
+    /// 平滑关闭服务器
+    ///
+    /// 与向 `run` 的关闭通道发送信号相同：服务器立即停止接受新连接，并通知所有在线连接
+    /// 处理完当前的请求、发送完响应后关闭；但等待时间使用 `timeout`，而不是配置的
+    /// `ShutdownMode`。超过 `timeout` 仍未结束的连接会被强制关闭。
+    /// 适合滚动部署：在新实例就绪后调用该方法，已经收到的请求都能得到响应。
+    ///
+    /// `run` 需要 `&self`，可以把服务器放在 `Arc` 中，一个任务运行服务器，另一个任务调用该方法。
+    /// 服务器没有在运行时（例如运行服务器的任务还没有开始执行），关闭请求会保留到下一次运行，
+    /// 该次运行开始后立即按该方式关闭。因此对一个不会再运行的服务器调用该方法会一直等待。
+    ///
+    /// # 参数
+    /// * `timeout` - 等待连接结束的最长时间
+    ///
+    /// # 返回值
+    /// 处理该请求的运行完全停止后返回关闭的结果统计，与 `run` 的返回值相同
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use tokio::sync::oneshot;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), zerust::ZerustError> {
+    /// let server = Arc::new(Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new())));
+    /// let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+    /// let running = server.clone();
+    /// let handle = tokio::spawn(async move { running.run(shutdown_rx).await });
+    ///
+    /// let report = server.shutdown_graceful(Duration::from_secs(30)).await;
+    /// assert_eq!(report.force_closed(), 0);
+    /// handle.await.unwrap()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown_graceful(&self, timeout: Duration) -> ShutdownReport {
+        let mut state = self.shutdown.state.subscribe();
+        // 正在运行的服务器结束后 runs 加一；没有在运行时由下一次运行处理该请求
+        let mut run = 0;
+        self.shutdown.state.send_modify(|state| {
+            state.request = Some(timeout);
+            run = state.runs + 1;
+        });
+        // 发送端属于服务器本身，等待期间不会被关闭
+        let state = state.wait_for(|state| state.runs >= run).await;
+        state.map(|state| state.report).unwrap_or_default()
+    }
+
+    /// 绑定监听地址，但暂不开始接受连接
+    ///
+    /// 绑定成功后返回 `BoundServer`，可以通过 `BoundServer::local_addr`
+    /// 获取实际绑定的地址（例如监听 `"127.0.0.1:0"` 时由系统分配的端口），
+    /// 然后调用 `BoundServer::run` 开始接受连接。
+    ///
+    /// # 返回值
+    ///
+    /// * `Ok(BoundServer)` - 已绑定监听地址的服务器
+    /// * `Err(ZerustError)` - 配置无效或绑定地址失败时返回的错误
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use tokio::sync::oneshot;
+    /// use zerust::{DefaultRouter, Server};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), zerust::ZerustError> {
+    /// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()));
+    ///
+    /// // 绑定后即可得到系统分配的端口，客户端可以立即连接
+    /// let bound = server.bind().await?;
+    /// let addr = bound.local_addr()?;
+    /// assert_ne!(addr.port(), 0);
+    ///
+    /// let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    /// let handle = tokio::spawn(async move { bound.run(shutdown_rx).await });
+    /// let _ = shutdown_tx.send(());
+    /// handle.await.unwrap()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn bind(self) -> Result<BoundServer, ZerustError> {
+        self.config.validate()?;
+        let listener = Listener::bind(&self.config).await?;
+        Ok(BoundServer {
+            server: self,
+            listener,
+        })
+    }
+
+    /// 在已绑定的监听器上接受并处理连接，直到收到关闭信号
+    ///
+    /// # 参数
+    /// * `listener` - 已绑定的监听器
+    /// * `shutdown` - 接收关闭信号的通道
+    async fn serve(
+        &self,
+        listener: Listener,
+        shutdown: oneshot::Receiver<()>,
+    ) -> Result<ShutdownReport, ZerustError> {
+        match listener {
+            Listener::Tcp(listener) => self.serve_on(listener, shutdown).await,
+            #[cfg(unix)]
+            Listener::Unix(listener) => self.serve_on(listener, shutdown).await,
+        }
+    }
+
+    /// 在自定义的监听器上接受并处理连接，直到收到关闭信号
+    ///
+    /// 配置中的监听地址被忽略，其余行为与 `Server::run` 相同。
+    /// 适合使用框架没有内置的监听器，或者在测试中模拟监听器的行为。
+    ///
+    /// # 参数
+    /// * `listener` - 已绑定的监听器
+    /// * `shutdown` - 接收关闭信号的通道
+    ///
+    /// # 返回值
+    /// * `Ok(ShutdownReport)` - 服务器收到关闭信号并已关闭，包含被强制关闭的连接数量
+    /// * `Err(ZerustError)` - 配置无效，或者服务器运行过程中发生错误
+    pub async fn run_on<L: Listen>(
+        &self,
+        listener: L,
+        shutdown: oneshot::Receiver<()>,
+    ) -> Result<ShutdownReport, ZerustError> {
+        self.config.validate()?;
+        self.serve_on(listener, shutdown).await
+    }
+
+    /// 在给定类型的监听器上接受并处理连接，参见 `serve`
+    async fn serve_on<L: Listen>(
+        &self,
+        listener: L,
+        mut shutdown: oneshot::Receiver<()>,
+    ) -> Result<ShutdownReport, ZerustError> {
+        // 运行开始前收到的关闭请求同样有效，运行结束时才清除
+        let mut graceful = self.shutdown.state.subscribe();
+        // 用于通知所有连接任务服务器正在关闭
+        let (closing_tx, closing_rx) = watch::channel(false);
+        // 用于通知所有连接任务立即结束，不再等待正在处理的请求
+        let (force_tx, force_rx) = watch::channel(false);
+        // 跟踪所有连接任务，以便关闭时等待它们结束
+        let mut connections = JoinSet::new();
+        // 限制同时在线的连接数，每个连接任务持有一个许可
+        let semaphore = self
+            .config
+            .max_connections
+            .map(|n| Arc::new(Semaphore::new(n)));
+        // 工作池的工作任务，服务器结束时一并结束
+        let mut workers = JoinSet::new();
+        let worker_pool = self.config.worker_pool.as_ref().map(|config| {
+            WorkerPool::start(
+                config,
+                self.router.clone(),
+                self.error_handler.clone(),
+                self.metrics.clone(),
+                &mut workers,
+            )
+        });
+        // 所有连接共享的请求处理配置
+        let service = Arc::new(ConnService {
+            router: self.router.clone(),
+            error_handler: self.error_handler.clone(),
+            heartbeat: self.config.heartbeat.clone(),
+            rate_limit: self.config.rate_limit.clone(),
+            #[cfg(feature = "prometheus")]
+            metrics_route: self.config.metrics_route,
+            protocol_version: self.config.protocol_version,
+            on_heartbeat: self.on_heartbeat.clone(),
+            on_conn_error: self.on_conn_error.clone(),
+            metrics: self.metrics.clone(),
+            worker_pool,
+            read_timeout: self.config.read_timeout,
+            write_timeout: self.config.write_timeout,
+            max_packet_size: self.config.max_packet_size,
+            oversized_response: self.config.oversized_response.clone(),
+            read_buffer_size: self.config.read_buffer_size,
+            codec: self.config.codec.clone(),
+            #[cfg(feature = "tls")]
+            tls: self.config.tls.clone().map(TlsAcceptor::from),
+            #[cfg(feature = "tls")]
+            on_tls_error: self.on_tls_error.clone(),
+        });
 
+        // 接受连接失败后，在该时间之前暂停接受连接
+        let mut accept_resume: Option<Instant> = None;
+        // 下一次检查空闲连接的时间，未设置空闲超时时为 `None`
+        let idle_timeout = self.config.idle_timeout;
+        let mut next_sweep = idle_timeout.map(|timeout| Instant::now() + timeout / 4);
         // 持续接受并处理客户端连接
-        loop {
-            // 使用tokio::select! 同时监听：
+        let result = loop {
+            // 使用select! 同时监听：
             // 1. 新的客户端连接
             // 2. 关闭信息
-            tokio::select! {
+            // 3. 已结束的连接任务（及时回收，避免 JoinSet 无限增长）
+            select! {
                 // 分支1 ：接收新连接
-                accept_result = listener.accept() =>{
+                accept_result = async {
+                    if let Some(resume) = accept_resume {
+                        sleep_until(resume).await;
+                    }
+                    self.accept(&listener, semaphore.as_ref()).await
+                } =>{
+                    accept_resume = None;
                     match accept_result {
-                        Ok((stream, _addr)) => {
-                            // 为每个连接创建独立的异步任务进行处理
-                            let router = self.router.clone();
-                            tokio::spawn(async move {
-                                let _ = Self::handle_connection(stream, router).await;
+                        Ok((stream, _addr, None)) if semaphore.is_some() => {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(remote_addr = %_addr, "connection limit reached, rejecting connection");
+                            // 连接数已达上限，按拒绝策略发送繁忙消息后关闭连接
+                            let busy_response = match &self.config.conn_limit_policy {
+                                ConnLimitPolicy::Reject { busy_response } => busy_response.clone(),
+                                ConnLimitPolicy::Wait => None,
+                            };
+                            // 未经握手的 TLS 连接无法接收明文消息
+                            #[cfg(feature = "tls")]
+                            let busy_response = busy_response.filter(|_| service.tls.is_none());
+                            let codec = self.config.codec.clone();
+                            connections.spawn(async move {
+                                Self::reject(stream, codec, busy_response).await;
+                                ConnExit::Rejected
                             });
                         }
-                        Err(e) => return Err(ZerustError::IoError(e)),
+                        Ok((stream, addr, permit)) => {
+                            // 分配连接ID，为每个连接创建独立的异步任务进行处理
+                            let conn_id = self.conn_manager.next_conn_id();
+                            // 设置失败不影响连接的正常使用，沿用系统默认行为即可
+                            if self.config.nodelay {
+                                let _ = L::set_nodelay(&stream);
+                            }
+                            if let Some(time) = self.config.keepalive {
+                                let _ = L::set_keepalive(&stream, time);
+                            }
+                            if let Some(linger) = self.config.linger {
+                                let _ = L::set_linger(&stream, linger);
+                            }
+                            let service = service.clone();
+                            let closing = closing_rx.clone();
+                            let mut force = force_rx.clone();
+                            let on_conn_start = self.on_conn_start.clone();
+                            let on_conn_stop = self.on_conn_stop.clone();
+                            // 登记到连接管理器，连接结束时移除
+                            let (push_tx, push_rx) = mpsc::channel(self.config.write_queue_capacity);
+                            let context = ConnContext::new(conn_id, addr);
+                            let handle = ConnectionHandle::new(context, push_tx, self.config.write_queue_policy);
+                            self.conn_manager.insert(handle.clone());
+                            self.metrics.record_connection_opened();
+                            // 任务结束（包括 panic）时移除登记并归还许可
+                            let guard = ConnGuard {
+                                conn_manager: self.conn_manager.clone(),
+                                metrics: self.metrics.clone(),
+                                conn_id,
+                                _permit: permit,
+                            };
+                            let conn_task = async move {
+                                // TLS 握手或协议握手失败的连接没有建立，不调用生命周期钩子
+                                let mut established = false;
+                                // 强制关闭时放弃连接的处理流程，但仍执行下面的清理和停止钩子
+                                let serve_conn = async {
+                                    #[cfg(feature = "tls")]
+                                    if let Some(acceptor) = &service.tls {
+                                        let handshake = async { Ok(acceptor.accept(stream).await?) };
+                                        let stream = match with_timeout(service.read_timeout, handshake).await {
+                                            Ok(stream) => stream,
+                                            Err(e) => {
+                                                #[cfg(feature = "tracing")]
+                                                tracing::warn!(error = %e, "TLS handshake failed");
+                                                if let Some(hook) = &service.on_tls_error {
+                                                    hook(handle.remote_addr(), &e);
+                                                }
+                                                return;
+                                            }
+                                        };
+                                        let Some(conn) = Self::handshake(service.connection(stream, handle.context()), &handle, &service).await else {
+                                            return;
+                                        };
+                                        established = true;
+                                        Self::serve_connection(conn, handle.clone(), push_rx, &service, closing, on_conn_start).await;
+                                        return;
+                                    }
+                                    let Some(conn) = Self::handshake(service.connection(stream, handle.context()), &handle, &service).await else {
+                                        return;
+                                    };
+                                    established = true;
+                                    Self::serve_connection(conn, handle.clone(), push_rx, &service, closing, on_conn_start).await;
+                                };
+                                let exit = select! {
+                                    _ = serve_conn => ConnExit::Closed,
+                                    _ = force.wait_for(|force| *force) => ConnExit::ForceClosed,
+                                };
+                                drop(guard);
+                                // handle_connection 的所有退出路径都汇集到这里，停止钩子只执行一次
+                                if established && let Some(hook) = on_conn_stop {
+                                    hook(handle.clone()).await;
+                                }
+                                // 停止钩子仍然可以读取连接的属性，之后释放它们
+                                handle.context().clear_properties();
+                                exit
+                            };
+                            // 连接任务中的日志都带有连接ID和客户端地址
+                            #[cfg(feature = "tracing")]
+                            let conn_task = tracing::Instrument::instrument(
+                                conn_task,
+                                tracing::info_span!("conn", conn_id, remote_addr = %addr),
+                            );
+                            connections.spawn(conn_task);
+                        }
+                        Err(e) => match self.config.accept_error_policy.retry_delay(&e) {
+                            Some(delay) => {
+                                #[cfg(feature = "tracing")]
+                                tracing::warn!(error = %e, ?delay, "failed to accept connection, retrying");
+                                accept_resume = Some(Instant::now() + delay);
+                            }
+                            None => {
+                                #[cfg(feature = "tracing")]
+                                tracing::error!(error = %e, "failed to accept connection");
+                                break Err(ZerustError::IoError(e));
+                            }
+                        },
                      }
                 }
                 // 分支2 : 接受关闭信号
                 _ = &mut shutdown =>{
-                    break Ok(()) // 退出 loop，结束 run
+                    break Ok(()) // 退出 loop，开始关闭流程
+                }
+                // 分支2' : 通过 shutdown_graceful 请求关闭
+                Ok(_) = graceful.wait_for(|state| state.request.is_some()) => {
+                    break Ok(())
+                }
+                // 分支3 : 回收已结束的连接任务
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                // 分支4 : 关闭空闲过久的连接
+                _ = Self::idle_until(next_sweep) => {
+                    if let Some(timeout) = idle_timeout {
+                        self.conn_manager.evict_idle(timeout);
+                        next_sweep = Some(Instant::now() + timeout / 4);
+                    }
+                }
+            }
+        };
+
+        // 停止接受新连接，新的连接请求会被操作系统拒绝
+        drop(listener);
+
+        // 通知所有连接停止读取新请求，并等待它们完成正在处理的请求
+        let _ = closing_tx.send(true);
+        // shutdown_graceful 指定的等待时间优先于配置的关闭方式
+        let requested = graceful.borrow().request;
+        let grace_period = match (requested, self.config.shutdown_mode) {
+            (Some(timeout), _) => Some(timeout),
+            (None, ShutdownMode::Wait) => None,
+            (None, ShutdownMode::Graceful { timeout }) => Some(timeout),
+            (None, ShutdownMode::Immediate) => Some(Duration::ZERO),
+        };
+        let mut report = ShutdownReport::default();
+        if let Some(grace_period) = grace_period {
+            let drain = async {
+                while let Some(result) = connections.join_next().await {
+                    report.record(result);
+                }
+            };
+            if timeout(grace_period, drain).await.is_err() {
+                // 超过等待时间，强制关闭剩余的连接
+                let _ = force_tx.send(true);
+            }
+        }
+        while let Some(result) = connections.join_next().await {
+            report.record(result);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            closed_cleanly = report.closed_cleanly,
+            force_closed = report.force_closed,
+            "server stopped"
+        );
+        // 本次运行处理了此前的关闭请求，之后的请求属于下一次运行
+        self.shutdown.state.send_modify(|state| {
+            state.request = None;
+            state.runs += 1;
+            state.report = report;
+        });
+        result.map(|()| report)
+    }
+
+    /// 接受一个新连接，并在限制连接数时为其获取许可
+    ///
+    /// * `ConnLimitPolicy::Wait` 策略下先等待许可再接受连接
+    /// * `ConnLimitPolicy::Reject` 策略下先接受连接再尝试获取许可，
+    ///   没有可用许可时返回的许可为 `None`
+    ///
+    /// 该方法在 `select!` 中被取消时，已获取的许可会随之释放。
+    async fn accept<L: Listen>(
+        &self,
+        listener: &L,
+        semaphore: Option<&Arc<Semaphore>>,
+    ) -> io::Result<(L::Stream, SocketAddr, Option<OwnedSemaphorePermit>)> {
+        let Some(semaphore) = semaphore else {
+            let (stream, addr) = listener.accept().await?;
+            return Ok((stream, addr, None));
+        };
+        match self.config.conn_limit_policy {
+            ConnLimitPolicy::Wait => {
+                // 信号量不会被关闭，获取许可不会失败
+                let permit = semaphore.clone().acquire_owned().await.ok();
+                let (stream, addr) = listener.accept().await?;
+                Ok((stream, addr, permit))
+            }
+            ConnLimitPolicy::Reject { .. } => {
+                let (stream, addr) = listener.accept().await?;
+                Ok((stream, addr, semaphore.clone().try_acquire_owned().ok()))
+            }
+        }
+    }
+
+    /// 等待到下一个检查时间（心跳或空闲连接），未开启对应功能时永远不会完成
+    async fn idle_until(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// 等待服务器的关闭通知
+    ///
+    /// 检查通知的当前值而不是等待下一次变化，因此在限流延迟等期间已经收到的通知不会被错过。
+    async fn closing(closing: &mut watch::Receiver<bool>) {
+        let _ = closing.wait_for(|closing| *closing).await;
+    }
+
+    /// 拒绝超出连接数上限的连接
+    ///
+    /// 如果配置了繁忙消息，会在关闭前把它写入套接字；写入最多等待
+    /// `REJECT_WRITE_TIMEOUT`，避免不读取数据的客户端拖住服务器关闭流程。
+    async fn reject<S: Transport>(
+        mut stream: S,
+        codec: Arc<dyn PacketCodec>,
+        busy_response: Option<Response>,
+    ) {
+        if let Some(resp) = busy_response
+            && let Ok(bytes) = codec.encode(resp.msg_id(), resp.data())
+        {
+            let _ = timeout(REJECT_WRITE_TIMEOUT, stream.write_all(&bytes)).await;
+        }
+    }
+
+    /// 调用连接建立钩子，然后处理连接直到结束
+    async fn serve_connection<S: Transport>(
+        conn: Connection<S>,
+        handle: ConnectionHandle,
+        push_rx: mpsc::Receiver<Response>,
+        service: &Arc<ConnService>,
+        closing: watch::Receiver<bool>,
+        on_conn_start: Option<ConnHook>,
+    ) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("connection established");
+        if let Some(hook) = on_conn_start {
+            hook(handle.clone()).await;
+        }
+        let result =
+            Self::handle_connection(conn, handle.clone(), push_rx, service.clone(), closing).await;
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(()) => tracing::debug!("connection closed"),
+            Err(e) => tracing::warn!(error = %e, "connection closed with error"),
+        }
+        if let (Err(e), Some(hook)) = (result, &service.on_conn_error) {
+            hook(&handle, &e);
+        }
+    }
+
+    /// 开启了协议握手时与客户端交换前导
+    ///
+    /// # 返回值
+    /// 握手成功或未开启握手时返回连接；握手失败时调用连接错误钩子并返回 `None`
+    async fn handshake<S: Transport>(
+        mut conn: Connection<S>,
+        handle: &ConnectionHandle,
+        service: &ConnService,
+    ) -> Option<Connection<S>> {
+        let Some(version) = service.protocol_version else {
+            return Some(conn);
+        };
+        match conn.accept_handshake(version).await {
+            Ok(_negotiated) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(version = _negotiated, "protocol handshake completed");
+                Some(conn)
+            }
+            Err(e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(error = %e, "protocol handshake failed");
+                if let Some(hook) = &service.on_conn_error {
+                    hook(handle, &e);
                 }
+                None
             }
         }
     }
 
     /// 处理TCP连接的异步函数
     ///
-    /// 该函数负责接收并处理来自客户端的HTTP请求，通过路由器分发请求并返回响应
+    /// 连接被拆分为读取端和写入端：读取循环接收请求并通过路由器生成响应，
+    /// 写入循环按顺序发送响应和通过 `ConnectionHandle` 推送的消息。
+    /// 两者并发执行，发送缓慢的响应不会推迟下一个请求的读取。
+    ///
+    /// 读取循环结束（客户端关闭、读取错误或服务器关闭）后，写入循环会发送完
+    /// 已经排队的消息，再关闭连接的写入端；写入失败时连接立即结束。
+    /// 客户端只关闭写入端（半关闭）时，已经收到的请求的响应仍然会被发送。
+    /// 发送了 `Response::with_close` 标记的响应后，连接不再处理后续的请求。
+    /// 发送队列已满时，响应等待队列出现空位，期间暂停读取后续的请求；
+    /// 推送的消息溢出且策略为 `WriteQueuePolicy::Disconnect` 时，连接立即结束。
     ///
     /// # 参数
-    /// * `stream` - TCP流连接，用于与客户端进行数据通信
-    /// * `router` - 路由器实例，用于处理HTTP请求并生成响应
+    /// * `conn` - 已按服务器配置创建的连接，用于与客户端进行数据通信
+    /// * `handle` - 连接的句柄，响应通过它进入发送队列
+    /// * `push_rx` - 连接的发送队列
+    /// * `service` - 请求处理配置
+    /// * `closing` - 服务器关闭通知
     ///
     /// # 返回值
     /// * `Result<(), ZerustError>` - 成功时返回空元组，失败时返回Zerust错误
-    async fn handle_connection(
-        stream: TcpStream,
-        router: Arc<dyn Router>,
+    async fn handle_connection<S: Transport>(
+        conn: Connection<S>,
+        handle: ConnectionHandle,
+        push_rx: mpsc::Receiver<Response>,
+        service: Arc<ConnService>,
+        closing: watch::Receiver<bool>,
     ) -> Result<(), ZerustError> {
-        let mut conn = Connection::new(stream);
+        let (reader, writer) = conn.split();
+        let (stop_tx, stop_rx) = oneshot::channel();
+        // 交给工作池的请求各持有一个发送端，全部处理完毕后 recv 返回 None
+        let (pending_tx, mut pending_rx) = mpsc::channel::<()>(1);
+        let read = async {
+            let result = Self::read_loop(reader, &handle, &service, closing, pending_tx).await;
+            // 等待交给工作池的请求处理完毕，它们的响应也需要发送
+            let _ = pending_rx.recv().await;
+            // 通知写入循环发送完已排队的消息后结束
+            let _ = stop_tx.send(());
+            result
+        };
+        let write = Self::write_loop(writer, push_rx, stop_rx, &service.metrics);
+        pin!(read, write);
+        select! {
+            result = &mut read => {
+                let write_result = write.await;
+                result.and(write_result)
+            }
+            // 写入失败，连接已经无法继续使用
+            result = &mut write => result,
+            // 客户端读取得太慢，按发送队列的策略断开
+            _ = handle.overflowed() => Err(ZerustError::WriteQueueFull),
+            // 连接空闲超过 `Server::with_idle_timeout` 设置的时间，属于正常关闭
+            _ = handle.idle_expired() => Ok(()),
+        }
+    }
 
+    /// 读取请求并把响应放入发送队列
+    ///
+    /// 处理函数返回的错误会转换为错误响应发送给客户端，连接保持打开。
+    /// 客户端在两个请求之间关闭连接是正常的断开，返回 `Ok(())`。
+    /// 在等待下一个请求时如果收到关闭通知，连接会立即结束；
+    /// 已经读取到的请求则会被完整处理，其响应进入发送队列后再结束，
+    /// 因限流而延迟的请求不再等待令牌。
+    /// 开启心跳时，连接空闲超过心跳间隔会发送心跳，连续多次未得到回应则返回
+    /// `ZerustError::Timeout`。
+    /// 开启工作池时，请求交给工作池处理，并携带 `pending` 的副本直到处理完毕。
+    async fn read_loop(
+        mut reader: ConnectionReader,
+        handle: &ConnectionHandle,
+        service: &ConnService,
+        mut closing: watch::Receiver<bool>,
+        pending: mpsc::Sender<()>,
+    ) -> Result<(), ZerustError> {
+        let mut heartbeat = service.heartbeat.clone().map(HeartbeatState::new);
+        let mut rate_limit = service.rate_limit.clone().map(TokenBucket::new);
         // 持续处理来自同一连接的多个请求
         loop {
-            // 读取客户端发送的HTTP请求
-            let req = match conn.read_request().await {
-                Ok(req) => req,
-                Err(e) => {
-                    return Err(e);
+            let deadline = heartbeat.as_ref().map(|state| state.deadline);
+            // 读取客户端发送的请求，同时监听心跳计时和服务器关闭通知
+            let req = select! {
+                result = reader.read_request() => match result {
+                    Ok(req) => req,
+                    Err(ZerustError::ConnectionClosed) => return Ok(()),
+                    Err(e @ ZerustError::MessageTooLarge { .. }) => {
+                        // 告知客户端消息过大，响应在连接关闭前发送
+                        if let Some(resp) = &service.oversized_response {
+                            handle.send_wait(resp.clone()).await?;
+                        }
+                        return Err(e);
+                    }
+                    Err(e) => return Err(e),
+                },
+                _ = Self::idle_until(deadline) => {
+                    // 只有开启心跳时该分支才会完成
+                    let Some(state) = heartbeat.as_mut() else { continue };
+                    if state.missed >= state.config.max_missed {
+                        return Err(ZerustError::Timeout);
+                    }
+                    state.missed += 1;
+                    state.deadline = Instant::now() + state.config.interval;
+                    let ping = Response::from_bytes(state.config.msg_id, state.config.payload.clone());
+                    handle.send_wait(ping).await?;
+                    continue;
                 }
+                _ = Self::closing(&mut closing) => return Ok(()),
             };
 
-            let resp = router.handle(&req);
-            conn.send_response(&resp).await?;
+            // 收到任何数据都说明客户端仍然在线
+            if let Some(state) = heartbeat.as_mut() {
+                state.on_activity();
+            }
+            // 控制帧和心跳回应由服务器直接处理，不经过路由器；
+            // ping 需要回复 pong，与普通请求一样在下文消耗令牌，其余的不产生任何输出
+            if req.msg_id() != PING_MSG_ID {
+                let heartbeat_msg_id = heartbeat.as_ref().map(|state| state.config.msg_id);
+                if req.msg_id() == PONG_MSG_ID || heartbeat_msg_id == Some(req.msg_id()) {
+                    if let Some(hook) = &service.on_heartbeat {
+                        hook(req).await;
+                    }
+                    continue;
+                }
+                // 其他保留的消息ID是不认识的控制帧，直接丢弃，不会交给兜底函数
+                if control::is_reserved(req.msg_id()) {
+                    continue;
+                }
+            }
+            // 响应进入发送队列之前，连接不会因空闲被关闭
+            let in_flight = handle.begin_request();
+
+            // 令牌不足时延迟处理，或者回复限流消息
+            if let (Some(bucket), Some(config)) = (rate_limit.as_mut(), &service.rate_limit) {
+                match config.policy() {
+                    RateLimitPolicy::Delay => {
+                        let delay = bucket.reserve();
+                        if !delay.is_zero() {
+                            // 收到关闭通知后不再等待，立即处理已经读取到的请求，之后连接结束
+                            select! {
+                                _ = sleep_until(Instant::now() + delay) => {}
+                                _ = Self::closing(&mut closing) => {}
+                            }
+                        }
+                    }
+                    RateLimitPolicy::Throttle { throttle_response } => {
+                        if !bucket.try_acquire() {
+                            let throttled = throttle_response.clone().inherit_seq(req.seq());
+                            handle.send_wait(throttled).await?;
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            if req.msg_id() == PING_MSG_ID {
+                let pong =
+                    Response::from_bytes(PONG_MSG_ID, req.data_bytes()).inherit_seq(req.seq());
+                handle.send_wait(pong).await?;
+                continue;
+            }
+
+            // 指标请求由服务器直接回复，不经过路由器；生成快照的开销较大，同样需要消耗令牌
+            #[cfg(feature = "prometheus")]
+            if service.metrics_route == Some(req.msg_id()) {
+                let text = service.metrics.snapshot().encode_prometheus();
+                let resp = Response::new(req.msg_id(), text.into_bytes()).inherit_seq(req.seq());
+                handle.send_wait(resp).await?;
+                continue;
+            }
+
+            #[cfg(feature = "tracing")]
+            let span =
+                tracing::debug_span!("request", msg_id = req.msg_id(), len = req.data().len());
+            let handling = Self::handle_request(req, handle, service, &pending, in_flight);
+            #[cfg(feature = "tracing")]
+            let handling = tracing::Instrument::instrument(handling, span);
+            handling.await?;
+        }
+    }
+
+    /// 处理一个请求，把响应放入发送队列
+    ///
+    /// 开启工作池时只把请求交给工作池，`in_flight` 随请求一起交给工作任务。
+    /// 处理函数返回的错误和发生的 panic 都转换为错误响应，连接继续处理后续请求。
+    async fn handle_request(
+        req: Request,
+        handle: &ConnectionHandle,
+        service: &ConnService,
+        pending: &mpsc::Sender<()>,
+        in_flight: RequestGuard,
+    ) -> Result<(), ZerustError> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("request received");
+        let req = req.with_connection(handle.clone());
+        if let Some(pool) = &service.worker_pool {
+            return pool.dispatch(req, handle, pending, in_flight).await;
+        }
+
+        // 响应使用请求的序列号，客户端据此匹配乱序到达的响应
+        let (msg_id, seq, len) = (req.msg_id(), req.seq(), req.data().len());
+        let routed = service.router.has_route(msg_id);
+        let started = Instant::now();
+        let result = handle_catching_panic(service.router.as_ref(), req).await;
+        service
+            .metrics
+            .record_request(msg_id, routed, len, started.elapsed(), result.is_err());
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(error = %e, "handler returned an error");
+                (service.error_handler)(msg_id, &e)
+            }
+        };
+        let sent = handle.send_wait(resp.inherit_seq(seq)).await;
+        drop(in_flight);
+        sent
+    }
+
+    /// 按顺序发送队列中的消息，直到收到结束通知或发送了要求关闭连接的响应
+    ///
+    /// 收到结束通知时，队列中已有的消息会先被发送完毕。结束前关闭连接的写入端，
+    /// 客户端会在收到所有消息后读到连接结束。
+    async fn write_loop(
+        writer: ConnectionWriter,
+        mut push_rx: mpsc::Receiver<Response>,
+        mut stop: oneshot::Receiver<()>,
+        metrics: &Metrics,
+    ) -> Result<(), ZerustError> {
+        loop {
+            select! {
+                // 优先发送排队的消息，队列为空时才检查结束通知
+                biased;
+                Some(resp) = push_rx.recv() => {
+                    writer.send_response(&resp).await?;
+                    for frame in resp.frames() {
+                        metrics.record_sent(frame.data().len());
+                    }
+                    if resp.closes_connection() {
+                        return writer.shutdown().await;
+                    }
+                }
+                _ = &mut stop => {
+                    // 客户端可能已经断开，此时关闭写入端失败不影响结果
+                    let _ = writer.shutdown().await;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// 连接任务处理请求所需的共享配置
+struct ConnService {
+    /// 路由器实例，用于分发请求到对应的处理函数
+    router: Arc<dyn Router + Send + Sync>,
+    /// 将处理函数返回的错误转换为错误响应
+    error_handler: ErrorHandler,
+    /// 心跳配置，`None` 表示不开启心跳
+    heartbeat: Option<HeartbeatConfig>,
+    /// 每个连接的限流配置，`None` 表示不限流
+    rate_limit: Option<RateLimitConfig>,
+    /// 返回 Prometheus 文本格式指标的消息ID，`None` 表示不开启
+    #[cfg(feature = "prometheus")]
+    metrics_route: Option<u32>,
+    /// 协议握手中服务器支持的最高协议版本，`None` 表示不进行握手
+    protocol_version: Option<u8>,
+    /// 收到心跳回应时调用的钩子
+    on_heartbeat: Option<HeartbeatHook>,
+    /// 连接因错误结束时调用的钩子
+    on_conn_error: Option<ConnErrorHook>,
+    /// 请求与流量的指标计数器
+    metrics: Arc<Metrics>,
+    /// 工作池，`None` 表示在连接任务中直接处理请求
+    worker_pool: Option<WorkerPool>,
+    /// 每个连接读取一个完整请求的超时时间，`None` 表示不限制
+    read_timeout: Option<Duration>,
+    /// 每个连接写入一条完整消息的超时时间，`None` 表示不限制
+    write_timeout: Option<Duration>,
+    /// 每个连接允许接收的最大消息体长度
+    max_packet_size: u32,
+    /// 收到过大的消息时，关闭连接前发送的响应
+    oversized_response: Option<Response>,
+    /// 每个连接的接收缓冲区每次扩容的字节数
+    read_buffer_size: usize,
+    /// 帧编解码工具
+    codec: Arc<dyn PacketCodec>,
+    /// TLS 握手工具，`None` 表示使用明文的 TCP 连接
+    #[cfg(feature = "tls")]
+    tls: Option<TlsAcceptor>,
+    /// TLS 握手失败时调用的钩子
+    #[cfg(feature = "tls")]
+    on_tls_error: Option<TlsErrorHook>,
+}
+
+impl ConnService {
+    /// 按服务器配置为接受的流创建连接
+    ///
+    /// 连接使用连接句柄的上下文，请求和生命周期钩子看到同一份自定义属性。
+    fn connection<S: Transport>(&self, stream: S, context: &ConnContext) -> Connection<S> {
+        Connection::from_stream(stream, context.remote_addr())
+            .with_context(context.clone())
+            .with_read_timeout(self.read_timeout)
+            .with_write_timeout(self.write_timeout)
+            .with_max_packet_size(self.max_packet_size)
+            .with_read_buffer_size(self.read_buffer_size)
+            .with_codec(self.codec.clone())
+    }
+}
+
+/// 已绑定的监听器
+enum Listener {
+    /// TCP监听器
+    Tcp(TcpListener),
+    /// Unix 域套接字监听器
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// 按服务器配置绑定监听地址
+    async fn bind(config: &ServerConfig) -> io::Result<Self> {
+        #[cfg(unix)]
+        if let Some(path) = &config.unix_path {
+            return UnixListener::bind(path).map(Listener::Unix);
+        }
+        TcpListener::bind(&config.addr).await.map(Listener::Tcp)
+    }
+}
+
+/// 可以接受连接的监听器，服务器的接受循环对所有监听器通用
+///
+/// 框架为 `TcpListener` 和 Unix 域套接字的监听器实现了该 trait；
+/// 其他监听器实现该 trait 后可以交给 `Server::run_on`。
+pub trait Listen: Send + Sync {
+    /// 接受的连接的流
+    type Stream: Transport;
+
+    /// 接受一个新连接，返回流和客户端地址
+    fn accept(&self) -> impl Future<Output = io::Result<(Self::Stream, SocketAddr)>> + Send;
+
+    /// 为新接受的流启用 `TCP_NODELAY`，不是 TCP 的流忽略该选项
+    fn set_nodelay(_stream: &Self::Stream) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// 为新接受的流开启 TCP keepalive，不是 TCP 的流忽略该选项
+    fn set_keepalive(_stream: &Self::Stream, _time: Duration) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// 设置新接受的流的 `SO_LINGER` 时间，不是 TCP 的流忽略该选项
+    fn set_linger(_stream: &Self::Stream, _linger: Duration) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Listen for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept(&self) -> impl Future<Output = io::Result<(TcpStream, SocketAddr)>> + Send {
+        TcpListener::accept(self)
+    }
+
+    fn set_nodelay(stream: &TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)
+    }
+
+    fn set_keepalive(stream: &TcpStream, time: Duration) -> io::Result<()> {
+        SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(time))
+    }
+
+    fn set_linger(stream: &TcpStream, linger: Duration) -> io::Result<()> {
+        SockRef::from(stream).set_linger(Some(linger))
+    }
+}
+
+#[cfg(unix)]
+impl Listen for UnixListener {
+    type Stream = UnixStream;
+
+    async fn accept(&self) -> io::Result<(UnixStream, SocketAddr)> {
+        let (stream, _) = UnixListener::accept(self).await?;
+        // Unix 域套接字的客户端没有网络地址
+        Ok((stream, SocketAddr::from(([0, 0, 0, 0], 0))))
+    }
+}
+
+/// 已绑定监听地址、尚未开始接受连接的服务器
+///
+/// 由 `Server::bind` 创建。与直接调用 `Server::run` 相比，
+/// 它允许调用方在开始接受连接之前获取实际绑定的地址。
+pub struct BoundServer {
+    /// 服务器配置与路由器
+    server: Server,
+    /// 已绑定的监听器
+    listener: Listener,
+}
+
+impl BoundServer {
+    /// 获取服务器实际绑定的本地地址
+    ///
+    /// # 返回值
+    ///
+    /// * `Ok(SocketAddr)` - 实际绑定的本地套接字地址
+    /// * `Err(ZerustError)` - 获取地址失败，或者监听的是 Unix 域套接字时返回的错误信息
+    pub fn local_addr(&self) -> Result<SocketAddr, ZerustError> {
+        match &self.listener {
+            Listener::Tcp(listener) => listener.local_addr().map_err(ZerustError::IoError),
+            #[cfg(unix)]
+            Listener::Unix(_) => Err(ZerustError::IoError(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unix socket has no socket address",
+            ))),
         }
     }
+
+    /// 获取服务器的连接管理器
+    ///
+    /// 与 `Server::conn_manager` 相同。
+    pub fn conn_manager(&self) -> Arc<ConnManager> {
+        self.server.conn_manager()
+    }
+
+    /// 向所有在线连接推送一条消息
+    ///
+    /// 与 `Server::broadcast` 相同。
+    pub fn broadcast(&self, msg_id: u32, data: impl Into<Bytes>) -> BroadcastReport {
+        self.server.broadcast(msg_id, data)
+    }
+
+    /// 向满足条件的在线连接推送一条消息
+    ///
+    /// 与 `Server::broadcast_filter` 相同。
+    pub fn broadcast_filter<F>(
+        &self,
+        msg_id: u32,
+        data: impl Into<Bytes>,
+        filter: F,
+    ) -> BroadcastReport
+    where
+        F: Fn(&ConnectionHandle) -> bool,
+    {
+        self.server.broadcast_filter(msg_id, data, filter)
+    }
+
+    /// 获取服务器的指标计数器
+    ///
+    /// 与 `Server::metrics` 相同。
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.server.metrics()
+    }
+
+    /// 生成服务器当前指标的快照
+    ///
+    /// 与 `Server::metrics_snapshot` 相同。
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        self.server.metrics_snapshot()
+    }
+
+    /// 把服务器当前的指标编码为 Prometheus 的文本格式
+    ///
+    /// 与 `Server::render_prometheus` 相同。
+    #[cfg(feature = "prometheus")]
+    pub fn render_prometheus(&self) -> String {
+        self.server.render_prometheus()
+    }
+
+    /// 开始接受并处理连接，直到收到关闭信号
+    ///
+    /// 行为与 `Server::run` 相同，只是跳过了绑定步骤。
+    ///
+    /// # 参数
+    ///
+    /// * `shutdown`: 接收关闭信号的通道。当发送端被 drop 或发送消息时，服务器将关闭。
+    ///
+    /// # 返回值
+    ///
+    /// * `Ok(ShutdownReport)` - 服务器收到关闭信号并已关闭，包含被强制关闭的连接数量
+    /// * `Err(ZerustError)` - 服务器运行过程中发生错误
+    pub async fn run(self, shutdown: oneshot::Receiver<()>) -> Result<ShutdownReport, ZerustError> {
+        self.server.serve(self.listener, shutdown).await
+    }
 }
@@ -0,0 +1,151 @@
+//! # 测试工具模块
+//!
+//! `TestServer` 在后台任务中运行服务器，供集成测试使用：监听系统分配的端口，
+//! 绑定完成后即可连接，不需要轮询端口或等待固定的时间；客户端自动使用服务器的
+//! 编解码工具和最大消息体长度，开启了协议握手时也会先完成握手。
+//!
+//! 需要开启 `test-util` 功能，通常只在 `[dev-dependencies]` 中开启：
+//!
+//! ```toml
+//! [dev-dependencies]
+//! zerust = { version = "1", features = ["test-util"] }
+//! ```
+//!
+//! # 示例
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use zerust::test_util::TestServer;
+//! use zerust::{DefaultRouter, Response, Server};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), zerust::ZerustError> {
+//! let router = Arc::new(DefaultRouter::new());
+//! router.add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec())).unwrap();
+//! let server = TestServer::start(Server::new("127.0.0.1:0", router)).await?;
+//!
+//! let mut client = server.client().await?;
+//! assert_eq!(client.request(1, b"ping").await?.data(), b"ping");
+//!
+//! server.shutdown().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::Client;
+use crate::codec::PacketCodec;
+use crate::conn_manager::ConnManager;
+use crate::connection::{Connection, connect_tcp};
+use crate::error::ZerustError;
+use crate::runtime::{JoinHandle, TcpStream, oneshot, spawn};
+use crate::server::{Server, ShutdownReport};
+use std::net::SocketAddr;
+use std::panic;
+use std::sync::Arc;
+
+/// 在后台任务中运行的测试服务器
+///
+/// 只支持 TCP 服务器，不支持 TLS。`TestServer` 被丢弃时服务器收到关闭信号，
+/// 但不会等待它停止；需要检查关闭结果时调用 `shutdown`。
+pub struct TestServer {
+    /// 服务器实际监听的地址
+    addr: SocketAddr,
+    /// 服务器的连接管理器
+    conn_manager: Arc<ConnManager>,
+    /// 服务器使用的编解码工具，客户端使用同一个
+    codec: Arc<dyn PacketCodec>,
+    /// 服务器允许的最大消息体长度
+    max_packet_size: u32,
+    /// 服务器开启的协议握手版本
+    protocol_version: Option<u8>,
+    /// 关闭信号的发送端
+    shutdown_tx: oneshot::Sender<()>,
+    /// 运行服务器的后台任务
+    handle: JoinHandle<Result<ShutdownReport, ZerustError>>,
+}
+
+impl TestServer {
+    /// 绑定监听地址，并在后台任务中开始接受连接
+    ///
+    /// 监听地址通常为 `"127.0.0.1:0"`，由系统分配空闲端口。
+    ///
+    /// # 参数
+    /// * `server` - 已配置好的服务器
+    ///
+    /// # 返回值
+    /// * `Ok(TestServer)` - 已经可以接受连接的测试服务器
+    /// * `Err(ZerustError)` - 配置无效、绑定地址失败，或者监听的不是 TCP 地址时返回的错误
+    pub async fn start(server: Server) -> Result<Self, ZerustError> {
+        let conn_manager = server.conn_manager();
+        let config = server.config();
+        let codec = config.codec().clone();
+        let max_packet_size = config.max_packet_size();
+        let protocol_version = config.protocol_version();
+        let bound = server.bind().await?;
+        let addr = bound.local_addr()?;
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let handle = spawn(bound.run(shutdown_rx));
+        Ok(Self {
+            addr,
+            conn_manager,
+            codec,
+            max_packet_size,
+            protocol_version,
+            shutdown_tx,
+            handle,
+        })
+    }
+
+    /// 获取服务器实际监听的地址
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 获取服务器的连接管理器
+    pub fn conn_manager(&self) -> Arc<ConnManager> {
+        self.conn_manager.clone()
+    }
+
+    /// 建立一个连接到服务器的 `Connection`
+    ///
+    /// 连接使用服务器的编解码工具和最大消息体长度；服务器开启了协议握手时，
+    /// 返回之前已经以服务器的版本完成握手。
+    ///
+    /// # 返回值
+    /// * `Ok(Connection)` - 已连接的连接
+    /// * `Err(ZerustError)` - 连接或握手失败时返回的错误
+    pub async fn connection(&self) -> Result<Connection<TcpStream>, ZerustError> {
+        let stream = connect_tcp(self.addr).await?;
+        let mut conn = Connection::new(stream)
+            .with_codec(self.codec.clone())
+            .with_max_packet_size(self.max_packet_size);
+        if let Some(version) = self.protocol_version {
+            conn.handshake(version).await?;
+        }
+        Ok(conn)
+    }
+
+    /// 建立一个连接到服务器的 `Client`，参见 `connection`
+    ///
+    /// # 返回值
+    /// * `Ok(Client)` - 已连接的客户端
+    /// * `Err(ZerustError)` - 连接或握手失败时返回的错误
+    pub async fn client(&self) -> Result<Client<TcpStream>, ZerustError> {
+        Ok(Client::new(self.connection().await?))
+    }
+
+    /// 关闭服务器并等待它停止
+    ///
+    /// 与向 `Server::run` 的关闭通道发送信号相同，按服务器配置的 `ShutdownMode`
+    /// 等待在线连接结束。服务器任务 panic 时该 panic 会在这里继续传播。
+    ///
+    /// # 返回值
+    /// 返回 `Server::run` 的结果
+    pub async fn shutdown(self) -> Result<ShutdownReport, ZerustError> {
+        let _ = self.shutdown_tx.send(());
+        match self.handle.await {
+            Ok(result) => result,
+            Err(e) => panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! # TLS 辅助模块
+//!
+//! 该模块提供创建 TLS 配置和建立 TLS 连接的便捷函数，均使用 `ring` 加密后端和
+//! rustls 默认的安全协议版本。需要更多控制（例如客户端证书认证）时，
+//! 可以直接使用重新导出的 `rustls` 创建配置，再交给 `Server::with_tls` 或 `TlsConnector`。
+//!
+//! 需要开启 `tls` 功能。
+//!
+//! # 示例
+//!
+//! ```rust,no_run
+//! use zerust::{Client, DefaultRouter, Server, tls};
+//! use std::sync::Arc;
+//!
+//! # async fn run() -> Result<(), zerust::ZerustError> {
+//! let config = tls::server_config_from_pem(
+//!     &std::fs::read("cert.pem")?,
+//!     &std::fs::read("key.pem")?,
+//! )?;
+//! let server = Server::new("0.0.0.0:8443", Arc::new(DefaultRouter::new())).with_tls(config);
+//!
+//! let connector = tls::connector_from_pem(&std::fs::read("ca.pem")?)?;
+//! let mut client = Client::connect_tls("127.0.0.1:8443", "localhost", &connector).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::connection::{Connection, connect_tcp};
+use crate::error::ZerustError;
+use crate::runtime::{TcpStream, ToSocketAddrs};
+use std::io;
+use std::sync::Arc;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::{self, RootCertStore};
+use tokio_rustls::{TlsConnector, client::TlsStream};
+
+/// 使用 `ring` 加密后端
+fn provider() -> Arc<rustls::crypto::CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+/// 把 rustls 和 PEM 解析的错误转换为配置错误
+fn invalid(err: impl std::fmt::Display) -> ZerustError {
+    ZerustError::InvalidConfig(format!("TLS: {err}"))
+}
+
+/// 使用证书链和私钥创建服务端配置，不要求客户端证书
+///
+/// # 参数
+/// * `cert_chain` - 服务器证书链，第一个为服务器证书
+/// * `key` - 服务器证书的私钥
+///
+/// # 返回值
+/// * `Ok(Arc<ServerConfig>)` - 可以交给 `Server::with_tls` 的配置
+/// * `Err(ZerustError::InvalidConfig)` - 证书与私钥不匹配或格式无效
+pub fn server_config(
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> Result<Arc<rustls::ServerConfig>, ZerustError> {
+    let config = rustls::ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(invalid)?
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, key)
+        .map_err(invalid)?;
+    Ok(Arc::new(config))
+}
+
+/// 使用 PEM 格式的证书链和私钥创建服务端配置
+///
+/// # 参数
+/// * `cert_pem` - PEM 格式的证书链
+/// * `key_pem` - PEM 格式的私钥，支持 PKCS#1、PKCS#8 和 SEC1
+///
+/// # 返回值
+/// * `Ok(Arc<ServerConfig>)` - 可以交给 `Server::with_tls` 的配置
+/// * `Err(ZerustError::InvalidConfig)` - PEM 解析失败，或证书与私钥无效
+pub fn server_config_from_pem(
+    cert_pem: &[u8],
+    key_pem: &[u8],
+) -> Result<Arc<rustls::ServerConfig>, ZerustError> {
+    let cert_chain = CertificateDer::pem_slice_iter(cert_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid)?;
+    let key = PrivateKeyDer::from_pem_slice(key_pem).map_err(invalid)?;
+    server_config(cert_chain, key)
+}
+
+/// 创建只信任给定根证书的客户端连接工具
+///
+/// # 参数
+/// * `roots` - 信任的根证书，例如签发服务器证书的 CA
+///
+/// # 返回值
+/// * `Ok(TlsConnector)` - 客户端连接工具，参见 `connect`
+/// * `Err(ZerustError::InvalidConfig)` - 根证书格式无效
+pub fn connector(
+    roots: impl IntoIterator<Item = CertificateDer<'static>>,
+) -> Result<TlsConnector, ZerustError> {
+    let mut store = RootCertStore::empty();
+    for cert in roots {
+        store.add(cert).map_err(invalid)?;
+    }
+    let config = rustls::ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(invalid)?
+        .with_root_certificates(store)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// 创建只信任 PEM 格式根证书的客户端连接工具
+///
+/// # 参数
+/// * `ca_pem` - PEM 格式的根证书，可以包含多个
+///
+/// # 返回值
+/// * `Ok(TlsConnector)` - 客户端连接工具，参见 `connect`
+/// * `Err(ZerustError::InvalidConfig)` - PEM 解析失败或根证书无效
+pub fn connector_from_pem(ca_pem: &[u8]) -> Result<TlsConnector, ZerustError> {
+    let roots = CertificateDer::pem_slice_iter(ca_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(invalid)?;
+    connector(roots)
+}
+
+/// 连接到服务器并完成 TLS 握手，返回使用默认编解码工具的连接
+///
+/// # 参数
+/// * `addr` - 服务器地址，例如 `"127.0.0.1:8443"`
+/// * `server_name` - 用于验证服务器证书的域名或 IP 地址
+/// * `connector` - 客户端连接工具
+///
+/// # 返回值
+/// * `Ok(Connection)` - 已完成握手的连接
+/// * `Err(ZerustError)` - 连接失败、`server_name` 无效或握手失败时返回的错误
+pub async fn connect(
+    addr: impl ToSocketAddrs,
+    server_name: &str,
+    connector: &TlsConnector,
+) -> Result<Connection<TlsStream<TcpStream>>, ZerustError> {
+    let server_name = ServerName::try_from(server_name.to_owned())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let stream = connect_tcp(addr).await?;
+    let stream = connector.connect(server_name, stream).await?;
+    Ok(Connection::new(stream))
+}
@@ -0,0 +1,612 @@
+//! # TOML 配置文件
+//!
+//! 从 TOML 文件加载服务器配置，需要开启 `toml` 功能。
+//!
+//! 提供 `ServerConfig::from_toml_file` 等方法和加载选项 `TomlOptions`，
+//! 配置项、环境变量和校验规则的说明参见 `ServerConfig::from_toml_file`。
+
+use crate::codec::CheckedDataPack;
+#[cfg(feature = "compression")]
+use crate::compression::{CompressionAlgorithm, CompressionConfig};
+use crate::config::{ServerBuilder, ServerConfig};
+use crate::datapack::{ByteOrderMode, DataPack, HeaderLayout};
+use crate::error::ZerustError;
+use crate::rate_limit::RateLimitConfig;
+use crate::server::ShutdownMode;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 默认的环境变量前缀
+pub const DEFAULT_ENV_PREFIX: &str = "ZERUST_";
+
+/// 顶层允许出现的配置项
+const TOP_LEVEL_KEYS: &[&str] = &[
+    "listen_addr",
+    "unix_path",
+    "max_connections",
+    "max_frame_size",
+    "read_buffer_size",
+    "write_queue_capacity",
+    "nodelay",
+    "keepalive_ms",
+    "linger_ms",
+    "read_timeout_ms",
+    "write_timeout_ms",
+    "idle_timeout_ms",
+    "shutdown_timeout_ms",
+    "protocol_version",
+];
+
+/// 各个表中允许出现的配置项
+const TABLE_KEYS: &[(&str, &[&str])] = &[
+    ("codec", &["byte_order", "header_layout", "checksum"]),
+    ("heartbeat", &["interval_ms", "max_missed"]),
+    ("worker_pool", &["size", "max_task_queue_len"]),
+    ("rate_limit", &["max_per_sec", "burst"]),
+    ("compression", &["threshold", "algorithm"]),
+    ("tls", &["cert_path", "key_path"]),
+];
+
+/// 加载 TOML 配置文件的选项
+///
+/// # 示例
+///
+/// ```rust
+/// use zerust::config::TomlOptions;
+///
+/// // 允许配置文件中出现其他程序使用的配置项，不读取环境变量
+/// let options = TomlOptions::new()
+///     .with_deny_unknown_keys(false)
+///     .with_env_prefix(None);
+/// assert!(!options.deny_unknown_keys());
+/// ```
+#[derive(Debug, Clone)]
+pub struct TomlOptions {
+    /// 出现未知的配置项时是否返回错误
+    deny_unknown_keys: bool,
+    /// 覆盖配置文件的环境变量的前缀，`None` 表示不读取环境变量
+    env_prefix: Option<String>,
+}
+
+impl Default for TomlOptions {
+    fn default() -> Self {
+        Self {
+            deny_unknown_keys: true,
+            env_prefix: Some(DEFAULT_ENV_PREFIX.to_string()),
+        }
+    }
+}
+
+impl TomlOptions {
+    /// 创建默认的选项：不允许未知的配置项，读取以 `ZERUST_` 开头的环境变量
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置出现未知的配置项时是否返回错误
+    ///
+    /// # 参数
+    /// * `deny` - `true` 表示返回错误，`false` 表示忽略未知的配置项
+    pub fn with_deny_unknown_keys(mut self, deny: bool) -> Self {
+        self.deny_unknown_keys = deny;
+        self
+    }
+
+    /// 设置覆盖配置文件的环境变量的前缀
+    ///
+    /// # 参数
+    /// * `prefix` - 环境变量的前缀，例如 `Some("GATEWAY_")` 读取 `GATEWAY_LISTEN_ADDR`；
+    ///   `None` 表示不读取环境变量
+    pub fn with_env_prefix(mut self, prefix: Option<&str>) -> Self {
+        self.env_prefix = prefix.map(str::to_string);
+        self
+    }
+
+    /// 获取出现未知的配置项时是否返回错误
+    pub fn deny_unknown_keys(&self) -> bool {
+        self.deny_unknown_keys
+    }
+
+    /// 获取覆盖配置文件的环境变量的前缀，`None` 表示不读取环境变量
+    pub fn env_prefix(&self) -> Option<&str> {
+        self.env_prefix.as_deref()
+    }
+}
+
+/// 配置文件的内容，与文件中的配置项一一对应
+#[derive(Deserialize, Default)]
+struct FileConfig {
+    listen_addr: Option<String>,
+    unix_path: Option<PathBuf>,
+    max_connections: Option<usize>,
+    max_frame_size: Option<u32>,
+    read_buffer_size: Option<usize>,
+    write_queue_capacity: Option<usize>,
+    nodelay: Option<bool>,
+    keepalive_ms: Option<u64>,
+    linger_ms: Option<u64>,
+    read_timeout_ms: Option<u64>,
+    write_timeout_ms: Option<u64>,
+    idle_timeout_ms: Option<u64>,
+    shutdown_timeout_ms: Option<u64>,
+    protocol_version: Option<u8>,
+    codec: Option<CodecTable>,
+    heartbeat: Option<HeartbeatTable>,
+    worker_pool: Option<WorkerPoolTable>,
+    rate_limit: Option<RateLimitTable>,
+    compression: Option<CompressionTable>,
+    tls: Option<TlsTable>,
+}
+
+/// `[codec]` 表
+#[derive(Deserialize, Default)]
+struct CodecTable {
+    byte_order: Option<ByteOrderName>,
+    header_layout: Option<HeaderLayoutName>,
+    checksum: Option<bool>,
+}
+
+/// `codec.byte_order` 的取值
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum ByteOrderName {
+    Little,
+    Big,
+}
+
+/// `codec.header_layout` 的取值
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum HeaderLayoutName {
+    Zerust,
+    Zinx,
+}
+
+/// `[heartbeat]` 表
+#[derive(Deserialize)]
+struct HeartbeatTable {
+    interval_ms: u64,
+    max_missed: u32,
+}
+
+/// `[worker_pool]` 表
+#[derive(Deserialize)]
+struct WorkerPoolTable {
+    size: usize,
+    max_task_queue_len: usize,
+}
+
+/// `[rate_limit]` 表
+#[derive(Deserialize)]
+struct RateLimitTable {
+    max_per_sec: u32,
+    burst: Option<u32>,
+}
+
+/// `[compression]` 表
+#[derive(Deserialize)]
+#[cfg_attr(not(feature = "compression"), allow(dead_code))]
+struct CompressionTable {
+    threshold: usize,
+    algorithm: Option<AlgorithmName>,
+}
+
+/// `compression.algorithm` 的取值
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(not(feature = "compression"), allow(dead_code))]
+enum AlgorithmName {
+    Zstd,
+    Gzip,
+}
+
+/// `[tls]` 表，两个路径都可以只由环境变量提供
+#[derive(Deserialize, Default)]
+#[cfg_attr(not(feature = "tls"), allow(dead_code))]
+struct TlsTable {
+    cert_path: Option<PathBuf>,
+    key_path: Option<PathBuf>,
+}
+
+impl ServerConfig {
+    /// 从 TOML 文件加载服务器配置
+    ///
+    /// 使用默认的 `TomlOptions`：不允许未知的配置项，以 `ZERUST_` 开头的环境变量覆盖文件中的配置。
+    /// 需要开启 `toml` 功能。
+    ///
+    /// 文件中的每一项都映射到 `ServerBuilder` 对应的方法，没有出现的项使用默认值。
+    /// 带注释的完整示例参见 `examples/zerust.toml`。
+    ///
+    /// # 配置项
+    ///
+    /// | 配置项 | 类型 | 对应的构建方法 |
+    /// |--------|------|----------------|
+    /// | `listen_addr` | 字符串，"主机:端口" | `addr` |
+    /// | `unix_path` | 路径，仅 Unix | `unix_path` |
+    /// | `max_connections` | 整数 | `max_connections` |
+    /// | `max_frame_size` | 整数，包含帧头的字节数 | `max_packet_size`（减去帧头和校验和的长度） |
+    /// | `read_buffer_size` | 整数 | `read_buffer_size` |
+    /// | `write_queue_capacity` | 整数 | `write_queue_capacity` |
+    /// | `nodelay` | 布尔 | `nodelay` |
+    /// | `keepalive_ms` / `linger_ms` | 毫秒 | `keepalive` / `linger` |
+    /// | `read_timeout_ms` / `write_timeout_ms` / `idle_timeout_ms` | 毫秒 | `read_timeout` / `write_timeout` / `idle_timeout` |
+    /// | `shutdown_timeout_ms` | 毫秒 | `shutdown_mode(ShutdownMode::Graceful { .. })` |
+    /// | `protocol_version` | 整数 | `protocol_version` |
+    /// | `[codec]` `byte_order` / `header_layout` / `checksum` | `"little"`/`"big"`、`"zerust"`/`"zinx"`、布尔 | `datapack` / `checksum` |
+    /// | `[heartbeat]` `interval_ms` / `max_missed` | 毫秒、整数 | `heartbeat` |
+    /// | `[worker_pool]` `size` / `max_task_queue_len` | 整数 | `worker_pool` |
+    /// | `[rate_limit]` `max_per_sec` / `burst` | 整数 | `rate_limit_config` |
+    /// | `[compression]` `threshold` / `algorithm` | 整数、`"zstd"`/`"gzip"`，需要 `compression` 功能 | `compression_config` |
+    /// | `[tls]` `cert_path` / `key_path` | PEM 文件的路径，需要 `tls` 功能 | `tls` |
+    ///
+    /// 无效的取值返回 `ZerustError::InvalidConfig`，错误信息以出错的配置项开头并说明原因，
+    /// 例如 `heartbeat.interval_ms must be greater than 0`。默认不允许出现未知的配置项，
+    /// 以免拼写错误的配置项被悄悄忽略；可以通过 `TomlOptions::with_deny_unknown_keys` 关闭。
+    /// `[tls]` 中的相对路径相对于配置文件所在的目录。
+    ///
+    /// # 环境变量
+    ///
+    /// 以下环境变量的优先级高于配置文件，适合在不同的部署环境中修改个别配置：
+    ///
+    /// | 环境变量 | 配置项 |
+    /// |----------|--------|
+    /// | `ZERUST_LISTEN_ADDR` | `listen_addr` |
+    /// | `ZERUST_MAX_CONNECTIONS` | `max_connections` |
+    /// | `ZERUST_MAX_FRAME_SIZE` | `max_frame_size` |
+    /// | `ZERUST_READ_TIMEOUT_MS` | `read_timeout_ms` |
+    /// | `ZERUST_WRITE_TIMEOUT_MS` | `write_timeout_ms` |
+    /// | `ZERUST_IDLE_TIMEOUT_MS` | `idle_timeout_ms` |
+    /// | `ZERUST_TLS_CERT_PATH` | `tls.cert_path` |
+    /// | `ZERUST_TLS_KEY_PATH` | `tls.key_path` |
+    ///
+    /// 前缀 `ZERUST_` 可以通过 `TomlOptions::with_env_prefix` 修改或关闭。
+    /// 环境变量中的相对路径相对于当前工作目录。
+    ///
+    /// # 参数
+    /// * `path` - 配置文件的路径
+    ///
+    /// # 返回值
+    /// * `Ok(ServerConfig)` - 通过 `ServerBuilder::from_config` 创建服务器
+    /// * `Err(ZerustError::IoError)` - 读取配置文件失败
+    /// * `Err(ZerustError::InvalidConfig)` - 文件格式错误、出现未知的配置项或者取值无效，
+    ///   错误信息以出错的配置项开头
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use zerust::{DefaultRouter, ServerBuilder, ServerConfig};
+    ///
+    /// # fn main() -> Result<(), zerust::ZerustError> {
+    /// let config = ServerConfig::from_toml_file("examples/zerust.toml")?;
+    /// let server = ServerBuilder::from_config(config)
+    ///     .router(Arc::new(DefaultRouter::new()))
+    ///     .try_build()?;
+    /// # let _ = server;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self, ZerustError> {
+        Self::from_toml_file_with(path, &TomlOptions::default())
+    }
+
+    /// 使用给定的选项从 TOML 文件加载服务器配置
+    ///
+    /// # 参数
+    /// * `path` - 配置文件的路径
+    /// * `options` - 加载选项
+    ///
+    /// # 返回值
+    /// 与 `from_toml_file` 相同
+    pub fn from_toml_file_with(
+        path: impl AsRef<Path>,
+        options: &TomlOptions,
+    ) -> Result<Self, ZerustError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        load(&text, base_dir, options)
+            .map_err(|e| ZerustError::InvalidConfig(format!("{}: {e}", path.display())))
+    }
+
+    /// 使用给定的选项解析 TOML 格式的服务器配置
+    ///
+    /// `[tls]` 中的相对路径相对于当前工作目录。
+    ///
+    /// # 参数
+    /// * `text` - TOML 格式的配置
+    /// * `options` - 加载选项
+    ///
+    /// # 返回值
+    /// * `Ok(ServerConfig)` - 解析出的配置
+    /// * `Err(ZerustError::InvalidConfig)` - 格式错误、出现未知的配置项或者取值无效
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use zerust::config::TomlOptions;
+    /// use zerust::{ServerConfig, ZerustError};
+    ///
+    /// let options = TomlOptions::new().with_env_prefix(None);
+    /// let config = ServerConfig::from_toml_str(
+    ///     "listen_addr = \"127.0.0.1:7000\"\nread_timeout_ms = 30000\n",
+    ///     &options,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(config.addr(), "127.0.0.1:7000");
+    /// assert_eq!(config.read_timeout(), Some(Duration::from_secs(30)));
+    ///
+    /// let err = ServerConfig::from_toml_str("[heartbeat]\ninterval_ms = 0\nmax_missed = 3\n", &options);
+    /// assert!(matches!(err, Err(ZerustError::InvalidConfig(msg)) if msg.starts_with("heartbeat.interval_ms")));
+    /// ```
+    pub fn from_toml_str(text: &str, options: &TomlOptions) -> Result<Self, ZerustError> {
+        load(text, Path::new(""), options).map_err(ZerustError::InvalidConfig)
+    }
+}
+
+/// 解析配置、应用环境变量并映射到构建器上，错误信息不包含文件路径
+fn load(text: &str, base_dir: &Path, options: &TomlOptions) -> Result<ServerConfig, String> {
+    let table: toml::Table = text.parse().map_err(|e| format!("{e}"))?;
+    if options.deny_unknown_keys {
+        check_unknown_keys(&table)?;
+    }
+    // 再按类型解析一次，错误信息中带有出错的位置和配置项
+    let mut file: FileConfig = toml::from_str(text).map_err(|e| format!("{e}"))?;
+    if let Some(tls) = &mut file.tls {
+        for path in [&mut tls.cert_path, &mut tls.key_path]
+            .into_iter()
+            .flatten()
+        {
+            *path = base_dir.join(&*path);
+        }
+    }
+    if let Some(prefix) = &options.env_prefix {
+        apply_env(&mut file, prefix)?;
+    }
+    let config = build(file)?.config;
+    config.validate().map_err(|e| match e {
+        ZerustError::InvalidConfig(msg) => msg,
+        e => e.to_string(),
+    })?;
+    Ok(config)
+}
+
+/// 检查是否出现了未知的配置项
+fn check_unknown_keys(table: &toml::Table) -> Result<(), String> {
+    for (key, value) in table {
+        if TOP_LEVEL_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let Some((_, keys)) = TABLE_KEYS.iter().find(|(name, _)| name == key) else {
+            return Err(format!("unknown key `{key}`"));
+        };
+        // 不是表时交给按类型解析时报告
+        if let Some(inner) = value.as_table()
+            && let Some(unknown) = inner.keys().find(|k| !keys.contains(&k.as_str()))
+        {
+            return Err(format!("unknown key `{key}.{unknown}`"));
+        }
+    }
+    Ok(())
+}
+
+/// 用环境变量覆盖配置文件中的配置项
+fn apply_env(file: &mut FileConfig, prefix: &str) -> Result<(), String> {
+    if let Some(addr) = env_var(prefix, "LISTEN_ADDR")? {
+        file.listen_addr = Some(addr);
+    }
+    if let Some(max) = env_parse(prefix, "MAX_CONNECTIONS")? {
+        file.max_connections = Some(max);
+    }
+    if let Some(size) = env_parse(prefix, "MAX_FRAME_SIZE")? {
+        file.max_frame_size = Some(size);
+    }
+    if let Some(ms) = env_parse(prefix, "READ_TIMEOUT_MS")? {
+        file.read_timeout_ms = Some(ms);
+    }
+    if let Some(ms) = env_parse(prefix, "WRITE_TIMEOUT_MS")? {
+        file.write_timeout_ms = Some(ms);
+    }
+    if let Some(ms) = env_parse(prefix, "IDLE_TIMEOUT_MS")? {
+        file.idle_timeout_ms = Some(ms);
+    }
+    if let Some(path) = env_var(prefix, "TLS_CERT_PATH")? {
+        file.tls.get_or_insert_with(TlsTable::default).cert_path = Some(path.into());
+    }
+    if let Some(path) = env_var(prefix, "TLS_KEY_PATH")? {
+        file.tls.get_or_insert_with(TlsTable::default).key_path = Some(path.into());
+    }
+    Ok(())
+}
+
+/// 读取一个环境变量，没有设置时返回 `None`
+fn env_var(prefix: &str, name: &str) -> Result<Option<String>, String> {
+    let var = format!("{prefix}{name}");
+    match std::env::var(&var) {
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(std::env::VarError::NotUnicode(_)) => Err(format!("{var} is not valid UTF-8")),
+    }
+}
+
+/// 读取一个环境变量并解析为数字，没有设置时返回 `None`
+fn env_parse<T: FromStr>(prefix: &str, name: &str) -> Result<Option<T>, String> {
+    env_var(prefix, name)?
+        .map(|value| {
+            value.trim().parse().map_err(|_| {
+                format!("{prefix}{name} must be a non-negative integer, got `{value}`")
+            })
+        })
+        .transpose()
+}
+
+/// 检查取值大于 0
+fn positive<T: Default + PartialEq>(key: &str, value: T) -> Result<T, String> {
+    if value == T::default() {
+        return Err(format!("{key} must be greater than 0"));
+    }
+    Ok(value)
+}
+
+/// 把以毫秒为单位的配置项转换为时间，取值必须大于 0
+fn millis(key: &str, ms: u64) -> Result<Duration, String> {
+    positive(key, ms).map(Duration::from_millis)
+}
+
+/// 把配置文件的内容映射到构建器上，并检查各个配置项
+fn build(file: FileConfig) -> Result<ServerBuilder, String> {
+    let mut builder = ServerBuilder::new();
+    if let Some(addr) = file.listen_addr {
+        let valid_port = addr
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+        if !valid_port {
+            return Err(format!(
+                "listen_addr must be in the form \"host:port\", got `{addr}`"
+            ));
+        }
+        builder = builder.addr(&addr);
+    }
+    if let Some(path) = file.unix_path {
+        #[cfg(unix)]
+        {
+            builder = builder.unix_path(path);
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = path;
+            return Err("unix_path is only supported on Unix".to_string());
+        }
+    }
+    if let Some(max) = file.max_connections {
+        builder = builder.max_connections(positive("max_connections", max)?);
+    }
+    if let Some(size) = file.read_buffer_size {
+        builder = builder.read_buffer_size(positive("read_buffer_size", size)?);
+    }
+    if let Some(capacity) = file.write_queue_capacity {
+        builder = builder.write_queue_capacity(positive("write_queue_capacity", capacity)?);
+    }
+    if let Some(nodelay) = file.nodelay {
+        builder = builder.nodelay(nodelay);
+    }
+    if let Some(ms) = file.keepalive_ms {
+        builder = builder.keepalive(millis("keepalive_ms", ms)?);
+    }
+    if let Some(ms) = file.linger_ms {
+        // SO_LINGER 为 0 表示关闭时直接丢弃未发送的数据，是有效的取值
+        builder = builder.linger(Duration::from_millis(ms));
+    }
+    if let Some(ms) = file.read_timeout_ms {
+        builder = builder.read_timeout(millis("read_timeout_ms", ms)?);
+    }
+    if let Some(ms) = file.write_timeout_ms {
+        builder = builder.write_timeout(millis("write_timeout_ms", ms)?);
+    }
+    if let Some(ms) = file.idle_timeout_ms {
+        builder = builder.idle_timeout(millis("idle_timeout_ms", ms)?);
+    }
+    if let Some(ms) = file.shutdown_timeout_ms {
+        let timeout = millis("shutdown_timeout_ms", ms)?;
+        builder = builder.shutdown_mode(ShutdownMode::Graceful { timeout });
+    }
+    if let Some(version) = file.protocol_version {
+        builder = builder.protocol_version(version);
+    }
+
+    // 帧格式决定了帧头的长度，需要在换算最大消息体长度之前确定
+    let codec = file.codec.unwrap_or_default();
+    let order = match codec.byte_order {
+        Some(ByteOrderName::Big) => ByteOrderMode::Big,
+        Some(ByteOrderName::Little) | None => ByteOrderMode::Little,
+    };
+    let checksum = codec.checksum.unwrap_or(false);
+    let overhead = if checksum {
+        if codec.header_layout == Some(HeaderLayoutName::Zinx) {
+            return Err(
+                "codec.checksum cannot be combined with codec.header_layout = \"zinx\"".to_string(),
+            );
+        }
+        builder = builder.codec(Arc::new(CheckedDataPack::new().with_order(order)));
+        DataPack::HEADER_SIZE + DataPack::CHECKSUM_SIZE
+    } else {
+        let layout = match codec.header_layout {
+            Some(HeaderLayoutName::Zinx) => HeaderLayout::LenThenId,
+            Some(HeaderLayoutName::Zerust) | None => HeaderLayout::IdThenLen,
+        };
+        builder = builder.datapack(DataPack::with_order(order).with_header_layout(layout));
+        DataPack::HEADER_SIZE
+    };
+    if let Some(size) = file.max_frame_size {
+        // 帧头的长度不超过 12 字节，转换不会溢出
+        let overhead = overhead as u32;
+        if size <= overhead {
+            return Err(format!(
+                "max_frame_size must be greater than the {overhead}-byte frame header, got {size}"
+            ));
+        }
+        builder = builder.max_packet_size(size - overhead);
+    }
+
+    if let Some(heartbeat) = file.heartbeat {
+        let interval = millis("heartbeat.interval_ms", heartbeat.interval_ms)?;
+        let max_missed = positive("heartbeat.max_missed", heartbeat.max_missed)?;
+        builder = builder.heartbeat(interval, max_missed);
+    }
+    if let Some(pool) = file.worker_pool {
+        let size = positive("worker_pool.size", pool.size)?;
+        let queue_len = positive("worker_pool.max_task_queue_len", pool.max_task_queue_len)?;
+        builder = builder.worker_pool(size, queue_len);
+    }
+    if let Some(rate_limit) = file.rate_limit {
+        let mut config =
+            RateLimitConfig::new(positive("rate_limit.max_per_sec", rate_limit.max_per_sec)?);
+        if let Some(burst) = rate_limit.burst {
+            config = config.with_burst(positive("rate_limit.burst", burst)?);
+        }
+        builder = builder.rate_limit_config(config);
+    }
+    if let Some(compression) = file.compression {
+        #[cfg(feature = "compression")]
+        {
+            let algorithm = match compression.algorithm {
+                Some(AlgorithmName::Gzip) => CompressionAlgorithm::Gzip,
+                Some(AlgorithmName::Zstd) | None => CompressionAlgorithm::Zstd,
+            };
+            let config = CompressionConfig::new(compression.threshold).with_algorithm(algorithm);
+            builder = builder.compression_config(config);
+        }
+        #[cfg(not(feature = "compression"))]
+        {
+            let _ = compression;
+            return Err("compression requires the `compression` feature".to_string());
+        }
+    }
+    if let Some(tls) = file.tls {
+        #[cfg(feature = "tls")]
+        {
+            let cert = read_pem("tls.cert_path", tls.cert_path)?;
+            let key = read_pem("tls.key_path", tls.key_path)?;
+            let config =
+                crate::tls::server_config_from_pem(&cert, &key).map_err(|e| format!("tls: {e}"))?;
+            builder = builder.tls(config);
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            let _ = tls;
+            return Err("tls requires the `tls` feature".to_string());
+        }
+    }
+    Ok(builder)
+}
+
+/// 读取 `[tls]` 中的一个 PEM 文件
+#[cfg(feature = "tls")]
+fn read_pem(key: &str, path: Option<PathBuf>) -> Result<Vec<u8>, String> {
+    let path = path.ok_or_else(|| format!("{key} is required"))?;
+    std::fs::read(&path).map_err(|e| format!("{key}: cannot read `{}`: {e}", path.display()))
+}
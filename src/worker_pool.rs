@@ -0,0 +1,261 @@
+//! # 工作池模块
+//!
+//! 默认情况下，每个连接在自己的任务中依次处理请求：耗时的处理函数会推迟该连接后续请求的读取，
+//! 也无法限制全局同时执行的处理函数数量。
+//!
+//! 开启工作池后（参见 `Server::with_worker_pool`），连接任务只负责读取请求，
+//! 请求按 `conn_id % 工作任务数` 放入固定数量的工作任务的有界队列中，由工作任务调用路由器，
+//! 响应再经过连接的发送队列写回客户端。同一个连接的请求总是由同一个工作任务按顺序处理，
+//! 因此响应顺序与请求顺序一致。这与 Zinx 的 `WorkerPool` 相同。
+
+use crate::conn_manager::{ConnectionHandle, RequestGuard};
+use crate::error::ZerustError;
+use crate::metrics::Metrics;
+use crate::request::Request;
+use crate::response::Response;
+use crate::router::{Router, handle_catching_panic};
+use crate::runtime::{Instant, JoinSet, Mutex, mpsc};
+use crate::server::ErrorHandler;
+use std::sync::Arc;
+
+/// 工作任务的队列已满时的处理策略
+///
+/// 通过 `WorkerPoolConfig::with_full_policy` 设置。
+#[derive(Debug, Clone, Default)]
+pub enum QueueFullPolicy {
+    /// 等待队列出现空位，期间暂停读取该连接的后续请求
+    ///
+    /// 未读取的数据会留在套接字的接收缓冲区中，形成背压。
+    #[default]
+    Wait,
+    /// 不处理该请求，直接回复繁忙消息
+    ///
+    /// 繁忙消息直接进入连接的发送队列，可能先于该连接排在队列中的请求的响应到达。
+    /// 发送队列已满时与普通响应一样等待出现空位，期间暂停读取该连接的后续请求。
+    Busy {
+        /// 回复给客户端的繁忙消息
+        busy_response: Response,
+    },
+}
+
+/// 工作池配置
+///
+/// # 示例
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use zerust::worker_pool::{QueueFullPolicy, WorkerPoolConfig};
+/// use zerust::{DefaultRouter, Response, Server};
+///
+/// // 4 个工作任务，每个最多排队 1024 个请求，队列满时回复繁忙消息
+/// let config = WorkerPoolConfig::new(4, 1024).with_full_policy(QueueFullPolicy::Busy {
+///     busy_response: Response::new(503, b"server busy".to_vec()),
+/// });
+/// let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+///     .with_worker_pool_config(config);
+/// ```
+#[derive(Debug, Clone)]
+pub struct WorkerPoolConfig {
+    /// 工作任务的数量
+    size: usize,
+    /// 每个工作任务最多排队的请求数量
+    max_task_queue_len: usize,
+    /// 队列已满时的处理策略
+    full_policy: QueueFullPolicy,
+}
+
+impl WorkerPoolConfig {
+    /// 创建工作池配置，队列已满时等待
+    ///
+    /// # 参数
+    /// * `size` - 工作任务的数量
+    /// * `max_task_queue_len` - 每个工作任务最多排队的请求数量
+    ///
+    /// 两者都必须大于 0，否则创建或启动服务器时返回 `ZerustError::InvalidConfig`
+    /// （参见 `ServerConfig::validate`）
+    pub fn new(size: usize, max_task_queue_len: usize) -> Self {
+        Self {
+            size,
+            max_task_queue_len,
+            full_policy: QueueFullPolicy::default(),
+        }
+    }
+
+    /// 设置队列已满时的处理策略
+    pub fn with_full_policy(mut self, policy: QueueFullPolicy) -> Self {
+        self.full_policy = policy;
+        self
+    }
+
+    /// 获取工作任务的数量
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 获取每个工作任务最多排队的请求数量
+    pub fn max_task_queue_len(&self) -> usize {
+        self.max_task_queue_len
+    }
+
+    /// 获取队列已满时的处理策略
+    pub fn full_policy(&self) -> &QueueFullPolicy {
+        &self.full_policy
+    }
+}
+
+/// 交给工作任务处理的请求
+struct Task {
+    /// 请求对象
+    req: Request,
+    /// 请求所属连接的句柄，响应通过它进入发送队列
+    handle: ConnectionHandle,
+    /// 在请求处理完成前保持连接的等待通道打开，参见 `WorkerPool::dispatch`
+    _pending: mpsc::Sender<()>,
+    /// 在响应进入发送队列前阻止连接因空闲被关闭
+    _in_flight: RequestGuard,
+    /// 分发请求时所在的 span，工作任务在其中处理该请求
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+/// 运行中的工作池
+///
+/// 由服务器在开始接受连接时创建，所有连接共享。
+pub(crate) struct WorkerPool {
+    /// 每个工作任务的请求队列
+    queues: Vec<mpsc::Sender<Task>>,
+    /// 队列已满时的处理策略
+    full_policy: QueueFullPolicy,
+}
+
+impl WorkerPool {
+    /// 启动工作任务
+    ///
+    /// 工作任务加入 `workers`，在所有队列的发送端被丢弃后结束。
+    pub(crate) fn start(
+        config: &WorkerPoolConfig,
+        router: Arc<dyn Router + Send + Sync>,
+        error_handler: ErrorHandler,
+        metrics: Arc<Metrics>,
+        workers: &mut JoinSet<()>,
+    ) -> Self {
+        let queues = (0..config.size)
+            .map(|_| {
+                let (tx, rx) = mpsc::channel(config.max_task_queue_len);
+                workers.spawn(Self::supervise(
+                    Arc::new(Mutex::new(rx)),
+                    router.clone(),
+                    error_handler.clone(),
+                    metrics.clone(),
+                ));
+                tx
+            })
+            .collect();
+        Self {
+            queues,
+            full_policy: config.full_policy.clone(),
+        }
+    }
+
+    /// 把请求交给处理该连接的工作任务
+    ///
+    /// `pending` 会随请求一起保存到处理完成，连接结束前通过它等待所有已分发的请求处理完毕；
+    /// `in_flight` 同样保存到处理完成，在此之前连接不会因空闲被关闭。
+    ///
+    /// # 返回值
+    /// * `Ok(())` - 请求已进入队列，或者已回复繁忙消息
+    /// * `Err(ZerustError::ConnectionClosed)` - 连接已经关闭
+    pub(crate) async fn dispatch(
+        &self,
+        req: Request,
+        handle: &ConnectionHandle,
+        pending: &mpsc::Sender<()>,
+        in_flight: RequestGuard,
+    ) -> Result<(), ZerustError> {
+        let queue = &self.queues[(handle.conn_id() % self.queues.len() as u64) as usize];
+        let task = Task {
+            req,
+            handle: handle.clone(),
+            _pending: pending.clone(),
+            _in_flight: in_flight,
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+        };
+        // 工作任务在服务器结束前不会退出，队列不会被关闭
+        match &self.full_policy {
+            QueueFullPolicy::Wait => {
+                let _ = queue.send(task).await;
+            }
+            QueueFullPolicy::Busy { busy_response } => {
+                if let Err(mpsc::error::TrySendError::Full(task)) = queue.try_send(task) {
+                    let busy = busy_response.clone().inherit_seq(task.req.seq());
+                    handle.send_wait(busy).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 运行一个工作任务，工作任务意外 panic 时重新启动
+    ///
+    /// 处理函数的 panic 已经被转换为错误响应，不会导致工作任务结束；
+    /// 其他原因导致的 panic 后，队列中的请求继续由新的工作任务处理。
+    async fn supervise(
+        queue: Arc<Mutex<mpsc::Receiver<Task>>>,
+        router: Arc<dyn Router + Send + Sync>,
+        error_handler: ErrorHandler,
+        metrics: Arc<Metrics>,
+    ) {
+        loop {
+            // 放在 JoinSet 中，工作池被关闭时一并结束
+            let mut worker = JoinSet::new();
+            worker.spawn(Self::work(
+                queue.clone(),
+                router.clone(),
+                error_handler.clone(),
+                metrics.clone(),
+            ));
+            match worker.join_next().await {
+                Some(Err(e)) if e.is_panic() => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("worker panicked, restarting");
+                    continue;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// 依次处理队列中的请求，直到队列的发送端全部被丢弃
+    async fn work(
+        queue: Arc<Mutex<mpsc::Receiver<Task>>>,
+        router: Arc<dyn Router + Send + Sync>,
+        error_handler: ErrorHandler,
+        metrics: Arc<Metrics>,
+    ) {
+        let mut queue = queue.lock().await;
+        while let Some(task) = queue.recv().await {
+            let (msg_id, seq, len) = (task.req.msg_id(), task.req.seq(), task.req.data().len());
+            let routed = router.has_route(msg_id);
+            let started = Instant::now();
+            let handling = handle_catching_panic(router.as_ref(), task.req);
+            #[cfg(feature = "tracing")]
+            let handling = tracing::Instrument::instrument(handling, task.span.clone());
+            let result = handling.await;
+            metrics.record_request(msg_id, routed, len, started.elapsed(), result.is_err());
+            let resp = match result {
+                Ok(resp) => resp,
+                Err(e) => {
+                    #[cfg(feature = "tracing")]
+                    task.span
+                        .in_scope(|| tracing::debug!(error = %e, "handler returned an error"));
+                    error_handler(msg_id, &e)
+                }
+            };
+            // 与连接任务直接处理时一样等待发送队列出现空位，响应不受 `WriteQueuePolicy` 影响；
+            // 连接结束（包括写入超时和被强制关闭）时发送队列的接收端随之释放，
+            // 等待立即结束，不读取数据的客户端不会一直占住工作任务
+            let _ = task.handle.send_wait(resp.inherit_seq(seq)).await;
+        }
+    }
+}
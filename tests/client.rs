@@ -0,0 +1,382 @@
+//! # 客户端测试
+//!
+//! 通过 `Client`、`ClientPool`、`ReconnectingClient` 和 `PipelineClient` 访问同一进程中运行的服务器。
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use zerust::client::{ConnectionState, PendingPolicy, ReconnectPolicy};
+use zerust::codec::SeqDataPack;
+use zerust::control::{PING_MSG_ID, PONG_MSG_ID};
+use zerust::datapack::DataPack;
+use zerust::server::ShutdownReport;
+use zerust::{
+    Client, ClientPool, DefaultRouter, PipelineClient, ReconnectingClient, Response, Server,
+    ZerustError,
+};
+
+/// 启动一个回显服务器，响应中附带处理该请求的连接ID
+async fn start_echo(
+    server: impl FnOnce(Arc<DefaultRouter>) -> Server,
+) -> (
+    SocketAddr,
+    oneshot::Sender<()>,
+    JoinHandle<Result<ShutdownReport, ZerustError>>,
+) {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    router
+        .add_route(2, |req| {
+            Response::new(req.msg_id(), req.conn_id().to_le_bytes().to_vec())
+        })
+        .unwrap();
+    let bound = server(router).bind().await.unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let handle = tokio::spawn(bound.run(shutdown_rx));
+    (addr, shutdown_tx, handle)
+}
+
+#[tokio::test]
+async fn client_round_trips_requests_in_order() {
+    let (addr, shutdown_tx, server_handle) =
+        start_echo(|router| Server::new("127.0.0.1:0", router)).await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    // 与服务器的默认设置相同，客户端的连接禁用 Nagle 算法
+    assert!(client.connection().nodelay().unwrap());
+    for i in 0..10u8 {
+        let resp = client.request(1, &[i; 3]).await.unwrap();
+        assert_eq!((resp.msg_id(), resp.data()), (1, &[i; 3][..]));
+    }
+    // 未注册的消息ID得到错误码为 404 的错误响应，连接仍然可用
+    let resp = client.request(9, b"").await.unwrap();
+    assert_eq!(
+        (resp.msg_id(), resp.error_body()),
+        (9, Some((404, "Route not found")))
+    );
+    assert_eq!(client.request(1, b"ok").await.unwrap().data(), b"ok");
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn client_decodes_error_envelope() {
+    let (addr, shutdown_tx, server_handle) = start_echo(|router| {
+        // 两个 u32 相除，除数为 0 时返回错误响应
+        router
+            .add_route(4, |req| {
+                let Some((a, b)) = req.data().split_first_chunk::<4>() else {
+                    return Response::error(req.msg_id(), 400, "expected two u32 values");
+                };
+                let (a, b) = (
+                    u32::from_le_bytes(*a),
+                    u32::from_le_bytes(b.try_into().unwrap()),
+                );
+                match a.checked_div(b) {
+                    Some(q) => Response::ok(req.msg_id(), q.to_le_bytes().to_vec()),
+                    None => Response::error(req.msg_id(), 422, "division by zero"),
+                }
+            })
+            .unwrap();
+        Server::new("127.0.0.1:0", router)
+    })
+    .await;
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let args = |a: u32, b: u32| [a.to_le_bytes(), b.to_le_bytes()].concat();
+    let resp = client.request(4, &args(42, 6)).await.unwrap();
+    assert_eq!((resp.msg_id(), resp.data()), (4, &7u32.to_le_bytes()[..]));
+
+    // 数据为小端序的错误码加上 UTF-8 的错误描述
+    let resp = client.request(4, &args(1, 0)).await.unwrap();
+    assert_eq!(resp.msg_id(), 4);
+    assert_eq!(
+        resp.data(),
+        [&422u32.to_le_bytes()[..], b"division by zero"].concat()
+    );
+    assert_eq!(resp.error_body(), Some((422, "division by zero")));
+    let resp = client.request(4, b"").await.unwrap();
+    assert_eq!(resp.error_body(), Some((400, "expected two u32 values")));
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn request_times_out_without_response() {
+    let (addr, shutdown_tx, server_handle) = start_echo(|router| {
+        // 不回复的处理函数
+        router.add_route(3, |_| Response::none()).unwrap();
+        Server::new("127.0.0.1:0", router)
+    })
+    .await;
+
+    let mut client = Client::connect(addr)
+        .await
+        .unwrap()
+        .with_request_timeout(Some(Duration::from_millis(50)));
+    assert_eq!(client.request_timeout(), Some(Duration::from_millis(50)));
+    assert_eq!(client.request(1, b"fast").await.unwrap().data(), b"fast");
+    let err = client.request(3, b"").await.unwrap_err();
+    assert!(matches!(err, ZerustError::Timeout));
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn pool_spreads_requests_across_connections() {
+    let (addr, shutdown_tx, server_handle) =
+        start_echo(|router| Server::new("127.0.0.1:0", router)).await;
+
+    let pool = Arc::new(ClientPool::connect(addr, 3).await.unwrap());
+    assert_eq!((pool.size(), pool.addr()), (3, addr));
+    let mut tasks = Vec::new();
+    for _ in 0..12 {
+        let pool = pool.clone();
+        tasks.push(tokio::spawn(async move { pool.request(2, b"").await }));
+    }
+    let mut conn_ids = HashSet::new();
+    for task in tasks {
+        let resp = task.await.unwrap().unwrap();
+        conn_ids.insert(u64::from_le_bytes(resp.data().try_into().unwrap()));
+    }
+    // 轮流使用三个连接
+    assert_eq!(conn_ids.len(), 3);
+
+    drop(pool);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn pool_reconnects_after_failed_request() {
+    let (addr, shutdown_tx, server_handle) =
+        start_echo(|router| Server::new("127.0.0.1:0", router).with_max_packet_size(8)).await;
+
+    let pool = ClientPool::connect(addr, 1).await.unwrap();
+    // 消息过大，服务器关闭连接
+    let err = pool.request(1, &[0; 16]).await.unwrap_err();
+    assert!(matches!(
+        err,
+        ZerustError::ConnectionClosed | ZerustError::IoError(_)
+    ));
+    // 下一个请求使用新的连接
+    let resp = pool.request(1, b"again").await.unwrap();
+    assert_eq!(resp.data(), b"again");
+
+    drop(pool);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn reconnecting_client_recovers_after_server_restart() {
+    let (addr, shutdown_tx, server_handle) =
+        start_echo(|router| Server::new("127.0.0.1:0", router)).await;
+
+    let states = Arc::new(Mutex::new(Vec::new()));
+    let seen = states.clone();
+    let policy = ReconnectPolicy::new()
+        .with_initial_delay(Duration::from_millis(10))
+        .with_max_delay(Duration::from_millis(50))
+        .with_pending_policy(PendingPolicy::RetryPending);
+    let client = Arc::new(
+        ReconnectingClient::new(addr)
+            .with_policy(policy)
+            .with_on_state_change(move |state| seen.lock().unwrap().push(state)),
+    );
+    assert_eq!(client.state(), ConnectionState::Disconnected);
+    assert_eq!(
+        client.request(1, b"before").await.unwrap().data(),
+        b"before"
+    );
+
+    // 停止服务器，在服务器重新启动之前发出的请求会一直重试连接
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    let pending = tokio::spawn({
+        let client = client.clone();
+        async move { client.request(1, b"after").await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!pending.is_finished());
+
+    let (_, shutdown_tx, server_handle) =
+        start_echo(|router| Server::new(&addr.to_string(), router)).await;
+    let resp = pending.await.unwrap().unwrap();
+    assert_eq!(resp.data(), b"after");
+    assert_eq!(
+        *states.lock().unwrap(),
+        [
+            ConnectionState::Connected,
+            ConnectionState::Disconnected,
+            ConnectionState::Connected
+        ]
+    );
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn reconnecting_client_gives_up_after_max_attempts() {
+    let (addr, shutdown_tx, server_handle) =
+        start_echo(|router| Server::new("127.0.0.1:0", router)).await;
+
+    let policy = ReconnectPolicy::new()
+        .with_initial_delay(Duration::from_millis(10))
+        .with_max_attempts(Some(3));
+    let client = ReconnectingClient::new(addr).with_policy(policy);
+    client.ensure_connected().await.unwrap();
+    assert_eq!(client.state(), ConnectionState::Connected);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    // 默认的 FailPending 使断开时的请求返回错误，之后的请求在尝试 3 次后放弃
+    assert!(client.request(1, b"lost").await.is_err());
+    assert_eq!(client.state(), ConnectionState::Disconnected);
+    assert!(matches!(
+        client.request(1, b"again").await,
+        Err(ZerustError::IoError(_))
+    ));
+}
+
+// 处理函数中使用了 Tokio 的任务和定时器
+#[cfg(feature = "tokio-runtime")]
+#[tokio::test]
+async fn pipeline_matches_out_of_order_responses() {
+    let router = Arc::new(DefaultRouter::new());
+    // 请求数据为延迟的毫秒数，处理函数在后台任务中延迟回复，先到的请求后得到响应
+    router
+        .add_async_route(1, |req| async move {
+            let handle = req.connection().unwrap().clone();
+            let seq = req.seq().unwrap();
+            let data = req.data().to_vec();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(data[0] as u64)).await;
+                let _ = handle.send(Response::new(1, data).with_seq(seq));
+            });
+            Response::none()
+        })
+        .unwrap();
+    // 同步回复的处理函数不需要手动设置序列号
+    router
+        .add_route(2, |req| Response::new(2, req.data().to_vec()))
+        .unwrap();
+    let bound = Server::new("127.0.0.1:0", router)
+        .with_codec(Arc::new(SeqDataPack::new()))
+        .bind()
+        .await
+        .unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(bound.run(shutdown_rx));
+
+    let client = Arc::new(PipelineClient::connect(addr).await.unwrap());
+    let finished = Arc::new(Mutex::new(Vec::new()));
+    let mut tasks = Vec::new();
+    for delay in [120u8, 60, 0] {
+        let (client, finished) = (client.clone(), finished.clone());
+        tasks.push(tokio::spawn(async move {
+            let resp = client.request(1, &[delay]).await.unwrap();
+            assert_eq!((resp.msg_id(), resp.data()), (1, &[delay][..]));
+            finished.lock().unwrap().push(delay);
+        }));
+        // 保证请求按顺序发出
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(*finished.lock().unwrap(), [0, 60, 120]);
+    assert_eq!(client.request(2, b"sync").await.unwrap().data(), b"sync");
+    assert_eq!(client.pending(), 0);
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn pipeline_delivers_unmatched_messages_to_push_hook() {
+    let router = Arc::new(DefaultRouter::new());
+    // 回复之前先主动推送一条没有序列号的消息
+    router
+        .add_route(1, |req| {
+            let handle = req.connection().unwrap();
+            let _ = handle.send(Response::new(100, b"notice".to_vec()));
+            Response::new(1, req.data().to_vec())
+        })
+        .unwrap();
+    let bound = Server::new("127.0.0.1:0", router)
+        .with_codec(Arc::new(SeqDataPack::new()))
+        .bind()
+        .await
+        .unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(bound.run(shutdown_rx));
+
+    let (push_tx, mut push_rx) = tokio::sync::mpsc::unbounded_channel();
+    let client = PipelineClient::connect(addr)
+        .await
+        .unwrap()
+        .with_on_push(move |msg| {
+            let _ = push_tx.send(msg);
+        });
+    let resp = client.request(1, b"hi").await.unwrap();
+    assert_eq!((resp.msg_id(), resp.data()), (1, &b"hi"[..]));
+    let pushed = push_rx.recv().await.unwrap();
+    assert_eq!((pushed.msg_id(), pushed.data()), (100, &b"notice"[..]));
+    assert_eq!(client.pending(), 0);
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn ping_matches_its_pong_and_keeps_other_messages() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    // 模拟的服务器在回复 pong 之前先推送一条消息和一个过期的 pong
+    let peer = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut header = [0u8; DataPack::HEADER_SIZE];
+        stream.read_exact(&mut header).await.unwrap();
+        let (msg_id, data_len) = DataPack::unpack_header(&header).unwrap();
+        assert_eq!(msg_id, PING_MSG_ID);
+        let mut nonce = vec![0u8; data_len as usize];
+        stream.read_exact(&mut nonce).await.unwrap();
+
+        let mut out = DataPack::try_pack(5, b"pushed").unwrap();
+        out.extend_from_slice(&DataPack::try_pack(PONG_MSG_ID, b"stale").unwrap());
+        out.extend_from_slice(&DataPack::try_pack(PONG_MSG_ID, &nonce).unwrap());
+        out.extend_from_slice(&DataPack::try_pack(PONG_MSG_ID, b"late").unwrap());
+        out.extend_from_slice(&DataPack::try_pack(6, b"after").unwrap());
+        stream.write_all(&out).await.unwrap();
+        stream
+    });
+
+    let mut client = Client::connect(addr).await.unwrap();
+    client.ping(Duration::from_secs(5)).await.unwrap();
+    // 等待期间收到的消息按顺序返回，多余的 pong 被忽略
+    let pushed = client.recv().await.unwrap();
+    assert_eq!((pushed.msg_id(), pushed.data()), (5, &b"pushed"[..]));
+    let after = client.recv().await.unwrap();
+    assert_eq!((after.msg_id(), after.data()), (6, &b"after"[..]));
+    drop(peer.await.unwrap());
+}
@@ -0,0 +1,420 @@
+//! # 帧编解码测试
+
+use bytes::{Buf, Bytes, BytesMut};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use zerust::codec::{CheckedDataPack, ChecksumPolicy, LengthPrefixCodec, PacketCodec, SeqDataPack};
+use zerust::datapack::{ByteOrderMode, DataPack};
+use zerust::{Client, ClientPool, DefaultRouter, Request, Response, Server, ZerustError};
+
+/// 私有线路格式：2 字节魔数 + u16 消息ID + u32 数据长度（均为大端序）+ 数据 + 1 字节校验和
+struct LegacyCodec;
+
+impl LegacyCodec {
+    const MAGIC: [u8; 2] = [0xCA, 0xFE];
+    const HEADER_SIZE: usize = 8;
+
+    /// 所有数据字节的异或值
+    fn checksum(data: &[u8]) -> u8 {
+        data.iter().fold(0, |acc, b| acc ^ b)
+    }
+}
+
+impl PacketCodec for LegacyCodec {
+    fn encode(&self, msg_id: u32, data: &[u8]) -> Result<Vec<u8>, ZerustError> {
+        let msg_id = u16::try_from(msg_id)
+            .map_err(|_| ZerustError::ProtocolError("msg_id exceeds u16".into()))?;
+        let mut buf = Self::MAGIC.to_vec();
+        buf.extend_from_slice(&msg_id.to_be_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        buf.extend_from_slice(data);
+        buf.push(Self::checksum(data));
+        Ok(buf)
+    }
+
+    fn decode(
+        &self,
+        buf: &mut BytesMut,
+        max_len: u32,
+    ) -> Result<Option<(u32, Bytes)>, ZerustError> {
+        if buf.len() < Self::HEADER_SIZE {
+            return Ok(None);
+        }
+        if buf[..2] != Self::MAGIC {
+            return Err(ZerustError::InvalidHeader);
+        }
+        let msg_id = u16::from_be_bytes([buf[2], buf[3]]) as u32;
+        let data_len = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+        if data_len > max_len {
+            return Err(ZerustError::MessageTooLarge {
+                size: data_len as u64,
+                limit: max_len as u64,
+            });
+        }
+        let frame_len = Self::HEADER_SIZE + data_len as usize + 1;
+        if buf.len() < frame_len {
+            return Ok(None);
+        }
+        buf.advance(Self::HEADER_SIZE);
+        let data = buf.split_to(data_len as usize).freeze();
+        if buf.get_u8() != Self::checksum(&data) {
+            return Err(ZerustError::ProtocolError("checksum mismatch".into()));
+        }
+        Ok(Some((msg_id, data)))
+    }
+}
+
+#[test]
+fn datapack_decode_waits_for_complete_frame() {
+    let codec = DataPack::default();
+    let frame = DataPack::try_pack(5, b"partial").unwrap();
+
+    // 数据不足时不消耗缓冲区
+    let mut buf = BytesMut::from(&frame[..10]);
+    assert_eq!(codec.decode(&mut buf, 1024).unwrap(), None);
+    assert_eq!(buf.len(), 10);
+
+    // 补齐数据后解析出完整的消息，并保留后续数据
+    buf.extend_from_slice(&frame[10..]);
+    buf.extend_from_slice(&DataPack::try_pack(6, b"").unwrap()[..4]);
+    assert_eq!(
+        codec.decode(&mut buf, 1024).unwrap(),
+        Some((5, Bytes::from_static(b"partial")))
+    );
+    assert_eq!(buf.len(), 4);
+}
+
+#[test]
+fn datapack_decode_shares_receive_buffer() {
+    let codec = DataPack::default();
+    let mut buf = BytesMut::new();
+    buf.extend_from_slice(&DataPack::try_pack(1, b"first").unwrap());
+    buf.extend_from_slice(&DataPack::try_pack(2, b"second").unwrap());
+    let base = buf.as_ptr() as usize;
+
+    // 消息体是接收缓冲区的切片，而不是新分配的内存
+    let (_, first) = codec.decode(&mut buf, 1024).unwrap().unwrap();
+    let (_, second) = codec.decode(&mut buf, 1024).unwrap().unwrap();
+    assert_eq!(first.as_ptr() as usize, base + DataPack::HEADER_SIZE);
+    assert_eq!(
+        second.as_ptr() as usize,
+        base + 2 * DataPack::HEADER_SIZE + first.len()
+    );
+
+    // 请求与解析出的数据共享同一块内存
+    let req = Request::from_bytes(2, second.clone());
+    assert_eq!(req.data_bytes().as_ptr(), second.as_ptr());
+    assert_eq!(req.data(), b"second");
+}
+
+#[test]
+fn datapack_decode_rejects_oversized_header() {
+    let codec = DataPack::default();
+    let mut buf = BytesMut::from(&DataPack::try_pack(1, &[0u8; 32]).unwrap()[..8]);
+    assert!(matches!(
+        codec.decode(&mut buf, 16),
+        Err(ZerustError::MessageTooLarge {
+            size: 32,
+            limit: 16
+        })
+    ));
+}
+
+/// 编码后再解码，返回解析出的消息，并检查缓冲区已被完全消耗
+fn round_trip(codec: &dyn PacketCodec, msg_id: u32, data: &[u8]) -> (u32, Bytes) {
+    let mut buf = BytesMut::from(&codec.encode(msg_id, data).unwrap()[..]);
+    let decoded = codec.decode(&mut buf, 1024).unwrap().unwrap();
+    assert!(buf.is_empty());
+    decoded
+}
+
+#[test]
+fn codecs_round_trip() {
+    for order in [ByteOrderMode::Little, ByteOrderMode::Big] {
+        let datapack = DataPack::with_order(order);
+        assert_eq!(
+            round_trip(&datapack, 7, b"hello"),
+            (7, Bytes::from_static(b"hello"))
+        );
+        assert_eq!(round_trip(&datapack, 8, b""), (8, Bytes::new()));
+
+        // 长度前缀格式不携带消息ID，解析出的消息都使用配置的消息ID
+        let length_prefix = LengthPrefixCodec::new().with_order(order).with_msg_id(3);
+        assert_eq!(
+            round_trip(&length_prefix, 7, b"hello"),
+            (3, Bytes::from_static(b"hello"))
+        );
+        assert_eq!(round_trip(&length_prefix, 8, b""), (3, Bytes::new()));
+    }
+
+    let frame = LengthPrefixCodec::new()
+        .with_order(ByteOrderMode::Big)
+        .encode(1, b"abc")
+        .unwrap();
+    assert_eq!(frame, [0, 0, 0, 3, b'a', b'b', b'c']);
+}
+
+#[test]
+fn checked_datapack_verifies_checksum() {
+    for order in [ByteOrderMode::Little, ByteOrderMode::Big] {
+        let codec = CheckedDataPack::new().with_order(order);
+        assert_eq!(
+            round_trip(&codec, 7, b"hello"),
+            (7, Bytes::from_static(b"hello"))
+        );
+        assert_eq!(round_trip(&codec, 8, b""), (8, Bytes::new()));
+    }
+
+    // 校验和到达之前不解析该帧
+    let codec = CheckedDataPack::new();
+    let frame = codec.encode(1, b"payload").unwrap();
+    let mut buf = BytesMut::from(&frame[..frame.len() - 1]);
+    assert!(codec.decode(&mut buf, 1024).unwrap().is_none());
+    buf.extend_from_slice(&frame[frame.len() - 1..]);
+    assert_eq!(
+        codec.decode(&mut buf, 1024).unwrap(),
+        Some((1, Bytes::from_static(b"payload")))
+    );
+
+    let mut corrupted = frame.clone();
+    corrupted[DataPack::HEADER_SIZE] ^= 0x80;
+    let mut buf = BytesMut::from(&corrupted[..]);
+    assert!(matches!(
+        codec.decode(&mut buf, 1024),
+        Err(ZerustError::ChecksumMismatch { .. })
+    ));
+}
+
+#[test]
+fn checked_datapack_can_skip_corrupted_frames() {
+    let codec = CheckedDataPack::new().with_mismatch_policy(ChecksumPolicy::Skip);
+    let mut corrupted = codec.encode(1, b"flaky").unwrap();
+    corrupted[DataPack::HEADER_SIZE] ^= 0x01;
+
+    // 只有损坏的帧时等待更多数据，损坏的帧已被丢弃
+    let mut buf = BytesMut::from(&corrupted[..]);
+    assert!(codec.decode(&mut buf, 1024).unwrap().is_none());
+    assert!(buf.is_empty());
+
+    // 损坏的帧之后的帧正常解析
+    let mut buf = BytesMut::from(&corrupted[..]);
+    buf.extend_from_slice(&codec.encode(2, b"intact").unwrap());
+    assert_eq!(
+        codec.decode(&mut buf, 1024).unwrap(),
+        Some((2, Bytes::from_static(b"intact")))
+    );
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn seq_datapack_carries_sequence_ids() {
+    for order in [ByteOrderMode::Little, ByteOrderMode::Big] {
+        let codec = SeqDataPack::default().with_order(order);
+        let mut frame = Vec::new();
+        codec
+            .encode_seq_into(5, Some(9), b"seq", &mut frame)
+            .unwrap();
+        assert_eq!(frame.len(), SeqDataPack::HEADER_SIZE + 3);
+
+        let mut buf = BytesMut::from(&frame[..SeqDataPack::HEADER_SIZE]);
+        assert_eq!(codec.decode_seq(&mut buf, 1024).unwrap(), None);
+        buf.extend_from_slice(&frame[SeqDataPack::HEADER_SIZE..]);
+        assert_eq!(
+            codec.decode_seq(&mut buf, 1024).unwrap(),
+            Some((5, Some(9), Bytes::from_static(b"seq")))
+        );
+        assert!(buf.is_empty());
+
+        // 不携带序列号的消息使用 0，按普通帧解析时序列号被丢弃
+        assert_eq!(
+            round_trip(&codec, 7, b"hello"),
+            (7, Bytes::from_static(b"hello"))
+        );
+    }
+
+    let frame = SeqDataPack::default()
+        .with_order(ByteOrderMode::Big)
+        .encode(1, b"")
+        .unwrap();
+    assert_eq!(frame, [0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    // 普通编解码工具解析时序列号为 None
+    let datapack = DataPack::default();
+    let mut buf = BytesMut::from(&PacketCodec::encode(&datapack, 2, b"x").unwrap()[..]);
+    assert_eq!(
+        datapack.decode_seq(&mut buf, 1024).unwrap(),
+        Some((2, None, Bytes::from_static(b"x")))
+    );
+}
+
+#[test]
+fn length_prefix_decode_waits_and_checks_limit() {
+    let codec = LengthPrefixCodec::new();
+    let frame = codec.encode(0, b"partial").unwrap();
+
+    let mut buf = BytesMut::from(&frame[..6]);
+    assert_eq!(codec.decode(&mut buf, 1024).unwrap(), None);
+    assert_eq!(buf.len(), 6);
+    buf.extend_from_slice(&frame[6..]);
+    assert_eq!(
+        codec.decode(&mut buf, 1024).unwrap(),
+        Some((0, Bytes::from_static(b"partial")))
+    );
+
+    let mut buf = BytesMut::from(&codec.encode(0, &[0u8; 32]).unwrap()[..4]);
+    assert!(matches!(
+        codec.decode(&mut buf, 16),
+        Err(ZerustError::MessageTooLarge {
+            size: 32,
+            limit: 16
+        })
+    ));
+}
+
+#[tokio::test]
+async fn server_uses_custom_codec() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let bound = Server::new("127.0.0.1:0", router)
+        .with_codec(Arc::new(LegacyCodec))
+        .bind()
+        .await
+        .unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(async move { bound.run(shutdown_rx).await });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = LegacyCodec.encode(1, b"legacy").unwrap();
+    stream.write_all(&request).await.unwrap();
+    let mut frame = vec![0u8; request.len()];
+    stream.read_exact(&mut frame).await.unwrap();
+    assert_eq!(frame, request);
+
+    // 校验和错误的帧会导致连接关闭
+    let mut corrupted = LegacyCodec.encode(1, b"bad").unwrap();
+    *corrupted.last_mut().unwrap() ^= 0xFF;
+    stream.write_all(&corrupted).await.unwrap();
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn server_with_checksum_closes_on_corrupted_frame() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let (error_tx, mut error_rx) = tokio::sync::mpsc::unbounded_channel();
+    let bound = Server::new("127.0.0.1:0", router)
+        .with_checksum(true)
+        .with_on_conn_error(move |_, e| {
+            let _ = error_tx.send(matches!(e, ZerustError::ChecksumMismatch { .. }));
+        })
+        .bind()
+        .await
+        .unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(async move { bound.run(shutdown_rx).await });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = DataPack::try_pack_checked(1, b"intact").unwrap();
+    stream.write_all(&request).await.unwrap();
+    let mut frame = vec![0u8; request.len()];
+    stream.read_exact(&mut frame).await.unwrap();
+    assert_eq!(frame, request);
+
+    let mut corrupted = DataPack::try_pack_checked(1, b"flaky link").unwrap();
+    corrupted[DataPack::HEADER_SIZE + 3] ^= 0x10;
+    stream.write_all(&corrupted).await.unwrap();
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    assert!(error_rx.recv().await.unwrap());
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn server_skips_corrupted_frames_and_client_helpers_agree() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let codec = Arc::new(CheckedDataPack::new().with_mismatch_policy(ChecksumPolicy::Skip));
+    let bound = Server::new("127.0.0.1:0", router)
+        .with_codec(codec.clone())
+        .bind()
+        .await
+        .unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(async move { bound.run(shutdown_rx).await });
+
+    // 损坏的帧被丢弃，连接保持，之后的请求正常得到响应
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut frames = DataPack::try_pack_checked(1, b"flaky link").unwrap();
+    frames[DataPack::HEADER_SIZE + 3] ^= 0x10;
+    let request = DataPack::try_pack_checked(1, b"intact").unwrap();
+    frames.extend_from_slice(&request);
+    stream.write_all(&frames).await.unwrap();
+    let mut frame = vec![0u8; request.len()];
+    stream.read_exact(&mut frame).await.unwrap();
+    assert_eq!(frame, request);
+
+    let mut client = Client::connect_with_codec(addr, codec.clone())
+        .await
+        .unwrap();
+    assert_eq!(client.request(1, b"ping").await.unwrap().data(), b"ping");
+    let pool = ClientPool::connect_with_codec(addr, 2, codec)
+        .await
+        .unwrap();
+    assert_eq!(pool.request(1, b"pong").await.unwrap().data(), b"pong");
+
+    drop((stream, client, pool));
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn server_routes_length_prefixed_frames_to_one_handler() {
+    let router = Arc::new(DefaultRouter::new());
+    // 根据数据内容自行分发
+    router
+        .add_route(0, |req| match req.data() {
+            b"ping" => Response::new(0, b"pong".to_vec()),
+            other => Response::new(0, other.to_ascii_uppercase()),
+        })
+        .unwrap();
+    let codec = LengthPrefixCodec::new();
+    let bound = Server::new("127.0.0.1:0", router)
+        .with_codec(Arc::new(codec))
+        .bind()
+        .await
+        .unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(async move { bound.run(shutdown_rx).await });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    for (request, expected) in [(&b"ping"[..], &b"pong"[..]), (b"hello", b"HELLO")] {
+        stream
+            .write_all(&codec.encode(0, request).unwrap())
+            .await
+            .unwrap();
+        let mut frame = vec![0u8; LengthPrefixCodec::HEADER_SIZE + expected.len()];
+        stream.read_exact(&mut frame).await.unwrap();
+        assert_eq!(frame, codec.encode(0, expected).unwrap());
+    }
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
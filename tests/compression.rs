@@ -0,0 +1,258 @@
+//! # 消息压缩测试
+//!
+//! 直接调用 `CompressedCodec` 检查帧格式，并通过开启了压缩的服务器完成请求。
+//! 需要开启 `compression` 功能；测试中直接使用 Tokio 的套接字，还需要开启 `tokio-runtime` 功能。
+
+#![cfg(all(feature = "compression", feature = "tokio-runtime"))]
+
+use bytes::{Bytes, BytesMut};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use zerust::codec::{PacketCodec, SeqDataPack};
+use zerust::compression::{CompressedCodec, CompressionAlgorithm, CompressionConfig};
+use zerust::connection::Connection;
+use zerust::datapack::{DEFAULT_MAX_PACKET_SIZE, DataPack};
+use zerust::{Client, DefaultRouter, Response, Server, ZerustError};
+
+/// 容易压缩的数据
+fn compressible(len: usize) -> Vec<u8> {
+    b"map tile 0123456789 "
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+/// 编码后再解码，返回帧和解码出的数据
+fn round_trip(codec: &CompressedCodec, data: &[u8]) -> (Vec<u8>, Bytes) {
+    let frame = codec.encode(7, data).unwrap();
+    let mut buf = BytesMut::from(&frame[..]);
+    let (msg_id, decoded) = codec
+        .decode(&mut buf, DEFAULT_MAX_PACKET_SIZE)
+        .unwrap()
+        .unwrap();
+    assert_eq!(msg_id, 7);
+    assert!(buf.is_empty());
+    (frame, decoded)
+}
+
+#[test]
+fn large_payloads_are_compressed() {
+    let data = compressible(4096);
+    for (algorithm, flag) in [
+        (CompressionAlgorithm::Zstd, 2),
+        (CompressionAlgorithm::Gzip, 1),
+    ] {
+        let codec = CompressedCodec::new(CompressionConfig::new(256).with_algorithm(algorithm));
+        let (frame, decoded) = round_trip(&codec, &data);
+        assert_eq!(decoded, data);
+        // 消息头之后是压缩标记，压缩后的帧明显小于原始数据
+        assert_eq!(frame[DataPack::HEADER_SIZE], flag);
+        assert!(frame.len() < data.len() / 4);
+    }
+}
+
+#[test]
+fn small_payloads_stay_uncompressed() {
+    let codec = CompressedCodec::new(CompressionConfig::new(256));
+    for data in [&b""[..], b"hello", &compressible(255)] {
+        let (frame, decoded) = round_trip(&codec, data);
+        assert_eq!(decoded, data);
+        assert_eq!(frame[DataPack::HEADER_SIZE], 0);
+        assert_eq!(frame.len(), DataPack::HEADER_SIZE + 1 + data.len());
+    }
+}
+
+#[test]
+fn incompressible_payloads_are_sent_as_is() {
+    // 伪随机数据压缩后不会变小
+    let mut state = 0x2545_f491_u32;
+    let data: Vec<u8> = (0..1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    let codec = CompressedCodec::new(CompressionConfig::new(16));
+    let (frame, decoded) = round_trip(&codec, &data);
+    assert_eq!(decoded, data);
+    assert_eq!(frame[DataPack::HEADER_SIZE], 0);
+}
+
+#[test]
+fn compressed_frames_keep_sequence_numbers() {
+    let codec = CompressedCodec::wrap(Arc::new(SeqDataPack::new()), CompressionConfig::new(64));
+    let data = compressible(1024);
+    let mut frame = Vec::new();
+    codec
+        .encode_seq_into(3, Some(42), &data, &mut frame)
+        .unwrap();
+    let mut buf = BytesMut::from(&frame[..]);
+    let (msg_id, seq, decoded) = codec
+        .decode_seq(&mut buf, DEFAULT_MAX_PACKET_SIZE)
+        .unwrap()
+        .unwrap();
+    assert_eq!((msg_id, seq, decoded), (3, Some(42), Bytes::from(data)));
+}
+
+#[test]
+fn decompressed_size_is_limited() {
+    // 1 MiB 的零压缩后只有几十字节，解压时仍然受最大消息体长度限制
+    let codec = CompressedCodec::new(CompressionConfig::new(0));
+    let frame = codec.encode(1, &vec![0; 1024 * 1024]).unwrap();
+    assert!(frame.len() < 1024);
+    let mut buf = BytesMut::from(&frame[..]);
+    let err = codec.decode(&mut buf, 1024).unwrap_err();
+    assert!(matches!(
+        err,
+        ZerustError::MessageTooLarge { limit: 1024, .. }
+    ));
+}
+
+#[test]
+fn corrupt_compressed_data_is_rejected() {
+    let mut frame = DataPack::try_pack(1, &[2, 0xde, 0xad, 0xbe, 0xef]).unwrap();
+    let mut buf = BytesMut::from(&frame[..]);
+    let codec = CompressedCodec::new(CompressionConfig::new(0));
+    assert!(matches!(
+        codec.decode(&mut buf, 1024),
+        Err(ZerustError::ProtocolError(_))
+    ));
+    // 未知的压缩标记
+    frame[DataPack::HEADER_SIZE] = 9;
+    let mut buf = BytesMut::from(&frame[..]);
+    assert!(matches!(
+        codec.decode(&mut buf, 1024),
+        Err(ZerustError::ProtocolError(_))
+    ));
+}
+
+#[test]
+fn forced_compression_ignores_threshold() {
+    let codec = CompressedCodec::new(CompressionConfig::new(1 << 20));
+    let data = compressible(4096);
+    let mut frame = Vec::new();
+    codec
+        .encode_compressed_into(7, None, &data, &mut frame)
+        .unwrap();
+    assert_eq!(frame[DataPack::HEADER_SIZE], 2);
+    let mut buf = BytesMut::from(&frame[..]);
+    assert_eq!(
+        codec.decode(&mut buf, DEFAULT_MAX_PACKET_SIZE).unwrap(),
+        Some((7, Bytes::from(data.clone())))
+    );
+
+    // 不支持压缩的编解码工具按原样编码
+    let mut plain = Vec::new();
+    DataPack::default()
+        .encode_compressed_into(7, None, &data, &mut plain)
+        .unwrap();
+    assert_eq!(plain, DataPack::try_pack(7, &data).unwrap());
+}
+
+#[tokio::test]
+async fn new_compressed_responses_skip_the_threshold() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    router
+        .add_route(2, |req| {
+            Response::new_compressed(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    // 阈值很大，只有标记了压缩的响应会被压缩
+    let server = Server::new("127.0.0.1:0", router)
+        .with_compression(1 << 20)
+        .bind()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(server.run(shutdown_rx));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let data = compressible(8192);
+    for (msg_id, flag) in [(1, 0), (2, 2)] {
+        let mut frame = vec![0];
+        frame.extend_from_slice(&data);
+        stream
+            .write_all(&DataPack::try_pack(msg_id, &frame).unwrap())
+            .await
+            .unwrap();
+        let mut header = [0u8; DataPack::HEADER_SIZE];
+        stream.read_exact(&mut header).await.unwrap();
+        let (_, data_len) = DataPack::unpack_header(&header).unwrap();
+        let mut payload = vec![0; data_len as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        assert_eq!(payload[0], flag);
+    }
+
+    // 客户端看到的是解压后的数据
+    let conn = Connection::new(TcpStream::connect(addr).await.unwrap()).with_codec(Arc::new(
+        CompressedCodec::new(CompressionConfig::new(1 << 20)),
+    ));
+    let mut client = Client::new(conn);
+    assert_eq!(client.request(2, &data).await.unwrap().data(), &data[..]);
+
+    drop((client, stream));
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn server_compresses_large_responses() {
+    let router = Arc::new(DefaultRouter::new());
+    // 处理函数看到的是解压后的数据
+    router
+        .add_route(1, |req| {
+            assert!(req.data().starts_with(b"map tile"));
+            Response::from_bytes(req.msg_id(), req.data_bytes())
+        })
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router)
+        .with_compression(512)
+        .bind()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(server.run(shutdown_rx));
+
+    // 客户端使用 gzip，服务器使用 zstd，双方都能解压对方的数据
+    let config = CompressionConfig::new(512).with_algorithm(CompressionAlgorithm::Gzip);
+    let conn = Connection::new(TcpStream::connect(addr).await.unwrap())
+        .with_codec(Arc::new(CompressedCodec::new(config)));
+    let mut client = Client::new(conn);
+    for len in [16, 8192] {
+        let data = compressible(len);
+        assert_eq!(client.request(1, &data).await.unwrap().data(), &data[..]);
+    }
+
+    // 直接读取服务器发送的帧：小的响应未压缩，大的响应被压缩
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    for (len, flag) in [(16, 0), (8192, 2)] {
+        let mut frame = vec![0];
+        frame.extend_from_slice(&compressible(len));
+        stream
+            .write_all(&DataPack::try_pack(1, &frame).unwrap())
+            .await
+            .unwrap();
+        let mut header = [0u8; DataPack::HEADER_SIZE];
+        stream.read_exact(&mut header).await.unwrap();
+        let (_, data_len) = DataPack::unpack_header(&header).unwrap();
+        let mut payload = vec![0; data_len as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        assert_eq!(payload[0], flag);
+        assert!(payload.len() <= len + 1);
+    }
+
+    drop((client, stream));
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
@@ -0,0 +1,312 @@
+//! # TOML 配置文件测试
+//!
+//! 检查 `ServerConfig::from_toml_file` 的映射、校验和环境变量覆盖。需要开启 `toml` 功能。
+
+#![cfg(feature = "toml")]
+
+use std::path::PathBuf;
+use std::time::Duration;
+use zerust::config::TomlOptions;
+use zerust::server::ShutdownMode;
+use zerust::{ServerBuilder, ServerConfig, ZerustError};
+
+/// 不读取环境变量的选项，避免测试受到运行环境的影响
+fn no_env() -> TomlOptions {
+    TomlOptions::new().with_env_prefix(None)
+}
+
+/// 把配置写入临时文件，返回文件路径
+fn write_config(name: &str, text: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "zerust_config_{}_{}.toml",
+        name,
+        std::process::id()
+    ));
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+/// 解析配置并返回错误信息
+fn config_error(text: &str) -> String {
+    match ServerConfig::from_toml_str(text, &no_env()) {
+        Err(ZerustError::InvalidConfig(msg)) => msg,
+        other => panic!("expected InvalidConfig, got {other:?}"),
+    }
+}
+
+#[test]
+fn example_config_file_is_valid() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/zerust.toml");
+    let config = ServerConfig::from_toml_file_with(path, &no_env()).unwrap();
+
+    assert_eq!(config.addr(), "127.0.0.1:8999");
+    assert_eq!(config.max_connections(), Some(1000));
+    // 65536 字节的帧减去 8 字节的帧头
+    assert_eq!(config.max_packet_size(), 65528);
+    assert_eq!(config.read_buffer_size(), 8192);
+    assert_eq!(config.write_queue_capacity(), 1024);
+    assert!(config.nodelay());
+    assert_eq!(config.keepalive(), Some(Duration::from_secs(60)));
+    assert_eq!(config.read_timeout(), Some(Duration::from_secs(30)));
+    assert_eq!(config.write_timeout(), Some(Duration::from_secs(10)));
+    assert_eq!(config.idle_timeout(), Some(Duration::from_secs(300)));
+    assert_eq!(
+        config.shutdown_mode(),
+        ShutdownMode::Graceful {
+            timeout: Duration::from_secs(5)
+        }
+    );
+    let heartbeat = config.heartbeat().unwrap();
+    assert_eq!(heartbeat.interval(), Duration::from_secs(10));
+    assert_eq!(heartbeat.max_missed(), 3);
+    assert_eq!(config.worker_pool().unwrap().size(), 4);
+    let rate_limit = config.rate_limit().unwrap();
+    assert_eq!(rate_limit.max_per_sec(), 100);
+    assert_eq!(rate_limit.burst(), 200);
+
+    // 加载的配置可以直接用于创建服务器
+    let server = ServerBuilder::from_config(config).try_build().unwrap();
+    assert_eq!(server.config().max_connections(), Some(1000));
+}
+
+#[test]
+fn missing_keys_use_defaults() {
+    let config = ServerConfig::from_toml_str("", &no_env()).unwrap();
+    let default = ServerConfig::default();
+    assert_eq!(config.addr(), default.addr());
+    assert_eq!(config.max_packet_size(), default.max_packet_size());
+    assert_eq!(config.read_timeout(), None);
+    assert!(config.heartbeat().is_none());
+    assert!(config.rate_limit().is_none());
+}
+
+#[test]
+fn codec_table_selects_frame_format() {
+    let config = ServerConfig::from_toml_str(
+        "max_frame_size = 112\n[codec]\nbyte_order = \"big\"\nchecksum = true\n",
+        &no_env(),
+    )
+    .unwrap();
+    // 开启校验和后帧头和校验和共 12 字节
+    assert_eq!(config.max_packet_size(), 100);
+    let frame = config.codec().encode(1, b"ab").unwrap();
+    assert_eq!(frame.len(), 8 + 2 + 4);
+    assert_eq!(&frame[..4], &1u32.to_be_bytes());
+
+    let config =
+        ServerConfig::from_toml_str("[codec]\nheader_layout = \"zinx\"\n", &no_env()).unwrap();
+    let frame = config.codec().encode(1, b"ab").unwrap();
+    assert_eq!(&frame[..4], &2u32.to_le_bytes());
+}
+
+#[test]
+fn invalid_values_name_the_offending_key() {
+    let cases = [
+        ("max_frame_size = 8", "max_frame_size"),
+        (
+            "max_frame_size = 12\n[codec]\nchecksum = true",
+            "max_frame_size",
+        ),
+        ("max_connections = 0", "max_connections"),
+        ("read_buffer_size = 0", "read_buffer_size"),
+        ("write_queue_capacity = 0", "write_queue_capacity"),
+        ("keepalive_ms = 0", "keepalive_ms"),
+        ("read_timeout_ms = 0", "read_timeout_ms"),
+        ("write_timeout_ms = 0", "write_timeout_ms"),
+        ("idle_timeout_ms = 0", "idle_timeout_ms"),
+        ("shutdown_timeout_ms = 0", "shutdown_timeout_ms"),
+        ("listen_addr = \"localhost\"", "listen_addr"),
+        ("listen_addr = \"127.0.0.1:70000\"", "listen_addr"),
+        (
+            "[heartbeat]\ninterval_ms = 0\nmax_missed = 3",
+            "heartbeat.interval_ms",
+        ),
+        (
+            "[heartbeat]\ninterval_ms = 1000\nmax_missed = 0",
+            "heartbeat.max_missed",
+        ),
+        (
+            "[worker_pool]\nsize = 0\nmax_task_queue_len = 16",
+            "worker_pool.size",
+        ),
+        (
+            "[worker_pool]\nsize = 4\nmax_task_queue_len = 0",
+            "worker_pool.max_task_queue_len",
+        ),
+        ("[rate_limit]\nmax_per_sec = 0", "rate_limit.max_per_sec"),
+        (
+            "[rate_limit]\nmax_per_sec = 10\nburst = 0",
+            "rate_limit.burst",
+        ),
+        (
+            "[codec]\nheader_layout = \"zinx\"\nchecksum = true",
+            "codec.checksum",
+        ),
+    ];
+    for (text, key) in cases {
+        let msg = config_error(text);
+        assert!(msg.starts_with(key), "{text:?}: {msg}");
+    }
+}
+
+#[test]
+fn wrong_types_and_enum_values_are_rejected() {
+    let msg = config_error("max_connections = \"many\"");
+    assert!(msg.contains("max_connections"), "{msg}");
+
+    let msg = config_error("[codec]\nbyte_order = \"middle\"");
+    assert!(msg.contains("byte_order"), "{msg}");
+
+    let msg = config_error("[heartbeat]\ninterval_ms = 1000");
+    assert!(msg.contains("max_missed"), "{msg}");
+
+    let msg = config_error("listen_addr = ");
+    assert!(!msg.is_empty());
+}
+
+#[test]
+fn file_errors_include_the_path() {
+    let path = write_config("invalid", "[heartbeat]\ninterval_ms = 0\nmax_missed = 3\n");
+    let result = ServerConfig::from_toml_file_with(&path, &no_env());
+    std::fs::remove_file(&path).unwrap();
+    match result {
+        Err(ZerustError::InvalidConfig(msg)) => {
+            assert!(msg.starts_with(&path.display().to_string()), "{msg}");
+            assert!(
+                msg.contains("heartbeat.interval_ms must be greater than 0"),
+                "{msg}"
+            );
+        }
+        other => panic!("expected InvalidConfig, got {other:?}"),
+    }
+
+    let missing = std::env::temp_dir().join("zerust_config_does_not_exist.toml");
+    assert!(matches!(
+        ServerConfig::from_toml_file(&missing),
+        Err(ZerustError::IoError(_))
+    ));
+}
+
+#[test]
+fn unknown_keys_are_denied_by_default() {
+    assert_eq!(
+        config_error("max_conections = 10"),
+        "unknown key `max_conections`"
+    );
+    assert_eq!(
+        config_error("[heartbeat]\ninterval_ms = 1000\nmax_missed = 3\nmsg_id = 7"),
+        "unknown key `heartbeat.msg_id`"
+    );
+    assert_eq!(
+        config_error("[logging]\nlevel = \"info\""),
+        "unknown key `logging`"
+    );
+
+    // 关闭检查后忽略未知的配置项，已知的配置项照常生效
+    let options = no_env().with_deny_unknown_keys(false);
+    let config = ServerConfig::from_toml_str(
+        "max_conections = 10\nmax_connections = 20\n[logging]\nlevel = \"info\"\n",
+        &options,
+    )
+    .unwrap();
+    assert_eq!(config.max_connections(), Some(20));
+}
+
+#[test]
+fn env_vars_take_precedence_over_file() {
+    let path = write_config(
+        "env",
+        "listen_addr = \"127.0.0.1:7000\"\nmax_connections = 10\nread_timeout_ms = 1000\n",
+    );
+    // 每个测试使用独立的前缀，不会影响并行运行的其他测试
+    // SAFETY: 只有本测试读写以该前缀开头的环境变量
+    unsafe {
+        std::env::set_var("ZERUST_TEST_ENV_LISTEN_ADDR", "127.0.0.1:7001");
+        std::env::set_var("ZERUST_TEST_ENV_MAX_FRAME_SIZE", "1032");
+        std::env::set_var("ZERUST_TEST_ENV_IDLE_TIMEOUT_MS", "2000");
+    }
+    let options = TomlOptions::new().with_env_prefix(Some("ZERUST_TEST_ENV_"));
+    let config = ServerConfig::from_toml_file_with(&path, &options).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // 环境变量覆盖文件中的配置项
+    assert_eq!(config.addr(), "127.0.0.1:7001");
+    // 文件中没有的配置项也可以由环境变量提供
+    assert_eq!(config.max_packet_size(), 1024);
+    assert_eq!(config.idle_timeout(), Some(Duration::from_secs(2)));
+    // 没有对应环境变量的配置项保留文件中的值
+    assert_eq!(config.max_connections(), Some(10));
+    assert_eq!(config.read_timeout(), Some(Duration::from_secs(1)));
+
+    // 关闭环境变量后只使用文件中的配置
+    let config = ServerConfig::from_toml_str(
+        "listen_addr = \"127.0.0.1:7000\"",
+        &TomlOptions::new().with_env_prefix(None),
+    )
+    .unwrap();
+    assert_eq!(config.addr(), "127.0.0.1:7000");
+}
+
+#[test]
+fn invalid_env_vars_name_the_variable() {
+    // SAFETY: 只有本测试读写以该前缀开头的环境变量
+    unsafe {
+        std::env::set_var("ZERUST_TEST_BAD_MAX_CONNECTIONS", "lots");
+        std::env::set_var("ZERUST_TEST_ZERO_READ_TIMEOUT_MS", "0");
+    }
+    let options = TomlOptions::new().with_env_prefix(Some("ZERUST_TEST_BAD_"));
+    match ServerConfig::from_toml_str("", &options) {
+        Err(ZerustError::InvalidConfig(msg)) => {
+            assert!(msg.starts_with("ZERUST_TEST_BAD_MAX_CONNECTIONS"), "{msg}")
+        }
+        other => panic!("expected InvalidConfig, got {other:?}"),
+    }
+
+    // 来自环境变量的取值同样经过校验
+    let options = TomlOptions::new().with_env_prefix(Some("ZERUST_TEST_ZERO_"));
+    match ServerConfig::from_toml_str("", &options) {
+        Err(ZerustError::InvalidConfig(msg)) => {
+            assert!(msg.starts_with("read_timeout_ms"), "{msg}")
+        }
+        other => panic!("expected InvalidConfig, got {other:?}"),
+    }
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn tls_paths_are_relative_to_the_config_file() {
+    let certs = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/certs");
+    let path = PathBuf::from(certs).join(format!("zerust_tls_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        "[tls]\ncert_path = \"cert.pem\"\nkey_path = \"key.pem\"\n",
+    )
+    .unwrap();
+    let config = ServerConfig::from_toml_file_with(&path, &no_env());
+    std::fs::remove_file(&path).unwrap();
+    assert!(config.unwrap().tls().is_some());
+
+    let msg = config_error("[tls]\ncert_path = \"missing.pem\"\nkey_path = \"missing.pem\"");
+    assert!(msg.starts_with("tls.cert_path"), "{msg}");
+    let msg = config_error("[tls]\nkey_path = \"missing.pem\"");
+    assert_eq!(msg, "tls.cert_path is required");
+}
+
+#[cfg(not(feature = "tls"))]
+#[test]
+fn tls_requires_the_tls_feature() {
+    let msg = config_error("[tls]\ncert_path = \"cert.pem\"\nkey_path = \"key.pem\"");
+    assert_eq!(msg, "tls requires the `tls` feature");
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compression_table_wraps_the_codec() {
+    let config = ServerConfig::from_toml_str(
+        "[compression]\nthreshold = 16\nalgorithm = \"gzip\"\n",
+        &no_env(),
+    )
+    .unwrap();
+    let frame = config.codec().encode(1, &[0u8; 256]).unwrap();
+    assert!(frame.len() < 256);
+}
@@ -0,0 +1,369 @@
+//! # 连接测试
+//!
+//! 直接把 Tokio 的 `TcpStream` 交给 `Connection`，需要开启 `tokio-runtime` 功能。
+
+#![cfg(feature = "tokio-runtime")]
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use zerust::connection::{Connection, TcpConnection};
+use zerust::control::{PING_MSG_ID, PONG_MSG_ID};
+use zerust::datapack::{ByteOrderMode, DataPack};
+use zerust::{Response, ZerustError};
+
+/// 建立一对本地 TCP 连接，返回 (服务端流, 客户端流)
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (server, client)
+}
+
+#[tokio::test]
+async fn oversized_header_is_rejected_before_reading_body() {
+    let (server, mut client) = tcp_pair().await;
+    let mut conn = Connection::new(server).with_max_packet_size(1024);
+
+    // 只发送一个声明 4 GiB 数据的消息头，不发送消息体
+    let mut header = Vec::new();
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    client.write_all(&header).await.unwrap();
+
+    let err = conn.read_request().await.unwrap_err();
+    assert!(matches!(
+        err,
+        ZerustError::MessageTooLarge { size, .. } if size == u32::MAX as u64
+    ));
+}
+
+#[tokio::test]
+async fn big_endian_connection_decodes_and_encodes() {
+    let (server, mut client) = tcp_pair().await;
+    let pack = DataPack::with_order(ByteOrderMode::Big);
+    let mut conn = Connection::new(server).with_datapack(pack);
+
+    client
+        .write_all(&pack.try_encode(0x0102, b"be").unwrap())
+        .await
+        .unwrap();
+    let req = conn.read_request().await.unwrap();
+    assert_eq!(req.msg_id(), 0x0102);
+    assert_eq!(req.data(), b"be");
+
+    conn.send_response(&Response::new(0x0304, b"ok".to_vec()))
+        .await
+        .unwrap();
+    let mut frame = [0u8; 10];
+    client.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &pack.try_encode(0x0304, b"ok").unwrap()[..]);
+}
+
+#[tokio::test]
+async fn read_times_out_when_client_sends_nothing() {
+    let (server, _client) = tcp_pair().await;
+    let mut conn = Connection::new(server).with_read_timeout(Some(Duration::from_millis(50)));
+
+    let err = conn.read_request().await.unwrap_err();
+    assert!(matches!(err, ZerustError::Timeout));
+}
+
+#[tokio::test]
+async fn read_without_timeout_waits_for_slow_client() {
+    let (server, mut client) = tcp_pair().await;
+    let mut conn = Connection::new(server).with_read_timeout(None);
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client
+            .write_all(&DataPack::try_pack(1, b"late").unwrap())
+            .await
+            .unwrap();
+        // 保持连接直到服务端读取完毕
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    });
+    assert_eq!(conn.read_request().await.unwrap().data(), b"late");
+}
+
+#[tokio::test]
+async fn write_times_out_when_client_never_reads() {
+    let (server, _client) = tcp_pair().await;
+    let mut conn = Connection::new(server).with_write_timeout(Some(Duration::from_millis(100)));
+
+    // 客户端从不读取，足够大的响应会填满内核缓冲区并导致写入超时
+    let resp = Response::new(1, vec![0u8; 64 * 1024 * 1024]);
+    let err = conn.send_response(&resp).await.unwrap_err();
+    assert!(matches!(err, ZerustError::Timeout));
+}
+
+#[tokio::test]
+async fn set_nodelay_is_applied_to_stream() {
+    let (server, _client) = tcp_pair().await;
+    let conn: TcpConnection = Connection::new(server);
+
+    conn.set_nodelay(true).unwrap();
+    assert!(conn.nodelay().unwrap());
+    conn.set_nodelay(false).unwrap();
+    assert!(!conn.nodelay().unwrap());
+}
+
+#[tokio::test]
+async fn keepalive_and_linger_are_applied_to_stream() {
+    let (server, _client) = tcp_pair().await;
+    let conn: TcpConnection = Connection::new(server);
+
+    conn.set_keepalive(Some(Duration::from_secs(30))).unwrap();
+    assert!(conn.keepalive().unwrap());
+    conn.set_keepalive(None).unwrap();
+    assert!(!conn.keepalive().unwrap());
+
+    conn.set_linger(Some(Duration::from_secs(1))).unwrap();
+    assert_eq!(conn.linger().unwrap(), Some(Duration::from_secs(1)));
+    conn.set_linger(None).unwrap();
+    assert_eq!(conn.linger().unwrap(), None);
+}
+
+#[tokio::test]
+async fn requests_carry_connection_id() {
+    let (server, mut client) = tcp_pair().await;
+    let mut conn = Connection::new(server).with_conn_id(7);
+    assert_eq!(conn.conn_id(), 7);
+
+    client
+        .write_all(&DataPack::try_pack(1, b"id").unwrap())
+        .await
+        .unwrap();
+    let req = conn.read_request().await.unwrap();
+    assert_eq!(req.conn_id(), 7);
+}
+
+#[tokio::test]
+async fn split_halves_read_and_write_independently() {
+    let (server, mut client) = tcp_pair().await;
+    let mut conn = Connection::new(server).with_conn_id(3);
+
+    // 拆分前已经读入缓冲区的数据由读取端保留
+    let mut bytes = DataPack::try_pack(1, b"first").unwrap();
+    bytes.extend_from_slice(&DataPack::try_pack(2, b"second").unwrap());
+    client.write_all(&bytes).await.unwrap();
+    assert_eq!(conn.read_request().await.unwrap().data(), b"first");
+    let (mut reader, writer) = conn.split();
+    assert_eq!(reader.conn_id(), 3);
+
+    // 读取端等待请求时，其他任务可以通过写入端的克隆主动推送消息
+    let pusher = writer.clone();
+    let read = tokio::spawn(async move {
+        let second = reader.read_request().await.unwrap();
+        let third = reader.read_request().await.unwrap();
+        (second, third)
+    });
+    pusher.send_msg(9, b"push").await.unwrap();
+    let mut frame = [0u8; 12];
+    client.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &DataPack::try_pack(9, b"push").unwrap()[..]);
+
+    client
+        .write_all(&DataPack::try_pack(3, b"third").unwrap())
+        .await
+        .unwrap();
+    let (second, third) = read.await.unwrap();
+    assert_eq!((second.msg_id(), second.data()), (2, &b"second"[..]));
+    assert_eq!((third.msg_id(), third.conn_id()), (3, 3));
+
+    writer
+        .send_response(&Response::new(4, b"ok".to_vec()))
+        .await
+        .unwrap();
+    let mut frame = [0u8; 10];
+    client.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &DataPack::try_pack(4, b"ok").unwrap()[..]);
+}
+
+#[tokio::test]
+async fn pipelined_frames_decode_in_order() {
+    let (server, mut client) = tcp_pair().await;
+    let mut conn = Connection::new(server);
+
+    // 1000 条长度不同的消息，按与帧边界无关的大小分块写入，覆盖各种部分读取的情况
+    let frames: Vec<(u32, Vec<u8>)> = (0..1000u32)
+        .map(|i| (i, vec![i as u8; (i % 65) as usize]))
+        .collect();
+    let bytes: Vec<u8> = frames
+        .iter()
+        .flat_map(|(msg_id, data)| DataPack::try_pack(*msg_id, data).unwrap())
+        .collect();
+    let writer = tokio::spawn(async move {
+        for chunk in bytes.chunks(777) {
+            client.write_all(chunk).await.unwrap();
+        }
+        client
+    });
+
+    for (msg_id, data) in &frames {
+        let req = conn.read_request().await.unwrap();
+        assert_eq!(req.msg_id(), *msg_id);
+        assert_eq!(req.data(), &data[..]);
+    }
+
+    // 客户端关闭后，读取返回连接已关闭
+    drop(writer.await.unwrap());
+    assert!(matches!(
+        conn.read_request().await,
+        Err(ZerustError::ConnectionClosed)
+    ));
+}
+
+#[tokio::test]
+async fn duplex_stream_reads_requests_and_sends_responses() {
+    let (server, mut client) = tokio::io::duplex(64);
+    let mut conn = Connection::new(server);
+
+    // 内存流没有网络地址
+    assert!(matches!(conn.remote_addr(), Err(ZerustError::IoError(_))));
+    assert!(conn.context().remote_addr().ip().is_unspecified());
+
+    // 缓冲区小于消息长度，读取需要分多次完成
+    let data = vec![7u8; 200];
+    let writer = tokio::spawn(async move {
+        client
+            .write_all(&DataPack::try_pack(5, &data).unwrap())
+            .await
+            .unwrap();
+        client
+    });
+    let req = conn.read_request().await.unwrap();
+    assert_eq!(req.msg_id(), 5);
+    assert_eq!(req.data(), &[7u8; 200][..]);
+    let mut client = writer.await.unwrap();
+
+    conn.send_response(&Response::new(6, b"pong".to_vec()))
+        .await
+        .unwrap();
+    let mut frame = [0u8; 12];
+    client.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &DataPack::try_pack(6, b"pong").unwrap()[..]);
+
+    drop(client);
+    assert!(matches!(
+        conn.read_request().await,
+        Err(ZerustError::ConnectionClosed)
+    ));
+}
+
+#[tokio::test]
+async fn eof_inside_frame_differs_from_clean_close() {
+    let (server, mut client) = tokio::io::duplex(64);
+    let mut conn = Connection::new(server);
+
+    // 只发送了半个帧就关闭写入端
+    let frame = DataPack::try_pack(1, b"truncated").unwrap();
+    client.write_all(&frame[..10]).await.unwrap();
+    client.shutdown().await.unwrap();
+    match conn.read_request().await {
+        Err(ZerustError::IoError(e)) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+        other => panic!("unexpected result: {other:?}"),
+    }
+
+    // 关闭连接的写入端后，对端读到结束
+    conn.send_response(&Response::new(2, b"last".to_vec()))
+        .await
+        .unwrap();
+    conn.shutdown().await.unwrap();
+    let mut rest = Vec::new();
+    client.read_to_end(&mut rest).await.unwrap();
+    assert_eq!(rest, DataPack::try_pack(2, b"last").unwrap());
+}
+
+#[tokio::test]
+async fn duplex_stream_splits_with_given_addr() {
+    let (server, mut client) = tokio::io::duplex(1024);
+    let addr = "10.0.0.1:4000".parse().unwrap();
+    let (mut reader, writer) = Connection::from_stream(server, addr).split();
+    assert_eq!(reader.context().remote_addr(), addr);
+
+    client
+        .write_all(&DataPack::try_pack(1, b"hi").unwrap())
+        .await
+        .unwrap();
+    let req = reader.read_request().await.unwrap();
+    assert_eq!(req.data(), b"hi");
+
+    writer.send_msg(2, b"ok").await.unwrap();
+    let mut frame = [0u8; 10];
+    client.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &DataPack::try_pack(2, b"ok").unwrap()[..]);
+}
+
+#[tokio::test]
+async fn joined_halves_work_as_transport() {
+    // 请求和响应分别经过两条单向的内存流
+    let (mut to_server, server_in) = tokio::io::duplex(64);
+    let (server_out, mut from_server) = tokio::io::duplex(64);
+    let (server_in, _) = tokio::io::split(server_in);
+    let (_, server_out) = tokio::io::split(server_out);
+    let mut conn = Connection::new(tokio::io::join(server_in, server_out));
+
+    to_server
+        .write_all(&DataPack::try_pack(3, b"in").unwrap())
+        .await
+        .unwrap();
+    let req = conn.read_request().await.unwrap();
+    assert_eq!((req.msg_id(), req.data()), (3, &b"in"[..]));
+    conn.send_msg(4, b"out").await.unwrap();
+    let mut frame = [0u8; 11];
+    from_server.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &DataPack::try_pack(4, b"out").unwrap()[..]);
+
+    // 拆分后同样可以使用
+    let (mut reader, writer) = conn.split();
+    to_server
+        .write_all(&DataPack::try_pack(5, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(reader.read_request().await.unwrap().msg_id(), 5);
+    writer.send_msg(6, b"").await.unwrap();
+    let mut frame = [0u8; 8];
+    from_server.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &DataPack::try_pack(6, b"").unwrap()[..]);
+}
+
+/// 从流中读取一个完整的帧，返回 (msg_id, data)
+async fn read_raw_frame(stream: &mut (impl AsyncReadExt + Unpin)) -> (u32, Vec<u8>) {
+    let mut header = [0u8; DataPack::HEADER_SIZE];
+    stream.read_exact(&mut header).await.unwrap();
+    let (msg_id, data_len) = DataPack::unpack_header(&header).unwrap();
+    let mut data = vec![0u8; data_len as usize];
+    stream.read_exact(&mut data).await.unwrap();
+    (msg_id, data)
+}
+
+#[tokio::test]
+async fn cancelled_pong_is_finished_before_next_write() {
+    // 管道容量小于 pong 帧，对端不读取时 pong 只能写入一部分
+    let (server, client) = tokio::io::duplex(4);
+    let (mut client, mut client_tx) = tokio::io::split(client);
+    let mut conn = Connection::new(server);
+    tokio::spawn(async move {
+        client_tx
+            .write_all(&DataPack::try_pack(PING_MSG_ID, b"ping payload").unwrap())
+            .await
+            .unwrap();
+        client_tx
+    });
+    let cancelled = tokio::time::timeout(Duration::from_millis(50), conn.read_request()).await;
+    assert!(cancelled.is_err());
+
+    // 之后发送的消息排在 pong 的剩余部分之后，两者不会交错
+    let peer = tokio::spawn(async move {
+        let pong = read_raw_frame(&mut client).await;
+        let msg = read_raw_frame(&mut client).await;
+        (pong, msg)
+    });
+    conn.send_msg(1, b"after").await.unwrap();
+    let (pong, msg) = peer.await.unwrap();
+    assert_eq!(pong, (PONG_MSG_ID, b"ping payload".to_vec()));
+    assert_eq!(msg, (1, b"after".to_vec()));
+}
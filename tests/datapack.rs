@@ -0,0 +1,309 @@
+//! # 协议编解码测试
+
+use bytes::BytesMut;
+use zerust::ZerustError;
+use zerust::datapack::{ByteOrderMode, DEFAULT_MAX_PACKET_SIZE, DataPack, HeaderLayout};
+
+#[test]
+fn pack_then_unpack_header_round_trips() {
+    let bytes = DataPack::try_pack(7, b"hello").unwrap();
+    assert_eq!(bytes.len(), 8 + 5);
+    assert_eq!(DataPack::unpack_header(&bytes[..8]).unwrap(), (7, 5));
+    assert_eq!(&bytes[8..], b"hello");
+}
+
+#[test]
+fn header_within_limit_is_accepted() {
+    let bytes = DataPack::try_pack(1, &[0u8; 16]).unwrap();
+    assert_eq!(
+        DataPack::unpack_header_with_limit(&bytes[..8], 16).unwrap(),
+        (1, 16)
+    );
+}
+
+#[test]
+fn short_header_is_invalid() {
+    let bytes = DataPack::try_pack(7, b"hello").unwrap();
+    for len in [0, 4, 7] {
+        let header = &bytes[..len];
+        assert!(matches!(
+            DataPack::unpack_header(header),
+            Err(ZerustError::InvalidHeader)
+        ));
+        assert!(matches!(
+            DataPack::unpack_header_with_limit(header, DEFAULT_MAX_PACKET_SIZE),
+            Err(ZerustError::InvalidHeader)
+        ));
+        assert!(matches!(
+            DataPack::with_order(ByteOrderMode::Big).decode_header(header),
+            Err(ZerustError::InvalidHeader)
+        ));
+    }
+}
+
+#[test]
+fn checked_frames_detect_corrupted_payload() {
+    let frame = DataPack::try_pack_checked(3, b"tick").unwrap();
+    assert_eq!(
+        frame.len(),
+        DataPack::HEADER_SIZE + 4 + DataPack::CHECKSUM_SIZE
+    );
+    assert_eq!(DataPack::unpack_checked(&frame).unwrap(), (3, &b"tick"[..]));
+    assert_eq!(
+        DataPack::unpack_checked(&DataPack::try_pack_checked(4, b"").unwrap()).unwrap(),
+        (4, &b""[..])
+    );
+
+    // 翻转数据中的一个比特
+    let mut corrupted = frame.clone();
+    corrupted[DataPack::HEADER_SIZE + 1] ^= 0x01;
+    assert!(matches!(
+        DataPack::unpack_checked(&corrupted),
+        Err(ZerustError::ChecksumMismatch { expected, actual }) if expected != actual
+    ));
+
+    // 缺少校验和的帧
+    assert!(matches!(
+        DataPack::unpack_checked(&frame[..frame.len() - 1]),
+        Err(ZerustError::ProtocolError(_))
+    ));
+    assert!(matches!(
+        DataPack::unpack_checked(&frame[..DataPack::HEADER_SIZE]),
+        Err(ZerustError::InvalidHeader)
+    ));
+}
+
+#[test]
+fn header_claiming_4_gib_is_rejected() {
+    // 消息头声明 data_len = u32::MAX（约 4 GiB）
+    let mut header = Vec::new();
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+
+    let err = DataPack::unpack_header_with_limit(&header, DEFAULT_MAX_PACKET_SIZE).unwrap_err();
+    assert!(matches!(
+        err,
+        ZerustError::MessageTooLarge { size, .. } if size == u32::MAX as u64
+    ));
+}
+
+#[test]
+fn little_endian_round_trip() {
+    let pack = DataPack::with_order(ByteOrderMode::Little);
+    let bytes = pack.try_encode(0x0102_0304, b"abc").unwrap();
+    assert_eq!(&bytes[..8], &[4, 3, 2, 1, 3, 0, 0, 0]);
+    assert_eq!(pack.decode_header(&bytes[..8]).unwrap(), (0x0102_0304, 3));
+    // 静态方法与默认实例保持一致
+    assert_eq!(bytes, DataPack::try_pack(0x0102_0304, b"abc").unwrap());
+}
+
+#[test]
+fn big_endian_round_trip() {
+    let pack = DataPack::with_order(ByteOrderMode::Big);
+    let bytes = pack.try_encode(0x0102_0304, b"abc").unwrap();
+    assert_eq!(&bytes[..8], &[1, 2, 3, 4, 0, 0, 0, 3]);
+    assert_eq!(pack.decode_header(&bytes[..8]).unwrap(), (0x0102_0304, 3));
+    assert_eq!(&bytes[8..], b"abc");
+}
+
+#[test]
+fn try_pack_matches_pack_for_normal_payloads() {
+    assert_eq!(
+        DataPack::try_pack(3, b"xyz").unwrap(),
+        DataPack::try_pack(3, b"xyz").unwrap()
+    );
+}
+
+#[test]
+fn pack_into_matches_pack_and_appends() {
+    let mut buf = b"prefix".to_vec();
+    DataPack::pack_into(3, b"payload", &mut buf).unwrap();
+    assert_eq!(&buf[..6], b"prefix");
+    assert_eq!(&buf[6..], &DataPack::try_pack(3, b"payload").unwrap()[..]);
+
+    // 复用缓冲区时产生的字节与 pack 完全相同，包括空数据和大端序
+    let big = DataPack::with_order(ByteOrderMode::Big);
+    for data in [&b""[..], &[0xAB; 64][..]] {
+        buf.clear();
+        DataPack::pack_into(7, data, &mut buf).unwrap();
+        assert_eq!(buf, DataPack::try_pack(7, data).unwrap());
+        buf.clear();
+        big.encode_into(7, data, &mut buf).unwrap();
+        assert_eq!(buf, big.try_encode(7, data).unwrap());
+    }
+}
+
+/// Zinx 示例客户端发送的请求帧：data_len = 28、msg_id = 1，均为小端序
+const ZINX_CLIENT_FRAME: &[u8] = b"\x1c\x00\x00\x00\x01\x00\x00\x00ZinxV0.5 client Test Message";
+/// Zinx 示例服务器回复的帧：data_len = 18、msg_id = 1
+const ZINX_SERVER_FRAME: &[u8] = b"\x12\x00\x00\x00\x01\x00\x00\x00ping...ping...ping";
+
+#[test]
+fn zinx_layout_matches_zinx_frames() {
+    let pack = DataPack::zinx();
+    assert_eq!(
+        (pack.order(), pack.header_layout()),
+        (ByteOrderMode::Little, HeaderLayout::LenThenId)
+    );
+    assert_eq!(pack.decode_header(ZINX_CLIENT_FRAME).unwrap(), (1, 28));
+    assert_eq!(&ZINX_CLIENT_FRAME[8..], b"ZinxV0.5 client Test Message");
+    assert_eq!(
+        pack.try_encode(1, b"ZinxV0.5 client Test Message").unwrap(),
+        ZINX_CLIENT_FRAME
+    );
+    assert_eq!(
+        pack.try_encode(1, b"ping...ping...ping").unwrap(),
+        ZINX_SERVER_FRAME
+    );
+
+    // 默认的字段顺序会把数据长度当作消息ID
+    assert_eq!(DataPack::unpack_header(ZINX_CLIENT_FRAME).unwrap(), (28, 1));
+    // 字段顺序和字节序可以组合
+    let big = DataPack::with_order(ByteOrderMode::Big).with_header_layout(HeaderLayout::LenThenId);
+    assert_eq!(
+        &big.try_encode(1, b"ab").unwrap()[..8],
+        &[0, 0, 0, 2, 0, 0, 0, 1]
+    );
+}
+
+#[test]
+fn zinx_layout_applies_limit_to_data_len() {
+    let pack = DataPack::zinx();
+    assert!(matches!(
+        pack.decode_header_with_limit(ZINX_CLIENT_FRAME, 16),
+        Err(ZerustError::MessageTooLarge {
+            size: 28,
+            limit: 16
+        })
+    ));
+}
+
+/// 几个不同长度的帧首尾相接组成的数据流，以及其中的消息
+fn frame_stream(datapack: &DataPack) -> (Vec<u8>, Vec<(u32, Vec<u8>)>) {
+    let messages = vec![
+        (1, Vec::new()),
+        (2, b"hello".to_vec()),
+        (3, (0..=255).cycle().take(300).collect()),
+        (u32::MAX, b"x".to_vec()),
+    ];
+    let mut stream = Vec::new();
+    for (msg_id, data) in &messages {
+        datapack.encode_into(*msg_id, data, &mut stream).unwrap();
+    }
+    (stream, messages)
+}
+
+/// 按 `chunks` 依次追加数据，每次追加后取出所有完整的帧
+fn unpack_in_chunks<'a>(
+    datapack: &DataPack,
+    chunks: impl IntoIterator<Item = &'a [u8]>,
+) -> Vec<(u32, Vec<u8>)> {
+    let mut received = Vec::new();
+    let mut frames = Vec::new();
+    for chunk in chunks {
+        received.extend_from_slice(chunk);
+        while let Some((msg_id, data, consumed)) = datapack
+            .try_decode(&received, DEFAULT_MAX_PACKET_SIZE)
+            .unwrap()
+        {
+            frames.push((msg_id, data.to_vec()));
+            received.drain(..consumed);
+        }
+    }
+    assert!(received.is_empty());
+    frames
+}
+
+#[test]
+fn try_unpack_never_misframes_at_any_split() {
+    for datapack in [DataPack::default(), DataPack::zinx()] {
+        let (stream, messages) = frame_stream(&datapack);
+        // 在每个位置把数据流切成两段
+        for split in 0..=stream.len() {
+            let (head, tail) = stream.split_at(split);
+            assert_eq!(
+                unpack_in_chunks(&datapack, [head, tail]),
+                messages,
+                "{split}"
+            );
+        }
+        // 逐字节到达
+        assert_eq!(unpack_in_chunks(&datapack, stream.chunks(1)), messages);
+    }
+}
+
+#[test]
+fn try_unpack_buf_never_misframes_at_any_split() {
+    let (stream, messages) = frame_stream(&DataPack::default());
+    for split in 0..=stream.len() {
+        let mut buf = BytesMut::new();
+        let mut frames = Vec::new();
+        for chunk in [&stream[..split], &stream[split..]] {
+            buf.extend_from_slice(chunk);
+            while let Some((msg_id, data)) = DataPack::try_unpack_buf(&mut buf).unwrap() {
+                frames.push((msg_id, data.to_vec()));
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(frames, messages, "{split}");
+    }
+}
+
+#[test]
+fn try_unpack_distinguishes_incomplete_and_oversized_frames() {
+    // 消息头不完整、数据不完整都只是需要更多数据
+    let frame = DataPack::try_pack(9, b"payload").unwrap();
+    assert!(DataPack::try_unpack(&frame[..3]).unwrap().is_none());
+    assert!(DataPack::try_unpack(&frame[..10]).unwrap().is_none());
+    assert_eq!(
+        DataPack::try_unpack(&frame).unwrap(),
+        Some((9, &b"payload"[..], frame.len()))
+    );
+
+    // 消息头声明的长度超过限制时，不等待数据直接返回错误
+    assert!(matches!(
+        DataPack::try_unpack_with_limit(&frame[..DataPack::HEADER_SIZE], 4),
+        Err(ZerustError::MessageTooLarge { size: 7, limit: 4 })
+    ));
+}
+
+#[test]
+fn try_unpack_request_handles_partial_exact_and_trailing_data() {
+    let first = DataPack::try_pack(1, b"first").unwrap();
+    let second = DataPack::try_pack(2, b"second frame").unwrap();
+
+    // 不完整的消息头和不完整的数据
+    for len in [0, 3, DataPack::HEADER_SIZE, first.len() - 1] {
+        assert!(
+            DataPack::try_unpack_request(&first[..len])
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    // 恰好一个帧
+    let (req, consumed) = DataPack::try_unpack_request(&first).unwrap().unwrap();
+    assert_eq!((req.msg_id(), req.data()), (1, &b"first"[..]));
+    assert_eq!(consumed, first.len());
+
+    // 一个半帧：解析出第一个帧，剩下的半个帧需要更多数据
+    let mut buf = first.clone();
+    buf.extend_from_slice(&second[..second.len() / 2]);
+    let (req, consumed) = DataPack::try_unpack_request(&buf).unwrap().unwrap();
+    assert_eq!((req.msg_id(), consumed), (1, first.len()));
+    buf.drain(..consumed);
+    assert!(DataPack::try_unpack_request(&buf).unwrap().is_none());
+    buf.extend_from_slice(&second[second.len() / 2..]);
+    let (req, consumed) = DataPack::try_unpack_request(&buf).unwrap().unwrap();
+    assert_eq!((req.msg_id(), req.data()), (2, &b"second frame"[..]));
+    assert_eq!(consumed, buf.len());
+
+    // 按实例配置的字段顺序解析
+    let zinx = DataPack::zinx();
+    let frame = zinx.try_encode(3, b"zinx").unwrap();
+    let (req, _) = zinx
+        .try_decode_request(&frame, DEFAULT_MAX_PACKET_SIZE)
+        .unwrap()
+        .unwrap();
+    assert_eq!((req.msg_id(), req.data()), (3, &b"zinx"[..]));
+}
@@ -0,0 +1,135 @@
+//! # Framed 编解码器测试
+//!
+//! 检查 `ZerustCodec` 的帧与 `DataPack` 一致，并通过 `Framed` 与服务器通信。
+//! 需要开启 `codec` 功能。
+
+#![cfg(feature = "codec")]
+
+use bytes::{Bytes, BytesMut};
+use futures::{SinkExt, StreamExt};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio_util::codec::{Decoder, Encoder, Framed};
+use zerust::datapack::{ByteOrderMode, DataPack};
+use zerust::framed::ZerustCodec;
+use zerust::{DefaultRouter, Response, Server, ZerustError};
+
+#[test]
+fn encoding_matches_datapack() {
+    for datapack in [
+        DataPack::default(),
+        DataPack::with_order(ByteOrderMode::Big),
+        DataPack::zinx(),
+    ] {
+        let mut codec = ZerustCodec::new().with_datapack(datapack);
+        for (msg_id, data) in [(0, &b""[..]), (7, b"hello"), (u32::MAX, &[0xab; 1000])] {
+            let mut dst = BytesMut::new();
+            codec
+                .encode((msg_id, Bytes::copy_from_slice(data)), &mut dst)
+                .unwrap();
+            assert_eq!(dst, datapack.try_encode(msg_id, data).unwrap());
+        }
+    }
+
+    // 批量响应依次编码其中的每条消息
+    let mut dst = BytesMut::new();
+    let batch = Response::batch([
+        Response::new(1, b"a".to_vec()),
+        Response::new(2, b"b".to_vec()),
+    ]);
+    ZerustCodec::new().encode(batch, &mut dst).unwrap();
+    let mut expected = DataPack::try_pack(1, b"a").unwrap();
+    expected.extend_from_slice(&DataPack::try_pack(2, b"b").unwrap());
+    assert_eq!(dst, expected);
+}
+
+#[test]
+fn decoding_waits_for_complete_frames_and_checks_limit() {
+    let mut codec = ZerustCodec::new().with_max_frame_length(16);
+    let mut stream = DataPack::try_pack(1, b"first").unwrap();
+    stream.extend_from_slice(&DataPack::try_pack(2, b"second").unwrap());
+
+    // 逐字节到达时每个帧完整后才被解码
+    let mut src = BytesMut::new();
+    let mut frames = Vec::new();
+    for byte in &stream {
+        src.extend_from_slice(&[*byte]);
+        while let Some((msg_id, data)) = codec.decode(&mut src).unwrap() {
+            frames.push((msg_id, data.freeze()));
+        }
+    }
+    assert!(src.is_empty());
+    assert_eq!(
+        frames,
+        [
+            (1, Bytes::from_static(b"first")),
+            (2, Bytes::from_static(b"second"))
+        ]
+    );
+
+    // 只有消息头时就拒绝过大的帧
+    let oversized = DataPack::try_pack(3, &[0; 17]).unwrap();
+    let mut src = BytesMut::from(&oversized[..DataPack::HEADER_SIZE]);
+    assert!(matches!(
+        codec.decode(&mut src),
+        Err(ZerustError::MessageTooLarge {
+            size: 17,
+            limit: 16
+        })
+    ));
+}
+
+#[tokio::test]
+async fn framed_reassembles_fragmented_reads() {
+    // 管道容量只有 3 字节，每次读取最多得到 3 字节，帧总是被拆开
+    let (mut writer, reader) = tokio::io::duplex(3);
+    let mut framed = Framed::new(reader, ZerustCodec::default());
+    let frames = [(1, vec![1u8; 10]), (2, Vec::new()), (3, b"tail".to_vec())];
+    let stream: Vec<u8> = frames
+        .iter()
+        .flat_map(|(msg_id, data)| DataPack::try_pack(*msg_id, data).unwrap())
+        .collect();
+    let write = tokio::spawn(async move {
+        for chunk in stream.chunks(5) {
+            writer.write_all(chunk).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+    });
+
+    for (msg_id, data) in &frames {
+        let (id, resp) = framed.next().await.unwrap().unwrap();
+        assert_eq!((id, &resp[..]), (*msg_id, &data[..]));
+    }
+    write.await.unwrap();
+    // 写入端关闭后，流在帧边界处正常结束
+    assert!(framed.next().await.is_none());
+}
+
+#[tokio::test]
+async fn framed_talks_to_server() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router).bind().await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(server.run(shutdown_rx));
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let mut framed = Framed::new(stream, ZerustCodec::new());
+    for data in [&b"ping"[..], b"", &[7; 4096]] {
+        framed
+            .send((1, Bytes::copy_from_slice(data)))
+            .await
+            .unwrap();
+        let (msg_id, resp) = framed.next().await.unwrap().unwrap();
+        assert_eq!((msg_id, &resp[..]), (1, data));
+    }
+
+    drop(framed);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
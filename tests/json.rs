@@ -0,0 +1,117 @@
+//! # JSON 消息测试
+//!
+//! 检查 `Request::parse_json`、`Response::from_json` 和 `DefaultRouter::add_json_route`。
+//! 需要开启 `serde_json` 功能；测试中直接使用 Tokio 的套接字，还需要开启 `tokio-runtime` 功能。
+
+#![cfg(all(feature = "serde_json", feature = "tokio-runtime"))]
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use zerust::connection::Connection;
+use zerust::{Client, DefaultRouter, Request, Response, Router, Server, ZerustError};
+
+#[derive(Debug, Deserialize)]
+struct Move {
+    player: String,
+    dx: i32,
+    dy: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Position {
+    player: String,
+    x: i32,
+    y: i32,
+}
+
+fn move_router() -> Arc<DefaultRouter> {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_json_route::<Move, Position>(1, |m| Position {
+            player: m.player,
+            x: 10 + m.dx,
+            y: 20 + m.dy,
+        })
+        .unwrap();
+    router
+}
+
+#[test]
+fn request_and_response_round_trip_json() {
+    let req = Request::new(1, br#"{"player":"alice","dx":1,"dy":-1}"#.to_vec());
+    let m: Move = req.parse_json().unwrap();
+    assert_eq!((m.player.as_str(), m.dx, m.dy), ("alice", 1, -1));
+
+    let pos = Position {
+        player: m.player,
+        x: 1,
+        y: 2,
+    };
+    let resp = Response::from_json(7, &pos).unwrap();
+    assert_eq!(resp.msg_id(), 7);
+    assert_eq!(resp.data(), br#"{"player":"alice","x":1,"y":2}"#);
+
+    // 键不是字符串的映射无法编码为 JSON
+    let map = BTreeMap::from([((1, 2), "a")]);
+    assert!(matches!(
+        Response::from_json(7, &map),
+        Err(ZerustError::ProtocolError(_))
+    ));
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_protocol_error() {
+    let router = move_router();
+    for data in [&b"{not json"[..], br#"{"player":"bob"}"#, b""] {
+        let err = router
+            .handle(Request::new(1, data.to_vec()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ZerustError::ProtocolError(_)), "{err:?}");
+    }
+}
+
+#[tokio::test]
+async fn json_route_replies_and_survives_bad_requests() {
+    let server = Server::new("127.0.0.1:0", move_router())
+        .bind()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(server.run(shutdown_rx));
+
+    let mut client = Client::new(Connection::new(TcpStream::connect(addr).await.unwrap()));
+    let resp = client
+        .request(1, br#"{"player":"alice","dx":2,"dy":3}"#)
+        .await
+        .unwrap();
+    assert_eq!(resp.msg_id(), 1);
+    assert_eq!(
+        resp.parse_json::<Position>().unwrap(),
+        Position {
+            player: "alice".to_string(),
+            x: 12,
+            y: 23
+        }
+    );
+
+    // 格式错误的请求得到错误响应，连接仍然可以继续使用
+    let resp = client.request(1, b"{\"player\":").await.unwrap();
+    assert_eq!(resp.msg_id(), 500);
+    let (code, message) = resp.error_body().unwrap();
+    assert_eq!(code, 500);
+    assert!(message.starts_with("Protocol error: invalid JSON request"));
+    let resp = client
+        .request(1, br#"{"player":"bob","dx":0,"dy":0}"#)
+        .await
+        .unwrap();
+    assert_eq!(resp.data(), br#"{"player":"bob","x":10,"y":20}"#);
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
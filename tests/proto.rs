@@ -0,0 +1,128 @@
+//! # protobuf 消息测试
+//!
+//! 检查 `Request::parse_proto`、`Response::from_proto` 和 `DefaultRouter::add_proto_route`。
+//! 需要开启 `prost` 功能。
+
+#![cfg(feature = "prost")]
+
+use prost::Message;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+use zerust::{Client, DefaultRouter, Request, Response, Router, Server, ZerustError};
+
+#[derive(Clone, PartialEq, Message)]
+struct Ping {
+    #[prost(uint64, tag = "1")]
+    id: u64,
+    #[prost(bytes = "bytes", tag = "2")]
+    payload: bytes::Bytes,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Pong {
+    #[prost(uint64, tag = "1")]
+    id: u64,
+    #[prost(uint32, tag = "2")]
+    len: u32,
+}
+
+fn ping_router() -> Arc<DefaultRouter> {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_proto_route::<Ping, Pong>(7, |ping| Pong {
+            id: ping.id,
+            len: ping.payload.len() as u32,
+        })
+        .unwrap();
+    router
+}
+
+#[test]
+fn request_and_response_round_trip_proto() {
+    let ping = Ping {
+        id: 9,
+        payload: bytes::Bytes::from_static(b"hello"),
+    };
+    let req = Request::new(7, ping.encode_to_vec());
+    assert_eq!(req.parse_proto::<Ping>().unwrap(), ping);
+
+    // 连续编码的响应各自独立，数据与 prost 的编码结果相同
+    let first = Response::from_proto(7, &Pong { id: 1, len: 2 });
+    let second = Response::from_proto(8, &Pong { id: 3, len: 4 });
+    assert_eq!(first.msg_id(), 7);
+    assert_eq!(first.data(), Pong { id: 1, len: 2 }.encode_to_vec());
+    assert_eq!(
+        second.parse_proto::<Pong>().unwrap(),
+        Pong { id: 3, len: 4 }
+    );
+    assert_eq!(first.parse_proto::<Pong>().unwrap(), Pong { id: 1, len: 2 });
+}
+
+#[tokio::test]
+async fn malformed_proto_is_a_protocol_error_with_msg_id() {
+    let err = ping_router()
+        .handle(Request::new(7, vec![0xff, 0xff]))
+        .await
+        .unwrap_err();
+    match err {
+        ZerustError::ProtocolError(msg) => assert!(msg.contains("msg_id 7"), "{msg}"),
+        e => panic!("unexpected error: {e:?}"),
+    }
+}
+
+#[tokio::test]
+async fn proto_route_replies_and_survives_bad_requests() {
+    let server = Server::new("127.0.0.1:0", ping_router())
+        .bind()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(server.run(shutdown_rx));
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let ping = Ping {
+        id: 42,
+        payload: bytes::Bytes::from_static(b"abc"),
+    };
+    let resp = client.request(7, &ping.encode_to_vec()).await.unwrap();
+    assert_eq!(resp.msg_id(), 7);
+    assert_eq!(resp.parse_proto::<Pong>().unwrap(), Pong { id: 42, len: 3 });
+
+    // 无法解码的请求得到错误响应，连接仍然可以继续使用
+    let resp = client.request(7, &[0xff, 0xff]).await.unwrap();
+    assert_eq!(resp.msg_id(), 500);
+    let (code, message) = resp.error_body().unwrap();
+    assert_eq!(code, 500);
+    assert!(message.contains("msg_id 7"));
+    let resp = client.request(7, &ping.encode_to_vec()).await.unwrap();
+    assert_eq!(resp.parse_proto::<Pong>().unwrap().id, 42);
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn decode_errors_use_the_configured_error_response() {
+    let server = Server::new("127.0.0.1:0", ping_router())
+        .with_error_handler(|msg_id, err| match err {
+            ZerustError::ProtocolError(_) => Response::error(msg_id, 400, "bad ping"),
+            err => Response::internal_error(err),
+        })
+        .bind()
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(server.run(shutdown_rx));
+
+    let mut client = Client::connect(addr).await.unwrap();
+    let resp = client.request(7, b"\x0a\x05ab").await.unwrap();
+    assert_eq!(resp.msg_id(), 7);
+    assert_eq!(resp.error_body(), Some((400, "bad ping")));
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
@@ -0,0 +1,531 @@
+//! # 路由系统测试
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zerust::router::{Interceptor, MessageHandler};
+use zerust::{DefaultRouter, Request, Response, Router, ZerustError};
+
+#[tokio::test]
+async fn sync_route_handles_request() {
+    let router = DefaultRouter::new();
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+
+    let resp = router
+        .handle(Request::new(1, b"ping".to_vec()))
+        .await
+        .unwrap();
+    assert_eq!(resp.msg_id(), 1);
+    assert_eq!(resp.data(), b"ping");
+}
+
+#[tokio::test]
+async fn async_route_is_awaited() {
+    let router = DefaultRouter::new();
+    router
+        .add_async_route(2, |req| async move {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Response::new(req.msg_id(), [req.data(), b"!"].concat())
+        })
+        .unwrap();
+
+    let resp = router
+        .handle(Request::new(2, b"hi".to_vec()))
+        .await
+        .unwrap();
+    assert_eq!(resp.msg_id(), 2);
+    assert_eq!(resp.data(), b"hi!");
+}
+
+#[tokio::test]
+async fn unknown_msg_id_returns_not_found() {
+    let router = DefaultRouter::new();
+    // 消息ID沿用请求的消息ID，数据为错误码和错误描述
+    let resp = router.handle(Request::new(42, Vec::new())).await.unwrap();
+    assert_eq!(resp.msg_id(), 42);
+    assert_eq!(resp.error_body(), Some((404, "Route not found")));
+}
+
+#[tokio::test]
+async fn fallible_route_propagates_error() {
+    let router = DefaultRouter::new();
+    router
+        .add_route_result(3, |req| {
+            if req.data().is_empty() {
+                return Err(ZerustError::ProtocolError("empty payload".into()));
+            }
+            Ok(Response::new(req.msg_id(), req.data().to_vec()))
+        })
+        .unwrap();
+
+    let resp = router.handle(Request::new(3, b"x".to_vec())).await.unwrap();
+    assert_eq!(resp.data(), b"x");
+
+    let err = router
+        .handle(Request::new(3, Vec::new()))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ZerustError::ProtocolError(_)));
+}
+
+/// 带状态的处理器：每次请求计数加一，并返回当前计数
+struct Counter {
+    count: AtomicU64,
+}
+
+impl MessageHandler for Counter {
+    fn handle(&self, req: &Request) -> Result<Response, ZerustError> {
+        let n = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        Ok(Response::new(req.msg_id(), n.to_le_bytes().to_vec()))
+    }
+}
+
+#[tokio::test]
+async fn stateful_handler_keeps_count_across_routes() {
+    let counter = Arc::new(Counter {
+        count: AtomicU64::new(0),
+    });
+    let router = DefaultRouter::new();
+    router.add_handler(10, counter.clone()).unwrap();
+    router.add_handler(11, counter.clone()).unwrap();
+
+    for (msg_id, expected) in [(10, 1u64), (11, 2), (10, 3)] {
+        let resp = router
+            .handle(Request::new(msg_id, Vec::new()))
+            .await
+            .unwrap();
+        assert_eq!(resp.msg_id(), msg_id);
+        assert_eq!(resp.data(), expected.to_le_bytes());
+    }
+    assert_eq!(counter.count.load(Ordering::Relaxed), 3);
+}
+
+/// 按 Zinx 的方式分三个阶段处理请求，并记录调用顺序
+struct Phased {
+    log: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl MessageHandler for Phased {
+    fn pre_handle(&self, _req: &Request) {
+        self.log.lock().unwrap().push("pre");
+    }
+
+    fn handle(&self, req: &Request) -> Result<Response, ZerustError> {
+        self.log.lock().unwrap().push("handle");
+        if req.data().is_empty() {
+            return Err(ZerustError::ProtocolError("empty".into()));
+        }
+        Ok(Response::new(req.msg_id(), req.data().to_vec()))
+    }
+
+    fn post_handle(&self, _req: &Request, resp: &mut Response) {
+        self.log.lock().unwrap().push("post");
+        *resp = Response::new(resp.msg_id(), [resp.data(), b"!"].concat());
+    }
+}
+
+#[tokio::test]
+async fn handler_phases_run_in_order() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let router = DefaultRouter::new();
+    router
+        .add_handler(1, Arc::new(Phased { log: log.clone() }))
+        .unwrap();
+
+    let resp = router
+        .handle(Request::new(1, b"hi".to_vec()))
+        .await
+        .unwrap();
+    assert_eq!(resp.data(), b"hi!");
+    assert_eq!(*log.lock().unwrap(), ["pre", "handle", "post"]);
+
+    // handle 失败时不调用 post_handle
+    log.lock().unwrap().clear();
+    assert!(router.handle(Request::new(1, Vec::new())).await.is_err());
+    assert_eq!(*log.lock().unwrap(), ["pre", "handle"]);
+}
+
+/// 记录调用顺序的拦截器，在响应数据末尾追加自己的名字
+struct Trace {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Interceptor for Trace {
+    fn before(&self, _req: &Request) -> Option<Response> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("before {}", self.name));
+        None
+    }
+
+    fn after(&self, _req: &Request, resp: Response) -> Response {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("after {}", self.name));
+        Response::new(resp.msg_id(), [resp.data(), self.name.as_bytes()].concat())
+    }
+}
+
+/// 拒绝数据为空的请求
+struct RejectEmpty;
+
+impl Interceptor for RejectEmpty {
+    fn before(&self, req: &Request) -> Option<Response> {
+        req.data()
+            .is_empty()
+            .then(|| Response::new(401, b"denied:".to_vec()))
+    }
+}
+
+#[tokio::test]
+async fn interceptors_run_before_in_order_and_after_in_reverse() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let router = DefaultRouter::new();
+    router
+        .add_async_route(1, |req| async move {
+            Response::new(req.msg_id(), [req.data(), b":"].concat())
+        })
+        .unwrap();
+    for name in ["a", "b"] {
+        router.add_interceptor(Arc::new(Trace {
+            name,
+            log: log.clone(),
+        }));
+    }
+
+    let resp = router.handle(Request::new(1, b"x".to_vec())).await.unwrap();
+    assert_eq!(resp.data(), b"x:ba");
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["before a", "before b", "after b", "after a"]
+    );
+}
+
+#[tokio::test]
+async fn interceptor_short_circuits_handler() {
+    let calls = Arc::new(AtomicU64::new(0));
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let router = DefaultRouter::new();
+    let counter = calls.clone();
+    router
+        .add_route(1, move |req| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    router.add_interceptor(Arc::new(Trace {
+        name: "outer",
+        log: log.clone(),
+    }));
+    router.add_interceptor(Arc::new(RejectEmpty));
+    router.add_interceptor(Arc::new(Trace {
+        name: "inner",
+        log: log.clone(),
+    }));
+
+    // 被拒绝的请求不会到达处理函数和内层拦截器，但仍经过外层拦截器的 after
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.msg_id(), 401);
+    assert_eq!(resp.data(), b"denied:outer");
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+    assert_eq!(*log.lock().unwrap(), ["before outer", "after outer"]);
+
+    let resp = router
+        .handle(Request::new(1, b"ok".to_vec()))
+        .await
+        .unwrap();
+    assert_eq!(resp.data(), b"okinnerouter");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+/// 统计调用次数的拦截器
+struct CountCalls(Arc<AtomicU64>);
+
+impl Interceptor for CountCalls {
+    fn before(&self, _req: &Request) -> Option<Response> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        None
+    }
+}
+
+#[tokio::test]
+async fn route_group_shares_interceptor() {
+    let calls = Arc::new(AtomicU64::new(0));
+    let router = DefaultRouter::new();
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), b"public".to_vec()))
+        .unwrap();
+
+    let group = router.group("player");
+    assert_eq!(group.name(), "player");
+    group.add_interceptor(Arc::new(CountCalls(calls.clone())));
+    group
+        .add_route(10, |req| Response::new(req.msg_id(), b"profile".to_vec()))
+        .unwrap();
+    group
+        .add_async_route(11, |req| async move {
+            Response::new(req.msg_id(), b"inventory".to_vec())
+        })
+        .unwrap();
+    assert!(router.merge(group).is_empty());
+
+    // 组内的两个处理函数都经过组的拦截器
+    for (msg_id, data) in [(10, &b"profile"[..]), (11, b"inventory")] {
+        let resp = router
+            .handle(Request::new(msg_id, Vec::new()))
+            .await
+            .unwrap();
+        assert_eq!((resp.msg_id(), resp.data()), (msg_id, data));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // 组外的处理函数不经过组的拦截器
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.data(), b"public");
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    assert_eq!(router.group_name(11).as_deref(), Some("player"));
+    assert_eq!(router.group_name(1), None);
+}
+
+#[tokio::test]
+async fn removed_route_falls_back_to_not_found() {
+    let router = DefaultRouter::new();
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), b"v1".to_vec()))
+        .unwrap();
+
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.data(), b"v1");
+
+    // 替换后的处理函数立即生效
+    assert!(router.replace_route(1, |req| Response::new(req.msg_id(), b"v2".to_vec())));
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.data(), b"v2");
+
+    assert!(router.remove_route(1));
+    assert!(!router.remove_route(1));
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.error_body(), Some((404, "Route not found")));
+
+    // 未注册的消息ID不会被 replace_route 添加
+    assert!(!router.replace_route(1, |req| Response::new(req.msg_id(), Vec::new())));
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.error_body(), Some((404, "Route not found")));
+}
+
+#[tokio::test]
+async fn duplicate_registration_is_reported_and_routes_are_listed() {
+    let router = DefaultRouter::new();
+    assert!(
+        router
+            .add_route(3, |req| Response::new(req.msg_id(), b"first".to_vec()))
+            .unwrap()
+    );
+    assert!(
+        router
+            .add_route_result(1, |req| Ok(Response::new(req.msg_id(), Vec::new())))
+            .unwrap()
+    );
+    // 重复注册返回 false，后注册的处理函数生效
+    assert!(
+        !router
+            .add_async_route(3, |req| async move {
+                Response::new(req.msg_id(), b"second".to_vec())
+            })
+            .unwrap()
+    );
+    let resp = router.handle(Request::new(3, Vec::new())).await.unwrap();
+    assert_eq!(resp.data(), b"second");
+
+    let group = router.group("plugin");
+    assert!(
+        group
+            .add_route(20, |req| Response::new(req.msg_id(), Vec::new()))
+            .unwrap()
+    );
+    assert!(
+        !group
+            .add_route(20, |req| Response::new(req.msg_id(), Vec::new()))
+            .unwrap()
+    );
+    assert!(router.merge(group).is_empty());
+    assert_eq!(router.routes(), vec![1, 3, 20]);
+
+    // 合并路由组时同样返回被替换的消息ID
+    let group = router.group("override");
+    for msg_id in [1, 3, 30] {
+        group
+            .add_route(msg_id, |req| Response::new(req.msg_id(), b"group".to_vec()))
+            .unwrap();
+    }
+    assert_eq!(router.merge(group), vec![1, 3]);
+    assert_eq!(router.group_name(3).as_deref(), Some("override"));
+    assert!(router.remove_route(30));
+
+    // 卸载插件时移除它注册的消息ID
+    assert!(router.remove_route(20));
+    assert_eq!(router.routes(), vec![1, 3]);
+}
+
+#[tokio::test]
+async fn middlewares_wrap_handler_and_not_found() {
+    let router = DefaultRouter::new();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    for name in ["outer", "inner"] {
+        let log = log.clone();
+        router.use_middleware(move |req, next| {
+            let log = log.clone();
+            async move {
+                log.lock().unwrap().push(format!("{name} before"));
+                let resp = next.run(req).await?;
+                log.lock().unwrap().push(format!("{name} after"));
+                // 后置处理可以修改响应
+                let mut data = resp.data().to_vec();
+                data.extend_from_slice(format!("+{name}").as_bytes());
+                Ok(Response::new(resp.msg_id(), data))
+            }
+        });
+    }
+    router
+        .add_async_route(1, |req| async move {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Response::new(req.msg_id(), b"pong".to_vec())
+        })
+        .unwrap();
+
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.data(), b"pong+inner+outer");
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["outer before", "inner before", "inner after", "outer after"]
+    );
+
+    // 未注册的消息同样经过中间件
+    let resp = router.handle(Request::new(2, Vec::new())).await.unwrap();
+    assert_eq!(resp.msg_id(), 2);
+    assert_eq!(
+        resp.error_body(),
+        Some((404, "Route not found+inner+outer"))
+    );
+}
+
+#[tokio::test]
+async fn middleware_short_circuits_handler() {
+    let router = DefaultRouter::new();
+    let calls = Arc::new(AtomicU64::new(0));
+    router.use_middleware(|req, next| async move {
+        if req.data().is_empty() {
+            return Ok(Response::new(400, b"empty".to_vec()));
+        }
+        next.run(req).await
+    });
+    let counter = calls.clone();
+    router
+        .add_route(1, move |req| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.msg_id(), 400);
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    let resp = router
+        .handle(Request::new(1, b"ok".to_vec()))
+        .await
+        .unwrap();
+    assert_eq!(resp.data(), b"ok");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn fallback_handles_unknown_msg_ids() {
+    let router = DefaultRouter::new();
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), b"known".to_vec()))
+        .unwrap();
+
+    let resp = router.handle(Request::new(7, Vec::new())).await.unwrap();
+    assert_eq!(resp.error_body(), Some((404, "Route not found")));
+
+    router.set_fallback(|req| Response::new(req.msg_id(), b"unknown".to_vec()));
+    let resp = router.handle(Request::new(7, Vec::new())).await.unwrap();
+    assert_eq!(resp.msg_id(), 7);
+    assert_eq!(resp.data(), b"unknown");
+
+    // 已注册的消息ID不受影响
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.data(), b"known");
+    assert!(router.has_route(1));
+    assert!(!router.has_route(7));
+
+    // 移除的路由同样交给兜底函数
+    router.remove_route(1);
+    assert!(!router.has_route(1));
+    let resp = router.handle(Request::new(1, Vec::new())).await.unwrap();
+    assert_eq!(resp.data(), b"unknown");
+}
+
+#[test]
+fn response_helpers_follow_conventions() {
+    let ack = Response::empty(5);
+    assert_eq!((ack.msg_id(), ack.data()), (5, &b""[..]));
+    assert!(!ack.is_none());
+
+    // 错误码为小端序的 4 字节，之后是错误描述
+    let err = Response::error(6, 403, "denied");
+    assert_eq!(&err.data()[..4], &403u32.to_le_bytes());
+    assert_eq!(err.error_body(), Some((403, "denied")));
+    assert_eq!(Response::new(1, b"abc".to_vec()).error_body(), None);
+    assert_eq!(Response::new(1, vec![0, 0, 0, 0, 0xff]).error_body(), None);
+
+    let resp = Response::builder()
+        .msg_id(8)
+        .data(b"key=".to_vec())
+        .push_bytes(b"value")
+        .push_bytes(b";")
+        .build();
+    assert_eq!((resp.msg_id(), resp.data()), (8, &b"key=value;"[..]));
+}
+
+#[test]
+fn control_msg_ids_cannot_be_routed() {
+    use zerust::control::{PING_MSG_ID, RESERVED_MSG_ID_START};
+
+    let router = DefaultRouter::new();
+    let group = router.group("control");
+    for msg_id in [RESERVED_MSG_ID_START, PING_MSG_ID, u32::MAX] {
+        let echo = |req: &Request| Response::new(req.msg_id(), Vec::new());
+        assert!(matches!(
+            router.add_route(msg_id, echo),
+            Err(ZerustError::ReservedMsgId(id)) if id == msg_id
+        ));
+        assert!(matches!(
+            router.add_async_route(msg_id, |req| async move { Response::empty(req.msg_id()) }),
+            Err(ZerustError::ReservedMsgId(_))
+        ));
+        assert!(matches!(
+            group.add_route(msg_id, echo),
+            Err(ZerustError::ReservedMsgId(_))
+        ));
+        assert!(!router.has_route(msg_id));
+    }
+    assert!(router.merge(group).is_empty());
+    assert!(router.routes().is_empty());
+
+    // 保留范围之前的消息ID可以正常注册
+    assert!(
+        router
+            .add_route(RESERVED_MSG_ID_START - 1, |req| Response::empty(
+                req.msg_id()
+            ))
+            .unwrap()
+    );
+}
@@ -0,0 +1,2406 @@
+//! # 服务器集成测试
+//!
+//! 通过真实的 TCP 连接驱动 `Server` → `DefaultRouter` → `Connection` 的完整链路。
+//! 测试中直接使用 Tokio 的套接字，需要开启 `tokio-runtime` 功能。
+
+#![cfg(feature = "tokio-runtime")]
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{Semaphore, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use zerust::connection::{Connection, DEFAULT_READ_BUFFER_SIZE, PROTOCOL_MAGIC};
+use zerust::control::{PAYLOAD_TOO_LARGE_MSG_ID, PING_MSG_ID, PONG_MSG_ID, RESERVED_MSG_ID_START};
+use zerust::datapack::{ByteOrderMode, DEFAULT_MAX_PACKET_SIZE, DataPack};
+use zerust::rate_limit::{RateLimitConfig, RateLimitPolicy};
+use zerust::server::{
+    AcceptErrorPolicy, ConnLimitPolicy, DEFAULT_ACCEPT_BACKOFF, DEFAULT_HEARTBEAT_MSG_ID,
+    HeartbeatConfig, Listen, ShutdownMode, ShutdownReport,
+};
+use zerust::worker_pool::{QueueFullPolicy, WorkerPoolConfig};
+use zerust::{
+    Client, ConnManager, ConnectionHandle, DefaultRouter, Response, Server, WriteQueuePolicy,
+    ZerustError,
+};
+
+/// 在随机端口上启动服务器，返回实际监听地址、关闭信号发送端和服务器任务句柄
+async fn start_server(
+    router: Arc<DefaultRouter>,
+) -> (
+    SocketAddr,
+    oneshot::Sender<()>,
+    JoinHandle<Result<ShutdownReport, ZerustError>>,
+) {
+    start(Server::new("127.0.0.1:0", router)).await
+}
+
+/// 启动已配置好的服务器，返回值同 `start_server`
+async fn start(
+    server: Server,
+) -> (
+    SocketAddr,
+    oneshot::Sender<()>,
+    JoinHandle<Result<ShutdownReport, ZerustError>>,
+) {
+    let bound = server.bind().await.unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let handle = tokio::spawn(async move { bound.run(shutdown_rx).await });
+    (addr, shutdown_tx, handle)
+}
+
+/// 从流中读取一个完整的响应帧，返回 (msg_id, data)
+async fn read_frame(stream: &mut TcpStream) -> (u32, Vec<u8>) {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).await.unwrap();
+    let (msg_id, data_len) = DataPack::unpack_header(&header).unwrap();
+    let mut data = vec![0u8; data_len as usize];
+    stream.read_exact(&mut data).await.unwrap();
+    (msg_id, data)
+}
+
+#[tokio::test]
+async fn echo_round_trip() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start_server(router).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"hello").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, b"hello".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn shutdown_waits_for_in_flight_request() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| {
+            // 模拟一个耗时的处理过程
+            std::thread::sleep(Duration::from_millis(200));
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+
+    let (addr, shutdown_tx, server_handle) = start_server(router).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"slow").unwrap())
+        .await
+        .unwrap();
+
+    // 请求处理期间发送关闭信号
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let _ = shutdown_tx.send(());
+
+    // 进行中的请求仍然得到响应，之后连接被关闭
+    assert_eq!(read_frame(&mut stream).await, (1, b"slow".to_vec()));
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn run_returns_ok_after_shutdown_signal() {
+    let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()));
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(async move { server.run(shutdown_rx).await });
+
+    let _ = shutdown_tx.send(());
+    assert!(server_handle.await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn run_returns_ok_when_shutdown_sender_dropped() {
+    let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()));
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let server_handle = tokio::spawn(async move { server.run(shutdown_rx).await });
+
+    drop(shutdown_tx);
+    assert!(server_handle.await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn bind_exposes_os_assigned_port() {
+    let bound = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+        .bind()
+        .await
+        .unwrap();
+    let addr = bound.local_addr().unwrap();
+    assert_ne!(addr.port(), 0);
+
+    // 绑定完成后即可连接，无需等待 run 开始
+    TcpStream::connect(addr).await.unwrap();
+}
+
+#[tokio::test]
+async fn routes_multiple_requests_on_one_connection() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    router
+        .add_route(2, |req| {
+            Response::new(req.msg_id(), req.data().iter().rev().copied().collect())
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start_server(router).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // 两个请求一次性写入，服务器应按顺序逐一响应
+    let mut batch = DataPack::try_pack(1, b"abc").unwrap();
+    batch.extend_from_slice(&DataPack::try_pack(2, b"abc").unwrap());
+    stream.write_all(&batch).await.unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, b"abc".to_vec()));
+    assert_eq!(read_frame(&mut stream).await, (2, b"cba".to_vec()));
+
+    // 未注册的消息ID返回 not_found 响应，连接保持可用
+    stream
+        .write_all(&DataPack::try_pack(99, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        read_frame(&mut stream).await,
+        (99, Response::not_found(99).data().to_vec())
+    );
+    stream
+        .write_all(&DataPack::try_pack(1, b"again").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, b"again".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+/// 注册一个要求非空负载的可失败路由
+fn fallible_router() -> Arc<DefaultRouter> {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route_result(1, |req| {
+            if req.data().is_empty() {
+                return Err(ZerustError::ProtocolError("empty payload".into()));
+            }
+            Ok(Response::new(req.msg_id(), req.data().to_vec()))
+        })
+        .unwrap();
+    router
+}
+
+#[tokio::test]
+async fn handler_error_becomes_error_response() {
+    let (addr, shutdown_tx, server_handle) = start_server(fallible_router()).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    // 处理失败时客户端收到默认的错误响应
+    stream
+        .write_all(&DataPack::try_pack(1, b"").unwrap())
+        .await
+        .unwrap();
+    let (msg_id, data) = read_frame(&mut stream).await;
+    let resp = Response::new(msg_id, data);
+    assert_eq!(resp.msg_id(), 500);
+    assert_eq!(
+        resp.error_body(),
+        Some((500, "Protocol error: empty payload"))
+    );
+
+    // 连接保持打开，后续请求正常处理
+    stream
+        .write_all(&DataPack::try_pack(1, b"ok").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, b"ok".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn metrics_count_requests_per_route() {
+    for worker_pool in [false, true] {
+        let router = fallible_router();
+        router
+            .add_route(2, |req| Response::new(req.msg_id(), req.data().to_vec()))
+            .unwrap();
+        let mut server = Server::new("127.0.0.1:0", router);
+        if worker_pool {
+            server = server.with_worker_pool(2, 16);
+        }
+        let metrics = server.metrics();
+        let (addr, shutdown_tx, server_handle) = start(server).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let idle = TcpStream::connect(addr).await.unwrap();
+        // 5 个成功的请求、2 个失败的请求和 3 个其他消息ID的请求
+        let requests: Vec<(u32, &[u8])> = [(1, &b"abc"[..]); 5]
+            .into_iter()
+            .chain([(1, &b""[..]); 2])
+            .chain([(2, &b"xy"[..]); 3])
+            .collect();
+        for &(msg_id, data) in &requests {
+            stream
+                .write_all(&DataPack::try_pack(msg_id, data).unwrap())
+                .await
+                .unwrap();
+            read_frame(&mut stream).await;
+        }
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.connections_accepted(), 2);
+        assert_eq!(snapshot.active_connections(), 2);
+        drop(idle);
+        while metrics.snapshot().connections_closed() != 1 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        assert_eq!(metrics.snapshot().active_connections(), 1);
+        drop(stream);
+        let _ = shutdown_tx.send(());
+        server_handle.await.unwrap().unwrap();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.requests(), snapshot.errors()), (10, 2));
+        assert_eq!(snapshot.bytes_in(), 5 * 3 + 3 * 2);
+        // 错误响应的内容为 4 字节的错误码和 "Protocol error: empty payload"
+        assert_eq!(snapshot.bytes_out(), 5 * 3 + 2 * (4 + 29) + 3 * 2);
+        let route = snapshot.route(1).unwrap();
+        assert_eq!((route.requests(), route.errors()), (7, 2));
+        assert!(route.max_latency() <= route.total_latency());
+        // 直方图中的请求数量与总数一致，估算的最大分位数不小于实际的最长耗时
+        assert_eq!(route.latency_buckets().iter().sum::<u64>(), 7);
+        assert!(route.latency_quantile(1.0).unwrap() >= route.max_latency());
+        let route = snapshot.route(2).unwrap();
+        assert_eq!((route.requests(), route.errors()), (3, 0));
+        assert_eq!(snapshot.routes().len(), 2);
+
+        #[cfg(feature = "prometheus")]
+        {
+            let text = snapshot.encode_prometheus();
+            assert!(text.contains("zerust_requests_total 10\n"));
+            assert!(text.contains("zerust_route_errors_total{msg_id=\"1\"} 2\n"));
+            assert!(text.contains("zerust_route_latency_seconds_count{msg_id=\"2\"} 3\n"));
+            assert!(
+                text.contains("zerust_route_latency_seconds_bucket{msg_id=\"2\",le=\"+Inf\"} 3\n")
+            );
+            assert!(text.contains("zerust_connections_accepted_total 2\n"));
+            assert!(text.contains("zerust_connections_active 0\n"));
+        }
+    }
+}
+
+#[tokio::test]
+async fn metrics_merge_unregistered_msg_ids() {
+    for worker_pool in [false, true] {
+        let mut server = Server::new("127.0.0.1:0", echo_router());
+        if worker_pool {
+            server = server.with_worker_pool(2, 16);
+        }
+        let metrics = server.metrics();
+        let (addr, shutdown_tx, server_handle) = start(server).await;
+
+        // 一个已注册的消息ID和 100 个不同的未注册消息ID
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        for msg_id in (100..200).chain([1]) {
+            stream
+                .write_all(&DataPack::try_pack(msg_id, b"x").unwrap())
+                .await
+                .unwrap();
+            read_frame(&mut stream).await;
+        }
+        drop(stream);
+        let _ = shutdown_tx.send(());
+        server_handle.await.unwrap().unwrap();
+
+        // 未注册的消息ID不单独统计
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.requests(), 101);
+        assert_eq!(snapshot.routes().keys().copied().collect::<Vec<_>>(), [1]);
+        assert_eq!(snapshot.unrouted().requests(), 100);
+
+        #[cfg(feature = "prometheus")]
+        {
+            let text = snapshot.encode_prometheus();
+            assert!(text.contains("zerust_route_requests_total{msg_id=\"1\"} 1\n"));
+            assert!(text.contains("zerust_route_requests_total{msg_id=\"unrouted\"} 100\n"));
+            assert!(!text.contains("msg_id=\"100\""));
+        }
+    }
+}
+
+#[cfg(feature = "prometheus")]
+#[tokio::test]
+async fn metrics_route_returns_prometheus_text() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router).with_metrics_route(9000);
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"abc").unwrap())
+        .await
+        .unwrap();
+    read_frame(&mut stream).await;
+    stream
+        .write_all(&DataPack::try_pack(9000, b"").unwrap())
+        .await
+        .unwrap();
+    let (msg_id, data) = read_frame(&mut stream).await;
+    assert_eq!(msg_id, 9000);
+    let text = String::from_utf8(data).unwrap();
+    assert!(text.contains("zerust_connections_active 1\n"));
+    assert!(text.contains("zerust_route_requests_total{msg_id=\"1\"} 1\n"));
+    assert!(text.contains("zerust_route_latency_seconds_bucket{msg_id=\"1\",le=\"+Inf\"} 1\n"));
+    // 指标请求本身不计入指标
+    assert!(!text.contains("msg_id=\"9000\""));
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+
+    // 指标消息ID不能与心跳消息ID相同
+    let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+        .with_heartbeat(Duration::from_secs(1), 3)
+        .with_metrics_route(zerust::server::DEFAULT_HEARTBEAT_MSG_ID);
+    assert!(matches!(
+        server.bind().await,
+        Err(ZerustError::InvalidConfig(_))
+    ));
+}
+
+#[cfg(feature = "prometheus")]
+#[tokio::test]
+async fn metrics_route_is_rate_limited() {
+    let config = RateLimitConfig::new(2).with_policy(RateLimitPolicy::Throttle {
+        throttle_response: Response::new(429, b"slow down".to_vec()),
+    });
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_metrics_route(9000)
+        .with_rate_limit_config(config);
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 令牌桶中只有 2 个令牌，第三次拉取指标得到限流消息
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let batch: Vec<u8> = (0..3)
+        .flat_map(|_| DataPack::try_pack(9000, b"").unwrap())
+        .collect();
+    stream.write_all(&batch).await.unwrap();
+    for _ in 0..2 {
+        assert_eq!(read_frame(&mut stream).await.0, 9000);
+    }
+    assert_eq!(read_frame(&mut stream).await, (429, b"slow down".to_vec()));
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn custom_error_handler_is_used() {
+    let server = Server::new("127.0.0.1:0", fallible_router())
+        .with_error_handler(|msg_id, _| Response::new(msg_id + 1000, b"bad".to_vec()));
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    stream
+        .write_all(&DataPack::try_pack(1, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1001, b"bad".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn slow_async_requests_on_different_connections_overlap() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_async_route(1, |req| async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start_server(router).await;
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    let mut second = TcpStream::connect(addr).await.unwrap();
+
+    let start = tokio::time::Instant::now();
+    first
+        .write_all(&DataPack::try_pack(1, b"a").unwrap())
+        .await
+        .unwrap();
+    second
+        .write_all(&DataPack::try_pack(1, b"b").unwrap())
+        .await
+        .unwrap();
+    let (a, b) = tokio::join!(read_frame(&mut first), read_frame(&mut second));
+    let elapsed = start.elapsed();
+
+    assert_eq!(a, (1, b"a".to_vec()));
+    assert_eq!(b, (1, b"b".to_vec()));
+    // 两个请求并发处理，总耗时应明显小于串行处理的 400ms
+    assert!(elapsed < Duration::from_millis(350), "took {elapsed:?}");
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn oversized_frame_closes_connection() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let (error_tx, mut error_rx) = mpsc::unbounded_channel();
+    let server = Server::new("127.0.0.1:0", router)
+        .with_max_packet_size(16)
+        .with_on_conn_error(move |_, err| {
+            let _ = error_tx.send(match err {
+                ZerustError::MessageTooLarge { size, limit } => Ok((*size, *limit)),
+                other => Err(other.to_string()),
+            });
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 限制以内的消息正常处理
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, &[7u8; 16]).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, vec![7u8; 16]));
+
+    // 只发送一个声明 4 GiB 数据的消息头，服务器回复消息过大后关闭连接
+    let mut header = Vec::new();
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    stream.write_all(&header).await.unwrap();
+    let (msg_id, data) = read_frame(&mut stream).await;
+    let resp = Response::new(msg_id, data);
+    assert_eq!(resp.msg_id(), PAYLOAD_TOO_LARGE_MSG_ID);
+    assert_eq!(resp.error_body(), Some((413, "Payload too large")));
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    // 连接以负载过大的错误结束
+    assert_eq!(error_rx.recv().await, Some(Ok((u32::MAX as u64, 16))));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn oversized_response_can_be_disabled() {
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_max_packet_size(4)
+        .with_oversized_response(None);
+    assert!(server.config().oversized_response().is_none());
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 不发送任何响应，直接关闭连接
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"too big").unwrap())
+        .await
+        .unwrap();
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn handler_close_sends_response_then_closes() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    router
+        .add_route(9, |_| Response::new(9, b"bye".to_vec()).with_close())
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router);
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 关闭连接之后的请求不会被处理
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut batch = DataPack::try_pack(1, b"first").unwrap();
+    batch.extend_from_slice(&DataPack::try_pack(9, b"").unwrap());
+    batch.extend_from_slice(&DataPack::try_pack(1, b"after").unwrap());
+    stream.write_all(&batch).await.unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, b"first".to_vec()));
+    assert_eq!(read_frame(&mut stream).await, (9, b"bye".to_vec()));
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.is_empty());
+    wait_for_connections(&manager, 0).await;
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn stream_route_sends_every_response_in_order() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route_stream(1, |req| {
+            (1..=3u8)
+                .map(|part| Response::new(req.msg_id(), [req.data(), &[part]].concat()))
+                .collect()
+        })
+        .unwrap();
+    router
+        .add_route(2, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start_server(router).await;
+
+    // 两个请求连续发送，第二个请求的响应排在第一个请求的三条响应之后
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"page").unwrap())
+        .await
+        .unwrap();
+    stream
+        .write_all(&DataPack::try_pack(2, b"next").unwrap())
+        .await
+        .unwrap();
+    for part in 1..=3 {
+        assert_eq!(
+            read_frame(&mut stream).await,
+            (1, [&b"page"[..], &[part]].concat())
+        );
+    }
+    assert_eq!(read_frame(&mut stream).await, (2, b"next".to_vec()));
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn zinx_datapack_talks_to_zinx_clients() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| {
+            assert_eq!(req.data(), b"ZinxV0.5 client Test Message");
+            Response::new(req.msg_id(), b"ping...ping...ping".to_vec())
+        })
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router).with_datapack(DataPack::zinx());
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // Zinx 示例客户端发送的字节和期望收到的字节
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"\x1c\x00\x00\x00\x01\x00\x00\x00ZinxV0.5 client Test Message")
+        .await
+        .unwrap();
+    let mut reply = [0; 8 + 18];
+    stream.read_exact(&mut reply).await.unwrap();
+    assert_eq!(
+        &reply,
+        b"\x12\x00\x00\x00\x01\x00\x00\x00ping...ping...ping"
+    );
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn half_closed_client_still_receives_responses() {
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let hook_stopped = stopped.clone();
+    let server = Server::new("127.0.0.1:0", echo_router()).with_on_conn_stop(move |_| {
+        hook_stopped.fetch_add(1, Ordering::SeqCst);
+        async {}
+    });
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 客户端发送请求后关闭写入端，服务器读到正常的结束，仍然发送响应后再关闭
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut batch = DataPack::try_pack(1, b"one").unwrap();
+    batch.extend_from_slice(&DataPack::try_pack(1, b"two").unwrap());
+    stream.write_all(&batch).await.unwrap();
+    stream.shutdown().await.unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, b"one".to_vec()));
+    assert_eq!(read_frame(&mut stream).await, (1, b"two".to_vec()));
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).await.unwrap();
+    assert!(rest.is_empty());
+    wait_for_connections(&manager, 0).await;
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    assert_eq!(stopped.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn only_real_failures_reach_conn_error_hook() {
+    let (error_tx, mut error_rx) = mpsc::unbounded_channel();
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let hook_stopped = stopped.clone();
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_max_packet_size(16)
+        .with_on_conn_error(move |_, err| {
+            let _ = error_tx.send(err.to_string());
+        })
+        .with_on_conn_stop(move |_| {
+            hook_stopped.fetch_add(1, Ordering::SeqCst);
+            async {}
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 连接后立即关闭，以及处理完请求后关闭，都是正常的断开
+    drop(TcpStream::connect(addr).await.unwrap());
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    assert_echo(&mut stream, b"bye").await;
+    drop(stream);
+    while stopped.load(Ordering::SeqCst) < 2 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert!(error_rx.try_recv().is_err());
+
+    // 消息过大和在帧的中间断开才是错误
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, &[0; 32]).unwrap())
+        .await
+        .unwrap();
+    assert!(error_rx.recv().await.unwrap().contains("too large"));
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"cut").unwrap()[..9])
+        .await
+        .unwrap();
+    drop(stream);
+    assert!(error_rx.recv().await.unwrap().contains("middle of a frame"));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    assert!(error_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn idle_connection_is_closed_after_read_timeout() {
+    let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+        .with_read_timeout(Duration::from_millis(100));
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 连接后什么都不发送
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let start = tokio::time::Instant::now();
+    let mut buf = [0u8; 1];
+    let n = tokio::time::timeout(Duration::from_secs(2), stream.read(&mut buf))
+        .await
+        .expect("server did not close the idle connection")
+        .unwrap();
+    assert_eq!(n, 0);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+/// 等待连接管理器中的在线连接数量达到预期值
+async fn wait_for_connections(manager: &ConnManager, expected: usize) {
+    while manager.len() != expected {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+#[tokio::test]
+async fn handler_broadcasts_to_all_connections() {
+    let router = Arc::new(DefaultRouter::new());
+    let server = Server::new("127.0.0.1:0", router.clone());
+    let manager = server.conn_manager();
+    let handler_manager = manager.clone();
+    router
+        .add_route(1, move |req| {
+            let sent = handler_manager.broadcast(Response::new(2, req.data().to_vec()));
+            Response::new(req.msg_id(), sent.to_le_bytes().to_vec())
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut alice = TcpStream::connect(addr).await.unwrap();
+    let mut bob = TcpStream::connect(addr).await.unwrap();
+    wait_for_connections(&manager, 2).await;
+
+    alice
+        .write_all(&DataPack::try_pack(1, b"hi").unwrap())
+        .await
+        .unwrap();
+    // 消息按进入发送队列的顺序发送：发送方先收到处理函数中发出的广播，再收到处理结果；
+    // 其他连接只收到广播
+    assert_eq!(read_frame(&mut alice).await, (2, b"hi".to_vec()));
+    assert_eq!(
+        read_frame(&mut alice).await,
+        (1, 2usize.to_le_bytes().to_vec())
+    );
+    assert_eq!(read_frame(&mut bob).await, (2, b"hi".to_vec()));
+
+    // 断开的连接会从管理器中移除
+    drop(bob);
+    wait_for_connections(&manager, 1).await;
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    assert!(manager.is_empty());
+}
+
+#[tokio::test]
+async fn server_broadcasts_to_filtered_connections() {
+    let router = Arc::new(DefaultRouter::new());
+    // 发送消息 1 的连接被标记为已订阅
+    router
+        .add_route(1, |req| {
+            req.context().unwrap().set_property("subscribed", true);
+            Response::new(req.msg_id(), Vec::new())
+        })
+        .unwrap();
+    let server = Arc::new(Server::new("127.0.0.1:0", router));
+    let manager = server.conn_manager();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn({
+        let server = server.clone();
+        async move { server.run_on(listener, shutdown_rx).await }
+    });
+
+    let mut subscriber = TcpStream::connect(addr).await.unwrap();
+    let mut other = TcpStream::connect(addr).await.unwrap();
+    wait_for_connections(&manager, 2).await;
+    subscriber
+        .write_all(&DataPack::try_pack(1, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut subscriber).await, (1, Vec::new()));
+
+    let report = server.broadcast_filter(7, &b"tick"[..], |handle| {
+        handle
+            .context()
+            .get_property::<bool>("subscribed")
+            .is_some()
+    });
+    assert_eq!((report.sent(), report.failed()), (1, 0));
+    let report = server.broadcast(8, b"all".to_vec());
+    assert_eq!((report.sent(), report.failed()), (2, 0));
+
+    // 未订阅的连接只收到面向所有连接的广播
+    assert_eq!(read_frame(&mut subscriber).await, (7, b"tick".to_vec()));
+    assert_eq!(read_frame(&mut subscriber).await, (8, b"all".to_vec()));
+    assert_eq!(read_frame(&mut other).await, (8, b"all".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+/// 不断推送大消息，直到客户端不读取数据导致发送队列溢出
+///
+/// 套接字的缓冲区被填满后写入任务停下来，之后的消息留在发送队列中。
+async fn fill_write_queue(handle: &ConnectionHandle, payload: &Response) {
+    for _ in 0..10_000 {
+        match handle.send(payload.clone()) {
+            Ok(()) => tokio::time::sleep(Duration::from_millis(1)).await,
+            Err(ZerustError::WriteQueueFull) => return,
+            Err(e) => panic!("unexpected error: {e}"),
+        }
+    }
+    panic!("write queue never filled up");
+}
+
+#[tokio::test]
+async fn write_queue_drops_pushes_to_stalled_reader() {
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_write_queue_capacity(4)
+        .with_write_queue_policy(WriteQueuePolicy::Drop);
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 客户端连接后不读取任何数据
+    let mut stalled = TcpStream::connect(addr).await.unwrap();
+    wait_for_connections(&manager, 1).await;
+    let handle = manager.get(1).unwrap();
+    let payload = Response::new(2, vec![7; 64 * 1024]);
+    fill_write_queue(&handle, &payload).await;
+
+    // 队列停在容量上限，之后的推送被丢弃并报告，连接保持在线
+    assert_eq!((handle.queued(), handle.queue_capacity()), (4, 4));
+    assert!(matches!(
+        manager.send_to(1, payload.clone()),
+        Err(ZerustError::WriteQueueFull)
+    ));
+    let report = manager.broadcast_filter(payload.clone(), |_| true);
+    assert_eq!(
+        (report.sent(), report.dropped(), report.failed()),
+        (0, 1, 0)
+    );
+    assert_eq!(handle.queued(), 4);
+    assert_eq!(manager.len(), 1);
+
+    // 等待空位的发送会阻塞，直到客户端开始读取
+    let blocked = tokio::time::timeout(
+        Duration::from_millis(100),
+        manager.send_to_wait(1, payload.clone()),
+    )
+    .await;
+    assert!(blocked.is_err());
+    let waiting = tokio::spawn({
+        let handle = handle.clone();
+        async move { handle.send_wait(Response::new(3, b"last".to_vec())).await }
+    });
+    loop {
+        let (msg_id, data) = read_frame(&mut stalled).await;
+        if msg_id == 3 {
+            assert_eq!(data, b"last");
+            break;
+        }
+        assert_eq!((msg_id, data.len()), (2, 64 * 1024));
+    }
+    waiting.await.unwrap().unwrap();
+
+    drop(stalled);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn write_queue_overflow_disconnects_stalled_reader() {
+    let (error_tx, mut error_rx) = mpsc::unbounded_channel();
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_write_queue_capacity(4)
+        .with_write_queue_policy(WriteQueuePolicy::Disconnect)
+        .with_on_conn_error(move |_, err| {
+            let _ = error_tx.send(matches!(err, ZerustError::WriteQueueFull));
+        });
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let stalled = TcpStream::connect(addr).await.unwrap();
+    let mut reader = TcpStream::connect(addr).await.unwrap();
+    wait_for_connections(&manager, 2).await;
+    fill_write_queue(
+        &manager.get(1).unwrap(),
+        &Response::new(2, vec![7; 64 * 1024]),
+    )
+    .await;
+
+    // 溢出的连接被断开，另一个连接不受影响
+    assert!(error_rx.recv().await.unwrap());
+    wait_for_connections(&manager, 1).await;
+    assert!(matches!(
+        manager.send_to(1, Response::new(2, Vec::new())),
+        Err(ZerustError::ConnectionClosed)
+    ));
+    assert_echo(&mut reader, b"still here").await;
+
+    drop((stalled, reader));
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn write_timeout_closes_connection_that_never_reads() {
+    let (error_tx, mut error_rx) = mpsc::unbounded_channel();
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_write_timeout(Duration::from_millis(100))
+        .with_on_conn_error(move |_, err| {
+            let _ = error_tx.send(matches!(err, ZerustError::Timeout));
+        });
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 客户端从不读取，足够大的消息会填满内核缓冲区并导致写入超时
+    let stalled = TcpStream::connect(addr).await.unwrap();
+    wait_for_connections(&manager, 1).await;
+    let handle = manager.get(1).unwrap();
+    handle
+        .send(Response::new(2, vec![0; 64 * 1024 * 1024]))
+        .unwrap();
+
+    assert!(error_rx.recv().await.unwrap());
+    wait_for_connections(&manager, 0).await;
+
+    drop(stalled);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn request_carries_conn_id_for_lookup() {
+    let router = Arc::new(DefaultRouter::new());
+    let server = Server::new("127.0.0.1:0", router.clone());
+    let manager = server.conn_manager();
+    router
+        .add_route(1, move |req| {
+            // 通过连接ID找到发送请求的连接，并推送一条额外的消息
+            let handle = manager.get(req.conn_id()).unwrap();
+            handle.send(Response::new(2, b"pushed".to_vec())).unwrap();
+            // 不存在的连接无法推送
+            assert!(
+                manager
+                    .send_to(u64::MAX, Response::new(2, Vec::new()))
+                    .is_err()
+            );
+            Response::new(req.msg_id(), req.conn_id().to_le_bytes().to_vec())
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"").unwrap())
+        .await
+        .unwrap();
+    // 处理函数中推送的消息先于响应进入发送队列
+    assert_eq!(read_frame(&mut stream).await, (2, b"pushed".to_vec()));
+    let (msg_id, data) = read_frame(&mut stream).await;
+    assert_eq!(msg_id, 1);
+    assert_eq!(u64::from_le_bytes(data.try_into().unwrap()), 1);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn connection_count_tracks_connects_and_disconnects() {
+    let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()));
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let first = TcpStream::connect(addr).await.unwrap();
+    let _second = TcpStream::connect(addr).await.unwrap();
+    let _third = TcpStream::connect(addr).await.unwrap();
+    wait_for_connections(&manager, 3).await;
+    assert_eq!(manager.connection_count(), 3);
+    // 连接ID从 1 开始单调递增
+    for conn_id in 1..=3 {
+        assert_eq!(manager.get(conn_id).unwrap().conn_id(), conn_id);
+    }
+
+    drop(first);
+    wait_for_connections(&manager, 2).await;
+    assert_eq!(manager.connection_count(), 2);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn conn_hooks_fire_once_per_connection() {
+    let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+    let start_tx = events_tx.clone();
+    let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()))
+        .with_max_packet_size(4)
+        .with_on_conn_start(move |conn| {
+            let events = start_tx.clone();
+            async move {
+                let _ = events.send(("start", conn.conn_id(), conn.remote_addr()));
+            }
+        })
+        .with_on_conn_stop(move |conn| {
+            let events = events_tx.clone();
+            async move {
+                let _ = events.send(("stop", conn.conn_id(), conn.remote_addr()));
+            }
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 第一个连接正常关闭，第二个连接因消息过大而出错
+    let first = TcpStream::connect(addr).await.unwrap();
+    let first_addr = first.local_addr().unwrap();
+    assert_eq!(events_rx.recv().await.unwrap(), ("start", 1, first_addr));
+    drop(first);
+    assert_eq!(events_rx.recv().await.unwrap(), ("stop", 1, first_addr));
+
+    let mut second = TcpStream::connect(addr).await.unwrap();
+    let second_addr = second.local_addr().unwrap();
+    assert_eq!(events_rx.recv().await.unwrap(), ("start", 2, second_addr));
+    second
+        .write_all(&DataPack::try_pack(1, b"too large").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(events_rx.recv().await.unwrap(), ("stop", 2, second_addr));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    // 所有钩子已执行完毕，没有重复的停止事件
+    drop(second);
+    assert!(events_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn conn_start_hook_sends_banner_before_responses() {
+    let server = Server::new("127.0.0.1:0", echo_router()).with_on_conn_start(|conn| async move {
+        conn.send(Response::new(100, b"welcome".to_vec())).unwrap();
+    });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 请求在连接后立即发送，客户端仍然先读到欢迎消息
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"hi").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut stream).await, (100, b"welcome".to_vec()));
+    assert_eq!(read_frame(&mut stream).await, (1, b"hi".to_vec()));
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn properties_persist_across_requests_until_stop_hook() {
+    let router = Arc::new(DefaultRouter::new());
+    // 登录时记录用户名，之后的请求读取它
+    router
+        .add_route(1, |req| {
+            let context = req.context().unwrap();
+            context.set_property("user", String::from_utf8_lossy(req.data()).into_owned());
+            Response::new(req.msg_id(), b"ok".to_vec())
+        })
+        .unwrap();
+    router
+        .add_route(2, |req| {
+            let user = req.context().unwrap().get_property::<String>("user");
+            let reply = user.map_or_else(|| b"anonymous".to_vec(), |user| user.as_bytes().to_vec());
+            Response::new(req.msg_id(), reply)
+        })
+        .unwrap();
+    let (stop_tx, mut stop_rx) = mpsc::unbounded_channel();
+    let server = Server::new("127.0.0.1:0", router).with_on_conn_stop(move |conn| {
+        let stop_tx = stop_tx.clone();
+        async move {
+            let user = conn.context().get_property::<String>("user");
+            let _ = stop_tx.send((user, conn));
+        }
+    });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client
+        .write_all(&DataPack::try_pack(2, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut client).await, (2, b"anonymous".to_vec()));
+    client
+        .write_all(&DataPack::try_pack(1, b"alice").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut client).await, (1, b"ok".to_vec()));
+    client
+        .write_all(&DataPack::try_pack(2, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut client).await, (2, b"alice".to_vec()));
+
+    // 停止钩子可以读取属性，之后属性被释放
+    drop(client);
+    let (user, conn) = stop_rx.recv().await.unwrap();
+    assert_eq!(user.as_deref().map(String::as_str), Some("alice"));
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    assert!(conn.context().get_property::<String>("user").is_none());
+}
+
+#[tokio::test]
+async fn chat_message_reaches_every_other_client() {
+    let router = Arc::new(DefaultRouter::new());
+    let server = Server::new("127.0.0.1:0", router.clone());
+    let manager = server.conn_manager();
+    let handler_manager = manager.clone();
+    router
+        .add_route(1, move |req| {
+            let message = Response::new(2, req.data().to_vec());
+            handler_manager.broadcast_except(req.conn_id(), message);
+            Response::new(req.msg_id(), b"sent".to_vec())
+        })
+        .unwrap();
+    // 通过 send_to 把消息发给指定连接
+    let direct_manager = manager.clone();
+    router
+        .add_route(3, move |req| {
+            let target = u64::from_le_bytes(req.data().try_into().unwrap());
+            let result = direct_manager.send_to(target, Response::new(4, b"direct".to_vec()));
+            Response::new(req.msg_id(), vec![result.is_ok() as u8])
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut alice = TcpStream::connect(addr).await.unwrap();
+    let mut bob = TcpStream::connect(addr).await.unwrap();
+    let mut carol = TcpStream::connect(addr).await.unwrap();
+    wait_for_connections(&manager, 3).await;
+
+    alice
+        .write_all(&DataPack::try_pack(1, b"hello").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut alice).await, (1, b"sent".to_vec()));
+    assert_eq!(read_frame(&mut bob).await, (2, b"hello".to_vec()));
+    assert_eq!(read_frame(&mut carol).await, (2, b"hello".to_vec()));
+
+    // carol 的连接ID为 3，alice 给她单独发一条消息
+    alice
+        .write_all(&DataPack::try_pack(3, &3u64.to_le_bytes()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut alice).await, (3, vec![1]));
+    assert_eq!(read_frame(&mut carol).await, (4, b"direct".to_vec()));
+
+    // alice 没有收到自己发出的聊天消息，下一帧是对这次请求的响应
+    alice
+        .write_all(&DataPack::try_pack(3, &9u64.to_le_bytes()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut alice).await, (3, vec![0]));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+/// 创建一个回显路由器，msg_id = 1 原样返回请求数据
+fn echo_router() -> Arc<DefaultRouter> {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    router
+}
+
+/// 发送一次回显请求并断言收到相同的数据
+async fn assert_echo(stream: &mut TcpStream, data: &[u8]) {
+    stream
+        .write_all(&DataPack::try_pack(1, data).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(stream).await, (1, data.to_vec()));
+}
+
+#[tokio::test]
+async fn idle_connections_are_closed_by_sweeper() {
+    let idle_timeout = Duration::from_millis(200);
+    let (error_tx, mut error_rx) = mpsc::unbounded_channel();
+    let (stop_tx, mut stop_rx) = mpsc::unbounded_channel();
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_idle_timeout(idle_timeout)
+        .with_on_conn_error(move |conn, _| {
+            let _ = error_tx.send(conn.conn_id());
+        })
+        .with_on_conn_stop(move |conn| {
+            let _ = stop_tx.send(conn.conn_id());
+            async {}
+        });
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 持续发送请求的连接不会被关闭
+    let mut idle = TcpStream::connect(addr).await.unwrap();
+    let mut active = TcpStream::connect(addr).await.unwrap();
+    assert_echo(&mut idle, b"once").await;
+    let started = tokio::time::Instant::now();
+    while started.elapsed() < idle_timeout * 2 {
+        assert_echo(&mut active, b"tick").await;
+        tokio::time::sleep(Duration::from_millis(40)).await;
+    }
+
+    // 空闲的连接在超过空闲时间后被关闭，属于正常关闭，不会调用错误钩子
+    let mut buf = [0u8; 1];
+    assert_eq!(idle.read(&mut buf).await.unwrap(), 0);
+    assert_eq!(stop_rx.recv().await, Some(1));
+    assert!(error_rx.try_recv().is_err());
+    wait_for_connections(&manager, 1).await;
+    let handle = manager.get(2).unwrap();
+    assert!(handle.idle_time() < idle_timeout);
+    assert_echo(&mut active, b"still here").await;
+
+    drop((idle, active));
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn requests_slower_than_idle_timeout_are_not_evicted() {
+    let idle_timeout = Duration::from_millis(100);
+    for worker_pool in [false, true] {
+        let router = echo_router();
+        router
+            .add_async_route(2, |req| async move {
+                tokio::time::sleep(Duration::from_millis(400)).await;
+                Response::new(req.msg_id(), req.data().to_vec())
+            })
+            .unwrap();
+        let mut server = Server::new("127.0.0.1:0", router).with_idle_timeout(idle_timeout);
+        if worker_pool {
+            server = server.with_worker_pool(2, 16);
+        }
+        let (addr, shutdown_tx, server_handle) = start(server).await;
+
+        // 处理期间连接不算空闲，响应正常送达
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(&DataPack::try_pack(2, b"slow").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(read_frame(&mut stream).await, (2, b"slow".to_vec()));
+
+        // 处理完成后重新开始计算空闲时间
+        assert_echo(&mut stream, b"after").await;
+        let mut buf = [0u8; 1];
+        let closed = tokio::time::timeout(Duration::from_secs(2), stream.read(&mut buf)).await;
+        assert_eq!(closed.unwrap().unwrap(), 0);
+
+        let _ = shutdown_tx.send(());
+        server_handle.await.unwrap().unwrap();
+    }
+}
+
+#[tokio::test]
+async fn connections_over_limit_are_rejected_with_busy_message() {
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_max_connections(2)
+        .with_conn_limit_policy(ConnLimitPolicy::Reject {
+            busy_response: Some(Response::new(503, b"busy".to_vec())),
+        });
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    let mut second = TcpStream::connect(addr).await.unwrap();
+    wait_for_connections(&manager, 2).await;
+
+    // 第三个连接收到繁忙消息后被关闭
+    let mut third = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(read_frame(&mut third).await, (503, b"busy".to_vec()));
+    let mut buf = [0u8; 1];
+    assert_eq!(third.read(&mut buf).await.unwrap(), 0);
+    assert_eq!(manager.connection_count(), 2);
+
+    // 已有的连接不受影响
+    assert_echo(&mut first, b"one").await;
+    assert_echo(&mut second, b"two").await;
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn rejected_connections_are_not_counted_in_shutdown_report() {
+    // 繁忙消息足够大，客户端不读取时拒绝任务会一直写到关闭期间
+    let server = Arc::new(
+        Server::new("127.0.0.1:0", echo_router())
+            .with_max_connections(1)
+            .with_conn_limit_policy(ConnLimitPolicy::Reject {
+                busy_response: Some(Response::new(503, vec![0; 4 * 1024 * 1024])),
+            }),
+    );
+    let manager = server.conn_manager();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn({
+        let server = server.clone();
+        async move { server.run_on(listener, shutdown_rx).await }
+    });
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    assert_echo(&mut first, b"one").await;
+    let _rejected = TcpStream::connect(addr).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(manager.connection_count(), 1);
+
+    // 只有真正服务过的连接计入统计
+    let report = server.shutdown_graceful(Duration::from_secs(5)).await;
+    assert_eq!((report.closed_cleanly(), report.force_closed()), (1, 0));
+    assert_eq!(server_handle.await.unwrap().unwrap(), report);
+}
+
+#[tokio::test]
+async fn connections_over_limit_wait_for_a_free_slot() {
+    let server = Server::new("127.0.0.1:0", echo_router()).with_max_connections(1);
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut first = TcpStream::connect(addr).await.unwrap();
+    assert_echo(&mut first, b"one").await;
+
+    // 第二个连接在监听队列中等待，请求暂时得不到处理
+    let mut second = TcpStream::connect(addr).await.unwrap();
+    second
+        .write_all(&DataPack::try_pack(1, b"two").unwrap())
+        .await
+        .unwrap();
+    let waiting = tokio::time::timeout(Duration::from_millis(100), read_frame(&mut second)).await;
+    assert!(waiting.is_err());
+    assert_eq!(manager.connection_count(), 1);
+
+    // 第一个连接结束后，第二个连接被接受并处理之前发送的请求
+    drop(first);
+    assert_eq!(read_frame(&mut second).await, (1, b"two".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+/// 创建一个慢速路由器，msg_id = 1 的请求需要等待数据中指定的毫秒数后才返回
+fn slow_router() -> Arc<DefaultRouter> {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_async_route(1, |req| async move {
+            let millis = u64::from_le_bytes(req.data().try_into().unwrap());
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            Response::new(req.msg_id(), b"done".to_vec())
+        })
+        .unwrap();
+    router
+}
+
+#[tokio::test]
+async fn graceful_shutdown_lets_slow_request_finish() {
+    let server =
+        Server::new("127.0.0.1:0", slow_router()).with_shutdown_mode(ShutdownMode::Graceful {
+            timeout: Duration::from_secs(5),
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, &200u64.to_le_bytes()).unwrap())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let _ = shutdown_tx.send(());
+
+    // 关闭期间不再接受新连接
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(TcpStream::connect(addr).await.is_err());
+
+    // 进行中的请求在超时之前完成
+    assert_eq!(read_frame(&mut stream).await, (1, b"done".to_vec()));
+    let report = server_handle.await.unwrap().unwrap();
+    assert_eq!(report.force_closed(), 0);
+}
+
+#[tokio::test]
+async fn graceful_shutdown_force_closes_after_timeout() {
+    let (stopped_tx, mut stopped_rx) = tokio::sync::mpsc::unbounded_channel();
+    let server = Server::new("127.0.0.1:0", slow_router())
+        .with_shutdown_mode(ShutdownMode::Graceful {
+            timeout: Duration::from_millis(50),
+        })
+        .with_on_conn_stop(move |conn| {
+            let stopped = stopped_tx.clone();
+            async move {
+                let _ = stopped.send(conn.conn_id());
+            }
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, &10_000u64.to_le_bytes()).unwrap())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let _ = shutdown_tx.send(());
+
+    // 请求没有在超时之前完成，连接被强制关闭，停止钩子仍然执行
+    let report = server_handle.await.unwrap().unwrap();
+    assert_eq!(report.force_closed(), 1);
+    assert_eq!(stopped_rx.recv().await, Some(1));
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn shutdown_graceful_drains_or_forces_connections() {
+    let server = Arc::new(Server::new("127.0.0.1:0", slow_router()));
+    for (timeout, clean, forced) in [(5_000, 2, 0), (500, 1, 1)] {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server_handle = tokio::spawn({
+            let server = server.clone();
+            async move { server.run_on(listener, shutdown_rx).await }
+        });
+
+        // 一个连接的请求需要 200ms，另一个连接的请求较快或者远远超过等待时间
+        let mut slow = TcpStream::connect(addr).await.unwrap();
+        let mut slower = TcpStream::connect(addr).await.unwrap();
+        let slower_millis: u64 = if forced == 0 { 300 } else { 10_000 };
+        slow.write_all(&DataPack::try_pack(1, &200u64.to_le_bytes()).unwrap())
+            .await
+            .unwrap();
+        slower
+            .write_all(&DataPack::try_pack(1, &slower_millis.to_le_bytes()).unwrap())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let report = server
+            .shutdown_graceful(Duration::from_millis(timeout))
+            .await;
+        assert_eq!(
+            (report.closed_cleanly(), report.force_closed()),
+            (clean, forced)
+        );
+        assert_eq!(server_handle.await.unwrap().unwrap(), report);
+
+        // 正常关闭的连接先收到响应再关闭，被强制关闭的连接没有响应
+        assert_eq!(read_frame(&mut slow).await, (1, b"done".to_vec()));
+        let mut rest = Vec::new();
+        slow.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+        let mut rest = Vec::new();
+        slower.read_to_end(&mut rest).await.unwrap();
+        if forced == 0 {
+            assert_eq!(rest, DataPack::try_pack(1, b"done").unwrap());
+        } else {
+            assert!(rest.is_empty());
+        }
+    }
+
+    // 运行开始之前的关闭请求由该次运行处理，运行开始后立即关闭
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn({
+        let server = server.clone();
+        async move { server.run_on(listener, shutdown_rx).await }
+    });
+    let report = tokio::time::timeout(
+        Duration::from_secs(5),
+        server.shutdown_graceful(Duration::from_secs(5)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report, ShutdownReport::default());
+    assert_eq!(server_handle.await.unwrap().unwrap(), report);
+}
+
+#[tokio::test]
+async fn handlers_see_connection_context() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| {
+            let ctx = req.context().unwrap();
+            assert!(ctx.connected_at().elapsed() < Duration::from_secs(5));
+            assert_eq!(req.remote_addr(), Some(ctx.remote_addr()));
+            let text = format!("{} {}", ctx.conn_id(), ctx.remote_addr());
+            Response::new(req.msg_id(), text.into_bytes())
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start_server(router).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"").unwrap())
+        .await
+        .unwrap();
+    let expected = format!("1 {}", stream.local_addr().unwrap());
+    assert_eq!(read_frame(&mut stream).await, (1, expected.into_bytes()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn login_is_bound_to_the_connection() {
+    // 记录已登录的连接ID
+    let logged_in = Arc::new(std::sync::Mutex::new(std::collections::HashSet::new()));
+    let router = Arc::new(DefaultRouter::new());
+    let login = logged_in.clone();
+    router
+        .add_route(1, move |req| {
+            login.lock().unwrap().insert(req.conn_id());
+            Response::new(req.msg_id(), b"welcome".to_vec())
+        })
+        .unwrap();
+    router
+        .add_route(2, move |req| {
+            if logged_in.lock().unwrap().contains(&req.conn_id()) {
+                Response::new(req.msg_id(), b"secret".to_vec())
+            } else {
+                Response::new(req.msg_id(), b"denied".to_vec())
+            }
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start_server(router).await;
+
+    let mut alice = TcpStream::connect(addr).await.unwrap();
+    let mut mallory = TcpStream::connect(addr).await.unwrap();
+    alice
+        .write_all(&DataPack::try_pack(1, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut alice).await, (1, b"welcome".to_vec()));
+
+    // 登录状态只属于登录的那个连接
+    alice
+        .write_all(&DataPack::try_pack(2, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut alice).await, (2, b"secret".to_vec()));
+    mallory
+        .write_all(&DataPack::try_pack(2, b"").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut mallory).await, (2, b"denied".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn handler_panic_returns_error_response_and_keeps_connection() {
+    let router = echo_router();
+    router.add_route(2, |_| panic!("handler bug")).unwrap();
+    router
+        .add_async_route(3, |_| async { panic!("async handler bug {}", 3) })
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router);
+    let manager = server.conn_manager();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 同步和异步处理函数的 panic 都转换为 500 响应，连接继续处理后续请求
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    for (msg_id, message) in [(2, "handler bug"), (3, "async handler bug 3")] {
+        stream
+            .write_all(&DataPack::try_pack(msg_id, b"").unwrap())
+            .await
+            .unwrap();
+        let (msg_id, data) = read_frame(&mut stream).await;
+        let resp = Response::new(msg_id, data);
+        let expected = format!("Handler panicked: {message}");
+        assert_eq!(resp.msg_id(), 500);
+        assert_eq!(resp.error_body(), Some((500, expected.as_str())));
+        assert_echo(&mut stream, b"after panic").await;
+    }
+    assert_eq!(manager.len(), 1);
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn handler_panic_reaches_error_handler_in_worker_pool() {
+    let router = echo_router();
+    router.add_route(2, |_| panic!("handler bug")).unwrap();
+    let server = Server::new("127.0.0.1:0", router)
+        .with_worker_pool(1, 4)
+        .with_error_handler(|msg_id, err| match err {
+            ZerustError::HandlerPanic(message) => {
+                Response::new(msg_id, message.clone().into_bytes())
+            }
+            err => Response::internal_error(err),
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 唯一的工作任务没有因为 panic 结束，之后的请求仍然由它处理
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    for _ in 0..2 {
+        stream
+            .write_all(&DataPack::try_pack(2, b"").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(read_frame(&mut stream).await, (2, b"handler bug".to_vec()));
+        assert_echo(&mut stream, b"still working").await;
+    }
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn big_endian_server_round_trip() {
+    let pack = DataPack::with_order(ByteOrderMode::Big);
+    let server = Server::new("127.0.0.1:0", echo_router()).with_datapack(pack);
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&pack.try_encode(1, b"network").unwrap())
+        .await
+        .unwrap();
+    let mut frame = [0u8; 8 + 7];
+    stream.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &pack.try_encode(1, b"network").unwrap()[..]);
+    assert_eq!(&frame[..4], &[0, 0, 0, 1]);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn builder_stores_options_and_serves() {
+    let server = Server::builder()
+        .addr("127.0.0.1:0")
+        .router(echo_router())
+        .read_timeout(Duration::from_secs(30))
+        .max_packet_size(1024)
+        .max_connections(8)
+        .nodelay(false)
+        .keepalive(Duration::from_secs(60))
+        .linger(Duration::ZERO)
+        .read_buffer_size(3)
+        .build();
+
+    let config = server.config();
+    assert_eq!(config.addr(), "127.0.0.1:0");
+    assert_eq!(config.read_timeout(), Some(Duration::from_secs(30)));
+    assert_eq!(config.max_packet_size(), 1024);
+    assert_eq!(config.read_buffer_size(), 3);
+    assert_eq!(config.max_connections(), Some(8));
+    assert!(!config.nodelay());
+    assert_eq!(config.keepalive(), Some(Duration::from_secs(60)));
+    assert_eq!(config.linger(), Some(Duration::ZERO));
+    assert_eq!(config.shutdown_mode(), ShutdownMode::Wait);
+
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    assert_echo(&mut stream, b"built").await;
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+/// 前几次接受连接时依次返回给定错误的监听器
+struct FlakyListener {
+    inner: tokio::net::TcpListener,
+    errors: std::sync::Mutex<Vec<io::Error>>,
+}
+
+impl FlakyListener {
+    async fn bind(errors: Vec<io::Error>) -> Self {
+        let inner = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let errors = std::sync::Mutex::new(errors.into_iter().rev().collect());
+        Self { inner, errors }
+    }
+}
+
+impl Listen for FlakyListener {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        if let Some(err) = self.errors.lock().unwrap().pop() {
+            return Err(err);
+        }
+        self.inner.accept().await
+    }
+}
+
+#[tokio::test]
+async fn transient_accept_errors_do_not_stop_the_server() {
+    // 客户端提前断开，以及文件描述符耗尽（EMFILE）
+    let errors = vec![
+        io::Error::from(io::ErrorKind::ConnectionAborted),
+        io::Error::other("too many open files"),
+    ];
+    let listener = FlakyListener::bind(errors).await;
+    let addr = listener.inner.local_addr().unwrap();
+    let server = Server::new("127.0.0.1:0", echo_router()).with_accept_error_policy(
+        AcceptErrorPolicy::Retry {
+            backoff: Duration::from_millis(20),
+        },
+    );
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(async move { server.run_on(listener, shutdown_rx).await });
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    assert_echo(&mut stream, b"still serving").await;
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn fatal_accept_error_policy_stops_the_server() {
+    let listener =
+        FlakyListener::bind(vec![io::Error::from(io::ErrorKind::ConnectionAborted)]).await;
+    let server = Server::builder()
+        .router(echo_router())
+        .accept_error_policy(AcceptErrorPolicy::Fatal)
+        .build();
+    assert_eq!(
+        server.config().accept_error_policy(),
+        AcceptErrorPolicy::Fatal
+    );
+
+    let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+    let err = server.run_on(listener, shutdown_rx).await.unwrap_err();
+    assert!(matches!(err, ZerustError::IoError(e) if e.kind() == io::ErrorKind::ConnectionAborted));
+}
+
+#[test]
+fn server_new_uses_default_options() {
+    let server = Server::new("127.0.0.1:0", Arc::new(DefaultRouter::new()));
+    let config = server.config();
+    assert_eq!(config.addr(), "127.0.0.1:0");
+    assert_eq!(config.read_timeout(), None);
+    assert_eq!(config.write_timeout(), None);
+    assert_eq!(config.idle_timeout(), None);
+    assert_eq!(config.max_packet_size(), DEFAULT_MAX_PACKET_SIZE);
+    assert_eq!(config.max_connections(), None);
+    assert!(config.nodelay());
+    assert_eq!(config.keepalive(), None);
+    assert_eq!(config.linger(), None);
+    assert_eq!(config.read_buffer_size(), DEFAULT_READ_BUFFER_SIZE);
+    assert_eq!(
+        config.accept_error_policy(),
+        AcceptErrorPolicy::Retry {
+            backoff: DEFAULT_ACCEPT_BACKOFF
+        }
+    );
+}
+
+#[tokio::test]
+async fn invalid_config_is_rejected() {
+    let invalid = [
+        Server::builder().max_connections(0).try_build(),
+        Server::builder().read_buffer_size(0).try_build(),
+        Server::builder().read_timeout(Duration::ZERO).try_build(),
+        Server::builder().write_timeout(Duration::ZERO).try_build(),
+        Server::builder().idle_timeout(Duration::ZERO).try_build(),
+        Server::builder().heartbeat(Duration::ZERO, 3).try_build(),
+        Server::builder()
+            .heartbeat(Duration::from_secs(1), 0)
+            .try_build(),
+        Server::builder().max_packet_size(0).try_build(),
+        Server::builder().worker_pool(0, 16).try_build(),
+        Server::builder().worker_pool(4, 0).try_build(),
+        Server::builder().rate_limit(0).try_build(),
+        Server::builder()
+            .rate_limit_config(RateLimitConfig::new(10).with_burst(0))
+            .try_build(),
+    ];
+    for result in invalid {
+        assert!(matches!(result, Err(ZerustError::InvalidConfig(_))));
+    }
+    assert!(Server::builder().max_connections(1).try_build().is_ok());
+
+    // 通过 with_* 方法设置的无效配置在绑定时被拒绝
+    let server = Server::new("127.0.0.1:0", echo_router()).with_max_connections(0);
+    assert!(matches!(
+        server.config().validate(),
+        Err(ZerustError::InvalidConfig(msg)) if msg.contains("max_connections")
+    ));
+    assert!(matches!(
+        server.bind().await,
+        Err(ZerustError::InvalidConfig(msg)) if msg.contains("max_connections")
+    ));
+    let server = Server::new("127.0.0.1:0", echo_router()).with_read_buffer_size(0);
+    assert!(matches!(
+        server.bind().await,
+        Err(ZerustError::InvalidConfig(msg)) if msg.contains("read_buffer_size")
+    ));
+}
+
+#[tokio::test]
+async fn heartbeat_closes_unresponsive_connection() {
+    let interval = Duration::from_millis(100);
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let counter = stopped.clone();
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_heartbeat(interval, 2)
+        .with_on_conn_stop(move |_| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 客户端连接后不再发送任何数据
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let started = tokio::time::Instant::now();
+    for _ in 0..2 {
+        assert_eq!(
+            read_frame(&mut stream).await,
+            (DEFAULT_HEARTBEAT_MSG_ID, Vec::new())
+        );
+    }
+
+    // 连续 2 次心跳没有回应，第 3 个间隔结束时连接被关闭
+    let mut buf = [0u8; 1];
+    assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    assert!(started.elapsed() >= interval * 3 - Duration::from_millis(20));
+
+    // 被心跳关闭的连接同样会调用停止钩子
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    assert_eq!(stopped.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn heartbeat_replies_keep_connection_alive() {
+    let replies = Arc::new(AtomicUsize::new(0));
+    let counter = replies.clone();
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_heartbeat_config(
+            HeartbeatConfig::new(Duration::from_millis(50), 1)
+                .with_msg_id(2)
+                .with_payload(b"ping".to_vec()),
+        )
+        .with_on_heartbeat(move |req| {
+            let counter = counter.clone();
+            async move {
+                assert_eq!(req.data(), b"pong");
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 每次收到心跳都回应，连接在多个间隔之后仍然可用
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    for _ in 0..4 {
+        assert_eq!(read_frame(&mut stream).await, (2, b"ping".to_vec()));
+        stream
+            .write_all(&DataPack::try_pack(2, b"pong").unwrap())
+            .await
+            .unwrap();
+    }
+    assert_echo(&mut stream, b"still alive").await;
+    assert_eq!(replies.load(Ordering::SeqCst), 4);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn server_answers_ping_without_routing_it() {
+    let routed = Arc::new(AtomicUsize::new(0));
+    let counter = routed.clone();
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, move |req| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    let fallback = routed.clone();
+    router.set_fallback(move |req| {
+        fallback.fetch_add(1, Ordering::SeqCst);
+        Response::not_found(req.msg_id())
+    });
+    let (addr, shutdown_tx, server_handle) = start_server(router).await;
+
+    // 不认识的控制帧被丢弃，同样不会交给兜底函数
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(RESERVED_MSG_ID_START, b"unknown").unwrap())
+        .await
+        .unwrap();
+
+    // pong 的数据与 ping 相同，ping 不会交给路由器
+    stream
+        .write_all(&DataPack::try_pack(PING_MSG_ID, b"probe").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        read_frame(&mut stream).await,
+        (PONG_MSG_ID, b"probe".to_vec())
+    );
+    assert_echo(&mut stream, b"after ping").await;
+    assert_eq!(routed.load(Ordering::SeqCst), 1);
+
+    // 客户端可以通过 ping 测量往返时间
+    let mut client = Client::connect(addr).await.unwrap();
+    let rtt = client.ping(Duration::from_secs(5)).await.unwrap();
+    assert!(rtt < Duration::from_secs(5));
+    assert_eq!(client.request(1, b"echo").await.unwrap().data(), b"echo");
+
+    drop((stream, client));
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn default_heartbeat_is_answered_by_client() {
+    let pongs = Arc::new(AtomicUsize::new(0));
+    let counter = pongs.clone();
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_heartbeat(Duration::from_millis(50), 1)
+        .with_on_heartbeat(move |req| {
+            let counter = counter.clone();
+            async move {
+                assert_eq!(req.msg_id(), PONG_MSG_ID);
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 客户端读取时自动回复 ping，连接在多个心跳间隔之后仍然可用
+    let mut client = Client::connect(addr).await.unwrap();
+    let idle = tokio::time::timeout(Duration::from_millis(300), client.recv()).await;
+    assert!(idle.is_err());
+    assert_eq!(client.request(1, b"alive").await.unwrap().data(), b"alive");
+    assert!(pongs.load(Ordering::SeqCst) >= 3);
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn slow_writes_do_not_block_reading() {
+    let handled = Arc::new(AtomicUsize::new(0));
+    let counter = handled.clone();
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, move |req| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            // 较大的响应会填满套接字缓冲区，使写入阻塞
+            Response::new(req.msg_id(), vec![0u8; 1024 * 1024])
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) =
+        start(Server::new("127.0.0.1:0", router).with_shutdown_mode(ShutdownMode::Immediate)).await;
+
+    // 客户端连续发送请求但不读取响应，服务器仍然会读取并处理所有请求
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    for _ in 0..16 {
+        stream
+            .write_all(&DataPack::try_pack(1, b"").unwrap())
+            .await
+            .unwrap();
+    }
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while handled.load(Ordering::SeqCst) < 16 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn worker_pool_keeps_per_connection_order() {
+    let router = Arc::new(DefaultRouter::new());
+    // 第一个字节是处理耗时（毫秒），耗时长的请求先到达
+    router
+        .add_async_route(1, |req| async move {
+            tokio::time::sleep(Duration::from_millis(req.data()[0] as u64)).await;
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) =
+        start(Server::new("127.0.0.1:0", router).with_worker_pool(2, 16)).await;
+
+    let mut streams = Vec::new();
+    for _ in 0..2 {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let batch: Vec<u8> = [[30u8], [0], [10]]
+            .iter()
+            .flat_map(|data| DataPack::try_pack(1, data).unwrap())
+            .collect();
+        stream.write_all(&batch).await.unwrap();
+        streams.push(stream);
+    }
+    for stream in &mut streams {
+        for expected in [30u8, 0, 10] {
+            assert_eq!(read_frame(stream).await, (1, vec![expected]));
+        }
+    }
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn worker_pool_keeps_other_connections_responsive() {
+    let router = echo_router();
+    // 阻塞工作任务所在线程的处理函数，模拟 CPU 密集的计算
+    router
+        .add_route(2, |req| {
+            std::thread::sleep(Duration::from_millis(300));
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) =
+        start(Server::new("127.0.0.1:0", router).with_worker_pool(2, 16)).await;
+
+    // 两个连接的ID不同，分别由两个工作任务处理
+    let mut busy = TcpStream::connect(addr).await.unwrap();
+    let mut other = TcpStream::connect(addr).await.unwrap();
+    busy.write_all(&DataPack::try_pack(2, b"heavy").unwrap())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let started = tokio::time::Instant::now();
+    for i in 0..5u8 {
+        assert_echo(&mut other, &[i]).await;
+    }
+    assert!(started.elapsed() < Duration::from_millis(200));
+    assert_eq!(read_frame(&mut busy).await, (2, b"heavy".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn worker_pool_replies_busy_when_queue_full() {
+    let router = Arc::new(DefaultRouter::new());
+    let (started_tx, mut started_rx) = mpsc::unbounded_channel();
+    let release = Arc::new(Semaphore::new(0));
+    let gate = release.clone();
+    router
+        .add_async_route(1, move |req| {
+            let started_tx = started_tx.clone();
+            let gate = gate.clone();
+            async move {
+                let _ = started_tx.send(());
+                gate.acquire().await.unwrap().forget();
+                Response::new(req.msg_id(), req.data().to_vec())
+            }
+        })
+        .unwrap();
+    let config = WorkerPoolConfig::new(1, 1).with_full_policy(QueueFullPolicy::Busy {
+        busy_response: Response::new(503, b"busy".to_vec()),
+    });
+    let (addr, shutdown_tx, server_handle) =
+        start(Server::new("127.0.0.1:0", router).with_worker_pool_config(config)).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"a").unwrap())
+        .await
+        .unwrap();
+    started_rx.recv().await.unwrap();
+
+    // 工作任务正在处理 a：b 进入队列，c 因为队列已满得到繁忙消息
+    let mut batch = DataPack::try_pack(1, b"b").unwrap();
+    batch.extend_from_slice(&DataPack::try_pack(1, b"c").unwrap());
+    stream.write_all(&batch).await.unwrap();
+    assert_eq!(read_frame(&mut stream).await, (503, b"busy".to_vec()));
+
+    release.add_permits(2);
+    assert_eq!(read_frame(&mut stream).await, (1, b"a".to_vec()));
+    assert_eq!(read_frame(&mut stream).await, (1, b"b".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn worker_pool_replies_wait_for_write_queue_space() {
+    // 发送队列远小于一次发出的请求数量，推送策略为丢弃
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_worker_pool(1, 64)
+        .with_write_queue_capacity(2)
+        .with_write_queue_policy(WriteQueuePolicy::Drop);
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 先发出全部请求再开始读取，工作任务的队列放得下全部请求，响应在发送队列中积压
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let payload = vec![7u8; 64 * 1024];
+    let batch: Vec<u8> = (0..32)
+        .flat_map(|_| DataPack::try_pack(1, &payload).unwrap())
+        .collect();
+    stream.write_all(&batch).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // 每个请求都得到响应，没有因为发送队列已满被丢弃
+    for _ in 0..32 {
+        let frame = tokio::time::timeout(Duration::from_secs(5), read_frame(&mut stream))
+            .await
+            .expect("response was dropped");
+        assert_eq!(frame, (1, payload.clone()));
+    }
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn rate_limit_throttles_bursts_beyond_the_limit() {
+    let config = RateLimitConfig::new(10).with_policy(RateLimitPolicy::Throttle {
+        throttle_response: Response::new(429, b"slow down".to_vec()),
+    });
+    let (addr, shutdown_tx, server_handle) =
+        start(Server::new("127.0.0.1:0", echo_router()).with_rate_limit_config(config)).await;
+
+    // 一次发出 100 个请求，第一秒内只有令牌桶中的约 10 个得到处理
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let batch: Vec<u8> = (0..100u8)
+        .flat_map(|i| DataPack::try_pack(1, &[i]).unwrap())
+        .collect();
+    stream.write_all(&batch).await.unwrap();
+    let mut processed = 0;
+    for _ in 0..100 {
+        match read_frame(&mut stream).await {
+            (1, _) => processed += 1,
+            (msg_id, data) => assert_eq!((msg_id, data), (429, b"slow down".to_vec())),
+        }
+    }
+    assert!((10..=11).contains(&processed), "processed {processed}");
+
+    // 令牌补充后连接恢复处理
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_echo(&mut stream, b"again").await;
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn rate_limit_applies_to_ping_frames() {
+    let config = RateLimitConfig::new(2).with_policy(RateLimitPolicy::Throttle {
+        throttle_response: Response::new(429, b"slow down".to_vec()),
+    });
+    let (addr, shutdown_tx, server_handle) =
+        start(Server::new("127.0.0.1:0", echo_router()).with_rate_limit_config(config)).await;
+
+    // 令牌桶中只有 2 个令牌，第三个 ping 得到限流消息而不是 pong
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let batch: Vec<u8> = (0..3u8)
+        .flat_map(|i| DataPack::try_pack(PING_MSG_ID, &[i]).unwrap())
+        .collect();
+    stream.write_all(&batch).await.unwrap();
+    for i in 0..2u8 {
+        assert_eq!(read_frame(&mut stream).await, (PONG_MSG_ID, vec![i]));
+    }
+    assert_eq!(read_frame(&mut stream).await, (429, b"slow down".to_vec()));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn rate_limit_delays_requests_beyond_the_limit() {
+    let (addr, shutdown_tx, server_handle) =
+        start(Server::new("127.0.0.1:0", echo_router()).with_rate_limit(20)).await;
+
+    // 前 20 个请求立即处理，其余 10 个按每秒 20 个的速度依次处理
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let started = tokio::time::Instant::now();
+    let batch: Vec<u8> = (0..30u8)
+        .flat_map(|i| DataPack::try_pack(1, &[i]).unwrap())
+        .collect();
+    stream.write_all(&batch).await.unwrap();
+    for i in 0..30u8 {
+        assert_eq!(read_frame(&mut stream).await, (1, vec![i]));
+    }
+    assert!(started.elapsed() >= Duration::from_millis(450));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn rate_limit_delay_does_not_hold_up_graceful_shutdown() {
+    // 每秒 1 个请求：第二个请求需要等待约一秒
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_rate_limit(1)
+        .with_shutdown_mode(ShutdownMode::Graceful {
+            timeout: Duration::from_millis(500),
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut batch = DataPack::try_pack(1, b"a").unwrap();
+    batch.extend_from_slice(&DataPack::try_pack(1, b"b").unwrap());
+    stream.write_all(&batch).await.unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, b"a".to_vec()));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // 关闭时不再等待令牌，已经读取的请求立即得到处理，连接正常关闭而不是被强制关闭
+    let started = tokio::time::Instant::now();
+    let _ = shutdown_tx.send(());
+    assert_eq!(read_frame(&mut stream).await, (1, b"b".to_vec()));
+    let report = server_handle.await.unwrap().unwrap();
+    assert!(started.elapsed() < Duration::from_millis(500));
+    assert_eq!((report.closed_cleanly(), report.force_closed()), (1, 0));
+}
+
+#[tokio::test]
+async fn handler_replies_later_through_connection_handle() {
+    let router = Arc::new(DefaultRouter::new());
+    let server = Server::new("127.0.0.1:0", router.clone());
+    let manager = server.conn_manager();
+    let (handle_tx, mut handle_rx) = mpsc::unbounded_channel();
+    router
+        .add_route(1, move |req| {
+            let conn = req.connection().unwrap().clone();
+            let _ = handle_tx.send(conn.clone());
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                for part in [&b"later 1"[..], b"later 2"] {
+                    conn.send(Response::new(1, part.to_vec())).unwrap();
+                }
+            });
+            // 暂不回复，连接继续处理后续请求
+            Response::none()
+        })
+        .unwrap();
+    router
+        .add_route(2, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut batch = DataPack::try_pack(1, b"job").unwrap();
+    batch.extend_from_slice(&DataPack::try_pack(2, b"now").unwrap());
+    stream.write_all(&batch).await.unwrap();
+    assert_eq!(read_frame(&mut stream).await, (2, b"now".to_vec()));
+    assert_eq!(read_frame(&mut stream).await, (1, b"later 1".to_vec()));
+    assert_eq!(read_frame(&mut stream).await, (1, b"later 2".to_vec()));
+
+    // 连接关闭后，保存的句柄发送失败而不是 panic
+    let conn = handle_rx.recv().await.unwrap();
+    drop(stream);
+    wait_for_connections(&manager, 0).await;
+    assert!(matches!(
+        conn.send(Response::new(1, Vec::new())),
+        Err(ZerustError::ConnectionClosed)
+    ));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn unix_socket_echo_round_trip() {
+    use tokio::net::UnixStream;
+
+    let path = std::env::temp_dir().join(format!("zerust-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let (addr_tx, mut addr_rx) = mpsc::unbounded_channel();
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, move |req| {
+            addr_tx
+                .send(req.connection().unwrap().remote_addr())
+                .unwrap();
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    let bound = Server::new_unix(&path, router).bind().await.unwrap();
+    assert!(bound.local_addr().is_err());
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(bound.run(shutdown_rx));
+
+    let mut client = UnixStream::connect(&path).await.unwrap();
+    client
+        .write_all(&DataPack::try_pack(1, b"ipc").unwrap())
+        .await
+        .unwrap();
+    let mut frame = [0u8; 11];
+    client.read_exact(&mut frame).await.unwrap();
+    assert_eq!(&frame[..], &DataPack::try_pack(1, b"ipc").unwrap()[..]);
+    // 客户端没有网络地址
+    assert!(addr_rx.recv().await.unwrap().ip().is_unspecified());
+
+    drop(client);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn protocol_handshake_negotiates_version() {
+    let router = Arc::new(DefaultRouter::new());
+    // 处理函数通过上下文获取协商出的版本
+    router
+        .add_route(1, |req| {
+            let version = req.context().unwrap().protocol_version();
+            Response::new(req.msg_id(), vec![version.unwrap_or(0)])
+        })
+        .unwrap();
+    let (addr, shutdown_tx, server_handle) =
+        start(Server::new("127.0.0.1:0", router).with_protocol_version(2)).await;
+
+    // 客户端支持更高的版本时使用服务器的版本，反之使用客户端的版本
+    for (client_version, negotiated) in [(5, 2), (2, 2), (1, 1)] {
+        let mut conn = Connection::new(TcpStream::connect(addr).await.unwrap());
+        assert_eq!(conn.handshake(client_version).await.unwrap(), negotiated);
+        assert_eq!(conn.context().protocol_version(), Some(negotiated));
+        let mut client = Client::new(conn);
+        assert_eq!(client.request(1, b"").await.unwrap().data(), &[negotiated]);
+    }
+
+    // 前导和第一个请求在同一次写入中发送，请求不会丢失
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut data = PROTOCOL_MAGIC.to_vec();
+    data.push(2);
+    data.extend_from_slice(&DataPack::try_pack(1, b"").unwrap());
+    stream.write_all(&data).await.unwrap();
+    let mut preamble = [0u8; 3];
+    stream.read_exact(&mut preamble).await.unwrap();
+    assert_eq!(preamble, [b'Z', b'R', 2]);
+    assert_eq!(read_frame(&mut stream).await, (1, vec![2]));
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn protocol_handshake_rejects_wrong_magic() {
+    let handled = Arc::new(AtomicUsize::new(0));
+    let started = Arc::new(AtomicUsize::new(0));
+    let router = Arc::new(DefaultRouter::new());
+    let route_handled = handled.clone();
+    router
+        .add_route(1, move |req| {
+            route_handled.fetch_add(1, Ordering::SeqCst);
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    let (error_tx, mut error_rx) = mpsc::unbounded_channel();
+    let hook_started = started.clone();
+    let server = Server::new("127.0.0.1:0", router)
+        .with_protocol_version(1)
+        .with_on_conn_start(move |_| {
+            hook_started.fetch_add(1, Ordering::SeqCst);
+            async {}
+        })
+        .with_on_conn_error(move |_, err| {
+            let _ = error_tx.send(matches!(err, ZerustError::ProtocolError(_)));
+        });
+    let (addr, shutdown_tx, server_handle) = start(server).await;
+
+    // 没有发送前导的旧客户端：第一个请求的消息头被当作前导，连接被直接关闭
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"hello").unwrap())
+        .await
+        .unwrap();
+    // 服务器可能没有读完客户端发送的数据，关闭时会重置连接
+    let mut rest = Vec::new();
+    let _ = stream.read_to_end(&mut rest).await;
+    assert!(rest.is_empty());
+    assert_eq!(error_rx.recv().await, Some(true));
+    assert_eq!(handled.load(Ordering::SeqCst), 0);
+    assert_eq!(started.load(Ordering::SeqCst), 0);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
@@ -0,0 +1,80 @@
+//! # smol 运行时后端测试
+//!
+//! 在 smol 的执行器上运行服务器和客户端，过程中没有 Tokio 运行时。
+//! 需要关闭默认功能并开启 `smol-runtime` 功能：
+//!
+//! ```bash
+//! cargo test --no-default-features --features smol-runtime --test smol
+//! ```
+
+#![cfg(all(feature = "smol-runtime", not(feature = "tokio-runtime")))]
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use zerust::{Client, DefaultRouter, Response, Server, ZerustError};
+
+/// 注册回显处理函数的路由器
+fn echo_router() -> Arc<DefaultRouter> {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    router
+}
+
+#[test]
+fn echo_over_smol() {
+    smol::block_on(async {
+        let server = Server::new("127.0.0.1:0", echo_router())
+            .bind()
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server_task = smol::spawn(server.run(shutdown_rx));
+
+        let mut client = Client::connect(addr).await.unwrap();
+        for payload in [&b"hello"[..], b"", &[7u8; 64 * 1024]] {
+            let resp = client.request(1, payload).await.unwrap();
+            assert_eq!(resp.msg_id(), 1);
+            assert_eq!(resp.data(), payload);
+        }
+
+        drop(client);
+        let _ = shutdown_tx.send(());
+        server_task.await.unwrap();
+    });
+}
+
+#[test]
+fn read_timeout_closes_idle_connection_over_smol() {
+    smol::block_on(async {
+        let server = Server::builder()
+            .addr("127.0.0.1:0")
+            .router(echo_router())
+            .read_timeout(Duration::from_millis(50))
+            .build()
+            .bind()
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server_task = smol::spawn(server.run(shutdown_rx));
+
+        // 服务器的定时器由 smol 驱动，超时后关闭连接
+        let mut client = Client::connect(addr).await.unwrap();
+        smol::Timer::after(Duration::from_millis(200)).await;
+        let result = client.request(1, b"late").await;
+        assert!(
+            matches!(
+                result,
+                Err(ZerustError::ConnectionClosed | ZerustError::IoError(_))
+            ),
+            "{result:?}"
+        );
+
+        let _ = shutdown_tx.send(());
+        server_task.await.unwrap();
+    });
+}
@@ -0,0 +1,48 @@
+//! # 测试工具测试
+//!
+//! 使用 `TestServer` 启动服务器、发送请求并关闭。需要开启 `test-util` 功能。
+
+#![cfg(feature = "test-util")]
+
+use std::sync::Arc;
+use zerust::codec::SeqDataPack;
+use zerust::test_util::TestServer;
+use zerust::{DefaultRouter, Response, Server};
+
+fn echo_router() -> Arc<DefaultRouter> {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    router
+}
+
+#[tokio::test]
+async fn test_server_echoes_message() {
+    let server = TestServer::start(Server::new("127.0.0.1:0", echo_router()))
+        .await
+        .unwrap();
+    let mut client = server.client().await.unwrap();
+    assert_eq!(client.request(1, b"hello").await.unwrap().data(), b"hello");
+    assert_eq!(server.conn_manager().len(), 1);
+
+    drop(client);
+    let report = server.shutdown().await.unwrap();
+    assert_eq!(report.force_closed(), 0);
+}
+
+#[tokio::test]
+async fn test_server_clients_follow_server_config() {
+    // 客户端使用服务器的帧格式，并完成协议握手
+    let server = Server::new("127.0.0.1:0", echo_router())
+        .with_codec(Arc::new(SeqDataPack::new()))
+        .with_protocol_version(3);
+    let server = TestServer::start(server).await.unwrap();
+    let conn = server.connection().await.unwrap();
+    assert_eq!(conn.context().protocol_version(), Some(3));
+    let mut client = zerust::Client::new(conn);
+    assert_eq!(client.request(1, b"seq").await.unwrap().data(), b"seq");
+
+    drop(client);
+    server.shutdown().await.unwrap();
+}
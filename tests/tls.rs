@@ -0,0 +1,151 @@
+//! # TLS 集成测试
+//!
+//! 使用内嵌的测试证书，通过真实的 TLS 连接驱动服务器。需要开启 `tls` 功能。
+
+#![cfg(feature = "tls")]
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use zerust::datapack::DataPack;
+use zerust::rustls;
+use zerust::rustls::pki_types::ServerName;
+use zerust::tokio_rustls::TlsConnector;
+use zerust::{Client, DefaultRouter, Response, Server, ZerustError, tls};
+
+const CA_PEM: &[u8] = include_bytes!("certs/ca.pem");
+const CERT_PEM: &[u8] = include_bytes!("certs/cert.pem");
+const KEY_PEM: &[u8] = include_bytes!("certs/key.pem");
+
+/// 使用测试证书创建服务端配置
+fn server_config() -> Arc<rustls::ServerConfig> {
+    tls::server_config_from_pem(CERT_PEM, KEY_PEM).unwrap()
+}
+
+/// 创建信任测试 CA 的客户端
+fn connector() -> TlsConnector {
+    tls::connector_from_pem(CA_PEM).unwrap()
+}
+
+/// 从流中读取一个完整的响应帧，返回 (msg_id, data)
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> (u32, Vec<u8>) {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).await.unwrap();
+    let (msg_id, data_len) = DataPack::unpack_header(&header).unwrap();
+    let mut data = vec![0u8; data_len as usize];
+    stream.read_exact(&mut data).await.unwrap();
+    (msg_id, data)
+}
+
+#[tokio::test]
+async fn tls_echo_keeps_remote_addr() {
+    let (addr_tx, mut addr_rx) = mpsc::unbounded_channel();
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, move |req| {
+            let conn = req.connection().unwrap();
+            addr_tx.send(conn.remote_addr()).unwrap();
+            Response::new(req.msg_id(), req.data().to_vec())
+        })
+        .unwrap();
+    let bound = Server::new("127.0.0.1:0", router)
+        .with_tls(server_config())
+        .bind()
+        .await
+        .unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(bound.run(shutdown_rx));
+
+    let tcp = TcpStream::connect(addr).await.unwrap();
+    let local: SocketAddr = tcp.local_addr().unwrap();
+    let mut stream = connector()
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await
+        .unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"secret").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(read_frame(&mut stream).await, (1, b"secret".to_vec()));
+    // 握手之前记录的客户端地址仍然可以在处理函数中获取
+    assert_eq!(addr_rx.recv().await.unwrap(), local);
+
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn failed_handshake_skips_conn_hooks() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let stopped = Arc::new(AtomicUsize::new(0));
+    let (tls_error_tx, mut tls_error_rx) = mpsc::unbounded_channel();
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router).with_tls(server_config());
+    let server = {
+        let started = started.clone();
+        let stopped = stopped.clone();
+        server
+            .with_on_conn_start(move |_| {
+                started.fetch_add(1, Ordering::SeqCst);
+                async {}
+            })
+            .with_on_conn_stop(move |_| {
+                stopped.fetch_add(1, Ordering::SeqCst);
+                async {}
+            })
+            .with_on_tls_error(move |addr, err| {
+                let _ = tls_error_tx.send((addr, err.to_string()));
+            })
+    };
+    let bound = server.bind().await.unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(bound.run(shutdown_rx));
+
+    // 明文客户端无法完成握手，服务器关闭连接
+    let mut plain = TcpStream::connect(addr).await.unwrap();
+    let plain_addr = plain.local_addr().unwrap();
+    plain
+        .write_all(&DataPack::try_pack(1, b"plain").unwrap())
+        .await
+        .unwrap();
+    let mut buf = Vec::new();
+    let _ = plain.read_to_end(&mut buf).await;
+    let (failed_addr, _) = tls_error_rx.recv().await.unwrap();
+    assert_eq!(failed_addr, plain_addr);
+
+    // 之后的 TLS 连接不受影响
+    let mut client = Client::connect_tls(addr, "127.0.0.1", &connector())
+        .await
+        .unwrap();
+    assert_eq!(client.request(1, b"ok").await.unwrap().data(), b"ok");
+    drop(client);
+
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+    assert_eq!(started.load(Ordering::SeqCst), 1);
+    assert_eq!(stopped.load(Ordering::SeqCst), 1);
+    assert!(tls_error_rx.try_recv().is_err());
+}
+
+#[test]
+fn invalid_pem_is_reported_as_config_error() {
+    assert!(matches!(
+        tls::server_config_from_pem(CERT_PEM, b"not a key"),
+        Err(ZerustError::InvalidConfig(_))
+    ));
+    // 证书与私钥不匹配
+    assert!(matches!(
+        tls::server_config_from_pem(CA_PEM, KEY_PEM),
+        Err(ZerustError::InvalidConfig(_))
+    ));
+    assert!(tls::connector_from_pem(b"").is_ok());
+}
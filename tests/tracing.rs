@@ -0,0 +1,86 @@
+//! # 结构化日志测试
+//!
+//! 需要开启 `tracing` 功能。
+
+#![cfg(feature = "tracing")]
+
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tracing_test::traced_test;
+use zerust::datapack::DataPack;
+use zerust::{DefaultRouter, Response, Server, ZerustError};
+
+#[tokio::test]
+#[traced_test]
+async fn connection_span_wraps_request_events() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route(1, |req| Response::new(req.msg_id(), req.data().to_vec()))
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router);
+    let manager = server.conn_manager();
+    let bound = server.bind().await.unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(bound.run(shutdown_rx));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let local = stream.local_addr().unwrap();
+    stream
+        .write_all(&DataPack::try_pack(1, b"traced").unwrap())
+        .await
+        .unwrap();
+    let mut frame = [0u8; 14];
+    stream.read_exact(&mut frame).await.unwrap();
+    drop(stream);
+    while !manager.is_empty() {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+
+    let span = format!("conn{{conn_id=1 remote_addr={local}}}");
+    assert!(logs_contain(&format!(
+        "{span}:request{{msg_id=1 len=6}}: zerust::server: request received"
+    )));
+    assert!(logs_contain(&format!(
+        "{span}: zerust::server: connection closed"
+    )));
+    assert!(logs_contain(
+        "server stopped closed_cleanly=0 force_closed=0"
+    ));
+}
+
+#[tokio::test]
+#[traced_test]
+async fn worker_pool_handles_requests_inside_request_span() {
+    let router = Arc::new(DefaultRouter::new());
+    router
+        .add_route_result(7, |_| Err(ZerustError::ProtocolError("rejected".into())))
+        .unwrap();
+    let server = Server::new("127.0.0.1:0", router).with_worker_pool(1, 4);
+    let bound = server.bind().await.unwrap();
+    let addr = bound.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let server_handle = tokio::spawn(bound.run(shutdown_rx));
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(&DataPack::try_pack(7, b"abc").unwrap())
+        .await
+        .unwrap();
+    // 错误响应的消息头
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).await.unwrap();
+    drop(stream);
+    let _ = shutdown_tx.send(());
+    server_handle.await.unwrap().unwrap();
+
+    // 工作任务中的日志仍然属于发出请求的连接
+    assert!(logs_contain(
+        "request{msg_id=7 len=3}: zerust::worker_pool: handler returned an error error=Protocol error: rejected"
+    ));
+    assert!(logs_contain("conn{conn_id=1 remote_addr="));
+}